@@ -1,7 +1,7 @@
 use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
 use metal_matrix::{
-    matrix_add, matrix_multiply, matrix_scalar_multiply, matrix_subtract, matrix_transpose, Matrix,
-    MetalContext,
+    cpu, matrix_add, matrix_multiply, matrix_scalar_multiply, matrix_subtract, matrix_transpose,
+    Matrix, MetalContext,
 };
 
 fn bench_matrix_multiply(c: &mut Criterion) {
@@ -156,12 +156,48 @@ fn bench_matrix_scalar_multiply(c: &mut Criterion) {
     group.finish();
 }
 
+fn bench_cpu_matrix_multiply(c: &mut Criterion) {
+    let mut group = c.benchmark_group("cpu_matrix_multiply");
+
+    // Compare the blocked multithreaded kernel with the naive triple loop
+    for size in [32, 64, 128, 256, 512].iter() {
+        // Create square matrices of the given size
+        let mut matrix_a = Matrix::new(*size, *size);
+        let mut matrix_b = Matrix::new(*size, *size);
+
+        // Initialize with some data
+        for i in 0..*size {
+            for j in 0..*size {
+                matrix_a.set(i, j, (i * size + j) as f32 * 0.01);
+                matrix_b.set(i, j, (j * size + i) as f32 * 0.01);
+            }
+        }
+
+        group.bench_with_input(BenchmarkId::new("blocked", size), size, |b, _| {
+            b.iter(|| black_box(cpu::matrix_multiply(&matrix_a, &matrix_b).unwrap()));
+        });
+        group.bench_with_input(BenchmarkId::new("naive", size), size, |b, _| {
+            b.iter(|| black_box(cpu::matrix_multiply_naive(&matrix_a, &matrix_b).unwrap()));
+        });
+    }
+
+    // Few rows and many columns: one row block split into parallel column tiles
+    let wide_a = Matrix::new(64, 1024);
+    let wide_b = Matrix::new(1024, 4096);
+    group.bench_function("blocked_64x1024x4096", |b| {
+        b.iter(|| black_box(cpu::matrix_multiply(&wide_a, &wide_b).unwrap()));
+    });
+
+    group.finish();
+}
+
 criterion_group!(
     benches,
     bench_matrix_multiply,
     bench_matrix_add,
     bench_matrix_subtract,
     bench_matrix_transpose,
-    bench_matrix_scalar_multiply
+    bench_matrix_scalar_multiply,
+    bench_cpu_matrix_multiply
 );
 criterion_main!(benches);
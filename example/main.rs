@@ -1,18 +1,25 @@
 use anyhow::Result;
+use metal_matrix::io::csv::CsvOptions;
+use metal_matrix::verify::{self, Operation};
 use metal_matrix::{
     matrix_add, matrix_multiply, matrix_scalar_multiply, matrix_subtract, matrix_transpose, Matrix,
-    MetalContext,
+    MetalContext, SelectedBackend,
 };
+use std::fs::File;
+use std::io::BufReader;
 
 fn main() -> Result<()> {
     // Initialize logger
     env_logger::init();
 
-    // Setup Metal context
-    let context = MetalContext::new()?;
-
-    // Test matrix operations
-    test_matrix_operations(&context)?;
+    // Verify a backend, multiply two CSV files if given, otherwise test matrix operations.
+    // The Metal context is only set up for the commands that use the GPU.
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.as_slice() {
+        [command, rest @ ..] if command == "verify" => verify_backends(rest)?,
+        [a, b] => multiply_csv_files(&MetalContext::new()?, a, b)?,
+        _ => test_matrix_operations(&MetalContext::new()?)?,
+    }
 
     Ok(())
 }
@@ -102,6 +109,47 @@ fn test_matrix_operations(context: &MetalContext) -> Result<()> {
     Ok(())
 }
 
+fn multiply_csv_files(context: &MetalContext, path_a: &str, path_b: &str) -> Result<()> {
+    let options = CsvOptions::default();
+    let matrix_a = Matrix::from_csv(BufReader::new(File::open(path_a)?), &options)?;
+    let matrix_b = Matrix::from_csv(BufReader::new(File::open(path_b)?), &options)?;
+
+    let result = matrix_multiply(context, &matrix_a, &matrix_b)?;
+    result.to_csv(std::io::stdout().lock(), &options)
+}
+
+/// `verify <operation> [size] [tested backend] [reference backend]`, e.g.
+/// `verify matrix_multiply 512 metal f64`. Fails if the results are outside tolerance.
+///
+/// A Metal context is only created if one of the backends is "metal".
+fn verify_backends(args: &[String]) -> Result<()> {
+    let operation = Operation::from_name(args.first().map_or("matrix_multiply", String::as_str))?;
+    let size: usize = args.get(1).map_or(Ok(256), |s| s.parse())?;
+    let tested_name = args.get(2).map_or("metal", String::as_str);
+    let reference_name = args.get(3).map_or("f64", String::as_str);
+
+    let context = if [tested_name, reference_name]
+        .iter()
+        .any(|name| name.eq_ignore_ascii_case("metal"))
+    {
+        Some(MetalContext::new()?)
+    } else {
+        None
+    };
+    let tested = SelectedBackend::from_name(tested_name, context.as_ref())?;
+    let reference = SelectedBackend::from_name(reference_name, context.as_ref())?;
+
+    let a = verify::sample_matrix(size, size, 1);
+    let b = verify::sample_matrix(size, size, 2);
+    let report = verify::verify(operation, &tested, &reference, &a, &b)?;
+
+    println!("{} vs {}: {}", tested.name(), reference.name(), report);
+    if !report.passed() {
+        anyhow::bail!("Verification failed");
+    }
+    Ok(())
+}
+
 fn print_matrix(matrix: &Matrix) {
     for i in 0..matrix.rows {
         for j in 0..matrix.cols {
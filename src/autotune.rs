@@ -0,0 +1,517 @@
+/*!
+ * # Threadgroup Autotuning
+ *
+ * This module chooses threadgroup sizes for the basic operations by measurement.
+ *
+ * The best threadgroup size for a kernel depends on the GPU and on the problem shape,
+ * so a fixed heuristic is rarely optimal everywhere. The `Autotuner` runs an operation
+ * with each candidate size from `candidates` and records the fastest in a
+ * `TuningProfile`, keyed by kernel, device name and shape class. Every operation in
+ * `operations` (and in batches) looks up its threadgroup size in the context's profile
+ * when it dispatches, falling back to `default_threadgroup_size` for shapes that have
+ * not been tuned.
+ *
+ * Profiles are saved as JSON so tuning only has to be done once per machine:
+ *
+ * ```json
+ * {
+ *   "version": 1,
+ *   "entries": [
+ *     {"kernel": "matrix_multiply", "device": "Apple M2", "shape": "256x256", "width": 32, "height": 8}
+ *   ]
+ * }
+ * ```
+ *
+ * Shapes are classified by rounding each grid dimension up to a power of two (capped
+ * at `MAX_SHAPE_DIMENSION`), so one measurement covers all similar shapes.
+ */
+
+use crate::matrix::Matrix;
+use crate::operations;
+use crate::MetalContext;
+use anyhow::{anyhow, bail, Context, Result};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::time::Instant;
+
+/// Grid dimensions above this are classified together.
+pub const MAX_SHAPE_DIMENSION: usize = 4096;
+
+/// Version written to and expected in profile files.
+const PROFILE_VERSION: u64 = 1;
+
+/// The width and height of a threadgroup (its depth is always 1).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ThreadgroupSize {
+    /// Threads along the grid's x axis
+    pub width: u64,
+
+    /// Threads along the grid's y axis
+    pub height: u64,
+}
+
+impl ThreadgroupSize {
+    /// Total number of threads in the threadgroup.
+    pub fn threads(&self) -> u64 {
+        self.width * self.height
+    }
+}
+
+/// Returns the shape class of a grid of `width` × `height` threads, e.g. "256x64".
+pub fn shape_class(width: usize, height: usize) -> String {
+    let bucket = |d: usize| d.max(1).next_power_of_two().min(MAX_SHAPE_DIMENSION);
+    format!("{}x{}", bucket(width), bucket(height))
+}
+
+/// The threadgroup size used when a profile has no entry for a dispatch.
+///
+/// One-dimensional grids (`height == 1`) use up to 256 threads in a row; others use
+/// rows of up to 16 threads, stacked as high as `max_threads` allows.
+pub fn default_threadgroup_size(width: usize, height: usize, max_threads: u64) -> ThreadgroupSize {
+    if height <= 1 {
+        ThreadgroupSize {
+            width: max_threads.min(256),
+            height: 1,
+        }
+    } else {
+        let tg_width = (width as u64).clamp(1, 16);
+        ThreadgroupSize {
+            width: tg_width,
+            height: (max_threads / tg_width).min(height as u64).max(1),
+        }
+    }
+}
+
+/// Candidate threadgroup sizes to benchmark for a grid of `width` × `height` threads.
+///
+/// Candidates have power-of-two sides, between 32 and `max_threads` threads in total,
+/// and no side much larger than the grid. The list is sorted and never empty: if no
+/// size qualifies, it holds the default size.
+pub fn candidates(width: usize, height: usize, max_threads: u64) -> Vec<ThreadgroupSize> {
+    let limit = |d: usize| (d.max(1) as u64).next_power_of_two();
+    let (max_width, max_height) = (limit(width), limit(height));
+    let powers = |max: u64| (0..=10).map(|p| 1u64 << p).filter(move |&s| s <= max);
+
+    let mut sizes: Vec<ThreadgroupSize> = powers(max_width.min(max_threads))
+        .flat_map(|w| {
+            powers(max_height.min(max_threads / w)).map(move |h| ThreadgroupSize {
+                width: w,
+                height: h,
+            })
+        })
+        .filter(|size| size.threads() >= 32.min(max_threads))
+        .collect();
+
+    if sizes.is_empty() {
+        sizes.push(default_threadgroup_size(width, height, max_threads));
+    }
+    sizes.sort();
+    sizes
+}
+
+/// Key of a `TuningProfile` entry.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ProfileKey {
+    /// Kernel function name
+    pub kernel: String,
+
+    /// Device name, as reported by Metal
+    pub device: String,
+
+    /// Shape class from `shape_class`
+    pub shape: String,
+}
+
+/// Fastest threadgroup sizes found for kernels, devices and shape classes.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TuningProfile {
+    /// The tuned sizes
+    pub entries: BTreeMap<ProfileKey, ThreadgroupSize>,
+}
+
+impl TuningProfile {
+    /// Creates an empty profile.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Looks up the tuned size for a dispatch of `kernel` over a `width` × `height` grid.
+    pub fn get(
+        &self,
+        kernel: &str,
+        device: &str,
+        width: usize,
+        height: usize,
+    ) -> Option<ThreadgroupSize> {
+        self.entries
+            .get(&ProfileKey {
+                kernel: kernel.to_string(),
+                device: device.to_string(),
+                shape: shape_class(width, height),
+            })
+            .copied()
+    }
+
+    /// Records the tuned size for dispatches of `kernel` over grids of this shape class.
+    pub fn insert(
+        &mut self,
+        kernel: &str,
+        device: &str,
+        width: usize,
+        height: usize,
+        size: ThreadgroupSize,
+    ) {
+        self.entries.insert(
+            ProfileKey {
+                kernel: kernel.to_string(),
+                device: device.to_string(),
+                shape: shape_class(width, height),
+            },
+            size,
+        );
+    }
+
+    /// Removes the tuned size for dispatches of `kernel` over grids of this shape class.
+    pub fn remove(&mut self, kernel: &str, device: &str, width: usize, height: usize) {
+        self.entries.remove(&ProfileKey {
+            kernel: kernel.to_string(),
+            device: device.to_string(),
+            shape: shape_class(width, height),
+        });
+    }
+
+    /// Returns the threadgroup size to dispatch with: the tuned size if there is one
+    /// that fits within `max_threads`, otherwise the default.
+    pub fn threadgroup_size(
+        &self,
+        kernel: &str,
+        device: &str,
+        width: usize,
+        height: usize,
+        max_threads: u64,
+    ) -> ThreadgroupSize {
+        self.get(kernel, device, width, height)
+            .filter(|size| size.width > 0 && size.height > 0 && size.threads() <= max_threads)
+            .unwrap_or_else(|| default_threadgroup_size(width, height, max_threads))
+    }
+
+    /// Returns the threadgroup size for a kernel whose threadgroup rows must hold whole
+    /// SIMD groups of `simd_width` threads: a tuned size only if its width is a
+    /// multiple of `simd_width`, otherwise one row of up to 256 threads.
+    pub fn simd_threadgroup_size(
+        &self,
+        kernel: &str,
+        device: &str,
+        width: usize,
+        height: usize,
+        max_threads: u64,
+        simd_width: u64,
+    ) -> ThreadgroupSize {
+        Some(self.threadgroup_size(kernel, device, width, height, max_threads))
+            .filter(|size| size.width.is_multiple_of(simd_width))
+            .unwrap_or(ThreadgroupSize {
+                width: (max_threads.min(256) / simd_width).max(1) * simd_width,
+                height: 1,
+            })
+    }
+
+    /// Serializes the profile as JSON.
+    pub fn to_json(&self) -> String {
+        let entries: Vec<Value> = self
+            .entries
+            .iter()
+            .map(|(key, size)| {
+                json!({
+                    "kernel": key.kernel,
+                    "device": key.device,
+                    "shape": key.shape,
+                    "width": size.width,
+                    "height": size.height,
+                })
+            })
+            .collect();
+
+        let profile = json!({ "version": PROFILE_VERSION, "entries": entries });
+        serde_json::to_string_pretty(&profile).expect("JSON values always serialize")
+    }
+
+    /// Parses a profile written by `to_json`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the text is not valid JSON, has a different version, or an
+    /// entry is missing a field or has a field of the wrong type.
+    pub fn from_json(text: &str) -> Result<Self> {
+        let profile: Value = serde_json::from_str(text).context("Invalid tuning profile")?;
+
+        let version = profile.get("version").and_then(Value::as_u64);
+        if version != Some(PROFILE_VERSION) {
+            bail!(
+                "Invalid tuning profile: unsupported version {}",
+                profile.get("version").unwrap_or(&Value::Null)
+            );
+        }
+
+        let entries = profile
+            .get("entries")
+            .and_then(Value::as_array)
+            .ok_or_else(|| anyhow!("Invalid tuning profile: missing 'entries' array"))?;
+
+        let mut result = Self::new();
+        for (index, entry) in entries.iter().enumerate() {
+            let string = |field: &str| {
+                entry.get(field).and_then(Value::as_str).ok_or_else(|| {
+                    anyhow!(
+                        "Invalid tuning profile entry {}: missing string '{}'",
+                        index,
+                        field
+                    )
+                })
+            };
+            let integer = |field: &str| {
+                entry.get(field).and_then(Value::as_u64).ok_or_else(|| {
+                    anyhow!(
+                        "Invalid tuning profile entry {}: missing integer '{}'",
+                        index,
+                        field
+                    )
+                })
+            };
+
+            result.entries.insert(
+                ProfileKey {
+                    kernel: string("kernel")?.to_string(),
+                    device: string("device")?.to_string(),
+                    shape: string("shape")?.to_string(),
+                },
+                ThreadgroupSize {
+                    width: integer("width")?,
+                    height: integer("height")?,
+                },
+            );
+        }
+        Ok(result)
+    }
+
+    /// Loads a profile from a JSON file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or is not a valid profile.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read tuning profile: {}", path.display()))?;
+        Self::from_json(&text)
+    }
+
+    /// Saves the profile to a JSON file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be written.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        fs::write(path, self.to_json())
+            .with_context(|| format!("Failed to write tuning profile: {}", path.display()))
+    }
+}
+
+/// An operation whose threadgroup size can be tuned.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TunedOperation {
+    /// `matrix_multiply`
+    MatrixMultiply,
+    /// `matrix_add`
+    MatrixAdd,
+    /// `matrix_subtract`
+    MatrixSubtract,
+    /// `matrix_transpose`
+    MatrixTranspose,
+    /// `matrix_scalar_multiply`
+    MatrixScalarMultiply,
+}
+
+impl TunedOperation {
+    /// All tunable operations.
+    pub const ALL: [TunedOperation; 5] = [
+        TunedOperation::MatrixMultiply,
+        TunedOperation::MatrixAdd,
+        TunedOperation::MatrixSubtract,
+        TunedOperation::MatrixTranspose,
+        TunedOperation::MatrixScalarMultiply,
+    ];
+
+    /// The name of the operation's kernel function.
+    pub fn kernel(&self) -> &'static str {
+        use crate::kernels::functions;
+        match self {
+            TunedOperation::MatrixMultiply => functions::MATRIX_MUL,
+            TunedOperation::MatrixAdd => functions::MATRIX_ADD,
+            TunedOperation::MatrixSubtract => functions::MATRIX_SUB,
+            TunedOperation::MatrixTranspose => functions::MATRIX_TRANSPOSE,
+            TunedOperation::MatrixScalarMultiply => functions::MATRIX_SCALAR_MUL,
+        }
+    }
+
+    /// The path of the operation's shader file.
+    pub fn path(&self) -> &'static str {
+        use crate::kernels::paths;
+        match self {
+            TunedOperation::MatrixMultiply => paths::MATRIX_MUL,
+            TunedOperation::MatrixAdd => paths::MATRIX_ADD,
+            TunedOperation::MatrixSubtract => paths::MATRIX_SUB,
+            TunedOperation::MatrixTranspose => paths::MATRIX_TRANSPOSE,
+            TunedOperation::MatrixScalarMultiply => paths::MATRIX_SCALAR_MUL,
+        }
+    }
+
+    /// The grid the operation dispatches for a `rows` × `cols` input.
+    fn grid(&self, rows: usize, cols: usize) -> (usize, usize) {
+        match self {
+            TunedOperation::MatrixMultiply | TunedOperation::MatrixTranspose => (cols, rows),
+            _ => (rows * cols, 1),
+        }
+    }
+
+    /// Runs the operation once.
+    fn run(&self, context: &MetalContext, a: &Matrix, b: &Matrix) -> Result<Matrix> {
+        match self {
+            TunedOperation::MatrixMultiply => operations::matrix_multiply(context, a, b),
+            TunedOperation::MatrixAdd => operations::matrix_add(context, a, b),
+            TunedOperation::MatrixSubtract => operations::matrix_subtract(context, a, b),
+            TunedOperation::MatrixTranspose => operations::matrix_transpose(context, a),
+            TunedOperation::MatrixScalarMultiply => {
+                operations::matrix_scalar_multiply(context, 2.0, a)
+            }
+        }
+    }
+}
+
+/// Benchmarks candidate threadgroup sizes and records the fastest in the context's
+/// tuning profile.
+///
+/// Candidates are timed by installing them in the shared tuning profile, so tuning
+/// must not run alongside other work on the same `MetalContext`: operations
+/// dispatched from other threads meanwhile would run with whichever candidate is
+/// being timed and skew its measurement. Tune before handing the context out.
+///
+/// # Example
+///
+/// ```no_run
+/// use metal_matrix::autotune::{Autotuner, TunedOperation};
+/// use metal_matrix::MetalContext;
+///
+/// let context = MetalContext::new().unwrap();
+/// context.load_tuning_profile("tuning.json").ok();
+///
+/// let tuner = Autotuner::new(&context);
+/// for &size in &[64, 256, 1024] {
+///     tuner.tune(TunedOperation::MatrixMultiply, size, size).unwrap();
+/// }
+/// context.tuning_profile().save("tuning.json").unwrap();
+/// ```
+pub struct Autotuner<'a> {
+    /// The context whose profile is updated
+    pub context: &'a MetalContext,
+
+    /// Timed runs per candidate; the fastest run counts
+    pub runs: usize,
+}
+
+impl<'a> Autotuner<'a> {
+    /// Creates an autotuner timing three runs per candidate.
+    pub fn new(context: &'a MetalContext) -> Self {
+        Self { context, runs: 3 }
+    }
+
+    /// Finds the fastest threadgroup size for an operation on a `rows` × `cols` input
+    /// and records it in the context's tuning profile.
+    ///
+    /// Multiplication is timed on a `rows` × `cols` by `cols` × `cols` product. The
+    /// profile holds each candidate while it is timed, so no other work may use the
+    /// context until this returns.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the fastest size or an error.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the operation fails, leaving the profile's entry for this
+    /// operation and shape as it was.
+    pub fn tune(
+        &self,
+        operation: TunedOperation,
+        rows: usize,
+        cols: usize,
+    ) -> Result<ThreadgroupSize> {
+        let kernel = operation.kernel();
+        let device = self.context.device.name().to_string();
+        let (width, height) = operation.grid(rows, cols);
+
+        let pipeline = self.context.load_kernel(operation.path(), kernel)?;
+        let max_threads = pipeline.max_total_threads_per_threadgroup();
+
+        let a = Matrix::new(rows, cols);
+        let b = match operation {
+            TunedOperation::MatrixMultiply => Matrix::new(cols, cols),
+            _ => Matrix::new(rows, cols),
+        };
+
+        // Warm up once so the first candidate does not pay for first-use costs
+        operation.run(self.context, &a, &b)?;
+
+        // Each candidate is timed by installing it in the profile, so the entry held
+        // before tuning is put back if a run fails
+        let previous = self
+            .context
+            .tuning_profile()
+            .get(kernel, &device, width, height);
+        let result = self.fastest(operation, &a, &b, &device, (width, height), max_threads);
+        let size = match result {
+            Ok(size) => Some(size),
+            Err(_) => previous,
+        };
+        self.context.update_tuning_profile(|profile| match size {
+            Some(size) => profile.insert(kernel, &device, width, height, size),
+            None => profile.remove(kernel, &device, width, height),
+        });
+        result
+    }
+
+    /// Times every candidate size and returns the fastest.
+    fn fastest(
+        &self,
+        operation: TunedOperation,
+        a: &Matrix,
+        b: &Matrix,
+        device: &str,
+        (width, height): (usize, usize),
+        max_threads: u64,
+    ) -> Result<ThreadgroupSize> {
+        let kernel = operation.kernel();
+        let mut best: Option<(f64, ThreadgroupSize)> = None;
+        for size in candidates(width, height, max_threads) {
+            self.context.update_tuning_profile(|profile| {
+                profile.insert(kernel, device, width, height, size)
+            });
+
+            let mut fastest = f64::INFINITY;
+            for _ in 0..self.runs.max(1) {
+                let start = Instant::now();
+                operation.run(self.context, a, b)?;
+                fastest = fastest.min(start.elapsed().as_secs_f64());
+            }
+
+            if best.is_none_or(|(time, _)| fastest < time) {
+                best = Some((fastest, size));
+            }
+        }
+
+        let (_, size) = best.expect("candidates are never empty");
+        Ok(size)
+    }
+}
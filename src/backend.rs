@@ -0,0 +1,232 @@
+/*!
+ * # Backends
+ *
+ * This module defines the `Backend` trait, the set of primitive operations that
+ * higher-level algorithms (triangular solves, iterative solvers, matrix functions)
+ * are built from.
+ *
+ * The following backends are provided:
+ * - `MetalContext`: runs every primitive on the GPU through the Metal kernels
+ * - `CpuBackend`: runs every primitive with the reference implementations in `cpu`
+ * - `BlasBackend` (feature `blas`): runs every primitive through the system BLAS
+ * - `F64Backend`: computes in double precision, as a reference for verification
+ * - `SelectedBackend`: any of the above, chosen at runtime
+ *
+ * Algorithms written against `Backend` can therefore be called with a
+ * `MetalContext` exactly like the basic operations, and tested on the CPU.
+ */
+
+use crate::cpu;
+use crate::matrix::Matrix;
+use crate::operations;
+use crate::verify::F64Backend;
+use crate::MetalContext;
+use anyhow::Result;
+
+/// Primitive matrix operations used by the higher-level algorithms.
+///
+/// Every method has the same semantics and error conditions as the
+/// operation of the same name in `operations` (or, for `solve` and `inverse`,
+/// in `cpu`).
+pub trait Backend {
+    /// Computes the matrix product C = A * B.
+    fn matrix_multiply(&self, a: &Matrix, b: &Matrix) -> Result<Matrix>;
+
+    /// Computes the element-wise sum C = A + B.
+    fn matrix_add(&self, a: &Matrix, b: &Matrix) -> Result<Matrix>;
+
+    /// Computes the element-wise difference C = A - B.
+    fn matrix_subtract(&self, a: &Matrix, b: &Matrix) -> Result<Matrix>;
+
+    /// Computes the transpose B = A^T.
+    fn matrix_transpose(&self, a: &Matrix) -> Result<Matrix>;
+
+    /// Computes the scaled matrix B = scalar * A.
+    fn matrix_scalar_multiply(&self, scalar: f32, a: &Matrix) -> Result<Matrix>;
+
+    /// Solves the dense linear system A * X = B.
+    ///
+    /// Defaults to the LU decomposition in `cpu::solve`.
+    fn solve(&self, a: &Matrix, b: &Matrix) -> Result<Matrix> {
+        cpu::solve(a, b)
+    }
+
+    /// Computes the inverse of a square matrix.
+    ///
+    /// Defaults to `cpu::inverse`.
+    fn inverse(&self, a: &Matrix) -> Result<Matrix> {
+        cpu::inverse(a)
+    }
+}
+
+impl Backend for MetalContext {
+    fn matrix_multiply(&self, a: &Matrix, b: &Matrix) -> Result<Matrix> {
+        operations::matrix_multiply(self, a, b)
+    }
+
+    fn matrix_add(&self, a: &Matrix, b: &Matrix) -> Result<Matrix> {
+        operations::matrix_add(self, a, b)
+    }
+
+    fn matrix_subtract(&self, a: &Matrix, b: &Matrix) -> Result<Matrix> {
+        operations::matrix_subtract(self, a, b)
+    }
+
+    fn matrix_transpose(&self, a: &Matrix) -> Result<Matrix> {
+        operations::matrix_transpose(self, a)
+    }
+
+    fn matrix_scalar_multiply(&self, scalar: f32, a: &Matrix) -> Result<Matrix> {
+        operations::matrix_scalar_multiply(self, scalar, a)
+    }
+}
+
+/// Backend running every operation on the CPU with the reference implementations.
+///
+/// # Example
+///
+/// ```
+/// use metal_matrix::{Backend, CpuBackend, Matrix};
+///
+/// let a = Matrix::with_data(2, 2, vec![1.0, 2.0, 3.0, 4.0]).unwrap();
+/// let result = CpuBackend.matrix_multiply(&a, &Matrix::identity(2)).unwrap();
+/// assert_eq!(result.data, a.data);
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CpuBackend;
+
+impl Backend for CpuBackend {
+    fn matrix_multiply(&self, a: &Matrix, b: &Matrix) -> Result<Matrix> {
+        cpu::matrix_multiply(a, b)
+    }
+
+    fn matrix_add(&self, a: &Matrix, b: &Matrix) -> Result<Matrix> {
+        cpu::matrix_add(a, b)
+    }
+
+    fn matrix_subtract(&self, a: &Matrix, b: &Matrix) -> Result<Matrix> {
+        cpu::matrix_subtract(a, b)
+    }
+
+    fn matrix_transpose(&self, a: &Matrix) -> Result<Matrix> {
+        cpu::matrix_transpose(a)
+    }
+
+    fn matrix_scalar_multiply(&self, scalar: f32, a: &Matrix) -> Result<Matrix> {
+        cpu::matrix_scalar_multiply(scalar, a)
+    }
+}
+
+/// A backend chosen at runtime, for example from a configuration file or command line.
+///
+/// # Example
+///
+/// ```
+/// use metal_matrix::backend::SelectedBackend;
+/// use metal_matrix::{Backend, Matrix};
+///
+/// let backend = SelectedBackend::from_name("cpu", None).unwrap();
+/// let a = Matrix::with_data(2, 2, vec![1.0, 2.0, 3.0, 4.0]).unwrap();
+/// assert_eq!(backend.matrix_transpose(&a).unwrap().data, vec![1.0, 3.0, 2.0, 4.0]);
+/// ```
+#[derive(Clone, Copy)]
+pub enum SelectedBackend<'a> {
+    /// The Metal kernels
+    Metal(&'a MetalContext),
+
+    /// The native Rust implementations in `cpu`
+    Cpu(CpuBackend),
+
+    /// The system BLAS and LAPACK
+    #[cfg(feature = "blas")]
+    Blas(crate::blas::BlasBackend),
+
+    /// Double-precision reference implementations
+    F64(F64Backend),
+}
+
+impl<'a> SelectedBackend<'a> {
+    /// Names accepted by `from_name` in this build.
+    pub const NAMES: &'static [&'static str] = &[
+        "metal",
+        "cpu",
+        #[cfg(feature = "blas")]
+        "blas",
+        "f64",
+    ];
+
+    /// Selects a backend by name: "metal", "cpu", "f64" or (with the `blas` feature)
+    /// "blas".
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the name is unknown, or is "metal" and no context is given.
+    pub fn from_name(name: &str, context: Option<&'a MetalContext>) -> Result<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "metal" => context
+                .map(SelectedBackend::Metal)
+                .ok_or_else(|| anyhow::anyhow!("The metal backend needs a MetalContext")),
+            "cpu" => Ok(SelectedBackend::Cpu(CpuBackend)),
+            #[cfg(feature = "blas")]
+            "blas" => Ok(SelectedBackend::Blas(crate::blas::BlasBackend)),
+            "f64" => Ok(SelectedBackend::F64(F64Backend)),
+            _ => anyhow::bail!(
+                "Unknown backend '{}' (expected one of: {})",
+                name,
+                Self::NAMES.join(", ")
+            ),
+        }
+    }
+
+    /// The name of the backend, as accepted by `from_name`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            SelectedBackend::Metal(_) => "metal",
+            SelectedBackend::Cpu(_) => "cpu",
+            #[cfg(feature = "blas")]
+            SelectedBackend::Blas(_) => "blas",
+            SelectedBackend::F64(_) => "f64",
+        }
+    }
+
+    /// The selected backend as a trait object.
+    fn backend(&self) -> &dyn Backend {
+        match self {
+            SelectedBackend::Metal(context) => *context,
+            SelectedBackend::Cpu(backend) => backend,
+            #[cfg(feature = "blas")]
+            SelectedBackend::Blas(backend) => backend,
+            SelectedBackend::F64(backend) => backend,
+        }
+    }
+}
+
+impl Backend for SelectedBackend<'_> {
+    fn matrix_multiply(&self, a: &Matrix, b: &Matrix) -> Result<Matrix> {
+        self.backend().matrix_multiply(a, b)
+    }
+
+    fn matrix_add(&self, a: &Matrix, b: &Matrix) -> Result<Matrix> {
+        self.backend().matrix_add(a, b)
+    }
+
+    fn matrix_subtract(&self, a: &Matrix, b: &Matrix) -> Result<Matrix> {
+        self.backend().matrix_subtract(a, b)
+    }
+
+    fn matrix_transpose(&self, a: &Matrix) -> Result<Matrix> {
+        self.backend().matrix_transpose(a)
+    }
+
+    fn matrix_scalar_multiply(&self, scalar: f32, a: &Matrix) -> Result<Matrix> {
+        self.backend().matrix_scalar_multiply(scalar, a)
+    }
+
+    fn solve(&self, a: &Matrix, b: &Matrix) -> Result<Matrix> {
+        self.backend().solve(a, b)
+    }
+
+    fn inverse(&self, a: &Matrix) -> Result<Matrix> {
+        self.backend().inverse(a)
+    }
+}
@@ -0,0 +1,631 @@
+/*!
+ * # Batched Execution
+ *
+ * This module records several GPU operations and submits them together in one
+ * command buffer, instead of creating a command buffer and waiting for it once per
+ * operation.
+ *
+ * Operations are recorded on a `CommandRecorder` against GPU-resident buffers:
+ * matrices are uploaded once, intermediate results stay on the GPU, and only the
+ * results that are read back are copied out. Dispatches are encoded for concurrent
+ * execution, with a memory barrier inserted only before an operation that reads a
+ * buffer written by an earlier one, so independent operations can overlap.
+ *
+ * Recording does not touch the GPU. `CommandRecorder::encode` replays the recorded
+ * commands onto any `CommandEncoder`; `MetalContext::batch` uses one that drives a
+ * Metal compute encoder, and `RecordingEncoder` captures the commands so their
+ * ordering can be tested without a GPU.
+ *
+ * ```
+ * use metal_matrix::batch::{CommandRecorder, EncoderEvent, RecordingEncoder};
+ * use metal_matrix::Matrix;
+ *
+ * let mut recorder = CommandRecorder::new();
+ * let a = recorder.upload(&Matrix::identity(2));
+ * let b = recorder.upload(&Matrix::identity(2));
+ * let c = recorder.matrix_add(&a, &b).unwrap();
+ * let d = recorder.matrix_multiply(&c, &a).unwrap();
+ *
+ * let mut encoder = RecordingEncoder::default();
+ * recorder.encode(&mut encoder);
+ *
+ * // The multiply waits for the addition that produced its first operand
+ * assert!(encoder.events.contains(&EncoderEvent::Barrier(vec![c.id()])));
+ * assert_eq!((d.rows, d.cols), (2, 2));
+ * ```
+ */
+
+use crate::buffer_pool::BufferPool;
+use crate::kernels;
+use crate::limits::check_32bit;
+use crate::matrix::Matrix;
+use crate::metal_context::{set_constant, MetalContext};
+use anyhow::{anyhow, bail, Result};
+use metal::*;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// A kernel function, identified by its shader file and function name.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct KernelRef {
+    /// Path to the Metal shader file
+    pub path: &'static str,
+
+    /// Name of the kernel function
+    pub function: &'static str,
+}
+
+/// A kernel argument.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Argument {
+    /// A recorded buffer, by id
+    Buffer(usize),
+    /// A `constant uint&` value
+    U32(u32),
+    /// A `constant float&` value
+    F32(f32),
+}
+
+/// The threads launched by a dispatch.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Grid {
+    /// One thread per element
+    Linear(usize),
+    /// One thread per (column, row) position
+    Planar {
+        /// Number of columns
+        width: usize,
+        /// Number of rows
+        height: usize,
+    },
+}
+
+/// A target for recorded commands.
+pub trait CommandEncoder {
+    /// Selects the kernel for the following arguments and dispatch.
+    fn set_kernel(&mut self, kernel: KernelRef);
+
+    /// Binds an argument of the current kernel.
+    fn set_argument(&mut self, index: u64, argument: Argument);
+
+    /// Launches the current kernel.
+    fn dispatch(&mut self, grid: Grid);
+
+    /// Waits for earlier dispatches writing the given buffers before continuing.
+    fn barrier(&mut self, buffers: &[usize]);
+}
+
+/// A command captured by a `RecordingEncoder`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum EncoderEvent {
+    /// A kernel was selected, by function name
+    SetKernel(&'static str),
+    /// An argument was bound
+    SetArgument(u64, Argument),
+    /// The current kernel was launched
+    Dispatch(Grid),
+    /// A memory barrier over the given buffers
+    Barrier(Vec<usize>),
+}
+
+/// A `CommandEncoder` that records the commands it receives.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RecordingEncoder {
+    /// The commands received, in order
+    pub events: Vec<EncoderEvent>,
+}
+
+impl CommandEncoder for RecordingEncoder {
+    fn set_kernel(&mut self, kernel: KernelRef) {
+        self.events.push(EncoderEvent::SetKernel(kernel.function));
+    }
+
+    fn set_argument(&mut self, index: u64, argument: Argument) {
+        self.events.push(EncoderEvent::SetArgument(index, argument));
+    }
+
+    fn dispatch(&mut self, grid: Grid) {
+        self.events.push(EncoderEvent::Dispatch(grid));
+    }
+
+    fn barrier(&mut self, buffers: &[usize]) {
+        self.events.push(EncoderEvent::Barrier(buffers.to_vec()));
+    }
+}
+
+/// A handle to a matrix held in a recorded GPU buffer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BatchMatrix {
+    id: usize,
+
+    /// Id of the recorder the matrix was recorded on
+    batch: u64,
+
+    /// Number of rows in the matrix
+    pub rows: usize,
+
+    /// Number of columns in the matrix
+    pub cols: usize,
+}
+
+impl BatchMatrix {
+    /// Returns the id of the buffer holding the matrix.
+    pub fn id(&self) -> usize {
+        self.id
+    }
+}
+
+struct Command {
+    kernel: KernelRef,
+    arguments: Vec<Argument>,
+    grid: Grid,
+}
+
+/// Records GPU operations for submission in a single command buffer.
+///
+/// Every operation writes a new buffer, so the only hazards between recorded
+/// operations are reads of earlier results.
+pub struct CommandRecorder {
+    /// Unique id, stored in every handle the recorder returns
+    batch: u64,
+    /// Shape and initial contents of each buffer
+    buffers: Vec<(usize, usize, Option<Vec<f32>>)>,
+    commands: Vec<Command>,
+}
+
+impl Default for CommandRecorder {
+    fn default() -> Self {
+        static NEXT_BATCH: AtomicU64 = AtomicU64::new(0);
+        Self {
+            batch: NEXT_BATCH.fetch_add(1, Ordering::Relaxed),
+            buffers: Vec::new(),
+            commands: Vec::new(),
+        }
+    }
+}
+
+impl CommandRecorder {
+    /// Creates an empty recorder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of recorded operations.
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    /// Returns `true` if no operations have been recorded.
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    /// Records copying a matrix into a new GPU buffer.
+    pub fn upload(&mut self, matrix: &Matrix) -> BatchMatrix {
+        self.buffer(matrix.rows, matrix.cols, Some(matrix.data.clone()))
+    }
+
+    /// Records a matrix multiplication: C = A * B
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if either matrix was not recorded on this recorder, or the
+    /// matrices have incompatible dimensions (a.cols != b.rows).
+    pub fn matrix_multiply(&mut self, a: &BatchMatrix, b: &BatchMatrix) -> Result<BatchMatrix> {
+        self.check_handle(a)?;
+        self.check_handle(b)?;
+        if a.cols != b.rows {
+            bail!("Matrix dimensions incompatible for multiplication");
+        }
+
+        let c = self.buffer(a.rows, b.cols, None);
+        self.command(
+            kernels::paths::MATRIX_MUL,
+            kernels::functions::MATRIX_MUL,
+            vec![
+                Argument::Buffer(a.id),
+                Argument::Buffer(b.id),
+                Argument::Buffer(c.id),
+                Argument::U32(a.rows as u32),
+                Argument::U32(b.cols as u32),
+                Argument::U32(a.cols as u32),
+            ],
+            Grid::Planar {
+                width: b.cols,
+                height: a.rows,
+            },
+        );
+        Ok(c)
+    }
+
+    /// Records a matrix addition: C = A + B
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if either matrix was not recorded on this recorder, or the
+    /// matrices have different dimensions.
+    pub fn matrix_add(&mut self, a: &BatchMatrix, b: &BatchMatrix) -> Result<BatchMatrix> {
+        self.check_handle(a)?;
+        self.check_handle(b)?;
+        if a.rows != b.rows || a.cols != b.cols {
+            bail!("Matrix dimensions must match for addition");
+        }
+        Ok(self.elementwise(
+            kernels::paths::MATRIX_ADD,
+            kernels::functions::MATRIX_ADD,
+            a,
+            b,
+        ))
+    }
+
+    /// Records a matrix subtraction: C = A - B
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if either matrix was not recorded on this recorder, or the
+    /// matrices have different dimensions.
+    pub fn matrix_subtract(&mut self, a: &BatchMatrix, b: &BatchMatrix) -> Result<BatchMatrix> {
+        self.check_handle(a)?;
+        self.check_handle(b)?;
+        if a.rows != b.rows || a.cols != b.cols {
+            bail!("Matrix dimensions must match for subtraction");
+        }
+        Ok(self.elementwise(
+            kernels::paths::MATRIX_SUB,
+            kernels::functions::MATRIX_SUB,
+            a,
+            b,
+        ))
+    }
+
+    /// Records a matrix transpose: B = A^T
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the matrix was not recorded on this recorder.
+    pub fn matrix_transpose(&mut self, a: &BatchMatrix) -> Result<BatchMatrix> {
+        self.check_handle(a)?;
+        let b = self.buffer(a.cols, a.rows, None);
+        self.command(
+            kernels::paths::MATRIX_TRANSPOSE,
+            kernels::functions::MATRIX_TRANSPOSE,
+            vec![
+                Argument::Buffer(a.id),
+                Argument::Buffer(b.id),
+                Argument::U32(a.rows as u32),
+                Argument::U32(a.cols as u32),
+            ],
+            Grid::Planar {
+                width: a.cols,
+                height: a.rows,
+            },
+        );
+        Ok(b)
+    }
+
+    /// Records a scalar multiplication: B = scalar * A
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the matrix was not recorded on this recorder.
+    pub fn matrix_scalar_multiply(&mut self, scalar: f32, a: &BatchMatrix) -> Result<BatchMatrix> {
+        self.check_handle(a)?;
+        let b = self.buffer(a.rows, a.cols, None);
+        self.command(
+            kernels::paths::MATRIX_SCALAR_MUL,
+            kernels::functions::MATRIX_SCALAR_MUL,
+            vec![
+                Argument::Buffer(a.id),
+                Argument::F32(scalar),
+                Argument::Buffer(b.id),
+            ],
+            Grid::Linear(a.rows * a.cols),
+        );
+        Ok(b)
+    }
+
+    /// Replays the recorded operations onto an encoder, inserting a barrier before
+    /// each operation that reads a buffer written since the last barrier on it.
+    pub fn encode<E: CommandEncoder>(&self, encoder: &mut E) {
+        let mut pending = HashSet::new();
+
+        for command in &self.commands {
+            let (output, inputs) = command_buffers(command);
+
+            let mut dependencies: Vec<usize> = inputs
+                .iter()
+                .copied()
+                .filter(|id| pending.contains(id))
+                .collect();
+            dependencies.dedup();
+            if !dependencies.is_empty() {
+                encoder.barrier(&dependencies);
+                for id in &dependencies {
+                    pending.remove(id);
+                }
+            }
+
+            encoder.set_kernel(command.kernel);
+            for (index, argument) in command.arguments.iter().enumerate() {
+                encoder.set_argument(index as u64, *argument);
+            }
+            encoder.dispatch(command.grid);
+            pending.insert(output);
+        }
+    }
+
+    /// Checks that `matrix` is a handle returned by this recorder.
+    fn check_handle(&self, matrix: &BatchMatrix) -> Result<()> {
+        if matrix.batch != self.batch || matrix.id >= self.buffers.len() {
+            bail!("Matrix was not recorded in this batch");
+        }
+        Ok(())
+    }
+
+    fn buffer(&mut self, rows: usize, cols: usize, data: Option<Vec<f32>>) -> BatchMatrix {
+        self.buffers.push((rows, cols, data));
+        BatchMatrix {
+            id: self.buffers.len() - 1,
+            batch: self.batch,
+            rows,
+            cols,
+        }
+    }
+
+    fn command(
+        &mut self,
+        path: &'static str,
+        function: &'static str,
+        arguments: Vec<Argument>,
+        grid: Grid,
+    ) {
+        self.commands.push(Command {
+            kernel: KernelRef { path, function },
+            arguments,
+            grid,
+        });
+    }
+
+    fn elementwise(
+        &mut self,
+        path: &'static str,
+        function: &'static str,
+        a: &BatchMatrix,
+        b: &BatchMatrix,
+    ) -> BatchMatrix {
+        let c = self.buffer(a.rows, a.cols, None);
+        self.command(
+            path,
+            function,
+            vec![
+                Argument::Buffer(a.id),
+                Argument::Buffer(b.id),
+                Argument::Buffer(c.id),
+            ],
+            Grid::Linear(a.rows * a.cols),
+        );
+        c
+    }
+}
+
+/// Returns the buffer a command writes, which is always its last buffer argument,
+/// and the buffers it reads.
+fn command_buffers(command: &Command) -> (usize, Vec<usize>) {
+    let mut ids: Vec<usize> = command
+        .arguments
+        .iter()
+        .filter_map(|argument| match argument {
+            Argument::Buffer(id) => Some(*id),
+            _ => None,
+        })
+        .collect();
+    let output = ids.pop().expect("every command writes a buffer");
+    (output, ids)
+}
+
+/// The results of a submitted batch.
+pub struct BatchResults<T> {
+    /// The value returned by the recording closure
+    pub output: T,
+
+    batch: u64,
+    buffers: Vec<Buffer>,
+    shapes: Vec<(usize, usize)>,
+    /// The context's buffer pool, to which the buffers are returned on drop
+    pool: Arc<Mutex<BufferPool<Buffer>>>,
+}
+
+impl<T> BatchResults<T> {
+    /// Copies a matrix recorded in the batch back from the GPU.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the handle was not recorded in this batch.
+    pub fn read(&self, matrix: &BatchMatrix) -> Result<Matrix> {
+        if matrix.batch != self.batch
+            || self.shapes.get(matrix.id) != Some(&(matrix.rows, matrix.cols))
+        {
+            bail!("Matrix was not recorded in this batch");
+        }
+
+        let size = matrix.rows * matrix.cols;
+        let result_ptr = self.buffers[matrix.id].contents() as *const f32;
+        let mut result_data = vec![0.0f32; size];
+
+        unsafe {
+            std::ptr::copy_nonoverlapping(result_ptr, result_data.as_mut_ptr(), size);
+        }
+
+        Matrix::with_data(matrix.rows, matrix.cols, result_data)
+    }
+}
+
+impl<T> Drop for BatchResults<T> {
+    fn drop(&mut self) {
+        // The batch has completed, so the GPU is finished with every buffer
+        let mut pool = self.pool.lock().unwrap();
+        for buffer in self.buffers.drain(..) {
+            pool.release(buffer);
+        }
+    }
+}
+
+impl MetalContext {
+    /// Records several operations and runs them in one command buffer.
+    ///
+    /// The closure records operations on a `CommandRecorder`. They are then encoded
+    /// into a single command buffer, which is committed and waited on once. The
+    /// returned `BatchResults` holds the closure's value and reads back any recorded
+    /// matrix.
+    ///
+    /// # Arguments
+    ///
+    /// * `record` - Function that records the operations
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the batch results or an error.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if recording fails or a kernel cannot be loaded, or a
+    /// `LimitError` if a recorded matrix exceeds the 32-bit kernel limits or does not
+    /// fit in a device buffer.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use metal_matrix::{MetalContext, Matrix};
+    ///
+    /// let context = MetalContext::new().unwrap();
+    /// let a = Matrix::with_data(2, 2, vec![1.0, 2.0, 3.0, 4.0]).unwrap();
+    /// let b = Matrix::identity(2);
+    ///
+    /// let results = context
+    ///     .batch(|recorder| {
+    ///         let a = recorder.upload(&a);
+    ///         let b = recorder.upload(&b);
+    ///         let product = recorder.matrix_multiply(&a, &b)?;
+    ///         recorder.matrix_add(&product, &a)
+    ///     })
+    ///     .unwrap();
+    ///
+    /// let sum = results.read(&results.output).unwrap();
+    /// assert_eq!(sum.data, vec![2.0, 4.0, 6.0, 8.0]);
+    /// ```
+    pub fn batch<T, F>(&self, record: F) -> Result<BatchResults<T>>
+    where
+        F: FnOnce(&mut CommandRecorder) -> Result<T>,
+    {
+        let mut recorder = CommandRecorder::new();
+        let output = record(&mut recorder)?;
+
+        let mut pipelines = HashMap::new();
+        for command in &recorder.commands {
+            if !pipelines.contains_key(&command.kernel) {
+                let kernel = command.kernel;
+                let pipeline = self.load_kernel(kernel.path, kernel.function)?;
+                pipelines.insert(kernel, pipeline);
+            }
+        }
+
+        // Batched kernels index with 32 bits
+        let limits = self.limits();
+        for &(rows, cols, _) in &recorder.buffers {
+            check_32bit(rows, cols)?;
+            limits.check_buffer(rows * cols)?;
+        }
+
+        let buffers: Vec<Buffer> = recorder
+            .buffers
+            .iter()
+            .map(|(rows, cols, data)| match data {
+                Some(data) if !data.is_empty() => self.acquire_buffer_with_data(data),
+                _ => self.acquire_buffer::<f32>((rows * cols).max(1)),
+            })
+            .collect();
+
+        let command_buffer = self.command_queue.new_command_buffer();
+        let encoder =
+            command_buffer.compute_command_encoder_with_dispatch_type(MTLDispatchType::Concurrent);
+        let mut metal_encoder = MetalEncoder {
+            context: self,
+            encoder,
+            buffers: &buffers,
+            pipelines: &pipelines,
+            kernel: None,
+        };
+        recorder.encode(&mut metal_encoder);
+        encoder.end_encoding();
+
+        command_buffer.commit();
+        command_buffer.wait_until_completed();
+        let results = BatchResults {
+            output,
+            batch: recorder.batch,
+            shapes: recorder.buffers.iter().map(|(r, c, _)| (*r, *c)).collect(),
+            buffers,
+            pool: Arc::clone(&self.buffer_pool),
+        };
+        if command_buffer.status() == MTLCommandBufferStatus::Error {
+            return Err(anyhow!("Batched command buffer failed"));
+        }
+
+        Ok(results)
+    }
+}
+
+/// Encodes recorded commands onto a Metal compute encoder.
+struct MetalEncoder<'a> {
+    context: &'a MetalContext,
+    encoder: &'a ComputeCommandEncoderRef,
+    buffers: &'a [Buffer],
+    pipelines: &'a HashMap<KernelRef, ComputePipelineState>,
+    kernel: Option<KernelRef>,
+}
+
+impl MetalEncoder<'_> {
+    fn dispatch_threads(&self, width: usize, height: usize) {
+        let kernel = self.kernel.expect("a kernel is set before dispatching");
+        let threadgroup_size =
+            self.context
+                .threadgroup_size(kernel.function, width, height, &self.pipelines[&kernel]);
+        let grid_size = MTLSize::new(width as u64, height as u64, 1);
+        self.encoder.dispatch_threads(grid_size, threadgroup_size);
+    }
+}
+
+impl CommandEncoder for MetalEncoder<'_> {
+    fn set_kernel(&mut self, kernel: KernelRef) {
+        self.encoder
+            .set_compute_pipeline_state(&self.pipelines[&kernel]);
+        self.kernel = Some(kernel);
+    }
+
+    fn set_argument(&mut self, index: u64, argument: Argument) {
+        match argument {
+            Argument::Buffer(id) => self.encoder.set_buffer(index, Some(&self.buffers[id]), 0),
+            Argument::U32(value) => set_constant(self.encoder, index, value),
+            Argument::F32(value) => set_constant(self.encoder, index, value),
+        }
+    }
+
+    fn dispatch(&mut self, grid: Grid) {
+        match grid {
+            Grid::Linear(count) if count > 0 => self.dispatch_threads(count, 1),
+            Grid::Planar { width, height } if width > 0 && height > 0 => {
+                self.dispatch_threads(width, height)
+            }
+            _ => {}
+        }
+    }
+
+    fn barrier(&mut self, buffers: &[usize]) {
+        let resources: Vec<&ResourceRef> = buffers
+            .iter()
+            .map(|&id| -> &ResourceRef { &self.buffers[id] })
+            .collect();
+        self.encoder.memory_barrier_with_resources(&resources);
+    }
+}
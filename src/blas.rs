@@ -0,0 +1,293 @@
+/*!
+ * # System BLAS/LAPACK
+ *
+ * This module runs the matrix operations through the system BLAS and LAPACK
+ * libraries (OpenBLAS on Linux, Accelerate on macOS). It is only compiled with the
+ * `blas` feature.
+ *
+ * The functions mirror those in `cpu` (same arguments, validation and error messages)
+ * and `BlasBackend` implements `Backend` with them, so any algorithm can use BLAS by
+ * being given a `BlasBackend`, or select it at runtime through `SelectedBackend`.
+ *
+ * `Matrix` data is row-major. Products are passed to `cblas_sgemm` with
+ * `CblasRowMajor`; LAPACK's `sgesv` only reads column-major data, so `solve` hands it
+ * transposed copies.
+ */
+
+use crate::backend::Backend;
+use crate::cpu;
+use crate::matrix::Matrix;
+use anyhow::{bail, Result};
+use std::os::raw::c_int;
+
+mod ffi {
+    use std::os::raw::c_int;
+
+    pub const CBLAS_ROW_MAJOR: c_int = 101;
+    pub const CBLAS_NO_TRANS: c_int = 111;
+
+    #[cfg_attr(target_os = "macos", link(name = "Accelerate", kind = "framework"))]
+    #[cfg_attr(not(target_os = "macos"), link(name = "openblas"))]
+    extern "C" {
+        #[allow(clippy::too_many_arguments)]
+        pub fn cblas_sgemm(
+            layout: c_int,
+            trans_a: c_int,
+            trans_b: c_int,
+            m: c_int,
+            n: c_int,
+            k: c_int,
+            alpha: f32,
+            a: *const f32,
+            lda: c_int,
+            b: *const f32,
+            ldb: c_int,
+            beta: f32,
+            c: *mut f32,
+            ldc: c_int,
+        );
+
+        pub fn cblas_saxpy(
+            n: c_int,
+            alpha: f32,
+            x: *const f32,
+            incx: c_int,
+            y: *mut f32,
+            incy: c_int,
+        );
+
+        pub fn cblas_sscal(n: c_int, alpha: f32, x: *mut f32, incx: c_int);
+
+        pub fn sgesv_(
+            n: *const c_int,
+            nrhs: *const c_int,
+            a: *mut f32,
+            lda: *const c_int,
+            ipiv: *mut c_int,
+            b: *mut f32,
+            ldb: *const c_int,
+            info: *mut c_int,
+        );
+    }
+}
+
+/// Converts a dimension to the integer type BLAS takes.
+fn blas_int(n: usize) -> Result<c_int> {
+    c_int::try_from(n).map_err(|_| anyhow::anyhow!("Matrix dimension {} too large for BLAS", n))
+}
+
+/// Performs matrix multiplication with `cblas_sgemm`: C = A * B
+///
+/// # Errors
+///
+/// Returns an error if the matrices have incompatible dimensions (a.cols != b.rows),
+/// or a dimension does not fit in a BLAS integer.
+pub fn matrix_multiply(a: &Matrix, b: &Matrix) -> Result<Matrix> {
+    if a.cols != b.rows {
+        bail!("Matrix dimensions incompatible for multiplication");
+    }
+
+    let (m, k, n) = (blas_int(a.rows)?, blas_int(a.cols)?, blas_int(b.cols)?);
+    let mut result = Matrix::new(a.rows, b.cols);
+    if m == 0 || n == 0 || k == 0 {
+        return Ok(result);
+    }
+
+    // SAFETY: a is m x k, b is k x n and result is m x n, all row-major and contiguous
+    unsafe {
+        ffi::cblas_sgemm(
+            ffi::CBLAS_ROW_MAJOR,
+            ffi::CBLAS_NO_TRANS,
+            ffi::CBLAS_NO_TRANS,
+            m,
+            n,
+            k,
+            1.0,
+            a.data.as_ptr(),
+            k,
+            b.data.as_ptr(),
+            n,
+            0.0,
+            result.data.as_mut_ptr(),
+            n,
+        );
+    }
+
+    Ok(result)
+}
+
+/// Computes `y + alpha * x` with `cblas_saxpy`.
+fn axpy(alpha: f32, x: &Matrix, y: &Matrix) -> Result<Matrix> {
+    let mut result = y.clone();
+    let len = blas_int(x.data.len())?;
+
+    // SAFETY: x and result both hold len contiguous elements
+    unsafe { ffi::cblas_saxpy(len, alpha, x.data.as_ptr(), 1, result.data.as_mut_ptr(), 1) };
+    Ok(result)
+}
+
+/// Performs matrix addition with `cblas_saxpy`: C = A + B
+///
+/// # Errors
+///
+/// Returns an error if the matrices have different dimensions.
+pub fn matrix_add(a: &Matrix, b: &Matrix) -> Result<Matrix> {
+    if a.rows != b.rows || a.cols != b.cols {
+        bail!("Matrix dimensions must match for addition");
+    }
+
+    axpy(1.0, b, a)
+}
+
+/// Performs matrix subtraction with `cblas_saxpy`: C = A - B
+///
+/// # Errors
+///
+/// Returns an error if the matrices have different dimensions.
+pub fn matrix_subtract(a: &Matrix, b: &Matrix) -> Result<Matrix> {
+    if a.rows != b.rows || a.cols != b.cols {
+        bail!("Matrix dimensions must match for subtraction");
+    }
+
+    axpy(-1.0, b, a)
+}
+
+/// Performs scalar multiplication with `cblas_sscal`: B = scalar * A
+///
+/// A zero scalar is multiplied element by element instead, because some BLAS builds
+/// (OpenBLAS among them) make `sscal` with zero store zeros over NaN and infinite
+/// entries, where the other backends give NaN.
+///
+/// # Errors
+///
+/// Returns an error if the matrix has too many elements for a BLAS integer.
+pub fn matrix_scalar_multiply(scalar: f32, a: &Matrix) -> Result<Matrix> {
+    let mut result = a.clone();
+    let len = blas_int(a.data.len())?;
+    if scalar == 0.0 {
+        result.data.iter_mut().for_each(|x| *x *= scalar);
+        return Ok(result);
+    }
+
+    // SAFETY: result holds len contiguous elements
+    unsafe { ffi::cblas_sscal(len, scalar, result.data.as_mut_ptr(), 1) };
+    Ok(result)
+}
+
+/// Solves the linear system A * X = B with LAPACK's `sgesv` (LU decomposition with
+/// partial pivoting).
+///
+/// # Errors
+///
+/// Returns an error if `a` is not square, `b` does not have n rows, or `a` is singular.
+///
+/// # Example
+///
+/// ```
+/// use metal_matrix::{blas, Matrix};
+///
+/// let a = Matrix::with_data(2, 2, vec![0.0, 2.0, 1.0, 1.0]).unwrap();
+/// let b = Matrix::vector(vec![4.0, 3.0]);
+///
+/// let x = blas::solve(&a, &b).unwrap();
+/// assert_eq!(x.data, vec![1.0, 2.0]);
+/// ```
+pub fn solve(a: &Matrix, b: &Matrix) -> Result<Matrix> {
+    if a.rows != a.cols {
+        bail!("Coefficient matrix must be square");
+    }
+    if a.rows != b.rows {
+        bail!("Matrix dimensions incompatible for solve");
+    }
+
+    let (n, nrhs) = (blas_int(a.rows)?, blas_int(b.cols)?);
+    if n == 0 || nrhs == 0 {
+        return Ok(b.clone());
+    }
+
+    // The row-major data of a transpose is the column-major data of the original
+    let mut lu = cpu::matrix_transpose(a)?;
+    let mut x = cpu::matrix_transpose(b)?;
+    let mut pivots = vec![0 as c_int; a.rows];
+    let mut info: c_int = 0;
+
+    // SAFETY: lu is n x n and x is n x nrhs, column-major with leading dimension n;
+    // pivots holds n entries
+    unsafe {
+        ffi::sgesv_(
+            &n,
+            &nrhs,
+            lu.data.as_mut_ptr(),
+            &n,
+            pivots.as_mut_ptr(),
+            x.data.as_mut_ptr(),
+            &n,
+            &mut info,
+        );
+    }
+
+    if info > 0 {
+        bail!("Matrix is singular");
+    }
+    if info < 0 {
+        bail!("Invalid argument {} to sgesv", -info);
+    }
+
+    cpu::matrix_transpose(&x)
+}
+
+/// Computes the inverse of a square matrix with LAPACK's `sgesv`.
+///
+/// # Errors
+///
+/// Returns an error if `a` is not square or is singular.
+pub fn inverse(a: &Matrix) -> Result<Matrix> {
+    solve(a, &Matrix::identity(a.rows))
+}
+
+/// Backend running every operation through the system BLAS and LAPACK.
+///
+/// Transposes have no BLAS routine and use the native implementation in `cpu`.
+///
+/// # Example
+///
+/// ```
+/// use metal_matrix::blas::BlasBackend;
+/// use metal_matrix::{Backend, Matrix};
+///
+/// let a = Matrix::with_data(2, 2, vec![1.0, 2.0, 3.0, 4.0]).unwrap();
+/// let result = BlasBackend.matrix_multiply(&a, &Matrix::identity(2)).unwrap();
+/// assert_eq!(result.data, a.data);
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BlasBackend;
+
+impl Backend for BlasBackend {
+    fn matrix_multiply(&self, a: &Matrix, b: &Matrix) -> Result<Matrix> {
+        matrix_multiply(a, b)
+    }
+
+    fn matrix_add(&self, a: &Matrix, b: &Matrix) -> Result<Matrix> {
+        matrix_add(a, b)
+    }
+
+    fn matrix_subtract(&self, a: &Matrix, b: &Matrix) -> Result<Matrix> {
+        matrix_subtract(a, b)
+    }
+
+    fn matrix_transpose(&self, a: &Matrix) -> Result<Matrix> {
+        cpu::matrix_transpose(a)
+    }
+
+    fn matrix_scalar_multiply(&self, scalar: f32, a: &Matrix) -> Result<Matrix> {
+        matrix_scalar_multiply(scalar, a)
+    }
+
+    fn solve(&self, a: &Matrix, b: &Matrix) -> Result<Matrix> {
+        solve(a, b)
+    }
+
+    fn inverse(&self, a: &Matrix) -> Result<Matrix> {
+        inverse(a)
+    }
+}
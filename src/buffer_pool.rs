@@ -0,0 +1,180 @@
+/*!
+ * # Buffer Pool
+ *
+ * This module provides `BufferPool`, which recycles GPU buffers between operations
+ * instead of allocating new ones for every input and output.
+ *
+ * Requests are rounded up to a power-of-two bucket size (at least `MIN_BUCKET_BYTES`),
+ * and a released buffer is kept in the bucket for its size until a later request of
+ * that bucket reuses it. The bytes held by idle buffers are capped; a released buffer
+ * that would exceed the cap is freed instead of kept.
+ *
+ * The pool is generic over the buffer type so the policy can be exercised without a
+ * GPU. `MetalContext` owns a `BufferPool<metal::Buffer>`.
+ *
+ * ```
+ * use metal_matrix::buffer_pool::{BufferPool, PoolBuffer};
+ *
+ * struct FakeBuffer(u64);
+ *
+ * impl PoolBuffer for FakeBuffer {
+ *     fn size(&self) -> u64 {
+ *         self.0
+ *     }
+ * }
+ *
+ * let mut pool = BufferPool::new(1 << 20);
+ * let buffer = pool.acquire(1000, FakeBuffer);
+ * assert_eq!(buffer.size(), 1024);
+ *
+ * pool.release(buffer);
+ * let reused = pool.acquire(600, FakeBuffer);
+ * assert_eq!(reused.size(), 1024);
+ *
+ * let stats = pool.stats();
+ * assert_eq!((stats.hits, stats.misses), (1, 1));
+ * ```
+ */
+
+use std::collections::BTreeMap;
+
+/// The smallest bucket size in bytes.
+pub const MIN_BUCKET_BYTES: u64 = 256;
+
+/// The default cap on bytes held by idle buffers (256 MiB).
+pub const DEFAULT_MAX_BYTES: u64 = 256 << 20;
+
+/// A buffer that can be held by a `BufferPool`.
+pub trait PoolBuffer {
+    /// Returns the size of the buffer in bytes.
+    fn size(&self) -> u64;
+}
+
+impl PoolBuffer for metal::Buffer {
+    fn size(&self) -> u64 {
+        self.length()
+    }
+}
+
+/// Counters describing how a `BufferPool` has been used.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PoolStats {
+    /// Requests served by a recycled buffer
+    pub hits: u64,
+
+    /// Requests that allocated a new buffer
+    pub misses: u64,
+
+    /// Bytes held by idle buffers in the pool
+    pub bytes_resident: u64,
+
+    /// Number of idle buffers in the pool
+    pub buffers_resident: usize,
+}
+
+/// A size-bucketed pool of reusable buffers.
+pub struct BufferPool<B> {
+    buckets: BTreeMap<u64, Vec<B>>,
+    max_bytes: u64,
+    stats: PoolStats,
+}
+
+impl<B: PoolBuffer> BufferPool<B> {
+    /// Creates an empty pool holding at most `max_bytes` of idle buffers.
+    pub fn new(max_bytes: u64) -> Self {
+        Self {
+            buckets: BTreeMap::new(),
+            max_bytes,
+            stats: PoolStats::default(),
+        }
+    }
+
+    /// Returns the bucket size used for a request of `bytes`.
+    pub fn bucket_size(bytes: u64) -> u64 {
+        bytes.max(MIN_BUCKET_BYTES).next_power_of_two()
+    }
+
+    /// Returns a buffer of at least `bytes`.
+    ///
+    /// An idle buffer from the matching bucket is reused if there is one; otherwise
+    /// `allocate` is called with the bucket size.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - Minimum size of the buffer
+    /// * `allocate` - Function that allocates a new buffer of the given size
+    pub fn acquire<F>(&mut self, bytes: u64, allocate: F) -> B
+    where
+        F: FnOnce(u64) -> B,
+    {
+        let bucket = Self::bucket_size(bytes);
+
+        if let Some(buffer) = self.buckets.get_mut(&bucket).and_then(Vec::pop) {
+            self.stats.hits += 1;
+            self.stats.bytes_resident -= buffer.size();
+            self.stats.buffers_resident -= 1;
+            return buffer;
+        }
+
+        self.stats.misses += 1;
+        allocate(bucket)
+    }
+
+    /// Returns a buffer to the pool for reuse.
+    ///
+    /// The buffer is freed instead if its size is not a bucket size (it was not
+    /// allocated by the pool) or if keeping it would exceed the cap.
+    pub fn release(&mut self, buffer: B) {
+        let size = buffer.size();
+        if Self::bucket_size(size) != size || self.stats.bytes_resident + size > self.max_bytes {
+            return;
+        }
+
+        self.stats.bytes_resident += size;
+        self.stats.buffers_resident += 1;
+        self.buckets.entry(size).or_default().push(buffer);
+    }
+
+    /// Frees all idle buffers.
+    pub fn trim(&mut self) {
+        self.buckets.clear();
+        self.stats.bytes_resident = 0;
+        self.stats.buffers_resident = 0;
+    }
+
+    /// Returns the cap on bytes held by idle buffers.
+    pub fn max_bytes(&self) -> u64 {
+        self.max_bytes
+    }
+
+    /// Sets the cap on bytes held by idle buffers, freeing the largest idle buffers
+    /// until the pool is within it.
+    pub fn set_max_bytes(&mut self, max_bytes: u64) {
+        self.max_bytes = max_bytes;
+
+        while self.stats.bytes_resident > max_bytes {
+            let mut largest = self
+                .buckets
+                .last_entry()
+                .expect("resident bytes imply a bucket");
+            if let Some(buffer) = largest.get_mut().pop() {
+                self.stats.bytes_resident -= buffer.size();
+                self.stats.buffers_resident -= 1;
+            }
+            if largest.get().is_empty() {
+                largest.remove();
+            }
+        }
+    }
+
+    /// Returns the pool's counters.
+    pub fn stats(&self) -> PoolStats {
+        self.stats
+    }
+}
+
+impl<B: PoolBuffer> Default for BufferPool<B> {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_BYTES)
+    }
+}
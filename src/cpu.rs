@@ -0,0 +1,486 @@
+/*!
+ * # CPU Reference Implementations
+ *
+ * This module provides straightforward CPU implementations of the matrix operations.
+ *
+ * They mirror the GPU operations in `operations` (same arguments minus the context,
+ * same validation and error messages) and serve as the reference the GPU results are
+ * checked against, as well as the implementation behind `CpuBackend`.
+ *
+ * Matrix multiplication is the exception to "straightforward": `matrix_multiply` is a
+ * cache-blocked, SIMD, multithreaded GEMM, while `matrix_multiply_naive` keeps the
+ * triple loop for comparison.
+ */
+
+mod gemm;
+
+use crate::matrix::Matrix;
+use crate::precision::{self, HalfMatrix, MixedMatrix, Precision};
+use crate::quantization::{self, QuantizedMatrix};
+use crate::sparse::CsrMatrix;
+use crate::triangular::{self, Diag, Side, Transpose, Uplo};
+use anyhow::Result;
+
+/// Performs matrix multiplication on the CPU: C = A * B
+///
+/// Uses a cache-blocked kernel with packed panels, an AVX2/FMA or NEON micro-kernel
+/// when the CPU supports one, and rayon threads over row blocks of the result.
+///
+/// # Arguments
+///
+/// * `a` - First matrix (M x K)
+/// * `b` - Second matrix (K x N)
+///
+/// # Returns
+///
+/// A `Result` containing the product matrix (M x N) or an error.
+///
+/// # Errors
+///
+/// Returns an error if the matrices have incompatible dimensions (a.cols != b.rows).
+///
+/// # Example
+///
+/// ```
+/// use metal_matrix::{cpu, Matrix};
+///
+/// let a = Matrix::with_data(2, 3, vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]).unwrap();
+/// let b = Matrix::with_data(3, 2, vec![7.0, 8.0, 9.0, 10.0, 11.0, 12.0]).unwrap();
+///
+/// let c = cpu::matrix_multiply(&a, &b).unwrap();
+/// assert_eq!(c.data, vec![58.0, 64.0, 139.0, 154.0]);
+/// ```
+pub fn matrix_multiply(a: &Matrix, b: &Matrix) -> Result<Matrix> {
+    if a.cols != b.rows {
+        anyhow::bail!("Matrix dimensions incompatible for multiplication");
+    }
+
+    Ok(gemm::multiply(a, b))
+}
+
+/// Performs matrix multiplication on the CPU with the naive triple loop: C = A * B
+///
+/// Kept as a baseline for benchmarks and tests of `matrix_multiply`.
+///
+/// # Errors
+///
+/// Returns an error if the matrices have incompatible dimensions (a.cols != b.rows).
+pub fn matrix_multiply_naive(a: &Matrix, b: &Matrix) -> Result<Matrix> {
+    if a.cols != b.rows {
+        anyhow::bail!("Matrix dimensions incompatible for multiplication");
+    }
+
+    let mut result = Matrix::new(a.rows, b.cols);
+    for row in 0..a.rows {
+        for col in 0..b.cols {
+            let mut sum = 0.0f32;
+            for i in 0..a.cols {
+                sum += a.get(row, i) * b.get(i, col);
+            }
+            result.set(row, col, sum);
+        }
+    }
+
+    Ok(result)
+}
+
+/// Performs mixed-precision matrix multiplication on the CPU: C = A * B
+///
+/// Converts the half-precision inputs to f32, accumulates each element in f32 in the
+/// same order as the GPU kernel and rounds it once to the output precision.
+///
+/// # Errors
+///
+/// Returns an error if the matrices have incompatible dimensions (a.cols != b.rows)
+/// or different formats.
+///
+/// # Example
+///
+/// ```
+/// use metal_matrix::precision::{HalfFormat, HalfMatrix, Precision};
+/// use metal_matrix::{cpu, Matrix};
+///
+/// let a = Matrix::with_data(1, 2, vec![1.0, 2.0]).unwrap();
+/// let b = Matrix::with_data(2, 1, vec![3.0, 4.0]).unwrap();
+/// let a = HalfMatrix::from_matrix(&a, HalfFormat::F16);
+/// let b = HalfMatrix::from_matrix(&b, HalfFormat::F16);
+///
+/// let c = cpu::mixed_matrix_multiply(&a, &b, Precision::F32).unwrap();
+/// assert_eq!(c.to_matrix().data, vec![11.0]);
+/// ```
+pub fn mixed_matrix_multiply(
+    a: &HalfMatrix,
+    b: &HalfMatrix,
+    output: Precision,
+) -> Result<MixedMatrix> {
+    precision::validate(a, b)?;
+
+    let product = matrix_multiply_naive(&a.to_matrix(), &b.to_matrix())?;
+    Ok(match output {
+        Precision::F32 => MixedMatrix::F32(product),
+        Precision::Half(format) => MixedMatrix::Half(HalfMatrix::from_matrix(&product, format)),
+    })
+}
+
+/// Performs matrix multiplication by quantized weights on the CPU: C = A * Wᵀ
+///
+/// Dequantizes the weights exactly as the GPU kernels do and accumulates each element
+/// in f32 in the same order.
+///
+/// # Errors
+///
+/// Returns an error if the matrices have incompatible dimensions (a.cols != w.cols)
+/// or the arrays of `w` do not match its shape and scheme.
+///
+/// # Example
+///
+/// ```
+/// use metal_matrix::quantization::{quantize, Scheme};
+/// use metal_matrix::{cpu, Matrix};
+///
+/// let x = Matrix::with_data(1, 2, vec![1.0, 2.0]).unwrap();
+/// let w = Matrix::with_data(2, 2, vec![-1.0, 14.0, 0.0, 7.5]).unwrap();
+/// let w = quantize(&w, Scheme::Int4Group(2)).unwrap();
+///
+/// // Both rows of w span 15 steps exactly, so they are quantized without error
+/// let y = cpu::quantized_matrix_multiply(&x, &w).unwrap();
+/// assert_eq!(y.data, vec![27.0, 15.0]);
+/// ```
+pub fn quantized_matrix_multiply(a: &Matrix, w: &QuantizedMatrix) -> Result<Matrix> {
+    quantization::validate(a, w)?;
+
+    let weights = quantization::dequantize(w);
+    let mut result = Matrix::new(a.rows, w.rows);
+    for row in 0..a.rows {
+        for col in 0..w.rows {
+            let mut sum = 0.0f32;
+            for i in 0..a.cols {
+                sum += a.get(row, i) * weights.get(col, i);
+            }
+            result.set(row, col, sum);
+        }
+    }
+
+    Ok(result)
+}
+
+/// Performs matrix addition on the CPU: C = A + B
+///
+/// # Errors
+///
+/// Returns an error if the matrices have different dimensions.
+pub fn matrix_add(a: &Matrix, b: &Matrix) -> Result<Matrix> {
+    if a.rows != b.rows || a.cols != b.cols {
+        anyhow::bail!("Matrix dimensions must match for addition");
+    }
+
+    let data = a.data.iter().zip(&b.data).map(|(x, y)| x + y).collect();
+    Matrix::with_data(a.rows, a.cols, data)
+}
+
+/// Performs matrix subtraction on the CPU: C = A - B
+///
+/// # Errors
+///
+/// Returns an error if the matrices have different dimensions.
+pub fn matrix_subtract(a: &Matrix, b: &Matrix) -> Result<Matrix> {
+    if a.rows != b.rows || a.cols != b.cols {
+        anyhow::bail!("Matrix dimensions must match for subtraction");
+    }
+
+    let data = a.data.iter().zip(&b.data).map(|(x, y)| x - y).collect();
+    Matrix::with_data(a.rows, a.cols, data)
+}
+
+/// Performs matrix transpose on the CPU: B = A^T
+pub fn matrix_transpose(a: &Matrix) -> Result<Matrix> {
+    let mut result = Matrix::new(a.cols, a.rows);
+    for row in 0..a.rows {
+        for col in 0..a.cols {
+            result.set(col, row, a.get(row, col));
+        }
+    }
+
+    Ok(result)
+}
+
+/// Performs scalar multiplication on the CPU: B = scalar * A
+pub fn matrix_scalar_multiply(scalar: f32, a: &Matrix) -> Result<Matrix> {
+    let data = a.data.iter().map(|x| scalar * x).collect();
+    Matrix::with_data(a.rows, a.cols, data)
+}
+
+/// Solves a triangular system by substitution on the CPU.
+///
+/// Reference implementation of `triangular::triangular_solve`: solves
+/// `op(T) * X = B` (`Side::Left`) or `X * op(T) = B` (`Side::Right`), one
+/// right-hand side at a time.
+///
+/// # Errors
+///
+/// Returns an error if `t` is not square, the dimensions of `t` and `b` are
+/// incompatible, or `t` has a zero on its diagonal (with `Diag::NonUnit`).
+pub fn triangular_solve(
+    t: &Matrix,
+    b: &Matrix,
+    uplo: Uplo,
+    trans: Transpose,
+    diag: Diag,
+    side: Side,
+) -> Result<Matrix> {
+    triangular::validate(t, b, side)?;
+    triangular::check_nonsingular(t, diag)?;
+
+    let n = t.rows;
+    let op = |i: usize, j: usize| triangular_element(t, uplo, trans, diag, i, j);
+    let op_lower = (uplo == Uplo::Lower) != (trans == Transpose::Trans);
+    let mut x = b.clone();
+
+    match side {
+        Side::Left => {
+            // op(T) * x_col = b_col for every column
+            let mut rhs = vec![0.0f32; n];
+            for col in 0..b.cols {
+                for (i, value) in rhs.iter_mut().enumerate() {
+                    *value = b.get(i, col);
+                }
+                substitute(n, op, op_lower, &mut rhs);
+                for (i, value) in rhs.iter().enumerate() {
+                    x.set(i, col, *value);
+                }
+            }
+        }
+        Side::Right => {
+            // x_row * op(T) = b_row  <=>  op(T)^T * x_row^T = b_row^T
+            for row in 0..b.rows {
+                let rhs = &mut x.data[row * n..(row + 1) * n];
+                substitute(n, |i, j| op(j, i), !op_lower, rhs);
+            }
+        }
+    }
+
+    Ok(x)
+}
+
+/// Multiplies by a triangular matrix on the CPU.
+///
+/// Reference implementation of `triangular::triangular_multiply`: computes
+/// `op(T) * B` (`Side::Left`) or `B * op(T)` (`Side::Right`), reading only the
+/// triangle of `t` selected by `uplo`.
+///
+/// # Errors
+///
+/// Returns an error if `t` is not square or the dimensions of `t` and `b` are incompatible.
+pub fn triangular_multiply(
+    t: &Matrix,
+    b: &Matrix,
+    uplo: Uplo,
+    trans: Transpose,
+    diag: Diag,
+    side: Side,
+) -> Result<Matrix> {
+    triangular::validate(t, b, side)?;
+
+    let n = t.rows;
+    let op = |i: usize, j: usize| triangular_element(t, uplo, trans, diag, i, j);
+    let mut result = Matrix::new(b.rows, b.cols);
+
+    for row in 0..b.rows {
+        for col in 0..b.cols {
+            let mut sum = 0.0f32;
+            for i in 0..n {
+                sum += match side {
+                    Side::Left => op(row, i) * b.get(i, col),
+                    Side::Right => b.get(row, i) * op(i, col),
+                };
+            }
+            result.set(row, col, sum);
+        }
+    }
+
+    Ok(result)
+}
+
+/// Element (i, j) of op(T), reading only the referenced triangle of `t`.
+fn triangular_element(
+    t: &Matrix,
+    uplo: Uplo,
+    trans: Transpose,
+    diag: Diag,
+    i: usize,
+    j: usize,
+) -> f32 {
+    let (row, col) = match trans {
+        Transpose::NoTrans => (i, j),
+        Transpose::Trans => (j, i),
+    };
+
+    if row == col {
+        return match diag {
+            Diag::Unit => 1.0,
+            Diag::NonUnit => t.get(row, col),
+        };
+    }
+
+    let referenced = match uplo {
+        Uplo::Lower => col < row,
+        Uplo::Upper => col > row,
+    };
+    if referenced {
+        t.get(row, col)
+    } else {
+        0.0
+    }
+}
+
+/// Solves `A * x = rhs` in place for an n×n triangular `A` given element-wise.
+fn substitute(n: usize, a: impl Fn(usize, usize) -> f32, lower: bool, rhs: &mut [f32]) {
+    if lower {
+        for i in 0..n {
+            let mut sum = rhs[i];
+            for (j, value) in rhs.iter().enumerate().take(i) {
+                sum -= a(i, j) * value;
+            }
+            rhs[i] = sum / a(i, i);
+        }
+    } else {
+        for i in (0..n).rev() {
+            let mut sum = rhs[i];
+            for (j, value) in rhs.iter().enumerate().skip(i + 1) {
+                sum -= a(i, j) * value;
+            }
+            rhs[i] = sum / a(i, i);
+        }
+    }
+}
+
+/// Solves the linear system A * X = B with LU decomposition and partial pivoting.
+///
+/// # Arguments
+///
+/// * `a` - The coefficient matrix (n × n)
+/// * `b` - The right-hand sides (n × r)
+///
+/// # Returns
+///
+/// A `Result` containing the solution X (n × r) or an error.
+///
+/// # Errors
+///
+/// Returns an error if `a` is not square, `b` does not have n rows, or `a` is singular.
+///
+/// # Example
+///
+/// ```
+/// use metal_matrix::{cpu, Matrix};
+///
+/// let a = Matrix::with_data(2, 2, vec![0.0, 2.0, 1.0, 1.0]).unwrap();
+/// let b = Matrix::vector(vec![4.0, 3.0]);
+///
+/// let x = cpu::solve(&a, &b).unwrap();
+/// assert_eq!(x.data, vec![1.0, 2.0]);
+/// ```
+pub fn solve(a: &Matrix, b: &Matrix) -> Result<Matrix> {
+    if a.rows != a.cols {
+        anyhow::bail!("Coefficient matrix must be square");
+    }
+    if a.rows != b.rows {
+        anyhow::bail!("Matrix dimensions incompatible for solve");
+    }
+
+    let n = a.rows;
+    let mut lu = a.clone();
+    let mut x = b.clone();
+
+    for k in 0..n {
+        // Partial pivoting: bring the largest remaining entry of column k to the diagonal
+        let pivot_row = (k..n)
+            .max_by(|&i, &j| lu.get(i, k).abs().total_cmp(&lu.get(j, k).abs()))
+            .unwrap_or(k);
+        if lu.get(pivot_row, k) == 0.0 {
+            anyhow::bail!("Matrix is singular");
+        }
+        if pivot_row != k {
+            swap_rows(&mut lu, k, pivot_row);
+            swap_rows(&mut x, k, pivot_row);
+        }
+
+        let pivot = lu.get(k, k);
+        for i in k + 1..n {
+            let factor = lu.get(i, k) / pivot;
+            lu.set(i, k, factor);
+            for j in k + 1..n {
+                lu.set(i, j, lu.get(i, j) - factor * lu.get(k, j));
+            }
+        }
+    }
+
+    let y = triangular_solve(
+        &lu,
+        &x,
+        Uplo::Lower,
+        Transpose::NoTrans,
+        Diag::Unit,
+        Side::Left,
+    )?;
+    triangular_solve(
+        &lu,
+        &y,
+        Uplo::Upper,
+        Transpose::NoTrans,
+        Diag::NonUnit,
+        Side::Left,
+    )
+}
+
+/// Computes the inverse of a square matrix with LU decomposition and partial pivoting.
+///
+/// # Errors
+///
+/// Returns an error if `a` is not square or is singular.
+pub fn inverse(a: &Matrix) -> Result<Matrix> {
+    solve(a, &Matrix::identity(a.rows))
+}
+
+fn swap_rows(m: &mut Matrix, i: usize, j: usize) {
+    for col in 0..m.cols {
+        m.data.swap(i * m.cols + col, j * m.cols + col);
+    }
+}
+
+/// Performs sparse matrix-vector multiplication on the CPU: y = A * x
+///
+/// # Errors
+///
+/// Returns an error if `x` is not an (a.cols)×1 vector.
+pub fn spmv(a: &CsrMatrix, x: &Matrix) -> Result<Matrix> {
+    if x.rows != a.cols || x.cols != 1 {
+        anyhow::bail!("Matrix dimensions incompatible for sparse multiplication");
+    }
+
+    spmm(a, x)
+}
+
+/// Performs sparse-dense matrix multiplication on the CPU: C = A * B
+///
+/// # Errors
+///
+/// Returns an error if the matrices have incompatible dimensions (a.cols != b.rows).
+pub fn spmm(a: &CsrMatrix, b: &Matrix) -> Result<Matrix> {
+    if a.cols != b.rows {
+        anyhow::bail!("Matrix dimensions incompatible for sparse multiplication");
+    }
+
+    let mut result = Matrix::new(a.rows, b.cols);
+    for row in 0..a.rows {
+        for i in a.row_range(row) {
+            let k = a.col_indices[i] as usize;
+            for col in 0..b.cols {
+                let value = result.get(row, col) + a.values[i] * b.get(k, col);
+                result.set(row, col, value);
+            }
+        }
+    }
+
+    Ok(result)
+}
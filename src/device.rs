@@ -0,0 +1,132 @@
+/*!
+ * # Device Selection
+ *
+ * This module describes the Metal devices available on the system and chooses
+ * between them.
+ *
+ * `DeviceInfo` is a plain description of a device, and `DeviceSelector` picks one
+ * from a list of them by name, registry id or preference. `MetalContext::list_devices`
+ * and `MetalContext::with_device` apply these to the real devices; because the
+ * selection works on plain data it can also be used on hand-written device lists.
+ *
+ * ```
+ * use metal_matrix::device::{DeviceInfo, DeviceSelector};
+ *
+ * let integrated = DeviceInfo {
+ *     name: "Intel UHD Graphics 630".to_string(),
+ *     registry_id: 1,
+ *     low_power: true,
+ *     headless: false,
+ *     removable: false,
+ *     recommended_working_set_size: 1 << 30,
+ *     unified_memory: true,
+ * };
+ * let discrete = DeviceInfo {
+ *     name: "AMD Radeon Pro W6800X".to_string(),
+ *     registry_id: 2,
+ *     low_power: false,
+ *     recommended_working_set_size: 32 << 30,
+ *     unified_memory: false,
+ *     ..integrated.clone()
+ * };
+ * let devices = [integrated, discrete];
+ *
+ * assert_eq!(DeviceSelector::HighestMemory.select(&devices), Some(1));
+ * assert_eq!(DeviceSelector::LowPower.select(&devices), Some(0));
+ * assert_eq!(DeviceSelector::Name("radeon".to_string()).select(&devices), Some(1));
+ * ```
+ */
+
+use metal::Device;
+
+/// A description of a Metal device.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DeviceInfo {
+    /// The device name, e.g. "Apple M2 Max"
+    pub name: String,
+
+    /// The IORegistry id, stable across processes for the same device
+    pub registry_id: u64,
+
+    /// Whether the device is a low-power (integrated) GPU
+    pub low_power: bool,
+
+    /// Whether the device has no attached displays
+    pub headless: bool,
+
+    /// Whether the device can be removed, e.g. an eGPU
+    pub removable: bool,
+
+    /// The approximate number of bytes the device can use without hurting performance
+    pub recommended_working_set_size: u64,
+
+    /// Whether the device shares memory with the CPU
+    pub unified_memory: bool,
+}
+
+impl DeviceInfo {
+    /// Describes a Metal device.
+    pub fn from_device(device: &Device) -> Self {
+        Self {
+            name: device.name().to_string(),
+            registry_id: device.registry_id(),
+            low_power: device.is_low_power(),
+            headless: device.is_headless(),
+            removable: device.is_removable(),
+            recommended_working_set_size: device.recommended_max_working_set_size(),
+            unified_memory: device.has_unified_memory(),
+        }
+    }
+}
+
+/// Chooses a device from the devices available.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DeviceSelector {
+    /// The device with this name, ignoring case. An exact match is preferred;
+    /// otherwise the first device whose name contains it is chosen.
+    Name(String),
+
+    /// The device with this registry id
+    RegistryId(u64),
+
+    /// The device with the largest recommended working set
+    HighestMemory,
+
+    /// The first low-power device, or the first device if none is low-power
+    LowPower,
+
+    /// The first device that is not low-power, or the first device if all are
+    HighPerformance,
+}
+
+impl DeviceSelector {
+    /// Returns the index of the selected device, or `None` if no device matches.
+    ///
+    /// Ties are broken in favour of the earlier device.
+    pub fn select(&self, devices: &[DeviceInfo]) -> Option<usize> {
+        let first = (!devices.is_empty()).then_some(0);
+
+        match self {
+            DeviceSelector::Name(name) => {
+                let name = name.to_lowercase();
+                devices
+                    .iter()
+                    .position(|d| d.name.to_lowercase() == name)
+                    .or_else(|| {
+                        devices
+                            .iter()
+                            .position(|d| d.name.to_lowercase().contains(&name))
+                    })
+            }
+            DeviceSelector::RegistryId(id) => devices.iter().position(|d| d.registry_id == *id),
+            DeviceSelector::HighestMemory => devices
+                .iter()
+                .enumerate()
+                .rev()
+                .max_by_key(|(_, d)| d.recommended_working_set_size)
+                .map(|(i, _)| i),
+            DeviceSelector::LowPower => devices.iter().position(|d| d.low_power).or(first),
+            DeviceSelector::HighPerformance => devices.iter().position(|d| !d.low_power).or(first),
+        }
+    }
+}
@@ -0,0 +1,455 @@
+/*!
+ * # Automatic Dispatch
+ *
+ * This module decides whether an operation should run on the CPU or on the GPU.
+ *
+ * Launching a GPU operation has a fixed cost (encoding, committing and waiting for a
+ * command buffer) plus the cost of copying the operands into and out of GPU buffers,
+ * so small problems finish sooner on the CPU. `CostModel` estimates both times from
+ * an operation's `OperationCost` (flop count, element count and bytes transferred)
+ * and picks the faster target. The estimate is a pure function of the model and the
+ * cost, so routing is deterministic.
+ *
+ * `AutoBackend` implements `Backend` by routing each operation with an
+ * `ExecutionPolicy`, so the higher-level algorithms can use it too. The default
+ * model's rates are conservative guesses; `CostModel::calibrate` measures them on the
+ * current machine, and `CostModel::load_or_calibrate` does so once and keeps the
+ * result in a JSON file.
+ *
+ * ```
+ * use metal_matrix::dispatch::{CostModel, OperationCost, Target};
+ *
+ * let model = CostModel::default();
+ * assert_eq!(model.route(&OperationCost::matrix_multiply(4, 4, 4)), Target::Cpu);
+ * assert_eq!(model.route(&OperationCost::matrix_multiply(1024, 1024, 1024)), Target::Gpu);
+ * ```
+ */
+
+use crate::backend::Backend;
+use crate::cpu;
+use crate::matrix::Matrix;
+use crate::operations;
+use crate::MetalContext;
+use anyhow::{anyhow, bail, Context, Result};
+use serde_json::{json, Value};
+use std::fs;
+use std::path::Path;
+use std::time::Instant;
+
+/// Where an operation runs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Target {
+    /// The reference implementations in `cpu`
+    Cpu,
+    /// The Metal kernels in `operations`
+    Gpu,
+}
+
+/// The work done by one operation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OperationCost {
+    /// Number of elements in the result
+    pub elements: u64,
+
+    /// Number of floating-point operations
+    pub flops: u64,
+
+    /// Bytes copied to and from the GPU if the operation runs there
+    pub transfer_bytes: u64,
+}
+
+impl OperationCost {
+    /// The cost of multiplying an (m × k) matrix by a (k × n) matrix.
+    pub fn matrix_multiply(m: usize, n: usize, k: usize) -> Self {
+        let (m, n, k) = (m as u64, n as u64, k as u64);
+        Self {
+            elements: m * n,
+            flops: 2 * m * n * k,
+            transfer_bytes: 4 * (m * k + k * n + m * n),
+        }
+    }
+
+    /// The cost of an element-wise operation on `inputs` matrices of `elements` each.
+    pub fn elementwise(elements: usize, inputs: usize) -> Self {
+        let elements = elements as u64;
+        Self {
+            elements,
+            flops: elements,
+            transfer_bytes: 4 * elements * (inputs as u64 + 1),
+        }
+    }
+}
+
+/// Estimates CPU and GPU run times to choose where an operation runs.
+///
+/// Times are in microseconds:
+/// - CPU: `flops / cpu_flops_per_us`
+/// - GPU: `gpu_overhead_us + transfer_bytes / transfer_bytes_per_us + flops / gpu_flops_per_us`
+///
+/// Operations producing fewer than `min_gpu_elements` elements always run on the CPU.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CostModel {
+    /// Fixed cost of one GPU operation in microseconds
+    pub gpu_overhead_us: f64,
+
+    /// GPU throughput in floating-point operations per microsecond
+    pub gpu_flops_per_us: f64,
+
+    /// CPU throughput in floating-point operations per microsecond
+    pub cpu_flops_per_us: f64,
+
+    /// Copy bandwidth between host memory and GPU buffers in bytes per microsecond
+    pub transfer_bytes_per_us: f64,
+
+    /// Result size below which the GPU is never used
+    pub min_gpu_elements: u64,
+}
+
+impl Default for CostModel {
+    fn default() -> Self {
+        Self {
+            gpu_overhead_us: 500.0,
+            gpu_flops_per_us: 100_000.0,
+            cpu_flops_per_us: 1_000.0,
+            transfer_bytes_per_us: 10_000.0,
+            min_gpu_elements: 4096,
+        }
+    }
+}
+
+/// Format version written to cost model files.
+const MODEL_VERSION: u64 = 1;
+
+/// Rate fields of the cost model file.
+const RATES: [&str; 4] = [
+    "gpu_overhead_us",
+    "gpu_flops_per_us",
+    "cpu_flops_per_us",
+    "transfer_bytes_per_us",
+];
+
+impl CostModel {
+    /// Estimated CPU time for an operation in microseconds.
+    pub fn cpu_time_us(&self, cost: &OperationCost) -> f64 {
+        cost.flops as f64 / self.cpu_flops_per_us
+    }
+
+    /// Estimated GPU time for an operation in microseconds.
+    pub fn gpu_time_us(&self, cost: &OperationCost) -> f64 {
+        self.gpu_overhead_us
+            + cost.transfer_bytes as f64 / self.transfer_bytes_per_us
+            + cost.flops as f64 / self.gpu_flops_per_us
+    }
+
+    /// Chooses where an operation runs. Ties go to the CPU.
+    pub fn route(&self, cost: &OperationCost) -> Target {
+        if cost.elements >= self.min_gpu_elements && self.gpu_time_us(cost) < self.cpu_time_us(cost)
+        {
+            Target::Gpu
+        } else {
+            Target::Cpu
+        }
+    }
+
+    /// Measures the model's rates on this machine with a short micro-benchmark.
+    ///
+    /// `min_gpu_elements` keeps its default value.
+    ///
+    /// # Arguments
+    ///
+    /// * `context` - The Metal context to measure
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the measured model or an error.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a GPU operation fails.
+    pub fn calibrate(context: &MetalContext) -> Result<Self> {
+        const RUNS: usize = 5;
+
+        // Fixed cost: a 1 × 1 addition is all overhead
+        let one = Matrix::with_data(1, 1, vec![1.0])?;
+        let gpu_overhead_us = fastest(RUNS, || {
+            operations::matrix_add(context, &one, &one).map(|_| ())
+        })?;
+
+        // Copy bandwidth: filling a 16 MiB buffer
+        let data = vec![1.0f32; 4 << 20];
+        let transfer_bytes_per_us = (data.len() * 4) as f64
+            / fastest(RUNS, || {
+                context.recycle_buffer(context.acquire_buffer_with_data(&data));
+                Ok(())
+            })?;
+
+        // Throughput: a product large enough for compute to dominate on each side
+        let gpu_size = 512;
+        let a = Matrix::new(gpu_size, gpu_size);
+        let gpu_us = fastest(RUNS, || {
+            operations::matrix_multiply(context, &a, &a).map(|_| ())
+        })?;
+        let gpu_cost = OperationCost::matrix_multiply(gpu_size, gpu_size, gpu_size);
+        let gpu_compute_us =
+            (gpu_us - gpu_overhead_us - gpu_cost.transfer_bytes as f64 / transfer_bytes_per_us)
+                .max(1.0);
+
+        let cpu_size = 128;
+        let b = Matrix::new(cpu_size, cpu_size);
+        let cpu_us = fastest(RUNS, || cpu::matrix_multiply(&b, &b).map(|_| ()))?;
+        let cpu_cost = OperationCost::matrix_multiply(cpu_size, cpu_size, cpu_size);
+
+        Ok(Self {
+            gpu_overhead_us,
+            gpu_flops_per_us: gpu_cost.flops as f64 / gpu_compute_us,
+            cpu_flops_per_us: cpu_cost.flops as f64 / cpu_us,
+            transfer_bytes_per_us,
+            ..Self::default()
+        })
+    }
+
+    /// Loads a model saved with `save`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or is not a valid model.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read cost model: {}", path.display()))?;
+        Self::from_json(&text)
+    }
+
+    /// Writes the model to a file readable by `load`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be written.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        fs::write(path, self.to_json())
+            .with_context(|| format!("Failed to write cost model: {}", path.display()))
+    }
+
+    /// Loads the model from `path`, or calibrates it and saves it there if the file
+    /// does not exist yet.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file exists but is invalid, calibration fails, or the
+    /// file cannot be written.
+    pub fn load_or_calibrate<P: AsRef<Path>>(context: &MetalContext, path: P) -> Result<Self> {
+        let path = path.as_ref();
+        if path.exists() {
+            return Self::load(path);
+        }
+
+        let model = Self::calibrate(context)?;
+        model.save(path)?;
+        Ok(model)
+    }
+
+    /// Serializes the model as JSON, in the same style as tuning profiles.
+    pub fn to_json(&self) -> String {
+        let model = json!({
+            "version": MODEL_VERSION,
+            "gpu_overhead_us": self.gpu_overhead_us,
+            "gpu_flops_per_us": self.gpu_flops_per_us,
+            "cpu_flops_per_us": self.cpu_flops_per_us,
+            "transfer_bytes_per_us": self.transfer_bytes_per_us,
+            "min_gpu_elements": self.min_gpu_elements,
+        });
+        serde_json::to_string_pretty(&model).expect("JSON values always serialize")
+    }
+
+    /// Parses a model written by `to_json`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the text is not valid JSON, has a different version, or a
+    /// field is missing, unknown or out of range. Throughputs must be positive,
+    /// `gpu_overhead_us` non-negative and `min_gpu_elements` a non-negative integer.
+    pub fn from_json(text: &str) -> Result<Self> {
+        let model: Value = serde_json::from_str(text).context("Invalid cost model")?;
+        let fields = model
+            .as_object()
+            .ok_or_else(|| anyhow!("Invalid cost model: expected a JSON object"))?;
+
+        let version = fields.get("version").and_then(Value::as_u64);
+        if version != Some(MODEL_VERSION) {
+            bail!(
+                "Invalid cost model: unsupported version {}",
+                fields.get("version").unwrap_or(&Value::Null)
+            );
+        }
+        if let Some(key) = fields.keys().find(|key| {
+            *key != "version" && *key != "min_gpu_elements" && !RATES.contains(&key.as_str())
+        }) {
+            bail!("Invalid cost model: unknown key '{}'", key);
+        }
+
+        let field = |key: &str| {
+            fields
+                .get(key)
+                .ok_or_else(|| anyhow!("Invalid cost model: missing '{}'", key))
+        };
+        let mut rates = [0.0; 4];
+        for (rate, key) in rates.iter_mut().zip(RATES) {
+            // The overhead may be zero, but the throughputs are divisors
+            let overhead = key == "gpu_overhead_us";
+            *rate = field(key)?
+                .as_f64()
+                .filter(|&value| value.is_finite() && (value > 0.0 || overhead && value == 0.0))
+                .ok_or_else(|| {
+                    anyhow!(
+                        "Invalid cost model: '{}' must be a finite {} number",
+                        key,
+                        if overhead { "non-negative" } else { "positive" }
+                    )
+                })?;
+        }
+        let min_gpu_elements = field("min_gpu_elements")?.as_u64().ok_or_else(|| {
+            anyhow!("Invalid cost model: 'min_gpu_elements' must be a non-negative integer")
+        })?;
+
+        Ok(Self {
+            gpu_overhead_us: rates[0],
+            gpu_flops_per_us: rates[1],
+            cpu_flops_per_us: rates[2],
+            transfer_bytes_per_us: rates[3],
+            min_gpu_elements,
+        })
+    }
+}
+
+/// Returns the fastest of `runs` timed runs in microseconds.
+fn fastest<F>(runs: usize, mut run: F) -> Result<f64>
+where
+    F: FnMut() -> Result<()>,
+{
+    let mut best = f64::INFINITY;
+    for _ in 0..runs {
+        let start = Instant::now();
+        run()?;
+        best = best.min(start.elapsed().as_secs_f64() * 1e6);
+    }
+    Ok(best.max(f64::MIN_POSITIVE))
+}
+
+/// How operations choose between the CPU and the GPU.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ExecutionPolicy {
+    /// Always run on the CPU
+    Cpu,
+    /// Always run on the GPU
+    Gpu,
+    /// Run wherever the cost model estimates is faster
+    Auto(CostModel),
+}
+
+impl ExecutionPolicy {
+    /// Chooses where an operation runs.
+    pub fn route(&self, cost: &OperationCost) -> Target {
+        match self {
+            ExecutionPolicy::Cpu => Target::Cpu,
+            ExecutionPolicy::Gpu => Target::Gpu,
+            ExecutionPolicy::Auto(model) => model.route(cost),
+        }
+    }
+}
+
+impl Default for ExecutionPolicy {
+    fn default() -> Self {
+        ExecutionPolicy::Auto(CostModel::default())
+    }
+}
+
+/// Backend that runs each operation on the CPU or the GPU according to a policy.
+///
+/// # Example
+///
+/// ```no_run
+/// use metal_matrix::dispatch::AutoBackend;
+/// use metal_matrix::{Backend, MetalContext, Matrix};
+///
+/// let context = MetalContext::new().unwrap();
+/// let backend = AutoBackend::new(&context);
+///
+/// // Small enough to run on the CPU
+/// let small = backend.matrix_add(&Matrix::identity(4), &Matrix::identity(4)).unwrap();
+///
+/// // Large enough to run on the GPU
+/// let large = Matrix::identity(1024);
+/// let product = backend.matrix_multiply(&large, &large).unwrap();
+/// ```
+pub struct AutoBackend<'a> {
+    /// The Metal context used for operations routed to the GPU
+    pub context: &'a MetalContext,
+
+    /// How operations are routed
+    pub policy: ExecutionPolicy,
+}
+
+impl<'a> AutoBackend<'a> {
+    /// Creates a backend with the default `Auto` policy.
+    pub fn new(context: &'a MetalContext) -> Self {
+        Self::with_policy(context, ExecutionPolicy::default())
+    }
+
+    /// Creates a backend with the given policy.
+    pub fn with_policy(context: &'a MetalContext, policy: ExecutionPolicy) -> Self {
+        Self { context, policy }
+    }
+}
+
+impl Backend for AutoBackend<'_> {
+    fn matrix_multiply(&self, a: &Matrix, b: &Matrix) -> Result<Matrix> {
+        match self
+            .policy
+            .route(&OperationCost::matrix_multiply(a.rows, b.cols, a.cols))
+        {
+            Target::Cpu => cpu::matrix_multiply(a, b),
+            Target::Gpu => operations::matrix_multiply(self.context, a, b),
+        }
+    }
+
+    fn matrix_add(&self, a: &Matrix, b: &Matrix) -> Result<Matrix> {
+        match self
+            .policy
+            .route(&OperationCost::elementwise(a.data.len(), 2))
+        {
+            Target::Cpu => cpu::matrix_add(a, b),
+            Target::Gpu => operations::matrix_add(self.context, a, b),
+        }
+    }
+
+    fn matrix_subtract(&self, a: &Matrix, b: &Matrix) -> Result<Matrix> {
+        match self
+            .policy
+            .route(&OperationCost::elementwise(a.data.len(), 2))
+        {
+            Target::Cpu => cpu::matrix_subtract(a, b),
+            Target::Gpu => operations::matrix_subtract(self.context, a, b),
+        }
+    }
+
+    fn matrix_transpose(&self, a: &Matrix) -> Result<Matrix> {
+        match self
+            .policy
+            .route(&OperationCost::elementwise(a.data.len(), 1))
+        {
+            Target::Cpu => cpu::matrix_transpose(a),
+            Target::Gpu => operations::matrix_transpose(self.context, a),
+        }
+    }
+
+    fn matrix_scalar_multiply(&self, scalar: f32, a: &Matrix) -> Result<Matrix> {
+        match self
+            .policy
+            .route(&OperationCost::elementwise(a.data.len(), 1))
+        {
+            Target::Cpu => cpu::matrix_scalar_multiply(scalar, a),
+            Target::Gpu => operations::matrix_scalar_multiply(self.context, scalar, a),
+        }
+    }
+}
@@ -0,0 +1,291 @@
+/*!
+ * # Delimited Text
+ *
+ * This module reads and writes matrices as CSV or other delimited text, such as
+ * spreadsheet exports.
+ *
+ * Files are read one line at a time, so only the parsed values of a large file are
+ * held in memory. Blank lines and a leading byte order mark are ignored, cells may be
+ * double-quoted (a quoted cell can contain the delimiter, and a quoted number can
+ * group its digits with commas, e.g. `"1,234.5"`), surrounding whitespace is removed
+ * from cells, and every row must have the same number of cells. Errors report the
+ * 1-based line and column of the offending cell.
+ *
+ * `NaN` cells count as missing values, so a matrix holding NaN that is written with
+ * `to_csv` only reads back with `MissingValues::Nan`. Infinities are written as `inf`
+ * and `-inf`, which read back as values.
+ *
+ * Text is a lossy exchange format for floating point values unless the default
+ * shortest round-trip formatting is used when writing; prefer `npy` for exchanging
+ * arrays with Python.
+ */
+
+use crate::matrix::Matrix;
+use anyhow::{anyhow, bail, Result};
+use std::io::{BufRead, Write};
+
+/// How empty cells and `NaN`/`NA` markers are handled when reading.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MissingValues {
+    /// Reject the file with an error naming the cell
+    Error,
+    /// Read missing cells as `f32::NAN`
+    Nan,
+    /// Read missing cells as the given value
+    Fill(f32),
+}
+
+/// Options for reading and writing delimited text.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CsvOptions {
+    /// Cell separator (default `,`)
+    pub delimiter: char,
+    /// Whether the first line is a header row; it is skipped when reading, and a
+    /// header of column numbers is written when writing (default `false`)
+    pub has_header: bool,
+    /// Handling of empty and `NaN`/`NA` cells when reading (default `Error`, which
+    /// also rejects the `NaN` cells `to_csv` writes for NaN elements)
+    pub missing: MissingValues,
+    /// Digits after the decimal point when writing, or `None` for the shortest text
+    /// that reads back to the same value (default `None`)
+    pub precision: Option<usize>,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: ',',
+            has_header: false,
+            missing: MissingValues::Error,
+            precision: None,
+        }
+    }
+}
+
+impl Matrix {
+    /// Reads a matrix from CSV or other delimited text.
+    ///
+    /// Each non-blank line is one row of the matrix.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - Source of the text
+    /// * `options` - Delimiter, header and missing value handling
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the matrix or an error.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error, including the line and column, if a cell is not a number,
+    /// a cell is missing and `options.missing` is `Error`, or a row has a different
+    /// number of cells than the first.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use metal_matrix::io::csv::{CsvOptions, MissingValues};
+    /// use metal_matrix::Matrix;
+    ///
+    /// let text = "x;y\n1.5;2\n3;\n";
+    /// let options = CsvOptions {
+    ///     delimiter: ';',
+    ///     has_header: true,
+    ///     missing: MissingValues::Fill(0.0),
+    ///     ..CsvOptions::default()
+    /// };
+    ///
+    /// let matrix = Matrix::from_csv(text.as_bytes(), &options).unwrap();
+    /// assert_eq!((matrix.rows, matrix.cols), (2, 2));
+    /// assert_eq!(matrix.data, vec![1.5, 2.0, 3.0, 0.0]);
+    /// ```
+    pub fn from_csv<R: BufRead>(mut reader: R, options: &CsvOptions) -> Result<Matrix> {
+        let mut data = Vec::new();
+        let mut cols = 0;
+        let mut rows = 0;
+        let mut line = String::new();
+        let mut line_number = 0;
+
+        loop {
+            line.clear();
+            if reader.read_line(&mut line)? == 0 {
+                break;
+            }
+            line_number += 1;
+
+            let mut text = line.trim_end_matches(['\n', '\r']);
+            if line_number == 1 {
+                text = text.strip_prefix('\u{feff}').unwrap_or(text);
+            }
+            if options.has_header && line_number == 1 {
+                continue;
+            }
+            if text.trim().is_empty() {
+                continue;
+            }
+
+            let start = data.len();
+            for (index, cell) in split_cells(text, options.delimiter).iter().enumerate() {
+                let value = parse_cell(cell, options.missing).map_err(|e| {
+                    anyhow!("CSV line {}, column {}: {}", line_number, index + 1, e)
+                })?;
+                data.push(value);
+            }
+
+            let count = data.len() - start;
+            if rows == 0 {
+                cols = count;
+            } else if count != cols {
+                bail!(
+                    "CSV line {}, column {}: expected {} columns, found {}",
+                    line_number,
+                    count.min(cols) + 1,
+                    cols,
+                    count
+                );
+            }
+            rows += 1;
+        }
+
+        Matrix::with_data(rows, cols, data)
+    }
+
+    /// Writes the matrix as CSV or other delimited text, one row per line.
+    ///
+    /// NaN elements are written as `NaN`, which `from_csv` treats as a missing value,
+    /// so reading them back needs `MissingValues::Nan`.
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - Destination of the text
+    /// * `options` - Delimiter, header and precision
+    ///
+    /// # Returns
+    ///
+    /// A `Result` indicating success or an I/O error.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use metal_matrix::io::csv::CsvOptions;
+    /// use metal_matrix::Matrix;
+    ///
+    /// let matrix = Matrix::with_data(2, 2, vec![1.0, 0.25, -3.0, 1.0 / 3.0]).unwrap();
+    /// let options = CsvOptions {
+    ///     precision: Some(2),
+    ///     ..CsvOptions::default()
+    /// };
+    ///
+    /// let mut text = Vec::new();
+    /// matrix.to_csv(&mut text, &options).unwrap();
+    /// assert_eq!(String::from_utf8(text).unwrap(), "1.00,0.25\n-3.00,0.33\n");
+    /// ```
+    pub fn to_csv<W: Write>(&self, mut writer: W, options: &CsvOptions) -> Result<()> {
+        let mut delimiter = [0u8; 4];
+        let delimiter = options.delimiter.encode_utf8(&mut delimiter).as_bytes();
+
+        if options.has_header {
+            for col in 0..self.cols {
+                if col > 0 {
+                    writer.write_all(delimiter)?;
+                }
+                write!(writer, "{}", col + 1)?;
+            }
+            writeln!(writer)?;
+        }
+
+        for row in 0..self.rows {
+            for col in 0..self.cols {
+                if col > 0 {
+                    writer.write_all(delimiter)?;
+                }
+                let value = self.get(row, col);
+                match options.precision {
+                    Some(precision) => write!(writer, "{:.*}", precision, value)?,
+                    None => write!(writer, "{}", value)?,
+                }
+            }
+            writeln!(writer)?;
+        }
+
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// A cell of a line, with its quotes removed.
+struct Cell {
+    text: String,
+    quoted: bool,
+}
+
+/// Splits a line into cells at delimiters outside double quotes. Quotes are removed,
+/// and `""` within quotes stands for one quote.
+fn split_cells(line: &str, delimiter: char) -> Vec<Cell> {
+    let mut cells = Vec::new();
+    let mut cell = Cell {
+        text: String::new(),
+        quoted: false,
+    };
+    let mut in_quotes = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                chars.next();
+                cell.text.push('"');
+            }
+            '"' => {
+                in_quotes = !in_quotes;
+                cell.quoted = true;
+            }
+            c if c == delimiter && !in_quotes => {
+                let next = Cell {
+                    text: String::new(),
+                    quoted: false,
+                };
+                cells.push(std::mem::replace(&mut cell, next));
+            }
+            c => cell.text.push(c),
+        }
+    }
+    cells.push(cell);
+    cells
+}
+
+/// Removes the commas from a number whose integer digits are grouped in threes, as
+/// in `1,234,567.5`.
+fn ungroup_digits(cell: &str) -> Option<String> {
+    let unsigned = cell.strip_prefix(['-', '+']).unwrap_or(cell);
+    let integer = unsigned.split(['.', 'e', 'E']).next().unwrap_or(unsigned);
+    let mut groups = integer.split(',');
+    let first = groups.next()?;
+    let grouped = (1..=3).contains(&first.len())
+        && first.bytes().all(|b| b.is_ascii_digit())
+        && groups.all(|g| g.len() == 3 && g.bytes().all(|b| b.is_ascii_digit()));
+
+    (grouped && integer.contains(',') && !unsigned[integer.len()..].contains(','))
+        .then(|| cell.replace(',', ""))
+}
+
+fn parse_cell(cell: &Cell, missing: MissingValues) -> Result<f32> {
+    let cell_text = cell.text.trim();
+    let ungrouped = cell.quoted.then(|| ungroup_digits(cell_text)).flatten();
+    let cell = ungrouped.as_deref().unwrap_or(cell_text);
+
+    let is_missing =
+        cell.is_empty() || cell.eq_ignore_ascii_case("nan") || cell.eq_ignore_ascii_case("na");
+    if is_missing {
+        return match missing {
+            MissingValues::Error if cell.is_empty() => Err(anyhow!("empty cell")),
+            MissingValues::Error => Err(anyhow!("missing value '{}'", cell)),
+            MissingValues::Nan => Ok(f32::NAN),
+            MissingValues::Fill(value) => Ok(value),
+        };
+    }
+
+    cell.parse::<f32>()
+        .map_err(|_| anyhow!("invalid number '{}'", cell))
+}
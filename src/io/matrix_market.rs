@@ -0,0 +1,442 @@
+/*!
+ * # Matrix Market
+ *
+ * This module reads and writes the Matrix Market exchange format (.mtx) used by
+ * the SuiteSparse Matrix Collection and most sparse linear algebra tools.
+ *
+ * Supported files:
+ * - `coordinate` (sparse triplets) and `array` (dense, column-major) formats
+ * - `real`, `integer` and `pattern` fields (pattern entries are read as 1.0)
+ * - `general`, `symmetric` and `skew-symmetric` qualifiers (the missing triangle
+ *   is filled in when reading)
+ *
+ * Files can be read into a dense `Matrix` or into `CooMatrix` triplets for sparse
+ * use. The writers produce `real general` files.
+ */
+
+use crate::matrix::Matrix;
+use crate::sparse::CooMatrix;
+use anyhow::Result;
+use std::io::{BufRead, Write};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Format {
+    Coordinate,
+    Array,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Field {
+    Real,
+    Integer,
+    Pattern,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Symmetry {
+    General,
+    Symmetric,
+    SkewSymmetric,
+}
+
+/// Reads a Matrix Market file into a dense matrix.
+///
+/// Duplicate coordinate entries are summed.
+///
+/// # Arguments
+///
+/// * `reader` - Source of the file contents
+///
+/// # Returns
+///
+/// A `Result` containing the matrix or an error.
+///
+/// # Errors
+///
+/// Returns an error, including the line number, if the file is malformed or uses an
+/// unsupported field (`complex`) or qualifier (`hermitian`).
+///
+/// # Example
+///
+/// ```
+/// use metal_matrix::io::matrix_market;
+///
+/// let file = "%%MatrixMarket matrix coordinate real symmetric\n\
+///             2 2 2\n\
+///             1 1 4.0\n\
+///             2 1 1.5\n";
+///
+/// let matrix = matrix_market::read_matrix(file.as_bytes()).unwrap();
+/// assert_eq!(matrix.data, vec![4.0, 1.5, 1.5, 0.0]);
+/// ```
+pub fn read_matrix<R: BufRead>(reader: R) -> Result<Matrix> {
+    let mut parser = Parser::new(reader)?;
+
+    // The entries are read before the matrix is allocated, so a file that declares a
+    // huge matrix but ends early fails on its contents rather than on the allocation
+    let mut entries = Vec::new();
+    let cols = parser.cols;
+    parser.read_entries(|row, col, value| entries.push((row * cols + col, value)))?;
+
+    let len = parser.rows * parser.cols;
+    let mut data = Vec::new();
+    data.try_reserve_exact(len).map_err(|_| {
+        anyhow::anyhow!(
+            "Matrix Market matrix of {}x{} is too large to allocate",
+            parser.rows,
+            parser.cols
+        )
+    })?;
+    data.resize(len, 0.0);
+    for (index, value) in entries {
+        data[index] += value;
+    }
+    Matrix::with_data(parser.rows, parser.cols, data)
+}
+
+/// Reads a Matrix Market file into (row, col, value) triplets.
+///
+/// Symmetric and skew-symmetric files are expanded to both triangles. Zeros of
+/// `array` files are not stored.
+///
+/// # Arguments
+///
+/// * `reader` - Source of the file contents
+///
+/// # Returns
+///
+/// A `Result` containing the triplets or an error.
+///
+/// # Errors
+///
+/// Returns an error, including the line number, if the file is malformed or uses an
+/// unsupported field (`complex`) or qualifier (`hermitian`).
+///
+/// # Example
+///
+/// ```
+/// use metal_matrix::io::matrix_market;
+///
+/// let file = "%%MatrixMarket matrix coordinate pattern general\n\
+///             % a comment\n\
+///             3 3 2\n\
+///             1 2\n\
+///             3 1\n";
+///
+/// let coo = matrix_market::read_coo(file.as_bytes()).unwrap();
+/// assert_eq!(coo.row_indices, vec![0, 2]);
+/// assert_eq!(coo.col_indices, vec![1, 0]);
+/// assert_eq!(coo.values, vec![1.0, 1.0]);
+/// ```
+pub fn read_coo<R: BufRead>(reader: R) -> Result<CooMatrix> {
+    let mut parser = Parser::new(reader)?;
+    let mut coo = CooMatrix::new(parser.rows, parser.cols);
+    let keep_zeros = parser.format == Format::Coordinate;
+    parser.read_entries(|row, col, value| {
+        if keep_zeros || value != 0.0 {
+            coo.row_indices.push(row);
+            coo.col_indices.push(col);
+            coo.values.push(value);
+        }
+    })?;
+    Ok(coo)
+}
+
+/// Writes a dense matrix as a Matrix Market `array real general` file.
+///
+/// Values are written in the shortest form that reads back to the same `f32`.
+///
+/// # Errors
+///
+/// Returns an error if writing fails.
+///
+/// # Example
+///
+/// ```
+/// use metal_matrix::{io::matrix_market, Matrix};
+///
+/// let matrix = Matrix::with_data(2, 2, vec![1.0, 2.0, 3.0, 4.5]).unwrap();
+/// let mut file = Vec::new();
+/// matrix_market::write_matrix(&mut file, &matrix).unwrap();
+///
+/// assert_eq!(
+///     String::from_utf8(file).unwrap(),
+///     "%%MatrixMarket matrix array real general\n2 2\n1\n3\n2\n4.5\n"
+/// );
+/// ```
+pub fn write_matrix<W: Write>(mut writer: W, matrix: &Matrix) -> Result<()> {
+    writeln!(writer, "%%MatrixMarket matrix array real general")?;
+    writeln!(writer, "{} {}", matrix.rows, matrix.cols)?;
+    for col in 0..matrix.cols {
+        for row in 0..matrix.rows {
+            writeln!(writer, "{}", matrix.get(row, col))?;
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Writes triplets as a Matrix Market `coordinate real general` file.
+///
+/// Entries are written in storage order, with 1-based indices.
+///
+/// # Errors
+///
+/// Returns an error if the triplet arrays have different lengths or writing fails.
+///
+/// # Example
+///
+/// ```
+/// use metal_matrix::{io::matrix_market, CooMatrix};
+///
+/// let mut coo = CooMatrix::new(2, 3);
+/// coo.push(1, 2, 0.25).unwrap();
+/// let mut file = Vec::new();
+/// matrix_market::write_coo(&mut file, &coo).unwrap();
+///
+/// assert_eq!(
+///     String::from_utf8(file).unwrap(),
+///     "%%MatrixMarket matrix coordinate real general\n2 3 1\n2 3 0.25\n"
+/// );
+/// ```
+pub fn write_coo<W: Write>(mut writer: W, coo: &CooMatrix) -> Result<()> {
+    if coo.row_indices.len() != coo.values.len() || coo.col_indices.len() != coo.values.len() {
+        anyhow::bail!("Coordinate arrays must have the same length");
+    }
+
+    writeln!(writer, "%%MatrixMarket matrix coordinate real general")?;
+    writeln!(writer, "{} {} {}", coo.rows, coo.cols, coo.nnz())?;
+    for i in 0..coo.nnz() {
+        writeln!(
+            writer,
+            "{} {} {}",
+            coo.row_indices[i] + 1,
+            coo.col_indices[i] + 1,
+            coo.values[i]
+        )?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Line-oriented parser holding the header and size of the file being read.
+struct Parser<R> {
+    lines: std::io::Lines<R>,
+    line_number: usize,
+    format: Format,
+    field: Field,
+    symmetry: Symmetry,
+    rows: usize,
+    cols: usize,
+    entries: usize,
+}
+
+impl<R: BufRead> Parser<R> {
+    /// Reads the header and size lines.
+    fn new(reader: R) -> Result<Self> {
+        let mut lines = reader.lines();
+        let header = lines
+            .next()
+            .transpose()?
+            .ok_or_else(|| anyhow::anyhow!("Matrix Market file is empty"))?;
+
+        let tokens: Vec<String> = header
+            .split_whitespace()
+            .map(|token| token.to_ascii_lowercase())
+            .collect();
+        if tokens.len() != 5 || tokens[0] != "%%matrixmarket" || tokens[1] != "matrix" {
+            anyhow::bail!("Matrix Market line 1: expected '%%MatrixMarket matrix <format> <field> <symmetry>'");
+        }
+
+        let format = match tokens[2].as_str() {
+            "coordinate" => Format::Coordinate,
+            "array" => Format::Array,
+            other => anyhow::bail!("Matrix Market line 1: unknown format '{}'", other),
+        };
+        let field = match tokens[3].as_str() {
+            "real" | "double" => Field::Real,
+            "integer" => Field::Integer,
+            "pattern" => Field::Pattern,
+            other => anyhow::bail!("Matrix Market line 1: unsupported field '{}'", other),
+        };
+        let symmetry = match tokens[4].as_str() {
+            "general" => Symmetry::General,
+            "symmetric" => Symmetry::Symmetric,
+            "skew-symmetric" => Symmetry::SkewSymmetric,
+            other => anyhow::bail!("Matrix Market line 1: unsupported symmetry '{}'", other),
+        };
+        if format == Format::Array && field == Field::Pattern {
+            anyhow::bail!("Matrix Market line 1: pattern field requires coordinate format");
+        }
+
+        let mut parser = Self {
+            lines,
+            line_number: 1,
+            format,
+            field,
+            symmetry,
+            rows: 0,
+            cols: 0,
+            entries: 0,
+        };
+
+        let size_line = parser
+            .next_data_line()?
+            .ok_or_else(|| anyhow::anyhow!("Matrix Market file is missing its size line"))?;
+        let size: Vec<usize> = size_line
+            .split_whitespace()
+            .map(|token| token.parse::<usize>())
+            .collect::<std::result::Result<_, _>>()
+            .map_err(|_| parser.error("invalid size line"))?;
+
+        match (format, size.as_slice()) {
+            (Format::Coordinate, &[rows, cols, entries]) => {
+                parser.rows = rows;
+                parser.cols = cols;
+                parser.entries = entries;
+            }
+            (Format::Array, &[rows, cols]) => {
+                parser.rows = rows;
+                parser.cols = cols;
+            }
+            _ => return Err(parser.error("invalid size line")),
+        }
+
+        let elements = parser
+            .rows
+            .checked_mul(parser.cols)
+            .ok_or_else(|| parser.error("matrix dimensions overflow"))?;
+        if symmetry != Symmetry::General && parser.rows != parser.cols {
+            return Err(parser.error("symmetric matrices must be square"));
+        }
+
+        if format == Format::Array {
+            // Neither triangle count can overflow once rows * cols fits
+            let below = parser.rows * parser.rows.saturating_sub(1) / 2;
+            parser.entries = match symmetry {
+                Symmetry::General => elements,
+                Symmetry::Symmetric => below + parser.rows,
+                Symmetry::SkewSymmetric => below,
+            };
+        }
+
+        Ok(parser)
+    }
+
+    /// Reads every entry, calling `emit` with 0-based indices for each stored element
+    /// and its mirror image in symmetric files.
+    fn read_entries(&mut self, mut emit: impl FnMut(usize, usize, f32)) -> Result<()> {
+        for index in 0..self.entries {
+            let line = self.next_data_line()?.ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Matrix Market file ended after {} of {} entries",
+                    index,
+                    self.entries
+                )
+            })?;
+            let mut tokens = line.split_whitespace();
+
+            let (row, col) = match self.format {
+                Format::Coordinate => {
+                    let row = self.parse_index(tokens.next(), self.rows)?;
+                    let col = self.parse_index(tokens.next(), self.cols)?;
+                    (row, col)
+                }
+                Format::Array => self.array_position(index),
+            };
+            let value = match self.field {
+                Field::Pattern => 1.0,
+                Field::Real | Field::Integer => self.parse_value(tokens.next())?,
+            };
+            if tokens.next().is_some() {
+                return Err(self.error("unexpected trailing data"));
+            }
+
+            match self.symmetry {
+                Symmetry::General => emit(row, col, value),
+                Symmetry::Symmetric => {
+                    if row < col {
+                        return Err(self.error("symmetric entries must be in the lower triangle"));
+                    }
+                    emit(row, col, value);
+                    if row != col {
+                        emit(col, row, value);
+                    }
+                }
+                Symmetry::SkewSymmetric => {
+                    if row <= col {
+                        return Err(self
+                            .error("skew-symmetric entries must be strictly below the diagonal"));
+                    }
+                    emit(row, col, value);
+                    emit(col, row, -value);
+                }
+            }
+        }
+
+        if self.next_data_line()?.is_some() {
+            return Err(self.error("more entries than declared in the size line"));
+        }
+
+        Ok(())
+    }
+
+    /// Position of the `index`-th value of an array file (column-major, triangle only
+    /// for symmetric files).
+    fn array_position(&self, index: usize) -> (usize, usize) {
+        let skip = match self.symmetry {
+            Symmetry::General => return (index % self.rows, index / self.rows),
+            Symmetry::Symmetric => 0,
+            Symmetry::SkewSymmetric => 1,
+        };
+
+        // Column j holds rows (j + skip)..n
+        let mut remaining = index;
+        let mut col = 0;
+        loop {
+            let length = self.rows - col - skip;
+            if remaining < length {
+                return (col + skip + remaining, col);
+            }
+            remaining -= length;
+            col += 1;
+        }
+    }
+
+    /// Returns the next line that is neither blank nor a comment.
+    fn next_data_line(&mut self) -> Result<Option<String>> {
+        for line in self.lines.by_ref() {
+            let line = line?;
+            self.line_number += 1;
+            let trimmed = line.trim();
+            if !trimmed.is_empty() && !trimmed.starts_with('%') {
+                return Ok(Some(trimmed.to_string()));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Parses a 1-based index and converts it to 0-based.
+    fn parse_index(&self, token: Option<&str>, limit: usize) -> Result<usize> {
+        let index = token
+            .and_then(|token| token.parse::<usize>().ok())
+            .ok_or_else(|| self.error("invalid index"))?;
+        if index == 0 || index > limit {
+            return Err(self.error("index out of bounds"));
+        }
+        Ok(index - 1)
+    }
+
+    fn parse_value(&self, token: Option<&str>) -> Result<f32> {
+        let token = token.ok_or_else(|| self.error("missing value"))?;
+        let value = match self.field {
+            Field::Integer => token.parse::<i64>().map(|value| value as f32).ok(),
+            _ => token.parse::<f32>().ok(),
+        };
+        value.ok_or_else(|| self.error(&format!("invalid value '{}'", token)))
+    }
+
+    fn error(&self, message: &str) -> anyhow::Error {
+        anyhow::anyhow!("Matrix Market line {}: {}", self.line_number, message)
+    }
+}
@@ -0,0 +1,22 @@
+/*!
+ * # Input and Output
+ *
+ * This module provides readers and writers for exchanging matrices with other tools.
+ *
+ * - `csv`: CSV and other delimited text
+ * - `matrix_market`: the Matrix Market (.mtx) exchange format
+ * - `npy`: NumPy's binary array (.npy) and archive (.npz) formats
+ * - `safetensors`: named tensors in the safetensors (.safetensors) format
+ */
+
+/// CSV and delimited text reader and writer
+pub mod csv;
+
+/// Matrix Market (.mtx) reader and writer
+pub mod matrix_market;
+
+/// NumPy (.npy/.npz) reader and writer
+pub mod npy;
+
+/// Safetensors (.safetensors) loader and writer
+pub mod safetensors;
@@ -0,0 +1,382 @@
+/*!
+ * # NumPy Arrays
+ *
+ * This module reads and writes NumPy's binary `.npy` format and `.npz` archives,
+ * so arrays can be exchanged with Python without the precision loss of a text
+ * format.
+ *
+ * Supported arrays:
+ * - `f4`, `f8` and `i4` dtypes in little-endian (`<`) or big-endian (`>`) byte order
+ * - C (row-major) and Fortran (column-major) order; Fortran arrays are transposed
+ *   into the row-major layout of `Matrix::data`
+ * - 2-D arrays, plus 1-D arrays which are read as column vectors (`Matrix::vector`)
+ *   and 0-D arrays which are read as 1×1 matrices
+ * - format versions 1.0, 2.0 and 3.0
+ *
+ * `f8` and `i4` values are converted to `f32` on reading. Matrices are always
+ * written as little-endian `f4` arrays in C order.
+ *
+ * `.npz` archives (as written by `numpy.savez` and `numpy.savez_compressed`) are
+ * zip files with one `.npy` member per named array.
+ */
+
+use crate::matrix::Matrix;
+use anyhow::{anyhow, bail, Context, Result};
+use std::collections::BTreeMap;
+use std::io::{Read, Seek, Write};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+const MAGIC: &[u8; 6] = b"\x93NUMPY";
+
+/// Total header size (magic, version, length and dictionary) is padded to a multiple
+/// of this, matching NumPy.
+const HEADER_ALIGNMENT: usize = 64;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum DType {
+    F32,
+    F64,
+    I32,
+}
+
+impl DType {
+    fn size(self) -> usize {
+        match self {
+            DType::F32 | DType::I32 => 4,
+            DType::F64 => 8,
+        }
+    }
+}
+
+/// The parsed `.npy` header dictionary.
+struct Header {
+    dtype: DType,
+    big_endian: bool,
+    fortran_order: bool,
+    shape: Vec<usize>,
+}
+
+impl Matrix {
+    /// Reads a matrix from a NumPy `.npy` file.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - Source of the file contents
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the matrix or an error.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file is malformed, has more than two dimensions or
+    /// uses an unsupported dtype.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use metal_matrix::Matrix;
+    ///
+    /// let matrix = Matrix::with_data(2, 2, vec![1.0, 2.0, 3.0, 4.0]).unwrap();
+    /// let mut file = Vec::new();
+    /// matrix.write_npy(&mut file).unwrap();
+    ///
+    /// let copy = Matrix::read_npy(file.as_slice()).unwrap();
+    /// assert_eq!(copy.data, matrix.data);
+    /// ```
+    pub fn read_npy<R: Read>(mut reader: R) -> Result<Matrix> {
+        let header = read_header(&mut reader)?;
+
+        let (rows, cols) = match header.shape[..] {
+            [] => (1, 1),
+            [n] => (n, 1),
+            [rows, cols] => (rows, cols),
+            _ => bail!(
+                "Invalid .npy file: {}-D arrays are not supported",
+                header.shape.len()
+            ),
+        };
+        let count = rows
+            .checked_mul(cols)
+            .ok_or_else(|| anyhow!("Invalid .npy file: array is too large"))?;
+
+        let length = count
+            .checked_mul(header.dtype.size())
+            .ok_or_else(|| anyhow!("Invalid .npy file: array is too large"))?;
+        let bytes = read_bytes(&mut reader, length, "array data")?;
+        let values = decode(&bytes, header.dtype, header.big_endian);
+
+        if header.fortran_order && header.shape.len() == 2 {
+            let mut matrix = Matrix::new(rows, cols);
+            for col in 0..cols {
+                for row in 0..rows {
+                    matrix.data[row * cols + col] = values[col * rows + row];
+                }
+            }
+            Ok(matrix)
+        } else if header.shape.len() == 1 {
+            Ok(Matrix::vector(values))
+        } else {
+            Matrix::with_data(rows, cols, values)
+        }
+    }
+
+    /// Writes the matrix as a NumPy `.npy` file.
+    ///
+    /// The array is written as a 2-D little-endian `float32` array in C order, so
+    /// `numpy.load` returns an array of shape `(rows, cols)`.
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - Destination of the file contents
+    ///
+    /// # Returns
+    ///
+    /// A `Result` indicating success or an I/O error.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use metal_matrix::Matrix;
+    ///
+    /// let mut file = Vec::new();
+    /// Matrix::identity(3).write_npy(&mut file).unwrap();
+    ///
+    /// assert!(file.starts_with(b"\x93NUMPY"));
+    /// assert_eq!(file.len() % 64, (9 * 4) % 64);
+    /// ```
+    pub fn write_npy<W: Write>(&self, mut writer: W) -> Result<()> {
+        let mut header = format!(
+            "{{'descr': '<f4', 'fortran_order': False, 'shape': ({}, {}), }}",
+            self.rows, self.cols
+        );
+
+        // Pad with spaces so the data starts on an aligned offset. A 2-D header always
+        // fits the 2-byte length field of format version 1.0.
+        let preamble = MAGIC.len() + 2 + 2;
+        let padding = HEADER_ALIGNMENT - (preamble + header.len() + 1) % HEADER_ALIGNMENT;
+        header.push_str(&" ".repeat(padding % HEADER_ALIGNMENT));
+        header.push('\n');
+
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[1, 0])?;
+        writer.write_all(&(header.len() as u16).to_le_bytes())?;
+        writer.write_all(header.as_bytes())?;
+
+        for value in &self.data {
+            writer.write_all(&value.to_le_bytes())?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Reads every array of a NumPy `.npz` archive.
+///
+/// Both stored (`numpy.savez`) and deflated (`numpy.savez_compressed`) archives are
+/// supported. The `.npy` extension is removed from member names, so arrays are keyed
+/// by the names they were saved under in Python.
+///
+/// # Arguments
+///
+/// * `reader` - Source of the archive
+///
+/// # Returns
+///
+/// A `Result` containing the matrices by name or an error.
+///
+/// # Errors
+///
+/// Returns an error, including the member name, if the archive or one of its arrays
+/// cannot be read.
+///
+/// # Example
+///
+/// ```
+/// use metal_matrix::io::npy;
+/// use metal_matrix::Matrix;
+/// use std::io::Cursor;
+///
+/// let weights = Matrix::with_data(2, 2, vec![1.0, 2.0, 3.0, 4.0]).unwrap();
+/// let bias = Matrix::vector(vec![0.5, -0.5]);
+///
+/// let mut archive = Cursor::new(Vec::new());
+/// npy::write_npz(&mut archive, [("weights", &weights), ("bias", &bias)]).unwrap();
+///
+/// let arrays = npy::read_npz(archive).unwrap();
+/// assert_eq!(arrays["weights"].data, weights.data);
+/// assert_eq!(arrays["bias"].data, bias.data);
+/// ```
+pub fn read_npz<R: Read + Seek>(reader: R) -> Result<BTreeMap<String, Matrix>> {
+    let mut archive = ZipArchive::new(reader).context("Invalid .npz file")?;
+    let mut matrices = BTreeMap::new();
+
+    for i in 0..archive.len() {
+        let file = archive.by_index(i).context("Invalid .npz file")?;
+        if file.is_dir() {
+            continue;
+        }
+
+        let member = file.name().to_string();
+        let name = member.strip_suffix(".npy").unwrap_or(&member).to_string();
+        let matrix =
+            Matrix::read_npy(file).with_context(|| format!("In .npz member '{}'", member))?;
+        matrices.insert(name, matrix);
+    }
+
+    Ok(matrices)
+}
+
+/// Writes named matrices as a NumPy `.npz` archive.
+///
+/// Each matrix is stored uncompressed as `<name>.npy`, like `numpy.savez`, and can be
+/// read in Python with `numpy.load(path)[name]`.
+///
+/// # Arguments
+///
+/// * `writer` - Destination of the archive
+/// * `matrices` - Pairs of array name and matrix
+///
+/// # Returns
+///
+/// A `Result` indicating success or an error.
+pub fn write_npz<'a, W, I, S>(writer: W, matrices: I) -> Result<()>
+where
+    W: Write + Seek,
+    I: IntoIterator<Item = (S, &'a Matrix)>,
+    S: AsRef<str>,
+{
+    let mut archive = ZipWriter::new(writer);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+
+    for (name, matrix) in matrices {
+        archive.start_file(format!("{}.npy", name.as_ref()), options)?;
+        matrix.write_npy(&mut archive)?;
+    }
+
+    archive.finish()?;
+    Ok(())
+}
+
+fn read_header<R: Read>(reader: &mut R) -> Result<Header> {
+    let mut preamble = [0u8; 8];
+    reader
+        .read_exact(&mut preamble)
+        .context("Invalid .npy file: missing header")?;
+    if &preamble[..6] != MAGIC {
+        bail!("Invalid .npy file: bad magic string");
+    }
+
+    let length = match preamble[6] {
+        1 => {
+            let mut length = [0u8; 2];
+            reader.read_exact(&mut length)?;
+            u16::from_le_bytes(length) as usize
+        }
+        2 | 3 => {
+            let mut length = [0u8; 4];
+            reader.read_exact(&mut length)?;
+            u32::from_le_bytes(length) as usize
+        }
+        major => bail!(
+            "Invalid .npy file: unsupported format version {}.{}",
+            major,
+            preamble[7]
+        ),
+    };
+
+    let dictionary = read_bytes(reader, length, "header")?;
+    let dictionary = String::from_utf8(dictionary)
+        .map_err(|_| anyhow!("Invalid .npy file: header is not valid text"))?;
+
+    parse_header(&dictionary)
+}
+
+/// Reads exactly `length` bytes. The buffer grows with the data actually read, so a
+/// header declaring more data than the file holds fails without allocating it.
+fn read_bytes<R: Read>(reader: &mut R, length: usize, what: &str) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    reader
+        .take(length as u64)
+        .read_to_end(&mut bytes)
+        .with_context(|| format!("Invalid .npy file: failed to read {}", what))?;
+    if bytes.len() != length {
+        bail!("Invalid .npy file: {} is truncated", what);
+    }
+    Ok(bytes)
+}
+
+/// Parses the Python literal dictionary of a `.npy` header, e.g.
+/// `{'descr': '<f8', 'fortran_order': False, 'shape': (3, 4), }`.
+fn parse_header(dictionary: &str) -> Result<Header> {
+    let descr = field(dictionary, "descr")?;
+    let descr = descr
+        .strip_prefix('\'')
+        .and_then(|d| d.split('\'').next())
+        .ok_or_else(|| anyhow!("Invalid .npy file: malformed 'descr'"))?;
+
+    let (big_endian, kind) = match descr.chars().next() {
+        Some('<' | '=' | '|') => (false, &descr[1..]),
+        Some('>') => (true, &descr[1..]),
+        _ => (false, descr),
+    };
+    let dtype = match kind {
+        "f4" => DType::F32,
+        "f8" => DType::F64,
+        "i4" => DType::I32,
+        _ => bail!("Invalid .npy file: unsupported dtype '{}'", descr),
+    };
+
+    let fortran_order = match field(dictionary, "fortran_order")? {
+        f if f.starts_with("True") => true,
+        f if f.starts_with("False") => false,
+        _ => bail!("Invalid .npy file: malformed 'fortran_order'"),
+    };
+
+    let shape = field(dictionary, "shape")?;
+    let shape = shape
+        .strip_prefix('(')
+        .and_then(|s| s.split(')').next())
+        .ok_or_else(|| anyhow!("Invalid .npy file: malformed 'shape'"))?;
+    let shape = shape
+        .split(',')
+        .map(str::trim)
+        .filter(|dim| !dim.is_empty())
+        .map(|dim| {
+            dim.trim_end_matches('L')
+                .parse::<usize>()
+                .map_err(|_| anyhow!("Invalid .npy file: malformed 'shape'"))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(Header {
+        dtype,
+        big_endian,
+        fortran_order,
+        shape,
+    })
+}
+
+/// Returns the text following `'key':` in the header dictionary.
+fn field<'a>(dictionary: &'a str, key: &str) -> Result<&'a str> {
+    let pattern = format!("'{}':", key);
+    let start = dictionary
+        .find(&pattern)
+        .ok_or_else(|| anyhow!("Invalid .npy file: header is missing '{}'", key))?;
+    Ok(dictionary[start + pattern.len()..].trim_start())
+}
+
+fn decode(bytes: &[u8], dtype: DType, big_endian: bool) -> Vec<f32> {
+    bytes
+        .chunks_exact(dtype.size())
+        .map(|chunk| match (dtype, big_endian) {
+            (DType::F32, false) => f32::from_le_bytes(chunk.try_into().unwrap()),
+            (DType::F32, true) => f32::from_be_bytes(chunk.try_into().unwrap()),
+            (DType::F64, false) => f64::from_le_bytes(chunk.try_into().unwrap()) as f32,
+            (DType::F64, true) => f64::from_be_bytes(chunk.try_into().unwrap()) as f32,
+            (DType::I32, false) => i32::from_le_bytes(chunk.try_into().unwrap()) as f32,
+            (DType::I32, true) => i32::from_be_bytes(chunk.try_into().unwrap()) as f32,
+        })
+        .collect()
+}
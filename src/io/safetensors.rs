@@ -0,0 +1,291 @@
+/*!
+ * # Safetensors
+ *
+ * This module loads named weight matrices from `.safetensors` files, the format
+ * used by Hugging Face model checkpoints, and writes matrices back out.
+ *
+ * Files are memory-mapped rather than read: opening a file only parses its JSON
+ * header, and the pages of a tensor are read from disk when that tensor is loaded,
+ * so a single matrix can be taken from a multi-gigabyte checkpoint cheaply.
+ *
+ * Supported tensors:
+ * - `F32`, `F64`, `F16` and `BF16` dtypes, converted to `f32` when loaded
+ * - 2-D tensors, plus 1-D tensors (such as biases) which are loaded as column vectors
+ *
+ * Matrices are written as `F32` tensors of shape `[rows, cols]`.
+ */
+
+use crate::matrix::Matrix;
+use crate::metal_context::MetalContext;
+use ::safetensors::tensor::{Metadata, TensorInfo};
+use ::safetensors::{Dtype, SafeTensors, View};
+use anyhow::{anyhow, bail, Context, Result};
+use half::{bf16, f16};
+use memmap2::Mmap;
+use metal::Buffer;
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+/// Size of the little-endian header length that starts every file.
+const HEADER_LENGTH_SIZE: usize = 8;
+
+/// A memory-mapped `.safetensors` file.
+///
+/// Tensors are loaded by name with `matrix` or, for the GPU, `buffer`.
+pub struct SafetensorsFile {
+    mmap: Mmap,
+    data_start: usize,
+    metadata: Metadata,
+}
+
+/// A matrix viewed as an `F32` tensor for serialization.
+struct MatrixView<'a> {
+    matrix: &'a Matrix,
+    shape: [usize; 2],
+}
+
+impl SafetensorsFile {
+    /// Opens and memory-maps a `.safetensors` file, parsing its header.
+    ///
+    /// The file must not be modified while it is open.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the file
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the opened file or an error.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be mapped or its header is invalid.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let file = File::open(path)
+            .with_context(|| format!("Failed to open safetensors file: {}", path.display()))?;
+
+        // SAFETY: the mapping is read-only, and the caller is required not to modify
+        // the file while it is open.
+        let mmap = unsafe { Mmap::map(&file) }
+            .with_context(|| format!("Failed to map safetensors file: {}", path.display()))?;
+
+        let (header_length, metadata) = SafeTensors::read_metadata(&mmap)
+            .map_err(|e| anyhow!("Invalid safetensors file: {} - {}", path.display(), e))?;
+
+        Ok(Self {
+            mmap,
+            data_start: HEADER_LENGTH_SIZE + header_length,
+            metadata,
+        })
+    }
+
+    /// Returns the names of the tensors in the file, in file order.
+    pub fn names(&self) -> Vec<String> {
+        self.metadata.offset_keys()
+    }
+
+    /// Returns the matrix dimensions `(rows, cols)` of a tensor without loading it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there is no tensor with this name or it is not 1-D or 2-D.
+    pub fn shape(&self, name: &str) -> Result<(usize, usize)> {
+        let info = self.info(name)?;
+        match info.shape[..] {
+            [rows] => Ok((rows, 1)),
+            [rows, cols] => Ok((rows, cols)),
+            _ => bail!(
+                "Tensor '{}' has shape {:?}, expected a 1-D or 2-D tensor",
+                name,
+                info.shape
+            ),
+        }
+    }
+
+    /// Loads a tensor into a matrix, converting its values to `f32`.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of the tensor
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the matrix or an error.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there is no tensor with this name, it is not 1-D or 2-D,
+    /// or its dtype is not `F32`, `F64`, `F16` or `BF16`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use metal_matrix::io::safetensors::{self, SafetensorsFile};
+    /// use metal_matrix::Matrix;
+    ///
+    /// let path = std::env::temp_dir().join("metal_matrix_doc.safetensors");
+    /// let weights = Matrix::with_data(2, 2, vec![1.0, 2.0, 3.0, 4.0]).unwrap();
+    /// safetensors::write_file(&path, [("weights", &weights)]).unwrap();
+    ///
+    /// let file = SafetensorsFile::open(&path).unwrap();
+    /// assert_eq!(file.names(), vec!["weights"]);
+    /// assert_eq!(file.matrix("weights").unwrap().data, weights.data);
+    /// ```
+    pub fn matrix(&self, name: &str) -> Result<Matrix> {
+        let (rows, cols) = self.shape(name)?;
+        let info = self.info(name)?;
+        let data = self.data(info);
+
+        let values: Vec<f32> = match info.dtype {
+            Dtype::F32 => data
+                .chunks_exact(4)
+                .map(|b| f32::from_le_bytes(b.try_into().unwrap()))
+                .collect(),
+            Dtype::F64 => data
+                .chunks_exact(8)
+                .map(|b| f64::from_le_bytes(b.try_into().unwrap()) as f32)
+                .collect(),
+            Dtype::F16 => data
+                .chunks_exact(2)
+                .map(|b| f16::from_le_bytes([b[0], b[1]]).to_f32())
+                .collect(),
+            Dtype::BF16 => data
+                .chunks_exact(2)
+                .map(|b| bf16::from_le_bytes([b[0], b[1]]).to_f32())
+                .collect(),
+            dtype => bail!("Tensor '{}' has unsupported dtype {:?}", name, dtype),
+        };
+
+        Matrix::with_data(rows, cols, values)
+    }
+
+    /// Loads a tensor into a Metal buffer of row-major `f32` values.
+    ///
+    /// `F32` tensors are copied straight from the mapped file into the buffer; other
+    /// dtypes are converted first. Use `shape` for the matrix dimensions.
+    ///
+    /// # Arguments
+    ///
+    /// * `context` - The Metal context to create the buffer with
+    /// * `name` - Name of the tensor
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the buffer or an error.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as `matrix`.
+    pub fn buffer(&self, context: &MetalContext, name: &str) -> Result<Buffer> {
+        self.shape(name)?;
+        let info = self.info(name)?;
+
+        if info.dtype == Dtype::F32 && cfg!(target_endian = "little") {
+            return Ok(context.new_buffer_with_data(self.data(info)));
+        }
+
+        let matrix = self.matrix(name)?;
+        Ok(context.new_buffer_with_data(&matrix.data))
+    }
+
+    fn info(&self, name: &str) -> Result<&TensorInfo> {
+        self.metadata
+            .info(name)
+            .ok_or_else(|| anyhow!("No tensor named '{}'", name))
+    }
+
+    fn data(&self, info: &TensorInfo) -> &[u8] {
+        let (start, end) = info.data_offsets;
+        &self.mmap[self.data_start + start..self.data_start + end]
+    }
+}
+
+/// Writes named matrices as a `.safetensors` file.
+///
+/// # Arguments
+///
+/// * `path` - Path of the file to create
+/// * `matrices` - Pairs of tensor name and matrix
+///
+/// # Returns
+///
+/// A `Result` indicating success or an error.
+pub fn write_file<'a, P, I, S>(path: P, matrices: I) -> Result<()>
+where
+    P: AsRef<Path>,
+    I: IntoIterator<Item = (S, &'a Matrix)>,
+    S: AsRef<str>,
+{
+    ::safetensors::serialize_to_file(views(matrices), None, path.as_ref())
+        .map_err(|e| anyhow!("Failed to write safetensors file: {}", e))
+}
+
+/// Writes named matrices in the `.safetensors` format.
+///
+/// The file is assembled in memory before writing; use `write_file` to stream
+/// large files to disk.
+///
+/// # Arguments
+///
+/// * `writer` - Destination of the file contents
+/// * `matrices` - Pairs of tensor name and matrix
+///
+/// # Returns
+///
+/// A `Result` indicating success or an error.
+pub fn write<'a, W, I, S>(mut writer: W, matrices: I) -> Result<()>
+where
+    W: Write,
+    I: IntoIterator<Item = (S, &'a Matrix)>,
+    S: AsRef<str>,
+{
+    let bytes = ::safetensors::serialize(views(matrices), None)
+        .map_err(|e| anyhow!("Failed to serialize safetensors: {}", e))?;
+    writer.write_all(&bytes)?;
+    writer.flush()?;
+    Ok(())
+}
+
+fn views<'a, I, S>(matrices: I) -> Vec<(String, MatrixView<'a>)>
+where
+    I: IntoIterator<Item = (S, &'a Matrix)>,
+    S: AsRef<str>,
+{
+    matrices
+        .into_iter()
+        .map(|(name, matrix)| {
+            let view = MatrixView {
+                matrix,
+                shape: [matrix.rows, matrix.cols],
+            };
+            (name.as_ref().to_string(), view)
+        })
+        .collect()
+}
+
+impl View for MatrixView<'_> {
+    fn dtype(&self) -> Dtype {
+        Dtype::F32
+    }
+
+    fn shape(&self) -> &[usize] {
+        &self.shape
+    }
+
+    fn data(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(
+            self.matrix
+                .data
+                .iter()
+                .flat_map(|value| value.to_le_bytes())
+                .collect(),
+        )
+    }
+
+    fn data_len(&self) -> usize {
+        self.matrix.data.len() * std::mem::size_of::<f32>()
+    }
+}
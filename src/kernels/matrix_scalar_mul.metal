@@ -20,4 +20,19 @@ kernel void matrix_scalar_multiply(device const float* A,
                                   uint index [[thread_position_in_grid]])
 {
     B[index] = scalar * A[index];
-} 
\ No newline at end of file
+}
+
+// 64-bit-index variant for matrices with more than 2^32 - 1 elements: a fixed grid
+// of threads strides over the elements, indexing with `ulong`.
+// - count: Number of elements
+kernel void matrix_scalar_multiply_64(device const float* A,
+                                     constant float& scalar,
+                                     device float* B,
+                                     constant ulong& count,
+                                     uint index [[thread_position_in_grid]],
+                                     uint threads [[threads_per_grid]])
+{
+    for (ulong i = index; i < count; i += threads) {
+        B[i] = scalar * A[i];
+    }
+}
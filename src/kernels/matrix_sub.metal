@@ -20,4 +20,19 @@ kernel void matrix_subtract(device const float* A,
                            uint index [[thread_position_in_grid]])
 {
     C[index] = A[index] - B[index];
-} 
\ No newline at end of file
+}
+
+// 64-bit-index variant for matrices with more than 2^32 - 1 elements: a fixed grid
+// of threads strides over the elements, indexing with `ulong`.
+// - count: Number of elements
+kernel void matrix_subtract_64(device const float* A,
+                              device const float* B,
+                              device float* C,
+                              constant ulong& count,
+                              uint index [[thread_position_in_grid]],
+                              uint threads [[threads_per_grid]])
+{
+    for (ulong i = index; i < count; i += threads) {
+        C[i] = A[i] - B[i];
+    }
+}
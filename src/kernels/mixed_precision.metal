@@ -0,0 +1,126 @@
+//
+// Mixed-Precision Kernels
+//
+// Conversions between f32 matrices and half-precision buffers, and matrix
+// multiplication of half-precision inputs with f32 accumulation.
+//
+// Two half-precision formats are supported:
+// - f16: IEEE binary16, stored as `half`
+// - bf16: the upper 16 bits of an f32, stored as `ushort` so the kernels do not
+//   depend on the `bfloat` type of newer Metal versions
+//
+// All conversions to a 16-bit format round to nearest, ties to even.
+//
+
+#include <metal_stdlib>
+using namespace metal;
+
+// Rounds an f32 to bf16, keeping NaNs quiet
+inline ushort float_to_bf16(float x)
+{
+    uint bits = as_type<uint>(x);
+    if ((bits & 0x7fffffff) > 0x7f800000) {
+        return ushort((bits >> 16) | 0x40);
+    }
+    bits += 0x7fff + ((bits >> 16) & 1);
+    return ushort(bits >> 16);
+}
+
+inline float bf16_to_float(ushort x)
+{
+    return as_type<float>(uint(x) << 16);
+}
+
+inline float load(device const half* p, uint i) { return float(p[i]); }
+inline float load(device const ushort* p, uint i) { return bf16_to_float(p[i]); }
+
+inline void store(device float* p, uint i, float x) { p[i] = x; }
+inline void store(device half* p, uint i, float x) { p[i] = half(x); }
+inline void store(device ushort* p, uint i, float x) { p[i] = float_to_bf16(x); }
+
+//
+// Conversions, one thread per element
+//
+// - A: Input buffer
+// - B: Output buffer
+// - index: Thread position in the grid
+//
+
+kernel void convert_f32_to_f16(device const float* A,
+                              device half* B,
+                              uint index [[thread_position_in_grid]])
+{
+    B[index] = half(A[index]);
+}
+
+kernel void convert_f16_to_f32(device const half* A,
+                              device float* B,
+                              uint index [[thread_position_in_grid]])
+{
+    B[index] = float(A[index]);
+}
+
+kernel void convert_f32_to_bf16(device const float* A,
+                               device ushort* B,
+                               uint index [[thread_position_in_grid]])
+{
+    B[index] = float_to_bf16(A[index]);
+}
+
+kernel void convert_bf16_to_f32(device const ushort* A,
+                               device float* B,
+                               uint index [[thread_position_in_grid]])
+{
+    B[index] = bf16_to_float(A[index]);
+}
+
+//
+// Matrix multiplication: C = A * B, accumulated in f32 and rounded once to the
+// output type. Each thread computes one element of the output matrix.
+//
+// - A: First input matrix (M × K)
+// - B: Second input matrix (K × N)
+// - C: Output matrix (M × N)
+// - M, N, K: Dimensions
+// - position: 2D thread position in the grid
+//
+
+template <typename In, typename Out>
+inline void mixed_matrix_multiply(device const In* A,
+                                  device const In* B,
+                                  device Out* C,
+                                  uint M,
+                                  uint N,
+                                  uint K,
+                                  uint2 position)
+{
+    uint row = position.y;
+    uint col = position.x;
+
+    if (row < M && col < N) {
+        float sum = 0.0f;
+        for (uint i = 0; i < K; i++) {
+            sum += load(A, row * K + i) * load(B, i * N + col);
+        }
+        store(C, row * N + col, sum);
+    }
+}
+
+#define MIXED_MATRIX_MULTIPLY(name, In, Out)                                  \
+kernel void name(device const In* A,                                          \
+                 device const In* B,                                          \
+                 device Out* C,                                               \
+                 constant uint& M,                                            \
+                 constant uint& N,                                            \
+                 constant uint& K,                                            \
+                 uint2 position [[thread_position_in_grid]])                  \
+{                                                                             \
+    mixed_matrix_multiply(A, B, C, M, N, K, position);                        \
+}
+
+MIXED_MATRIX_MULTIPLY(matrix_multiply_f16_f32, half, float)
+MIXED_MATRIX_MULTIPLY(matrix_multiply_f16_f16, half, half)
+MIXED_MATRIX_MULTIPLY(matrix_multiply_f16_bf16, half, ushort)
+MIXED_MATRIX_MULTIPLY(matrix_multiply_bf16_f32, ushort, float)
+MIXED_MATRIX_MULTIPLY(matrix_multiply_bf16_f16, ushort, half)
+MIXED_MATRIX_MULTIPLY(matrix_multiply_bf16_bf16, ushort, ushort)
@@ -26,6 +26,15 @@ pub mod paths {
 
     /// Path to the matrix scalar multiplication kernel
     pub const MATRIX_SCALAR_MUL: &str = "src/kernels/matrix_scalar_mul.metal";
+
+    /// Path to the sparse matrix kernels
+    pub const SPARSE: &str = "src/kernels/sparse.metal";
+
+    /// Path to the half-precision conversion and mixed-precision multiplication kernels
+    pub const MIXED_PRECISION: &str = "src/kernels/mixed_precision.metal";
+
+    /// Path to the quantized matrix multiplication kernels
+    pub const QUANTIZED: &str = "src/kernels/quantized.metal";
 }
 
 /// Names of kernel functions
@@ -44,4 +53,61 @@ pub mod functions {
 
     /// Matrix scalar multiplication kernel function name
     pub const MATRIX_SCALAR_MUL: &str = "matrix_scalar_multiply";
+
+    /// Matrix addition kernel function name, with 64-bit indices
+    pub const MATRIX_ADD_64: &str = "matrix_add_64";
+
+    /// Matrix subtraction kernel function name, with 64-bit indices
+    pub const MATRIX_SUB_64: &str = "matrix_subtract_64";
+
+    /// Matrix scalar multiplication kernel function name, with 64-bit indices
+    pub const MATRIX_SCALAR_MUL_64: &str = "matrix_scalar_multiply_64";
+
+    /// Sparse matrix-vector product kernel function name (one thread per row)
+    pub const SPMV_SCALAR: &str = "spmv_scalar";
+
+    /// Sparse matrix-vector product kernel function name (one SIMD group per row)
+    pub const SPMV_VECTOR: &str = "spmv_vector";
+
+    /// Sparse-dense matrix product kernel function name (one thread per output element)
+    pub const SPMM_SCALAR: &str = "spmm_scalar";
+
+    /// Sparse-dense matrix product kernel function name (one SIMD group per output element)
+    pub const SPMM_VECTOR: &str = "spmm_vector";
+
+    /// f32 to f16 conversion kernel function name
+    pub const CONVERT_F32_TO_F16: &str = "convert_f32_to_f16";
+
+    /// f16 to f32 conversion kernel function name
+    pub const CONVERT_F16_TO_F32: &str = "convert_f16_to_f32";
+
+    /// f32 to bf16 conversion kernel function name
+    pub const CONVERT_F32_TO_BF16: &str = "convert_f32_to_bf16";
+
+    /// bf16 to f32 conversion kernel function name
+    pub const CONVERT_BF16_TO_F32: &str = "convert_bf16_to_f32";
+
+    /// Matrix multiplication kernel function name, f16 inputs and f32 output
+    pub const MATRIX_MUL_F16_F32: &str = "matrix_multiply_f16_f32";
+
+    /// Matrix multiplication kernel function name, f16 inputs and f16 output
+    pub const MATRIX_MUL_F16_F16: &str = "matrix_multiply_f16_f16";
+
+    /// Matrix multiplication kernel function name, f16 inputs and bf16 output
+    pub const MATRIX_MUL_F16_BF16: &str = "matrix_multiply_f16_bf16";
+
+    /// Matrix multiplication kernel function name, bf16 inputs and f32 output
+    pub const MATRIX_MUL_BF16_F32: &str = "matrix_multiply_bf16_f32";
+
+    /// Matrix multiplication kernel function name, bf16 inputs and f16 output
+    pub const MATRIX_MUL_BF16_F16: &str = "matrix_multiply_bf16_f16";
+
+    /// Matrix multiplication kernel function name, bf16 inputs and bf16 output
+    pub const MATRIX_MUL_BF16_BF16: &str = "matrix_multiply_bf16_bf16";
+
+    /// Quantized matrix multiplication kernel function name, int8 per-row weights
+    pub const QUANTIZED_MATRIX_MUL_INT8: &str = "quantized_matrix_multiply_int8";
+
+    /// Quantized matrix multiplication kernel function name, int4 grouped weights
+    pub const QUANTIZED_MATRIX_MUL_INT4: &str = "quantized_matrix_multiply_int4";
 }
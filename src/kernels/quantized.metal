@@ -0,0 +1,88 @@
+//
+// Quantized Matrix Multiplication Kernels
+//
+// These kernels multiply a dense f32 matrix by the transpose of a weight matrix
+// stored with weight-only quantization: C = A * W^T, where W is N × K (one row per
+// output channel, as linear-layer weights are stored).
+//
+// Each weight is dequantized inside the inner loop as
+//
+//     float(int(q) - int(zero_point)) * scale
+//
+// which is exactly the value `quantization::dequantize` produces on the CPU, and
+// accumulated in f32. Each thread computes one element of the output matrix.
+//
+// Parameters:
+// - A: Dense input matrix (M × K, row-major)
+// - W: Quantized codes of the weight matrix (N rows of K codes)
+// - scales: Scale of each row (int8) or group (int4)
+// - zero_points: Zero point of each row (int8) or group (int4)
+// - C: Output matrix (M × N, row-major)
+// - M, N, K: Dimensions
+// - G: Group size (int4 only)
+// - position: 2D thread position in the grid
+//
+
+#include <metal_stdlib>
+using namespace metal;
+
+// Int8 per row: one byte per code, one scale and zero point per row of W
+kernel void quantized_matrix_multiply_int8(device const float* A,
+                                           device const uchar* W,
+                                           device const float* scales,
+                                           device const uchar* zero_points,
+                                           device float* C,
+                                           constant uint& M,
+                                           constant uint& N,
+                                           constant uint& K,
+                                           uint2 position [[thread_position_in_grid]])
+{
+    uint row = position.y;
+    uint col = position.x;
+
+    if (row < M && col < N) {
+        device const uchar* w = W + col * K;
+        float scale = scales[col];
+        int zero_point = zero_points[col];
+
+        float sum = 0.0f;
+        for (uint i = 0; i < K; i++) {
+            float weight = float(int(w[i]) - zero_point) * scale;
+            sum += A[row * K + i] * weight;
+        }
+        C[row * N + col] = sum;
+    }
+}
+
+// Int4 groups: two codes per byte (even column in the low nibble), each row of W
+// padded to a whole number of bytes, and one scale and zero point per G
+// consecutive codes of a row (the last group of a row may be shorter)
+kernel void quantized_matrix_multiply_int4(device const float* A,
+                                           device const uchar* W,
+                                           device const float* scales,
+                                           device const uchar* zero_points,
+                                           device float* C,
+                                           constant uint& M,
+                                           constant uint& N,
+                                           constant uint& K,
+                                           constant uint& G,
+                                           uint2 position [[thread_position_in_grid]])
+{
+    uint row = position.y;
+    uint col = position.x;
+
+    if (row < M && col < N) {
+        device const uchar* w = W + col * ((K + 1) / 2);
+        uint first_group = col * ((K + G - 1) / G);
+
+        float sum = 0.0f;
+        for (uint i = 0; i < K; i++) {
+            uchar byte = w[i / 2];
+            int code = (i & 1) ? (byte >> 4) : (byte & 0xf);
+            uint group = first_group + i / G;
+            float weight = float(code - int(zero_points[group])) * scales[group];
+            sum += A[row * K + i] * weight;
+        }
+        C[row * N + col] = sum;
+    }
+}
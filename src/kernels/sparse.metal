@@ -0,0 +1,120 @@
+//
+// Sparse Matrix Kernels
+//
+// These kernels multiply a sparse matrix in compressed sparse row (CSR) format
+// by a dense vector (SpMV) or a dense matrix (SpMM).
+//
+// Two strategies are provided for each product:
+// - scalar: one thread per output element walks the non-zeros of its row
+// - vector: one SIMD group (32 threads) per output element splits the non-zeros
+//   of its row across lanes and reduces with simd_sum, which is faster for rows
+//   with many non-zeros
+//
+// Parameters:
+// - row_offsets: Start of each row in col_indices/values (rows + 1 entries)
+// - col_indices: Column index of each non-zero
+// - values: Value of each non-zero
+// - x / B: Dense input vector or matrix (K × N, row-major)
+// - y / C: Dense output vector or matrix (M × N, row-major)
+// - rows: Number of rows of the sparse matrix (M)
+// - n: Number of columns of the dense matrix (N)
+//
+
+#include <metal_stdlib>
+using namespace metal;
+
+constant uint SIMD_WIDTH = 32;
+
+// y = A * x, one thread per row
+kernel void spmv_scalar(device const uint* row_offsets,
+                        device const uint* col_indices,
+                        device const float* values,
+                        device const float* x,
+                        device float* y,
+                        constant uint& rows,
+                        uint row [[thread_position_in_grid]])
+{
+    if (row < rows) {
+        float sum = 0.0f;
+        for (uint i = row_offsets[row]; i < row_offsets[row + 1]; i++) {
+            sum += values[i] * x[col_indices[i]];
+        }
+        y[row] = sum;
+    }
+}
+
+// y = A * x, one SIMD group per row
+kernel void spmv_vector(device const uint* row_offsets,
+                        device const uint* col_indices,
+                        device const float* values,
+                        device const float* x,
+                        device float* y,
+                        constant uint& rows,
+                        uint index [[thread_position_in_grid]],
+                        uint lane [[thread_index_in_simdgroup]])
+{
+    uint row = index / SIMD_WIDTH;
+    if (row >= rows) {
+        return;
+    }
+
+    float sum = 0.0f;
+    for (uint i = row_offsets[row] + lane; i < row_offsets[row + 1]; i += SIMD_WIDTH) {
+        sum += values[i] * x[col_indices[i]];
+    }
+
+    sum = simd_sum(sum);
+    if (lane == 0) {
+        y[row] = sum;
+    }
+}
+
+// C = A * B, one thread per output element
+kernel void spmm_scalar(device const uint* row_offsets,
+                        device const uint* col_indices,
+                        device const float* values,
+                        device const float* B,
+                        device float* C,
+                        constant uint& rows,
+                        constant uint& n,
+                        uint2 position [[thread_position_in_grid]])
+{
+    uint row = position.y;
+    uint col = position.x;
+
+    if (row < rows && col < n) {
+        float sum = 0.0f;
+        for (uint i = row_offsets[row]; i < row_offsets[row + 1]; i++) {
+            sum += values[i] * B[col_indices[i] * n + col];
+        }
+        C[row * n + col] = sum;
+    }
+}
+
+// C = A * B, one SIMD group per output element
+kernel void spmm_vector(device const uint* row_offsets,
+                        device const uint* col_indices,
+                        device const float* values,
+                        device const float* B,
+                        device float* C,
+                        constant uint& rows,
+                        constant uint& n,
+                        uint2 position [[thread_position_in_grid]],
+                        uint lane [[thread_index_in_simdgroup]])
+{
+    uint row = position.y;
+    uint col = position.x / SIMD_WIDTH;
+    if (row >= rows || col >= n) {
+        return;
+    }
+
+    float sum = 0.0f;
+    for (uint i = row_offsets[row] + lane; i < row_offsets[row + 1]; i += SIMD_WIDTH) {
+        sum += values[i] * B[col_indices[i] * n + col];
+    }
+
+    sum = simd_sum(sum);
+    if (lane == 0) {
+        C[row * n + col] = sum;
+    }
+}
@@ -0,0 +1,869 @@
+/*!
+ * # Lazy Expressions
+ *
+ * This module provides a lazy expression API that records matrix operations as a
+ * graph and evaluates the whole graph at once, instead of launching one kernel and
+ * waiting for one readback per operation.
+ *
+ * ```
+ * use metal_matrix::Matrix;
+ *
+ * let a = Matrix::with_data(2, 2, vec![1.0, -2.0, 3.0, -4.0]).unwrap();
+ * let b = Matrix::identity(2);
+ * let c = Matrix::with_data(2, 2, vec![0.5, 0.5, 0.5, 0.5]).unwrap();
+ *
+ * let expr = a.lazy().matmul(&b).add(&c).relu();
+ * assert_eq!(expr.shape().unwrap(), (2, 2));
+ *
+ * // One kernel: the addition and ReLU are folded into the matmul
+ * let plan = expr.compile().unwrap();
+ * assert_eq!(plan.kernels().len(), 1);
+ *
+ * assert_eq!(plan.eval_cpu().unwrap().data, vec![1.5, 0.0, 3.5, 0.0]);
+ * ```
+ *
+ * ## Fusion
+ *
+ * `Expr::compile` turns the graph into a `Plan` of generated Metal kernels:
+ * - chains and trees of element-wise operations become a single kernel that reads
+ *   each operand once and writes only the final result
+ * - an element-wise expression consuming a matrix product is folded into the matmul
+ *   kernel as an epilogue applied to each accumulated element
+ * - results used more than once, and operands of a matmul, are written to their own
+ *   buffer and shared
+ *
+ * Scalars are passed to the kernels as arguments rather than written into the
+ * source, so expressions that differ only in their scalars share compiled pipelines.
+ *
+ * Evaluating a plan on the GPU encodes all of its kernels into a single command
+ * buffer and waits once. `Plan::eval_cpu` interprets the same kernels on the CPU, so
+ * the fusion pass can be tested without a GPU.
+ *
+ * Shapes are checked as the graph is built; a mismatch is reported by `shape`,
+ * `compile` and the `eval` methods.
+ */
+
+use crate::limits::check_32bit;
+use crate::matrix::Matrix;
+use crate::metal_context::{set_constant, MetalContext};
+use anyhow::{anyhow, Result};
+use metal::*;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::rc::Rc;
+
+/// A lazily evaluated matrix expression.
+///
+/// Expressions are cheap to clone; clones share the same graph node, so an
+/// expression used twice is computed once.
+#[derive(Clone)]
+pub struct Expr {
+    node: Rc<Node>,
+}
+
+struct Node {
+    op: Op,
+    shape: std::result::Result<(usize, usize), String>,
+}
+
+enum Op {
+    Input(Matrix),
+    MatMul(Expr, Expr),
+    Binary(BinaryOp, Expr, Expr),
+    Unary(UnaryOp, Expr),
+    Scale(f32, Expr),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum UnaryOp {
+    Neg,
+    Relu,
+    Sigmoid,
+    Tanh,
+    Exp,
+}
+
+/// The kind of computation a fused kernel performs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KernelKind {
+    /// Element-wise operations over equally shaped operands
+    Elementwise,
+    /// A matrix product followed by an element-wise epilogue
+    MatMul,
+}
+
+/// A generated kernel of a compiled `Plan`.
+pub struct FusedKernel {
+    name: String,
+    source: String,
+    kind: KernelKind,
+    /// Slots of the A and B operands of a matmul kernel
+    matmul: Option<(usize, usize)>,
+    /// Slots read element-wise, in parameter order
+    loads: Vec<usize>,
+    /// Values of the `scalars` argument
+    scalars: Vec<f32>,
+    instructions: Vec<Instruction>,
+    output: usize,
+}
+
+/// One step of a fused kernel; each instruction defines the next register.
+#[derive(Clone, Copy, Debug)]
+enum Instruction {
+    Load(usize),
+    Accumulator,
+    Binary(BinaryOp, usize, usize),
+    Unary(UnaryOp, usize),
+    /// Multiplies a register by an entry of the kernel's scalars
+    Scale(usize, usize),
+}
+
+/// A compiled expression: a sequence of fused kernels over a set of buffers.
+///
+/// Buffers are identified by slot; the first slots hold the input matrices and the
+/// rest hold kernel outputs.
+pub struct Plan {
+    inputs: Vec<Expr>,
+    shapes: Vec<(usize, usize)>,
+    kernels: Vec<FusedKernel>,
+    output: usize,
+}
+
+impl Matrix {
+    /// Starts a lazy expression from this matrix.
+    ///
+    /// The matrix data is copied into the expression.
+    pub fn lazy(&self) -> Expr {
+        Expr::from(self)
+    }
+}
+
+impl From<Matrix> for Expr {
+    fn from(matrix: Matrix) -> Self {
+        Expr::new(Op::Input(matrix))
+    }
+}
+
+impl From<&Matrix> for Expr {
+    fn from(matrix: &Matrix) -> Self {
+        Expr::new(Op::Input(matrix.clone()))
+    }
+}
+
+impl From<&Expr> for Expr {
+    fn from(expr: &Expr) -> Self {
+        expr.clone()
+    }
+}
+
+impl Expr {
+    fn new(op: Op) -> Self {
+        let shape = infer_shape(&op);
+        Self {
+            node: Rc::new(Node { op, shape }),
+        }
+    }
+
+    /// Returns the shape `(rows, cols)` of the expression's result.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any operation in the expression has incompatible
+    /// operand shapes.
+    pub fn shape(&self) -> Result<(usize, usize)> {
+        self.node.shape.clone().map_err(|e| anyhow!(e))
+    }
+
+    /// Matrix product: `self * other`.
+    pub fn matmul(&self, other: impl Into<Expr>) -> Expr {
+        Expr::new(Op::MatMul(self.clone(), other.into()))
+    }
+
+    /// Element-wise sum: `self + other`.
+    pub fn add(&self, other: impl Into<Expr>) -> Expr {
+        Expr::new(Op::Binary(BinaryOp::Add, self.clone(), other.into()))
+    }
+
+    /// Element-wise difference: `self - other`.
+    pub fn sub(&self, other: impl Into<Expr>) -> Expr {
+        Expr::new(Op::Binary(BinaryOp::Sub, self.clone(), other.into()))
+    }
+
+    /// Element-wise (Hadamard) product.
+    pub fn mul(&self, other: impl Into<Expr>) -> Expr {
+        Expr::new(Op::Binary(BinaryOp::Mul, self.clone(), other.into()))
+    }
+
+    /// Multiplies every element by a scalar.
+    pub fn scale(&self, scalar: f32) -> Expr {
+        Expr::new(Op::Scale(scalar, self.clone()))
+    }
+
+    /// Negates every element.
+    pub fn neg(&self) -> Expr {
+        Expr::new(Op::Unary(UnaryOp::Neg, self.clone()))
+    }
+
+    /// Rectified linear unit: `max(x, 0)`.
+    pub fn relu(&self) -> Expr {
+        Expr::new(Op::Unary(UnaryOp::Relu, self.clone()))
+    }
+
+    /// Logistic sigmoid: `1 / (1 + exp(-x))`.
+    pub fn sigmoid(&self) -> Expr {
+        Expr::new(Op::Unary(UnaryOp::Sigmoid, self.clone()))
+    }
+
+    /// Hyperbolic tangent.
+    pub fn tanh(&self) -> Expr {
+        Expr::new(Op::Unary(UnaryOp::Tanh, self.clone()))
+    }
+
+    /// Element-wise exponential.
+    pub fn exp(&self) -> Expr {
+        Expr::new(Op::Unary(UnaryOp::Exp, self.clone()))
+    }
+
+    /// Compiles the expression into a plan of fused kernels.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the expression has incompatible operand shapes.
+    pub fn compile(&self) -> Result<Plan> {
+        self.shape()?;
+        Ok(Compiler::new(self).compile())
+    }
+
+    /// Evaluates the expression on the GPU in a single command buffer.
+    ///
+    /// # Arguments
+    ///
+    /// * `context` - The Metal context for GPU computation
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the result matrix or an error.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the expression has incompatible operand shapes or a
+    /// generated kernel fails to compile.
+    pub fn eval(&self, context: &MetalContext) -> Result<Matrix> {
+        self.compile()?.eval(context)
+    }
+
+    /// Evaluates the expression on the CPU by interpreting the fused kernels.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the expression has incompatible operand shapes.
+    pub fn eval_cpu(&self) -> Result<Matrix> {
+        self.compile()?.eval_cpu()
+    }
+
+    fn children(&self) -> Vec<&Expr> {
+        match &self.node.op {
+            Op::Input(_) => vec![],
+            Op::MatMul(a, b) | Op::Binary(_, a, b) => vec![a, b],
+            Op::Unary(_, a) | Op::Scale(_, a) => vec![a],
+        }
+    }
+
+    fn id(&self) -> *const Node {
+        Rc::as_ptr(&self.node)
+    }
+}
+
+fn infer_shape(op: &Op) -> std::result::Result<(usize, usize), String> {
+    match op {
+        Op::Input(matrix) => Ok((matrix.rows, matrix.cols)),
+        Op::MatMul(a, b) => {
+            let (m, k) = a.node.shape.clone()?;
+            let (rows_b, n) = b.node.shape.clone()?;
+            if k != rows_b {
+                return Err("Matrix dimensions incompatible for multiplication".to_string());
+            }
+            Ok((m, n))
+        }
+        Op::Binary(op, a, b) => {
+            let shape = a.node.shape.clone()?;
+            if shape != b.node.shape.clone()? {
+                let name = match op {
+                    BinaryOp::Add => "addition",
+                    BinaryOp::Sub => "subtraction",
+                    BinaryOp::Mul => "element-wise multiplication",
+                };
+                return Err(format!("Matrix dimensions must match for {}", name));
+            }
+            Ok(shape)
+        }
+        Op::Unary(_, a) | Op::Scale(_, a) => a.node.shape.clone(),
+    }
+}
+
+/// Fusion pass state: the graph in post-order with use counts and buffer slots.
+struct Compiler<'a> {
+    /// Nodes in post-order (operands before their users); the root is last
+    nodes: Vec<&'a Expr>,
+    index: HashMap<*const Node, usize>,
+    uses: Vec<usize>,
+    /// Whether a node's result is written to its own buffer
+    materialized: Vec<bool>,
+    /// The matmul folded into each element-wise kernel root, if any
+    epilogue_of: HashMap<usize, usize>,
+    slots: HashMap<usize, usize>,
+}
+
+impl<'a> Compiler<'a> {
+    fn new(root: &'a Expr) -> Self {
+        let mut compiler = Self {
+            nodes: Vec::new(),
+            index: HashMap::new(),
+            uses: Vec::new(),
+            materialized: Vec::new(),
+            epilogue_of: HashMap::new(),
+            slots: HashMap::new(),
+        };
+        compiler.visit(root);
+        compiler
+    }
+
+    fn visit(&mut self, expr: &'a Expr) -> usize {
+        if let Some(&i) = self.index.get(&expr.id()) {
+            return i;
+        }
+        for child in expr.children() {
+            let c = self.visit(child);
+            self.uses[c] += 1;
+        }
+        let i = self.nodes.len();
+        self.nodes.push(expr);
+        self.uses.push(0);
+        self.index.insert(expr.id(), i);
+        i
+    }
+
+    fn node(&self, i: usize) -> &'a Op {
+        &self.nodes[i].node.op
+    }
+
+    fn child_ids(&self, i: usize) -> Vec<usize> {
+        self.nodes[i]
+            .children()
+            .iter()
+            .map(|c| self.index[&c.id()])
+            .collect()
+    }
+
+    fn shape(&self, i: usize) -> (usize, usize) {
+        self.nodes[i].node.shape.clone().unwrap()
+    }
+
+    fn compile(mut self) -> Plan {
+        let n = self.nodes.len();
+        let root = n - 1;
+
+        // Inputs, matmuls and their operands, shared results and the root get buffers
+        self.materialized = (0..n)
+            .map(|i| {
+                let op = self.node(i);
+                matches!(op, Op::Input(_) | Op::MatMul(..)) || self.uses[i] > 1 || i == root
+            })
+            .collect();
+        for i in 0..n {
+            if let Op::MatMul(..) = self.node(i) {
+                for c in self.child_ids(i) {
+                    self.materialized[c] = true;
+                }
+            }
+        }
+
+        // Fold a matmul used only by an element-wise kernel into that kernel
+        for i in 0..n {
+            if self.materialized[i] && is_elementwise(self.node(i)) {
+                let folded = self.region_leaves(i).into_iter().find(|&leaf| {
+                    matches!(self.node(leaf), Op::MatMul(..)) && self.uses[leaf] == 1
+                });
+                if let Some(matmul) = folded {
+                    self.epilogue_of.insert(i, matmul);
+                }
+            }
+        }
+        let folded: Vec<usize> = self.epilogue_of.values().copied().collect();
+
+        // Inputs take the first slots, then each kernel output in evaluation order
+        let mut inputs = Vec::new();
+        let mut shapes = Vec::new();
+        for i in 0..n {
+            if let Op::Input(_) = self.node(i) {
+                self.slots.insert(i, inputs.len());
+                inputs.push(self.nodes[i].clone());
+                shapes.push(self.shape(i));
+            }
+        }
+
+        let mut kernels = Vec::new();
+        for i in 0..n {
+            let op = self.node(i);
+            if !self.materialized[i] || matches!(op, Op::Input(_)) || folded.contains(&i) {
+                continue;
+            }
+            let slot = shapes.len();
+            shapes.push(self.shape(i));
+            self.slots.insert(i, slot);
+            kernels.push(self.kernel(i, format!("fused_{}", kernels.len()), slot));
+        }
+
+        Plan {
+            inputs,
+            shapes,
+            kernels,
+            output: self.slots[&root],
+        }
+    }
+
+    /// Returns the materialized nodes read by the element-wise kernel rooted at `i`.
+    fn region_leaves(&self, i: usize) -> Vec<usize> {
+        let mut leaves = Vec::new();
+        let mut stack = self.child_ids(i);
+        while let Some(c) = stack.pop() {
+            if self.materialized[c] {
+                leaves.push(c);
+            } else {
+                stack.extend(self.child_ids(c));
+            }
+        }
+        leaves
+    }
+
+    fn kernel(&self, i: usize, name: String, output: usize) -> FusedKernel {
+        let mut builder = KernelBuilder::default();
+
+        let (kind, matmul) = match (self.node(i), self.epilogue_of.get(&i)) {
+            (Op::MatMul(..), _) => {
+                builder.instructions.push(Instruction::Accumulator);
+                (KernelKind::MatMul, Some(self.operand_slots(i)))
+            }
+            (_, Some(&folded)) => {
+                builder.folded = Some(folded);
+                self.emit_op(i, &mut builder);
+                (KernelKind::MatMul, Some(self.operand_slots(folded)))
+            }
+            _ => {
+                self.emit_op(i, &mut builder);
+                (KernelKind::Elementwise, None)
+            }
+        };
+
+        let source = generate_source(
+            &name,
+            kind,
+            builder.loads.len(),
+            !builder.scalars.is_empty(),
+            &builder.instructions,
+        );
+        FusedKernel {
+            name,
+            source,
+            kind,
+            matmul,
+            loads: builder.loads,
+            scalars: builder.scalars,
+            instructions: builder.instructions,
+            output,
+        }
+    }
+
+    fn operand_slots(&self, matmul: usize) -> (usize, usize) {
+        let children = self.child_ids(matmul);
+        (self.slots[&children[0]], self.slots[&children[1]])
+    }
+
+    /// Emits the instructions computing node `c` as an operand within a kernel.
+    fn emit(&self, c: usize, builder: &mut KernelBuilder) -> usize {
+        if builder.folded == Some(c) {
+            builder.push(Instruction::Accumulator)
+        } else if self.materialized[c] {
+            let slot = self.slots[&c];
+            let load = match builder.loads.iter().position(|&s| s == slot) {
+                Some(load) => load,
+                None => {
+                    builder.loads.push(slot);
+                    builder.loads.len() - 1
+                }
+            };
+            builder.push(Instruction::Load(load))
+        } else {
+            self.emit_op(c, builder)
+        }
+    }
+
+    /// Emits the instructions computing the operation of node `i` itself.
+    fn emit_op(&self, i: usize, builder: &mut KernelBuilder) -> usize {
+        let children = self.child_ids(i);
+        let instruction = match self.node(i) {
+            Op::Binary(op, ..) => {
+                let a = self.emit(children[0], builder);
+                let b = self.emit(children[1], builder);
+                Instruction::Binary(*op, a, b)
+            }
+            Op::Unary(op, _) => Instruction::Unary(*op, self.emit(children[0], builder)),
+            Op::Scale(scalar, _) => {
+                let a = self.emit(children[0], builder);
+                builder.scalars.push(*scalar);
+                Instruction::Scale(builder.scalars.len() - 1, a)
+            }
+            Op::Input(_) | Op::MatMul(..) => unreachable!("not an element-wise operation"),
+        };
+        builder.push(instruction)
+    }
+}
+
+fn is_elementwise(op: &Op) -> bool {
+    matches!(op, Op::Binary(..) | Op::Unary(..) | Op::Scale(..))
+}
+
+#[derive(Default)]
+struct KernelBuilder {
+    loads: Vec<usize>,
+    scalars: Vec<f32>,
+    instructions: Vec<Instruction>,
+    folded: Option<usize>,
+}
+
+impl KernelBuilder {
+    fn push(&mut self, instruction: Instruction) -> usize {
+        self.instructions.push(instruction);
+        self.instructions.len() - 1
+    }
+}
+
+/// Generates the Metal source of a fused kernel.
+fn generate_source(
+    name: &str,
+    kind: KernelKind,
+    loads: usize,
+    scalars: bool,
+    instructions: &[Instruction],
+) -> String {
+    let mut parameters = Vec::new();
+    if kind == KernelKind::MatMul {
+        parameters.push("device const float* A".to_string());
+        parameters.push("device const float* B".to_string());
+    }
+    for load in 0..loads {
+        parameters.push(format!("device const float* in{}", load));
+    }
+    if scalars {
+        parameters.push("constant float* scalars".to_string());
+    }
+    parameters.push("device float* out".to_string());
+    match kind {
+        KernelKind::Elementwise => {
+            parameters.push("constant uint& count".to_string());
+            parameters.push("uint id [[thread_position_in_grid]]".to_string());
+        }
+        KernelKind::MatMul => {
+            parameters.push("constant uint& M".to_string());
+            parameters.push("constant uint& N".to_string());
+            parameters.push("constant uint& K".to_string());
+            parameters.push("uint2 position [[thread_position_in_grid]]".to_string());
+        }
+    }
+
+    let signature = format!("kernel void {}(", name);
+    let indent = " ".repeat(signature.len());
+
+    let mut source = String::new();
+    source.push_str("#include <metal_stdlib>\nusing namespace metal;\n\n");
+    source.push_str(&signature);
+    source.push_str(&parameters.join(&format!(",\n{}", indent)));
+    source.push_str(")\n{\n");
+
+    match kind {
+        KernelKind::Elementwise => {
+            source.push_str("    if (id >= count) {\n        return;\n    }\n\n");
+        }
+        KernelKind::MatMul => {
+            source.push_str(concat!(
+                "    uint row = position.y;\n",
+                "    uint col = position.x;\n",
+                "    if (row >= M || col >= N) {\n",
+                "        return;\n",
+                "    }\n",
+                "\n",
+                "    float acc = 0.0f;\n",
+                "    for (uint i = 0; i < K; i++) {\n",
+                "        acc += A[row * K + i] * B[i * N + col];\n",
+                "    }\n",
+                "\n",
+                "    uint id = row * N + col;\n",
+            ));
+        }
+    }
+
+    for (r, instruction) in instructions.iter().enumerate() {
+        let value = match *instruction {
+            Instruction::Load(load) => format!("in{}[id]", load),
+            Instruction::Accumulator => "acc".to_string(),
+            Instruction::Binary(op, a, b) => {
+                let symbol = match op {
+                    BinaryOp::Add => "+",
+                    BinaryOp::Sub => "-",
+                    BinaryOp::Mul => "*",
+                };
+                format!("v{} {} v{}", a, symbol, b)
+            }
+            Instruction::Unary(op, a) => match op {
+                UnaryOp::Neg => format!("-v{}", a),
+                UnaryOp::Relu => format!("max(v{}, 0.0f)", a),
+                UnaryOp::Sigmoid => format!("1.0f / (1.0f + exp(-v{}))", a),
+                UnaryOp::Tanh => format!("tanh(v{})", a),
+                UnaryOp::Exp => format!("exp(v{})", a),
+            },
+            Instruction::Scale(scalar, a) => format!("v{} * scalars[{}]", a, scalar),
+        };
+        writeln!(source, "    float v{} = {};", r, value).unwrap();
+    }
+    writeln!(source, "    out[id] = v{};", instructions.len() - 1).unwrap();
+    source.push_str("}\n");
+
+    source
+}
+
+impl FusedKernel {
+    /// Returns the kernel function name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the generated Metal source code.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Returns the kind of computation the kernel performs.
+    pub fn kind(&self) -> KernelKind {
+        self.kind
+    }
+
+    /// Computes one output element from the accumulated matmul value and the loaded
+    /// operands, mirroring the generated source.
+    fn evaluate(&self, acc: f32, load: impl Fn(usize) -> f32, registers: &mut Vec<f32>) -> f32 {
+        registers.clear();
+        for instruction in &self.instructions {
+            let value = match *instruction {
+                Instruction::Load(l) => load(l),
+                Instruction::Accumulator => acc,
+                Instruction::Binary(op, a, b) => match op {
+                    BinaryOp::Add => registers[a] + registers[b],
+                    BinaryOp::Sub => registers[a] - registers[b],
+                    BinaryOp::Mul => registers[a] * registers[b],
+                },
+                Instruction::Unary(op, a) => match op {
+                    UnaryOp::Neg => -registers[a],
+                    UnaryOp::Relu => registers[a].max(0.0),
+                    UnaryOp::Sigmoid => 1.0 / (1.0 + (-registers[a]).exp()),
+                    UnaryOp::Tanh => registers[a].tanh(),
+                    UnaryOp::Exp => registers[a].exp(),
+                },
+                Instruction::Scale(scalar, a) => registers[a] * self.scalars[scalar],
+            };
+            registers.push(value);
+        }
+        registers[registers.len() - 1]
+    }
+}
+
+impl Plan {
+    /// Returns the generated kernels in evaluation order.
+    pub fn kernels(&self) -> &[FusedKernel] {
+        &self.kernels
+    }
+
+    /// Runs the plan on the GPU, encoding every kernel into one command buffer.
+    ///
+    /// # Arguments
+    ///
+    /// * `context` - The Metal context for GPU computation
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the result matrix or an error.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LimitError` if a matrix in the plan exceeds the 32-bit kernel
+    /// limits or does not fit in a device buffer, or an error if a kernel fails to
+    /// compile.
+    pub fn eval(&self, context: &MetalContext) -> Result<Matrix> {
+        if let Some(Op::Input(matrix)) = self.inputs.get(self.output).map(|e| &e.node.op) {
+            return Ok(matrix.clone());
+        }
+        let (rows, cols) = self.shapes[self.output];
+        if rows * cols == 0 {
+            return Ok(Matrix::new(rows, cols));
+        }
+
+        // Fused kernels index with 32 bits
+        let limits = context.limits();
+        for &(rows, cols) in &self.shapes {
+            check_32bit(rows, cols)?;
+            limits.check_buffer(rows * cols)?;
+        }
+
+        let pipelines = self
+            .kernels
+            .iter()
+            .map(|kernel| context.load_kernel_from_source(&kernel.source, &kernel.name))
+            .collect::<Result<Vec<_>>>()?;
+
+        let buffers: Vec<Buffer> = (0..self.shapes.len())
+            .map(|slot| match self.inputs.get(slot).map(|e| &e.node.op) {
+                // Metal has no zero-length buffers; empty operands are never read
+                Some(Op::Input(matrix)) if !matrix.data.is_empty() => {
+                    context.acquire_buffer_with_data(&matrix.data)
+                }
+                _ => {
+                    let (rows, cols) = self.shapes[slot];
+                    context.acquire_buffer::<f32>((rows * cols).max(1))
+                }
+            })
+            .collect();
+        let scalar_buffers: Vec<Option<Buffer>> = self
+            .kernels
+            .iter()
+            .map(|kernel| {
+                (!kernel.scalars.is_empty())
+                    .then(|| context.acquire_buffer_with_data(&kernel.scalars))
+            })
+            .collect();
+
+        // Dimension arguments: `count` for element-wise kernels, `M, N, K` for matmuls
+        let dimensions: Vec<Vec<u32>> = self
+            .kernels
+            .iter()
+            .map(|kernel| {
+                let (rows, cols) = self.shapes[kernel.output];
+                match kernel.matmul {
+                    None => vec![(rows * cols) as u32],
+                    Some((a, _)) => vec![rows as u32, cols as u32, self.shapes[a].1 as u32],
+                }
+            })
+            .collect();
+
+        // Dispatches within one compute encoder run in order, so each kernel sees the
+        // outputs of the kernels before it.
+        let result = context.execute_compute(|encoder| {
+            for (((kernel, pipeline), dimensions), scalars) in self
+                .kernels
+                .iter()
+                .zip(&pipelines)
+                .zip(&dimensions)
+                .zip(&scalar_buffers)
+            {
+                let (rows, cols) = self.shapes[kernel.output];
+                if rows * cols == 0 {
+                    continue;
+                }
+
+                encoder.set_compute_pipeline_state(pipeline);
+                let mut index = 0;
+                let mut bind = |buffer: &Buffer| {
+                    encoder.set_buffer(index, Some(buffer), 0);
+                    index += 1;
+                };
+                if let Some((a, b)) = kernel.matmul {
+                    bind(&buffers[a]);
+                    bind(&buffers[b]);
+                }
+                for &slot in &kernel.loads {
+                    bind(&buffers[slot]);
+                }
+                if let Some(scalars) = scalars {
+                    bind(scalars);
+                }
+                bind(&buffers[kernel.output]);
+                for &dimension in dimensions {
+                    set_constant(encoder, index, dimension);
+                    index += 1;
+                }
+
+                let (width, height) = match kernel.kind {
+                    KernelKind::Elementwise => (rows * cols, 1),
+                    KernelKind::MatMul => (cols, rows),
+                };
+                let grid_size = MTLSize::new(width as u64, height as u64, 1);
+                let threadgroup_size =
+                    context.threadgroup_size(&kernel.name, width, height, pipeline);
+                encoder.dispatch_threads(grid_size, threadgroup_size);
+            }
+        });
+
+        let result_ptr = buffers[self.output].contents() as *const f32;
+        let mut result_data = vec![0.0f32; rows * cols];
+        unsafe {
+            std::ptr::copy_nonoverlapping(result_ptr, result_data.as_mut_ptr(), rows * cols);
+        }
+        for buffer in buffers
+            .into_iter()
+            .chain(scalar_buffers.into_iter().flatten())
+        {
+            context.recycle_buffer(buffer);
+        }
+        result?;
+
+        Matrix::with_data(rows, cols, result_data)
+    }
+
+    /// Runs the plan on the CPU by interpreting each fused kernel.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the result matrix or an error.
+    pub fn eval_cpu(&self) -> Result<Matrix> {
+        let mut values: Vec<Vec<f32>> = self
+            .inputs
+            .iter()
+            .map(|expr| match &expr.node.op {
+                Op::Input(matrix) => matrix.data.clone(),
+                _ => unreachable!("plan inputs are input nodes"),
+            })
+            .collect();
+
+        let mut registers = Vec::new();
+        for kernel in &self.kernels {
+            let (rows, cols) = self.shapes[kernel.output];
+            let mut output = vec![0.0f32; rows * cols];
+
+            for row in 0..rows {
+                for col in 0..cols {
+                    let id = row * cols + col;
+                    let acc = match kernel.matmul {
+                        Some((a, b)) => {
+                            let k = self.shapes[a].1;
+                            (0..k).fold(0.0f32, |acc, i| {
+                                acc + values[a][row * k + i] * values[b][i * cols + col]
+                            })
+                        }
+                        None => 0.0,
+                    };
+                    output[id] =
+                        kernel.evaluate(acc, |l| values[kernel.loads[l]][id], &mut registers);
+                }
+            }
+
+            values.push(output);
+        }
+
+        let (rows, cols) = self.shapes[self.output];
+        Matrix::with_data(rows, cols, values.swap_remove(self.output))
+    }
+}
@@ -12,7 +12,31 @@
  * - GPU-accelerated matrix operations
  * - Clean, ergonomic API
  * - Support for vectors as 1D matrices
- * - CPU fallback implementations
+ * - CPU reference implementations and a pluggable `Backend` trait
+ * - Multithreaded, cache-blocked SIMD matrix multiplication on the CPU
+ * - Optional system BLAS/LAPACK backend (feature `blas`)
+ * - Cross-backend numerical verification with per-operation tolerances
+ * - f16/bf16 matrices and mixed-precision multiplication with f32 accumulation
+ * - Int8/int4 weight quantization with multiplication that dequantizes on the fly
+ * - Triangular solves and multiplication (TRSM/TRMM)
+ * - Iterative Krylov solvers (CG, BiCGSTAB, GMRES) with preconditioners
+ * - Matrix functions (exponential, logarithm, square root, integer power)
+ * - Sparse matrices (COO, CSR, CSC) with GPU sparse-dense products
+ * - Matrix Market file reading and writing
+ * - NumPy .npy/.npz import and export
+ * - CSV and delimited text import and export
+ * - Memory-mapped safetensors loading of named weight matrices
+ * - Memory-mapped matrices with out-of-core multiplication
+ * - Lazy expressions that fuse operations into generated kernels
+ * - Batched execution of several operations in one command buffer
+ * - Asynchronous operations returning runtime-agnostic futures
+ * - Pooled reuse of GPU buffers across operations
+ * - Device enumeration and selection on multi-GPU systems
+ * - Automatic CPU/GPU dispatch from a calibrated cost model
+ * - Threadgroup size autotuning with JSON tuning profiles
+ * - Typed errors for shapes beyond kernel index and device buffer limits, and
+ *   64-bit-index kernels for very large element-wise operations
+ * - Optional `serde` support for `Matrix` (feature `serde`)
  * - Comprehensive error handling
  *
  * ## Example
@@ -52,6 +76,81 @@ pub mod operations;
 /// Matrix data structure and methods
 pub mod matrix;
 
+/// Backend abstraction over GPU and CPU execution
+pub mod backend;
+
+/// CPU reference implementations
+pub mod cpu;
+
+/// Triangular solves and multiplication
+pub mod triangular;
+
+/// Iterative solvers for large linear systems
+pub mod solvers;
+
+/// Matrix exponential, logarithm, square root and power
+pub mod matrix_functions;
+
+/// Sparse matrix formats and sparse-dense products
+pub mod sparse;
+
+/// Readers and writers for matrix file formats
+pub mod io;
+
+/// Memory-mapped file-backed matrices
+pub mod mapped;
+
+/// Lazy expression graphs with kernel fusion
+pub mod lazy;
+
+/// Batched command recording into a single command buffer
+pub mod batch;
+
+/// Pending results of asynchronous GPU operations
+pub mod pending;
+
+/// Size-bucketed pool of reusable GPU buffers
+pub mod buffer_pool;
+
+/// Enumeration and selection of Metal devices
+pub mod device;
+
+/// Cost-based routing of operations between the CPU and the GPU
+pub mod dispatch;
+
+/// Threadgroup size autotuning with persisted profiles
+pub mod autotune;
+
+/// Kernel index and device buffer limits on matrix shapes
+pub mod limits;
+
+/// Operations backed by the system BLAS and LAPACK
+#[cfg(feature = "blas")]
+pub mod blas;
+
+/// Numerical verification of one backend against another
+pub mod verify;
+
+/// Half-precision matrices and mixed-precision matrix multiplication
+pub mod precision;
+
+/// Weight-only int8/int4 quantization and quantized matrix multiplication
+pub mod quantization;
+
+/// Serialize and Deserialize implementations for Matrix
+#[cfg(feature = "serde")]
+mod serialization;
+
+pub use backend::{Backend, CpuBackend, SelectedBackend};
+pub use batch::{BatchMatrix, BatchResults, CommandRecorder};
+pub use dispatch::{AutoBackend, ExecutionPolicy};
+pub use lazy::{Expr, FusedKernel, KernelKind, Plan};
+pub use mapped::*;
 pub use matrix::Matrix;
+pub use matrix_functions::*;
 pub use metal_context::MetalContext;
 pub use operations::*;
+pub use pending::PendingMatrix;
+pub use solvers::*;
+pub use sparse::*;
+pub use triangular::*;
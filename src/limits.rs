@@ -0,0 +1,178 @@
+/*!
+ * # Kernel and Device Limits
+ *
+ * This module checks matrix shapes against the limits of the kernels and the device
+ * before any buffer is created.
+ *
+ * Most kernels take their dimensions as `uint` and index with 32-bit arithmetic, so
+ * every dimension and every element count they address must fit in a `u32`; larger
+ * values would silently wrap. The element-wise operations switch to 64-bit-index
+ * kernel variants instead (see `IndexWidth`). Independently of the index type, no
+ * buffer may be longer than the device's `max_buffer_length`.
+ *
+ * Violations are reported as a `LimitError` wrapped in the usual `anyhow::Error`;
+ * use `downcast_ref::<LimitError>()` to tell them apart from other failures. The
+ * checks work on plain numbers, so they can be exercised without a GPU.
+ *
+ * ```
+ * use metal_matrix::limits::{DeviceLimits, IndexWidth, LimitError};
+ *
+ * let limits = DeviceLimits {
+ *     max_buffer_length: 1 << 42,
+ * };
+ *
+ * // 2^20 x 2^20 elements can be added with 64-bit indices, but not multiplied
+ * let n = 1 << 20;
+ * assert_eq!(limits.check_elementwise(n, n), Ok(IndexWidth::U64));
+ * assert_eq!(
+ *     limits.check_matrix_multiply(n, n, n),
+ *     Err(LimitError::IndexOverflow { elements: 1 << 40 })
+ * );
+ * ```
+ */
+
+use metal::Device;
+use std::mem::size_of;
+
+/// The largest dimension or element count a 32-bit kernel can address.
+pub const MAX_32BIT_INDEX: u64 = u32::MAX as u64;
+
+/// A shape that exceeds a kernel or device limit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum LimitError {
+    /// A dimension does not fit in the `uint` a kernel takes it as
+    #[error("Matrix dimension {dimension} exceeds the kernel limit of {MAX_32BIT_INDEX}")]
+    DimensionTooLarge {
+        /// The offending dimension
+        dimension: usize,
+    },
+
+    /// A matrix has more elements than a 32-bit kernel index can address
+    #[error(
+        "Matrix of {elements} elements exceeds the 32-bit kernel index limit of {MAX_32BIT_INDEX}"
+    )]
+    IndexOverflow {
+        /// Number of elements in the matrix
+        elements: u64,
+    },
+
+    /// A buffer would be longer than the device allows
+    #[error("Buffer of {bytes} bytes exceeds the device's maximum buffer length of {max} bytes")]
+    BufferTooLarge {
+        /// Length of the buffer in bytes
+        bytes: u64,
+
+        /// The device's `max_buffer_length`
+        max: u64,
+    },
+}
+
+/// The index type of the kernel an element-wise operation runs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IndexWidth {
+    /// One thread per element, indexed with `uint`
+    U32,
+
+    /// A fixed number of threads striding over the elements with `ulong` indices
+    U64,
+}
+
+impl IndexWidth {
+    /// Returns the index width needed to address `elements` elements.
+    pub fn for_elements(elements: usize) -> Self {
+        if elements as u64 <= MAX_32BIT_INDEX {
+            IndexWidth::U32
+        } else {
+            IndexWidth::U64
+        }
+    }
+}
+
+/// Checks that a `rows` × `cols` matrix can be addressed by a 32-bit kernel: both
+/// dimensions and the element count fit in a `u32`.
+///
+/// # Errors
+///
+/// Returns `DimensionTooLarge` or `IndexOverflow` if they do not.
+pub fn check_32bit(rows: usize, cols: usize) -> Result<(), LimitError> {
+    for dimension in [rows, cols] {
+        if dimension as u64 > MAX_32BIT_INDEX {
+            return Err(LimitError::DimensionTooLarge { dimension });
+        }
+    }
+
+    let elements = rows as u64 * cols as u64;
+    if elements > MAX_32BIT_INDEX {
+        return Err(LimitError::IndexOverflow { elements });
+    }
+    Ok(())
+}
+
+/// Limits of a Metal device that bound the matrices it can process.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DeviceLimits {
+    /// The longest buffer the device can allocate, in bytes
+    pub max_buffer_length: u64,
+}
+
+impl DeviceLimits {
+    /// Reads the limits of a Metal device.
+    pub fn from_device(device: &Device) -> Self {
+        Self {
+            max_buffer_length: device.max_buffer_length(),
+        }
+    }
+
+    /// Checks that a buffer of `elements` `f32` values fits on the device.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BufferTooLarge` if it does not.
+    pub fn check_buffer(&self, elements: usize) -> Result<(), LimitError> {
+        let bytes = (elements as u64).saturating_mul(size_of::<f32>() as u64);
+        if bytes > self.max_buffer_length {
+            return Err(LimitError::BufferTooLarge {
+                bytes,
+                max: self.max_buffer_length,
+            });
+        }
+        Ok(())
+    }
+
+    /// Checks an element-wise operation on `rows` × `cols` matrices and returns the
+    /// index width of the kernel to run.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BufferTooLarge` if a matrix does not fit in a buffer.
+    pub fn check_elementwise(&self, rows: usize, cols: usize) -> Result<IndexWidth, LimitError> {
+        let elements = rows.saturating_mul(cols);
+        self.check_buffer(elements)?;
+        Ok(IndexWidth::for_elements(elements))
+    }
+
+    /// Checks a product of an `m` × `k` and a `k` × `n` matrix.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a dimension or the element count of an operand or the
+    /// result exceeds the 32-bit kernel limits, or a matrix does not fit in a buffer.
+    pub fn check_matrix_multiply(&self, m: usize, k: usize, n: usize) -> Result<(), LimitError> {
+        for (rows, cols) in [(m, k), (k, n), (m, n)] {
+            check_32bit(rows, cols)?;
+            self.check_buffer(rows * cols)?;
+        }
+        Ok(())
+    }
+
+    /// Checks a transpose of a `rows` × `cols` matrix.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a dimension or the element count exceeds the 32-bit
+    /// kernel limits, or the matrix does not fit in a buffer.
+    pub fn check_transpose(&self, rows: usize, cols: usize) -> Result<(), LimitError> {
+        check_32bit(rows, cols)?;
+        self.check_buffer(rows * cols)
+    }
+}
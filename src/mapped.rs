@@ -0,0 +1,366 @@
+/*!
+ * # Memory-Mapped Matrices
+ *
+ * This module provides file-backed matrices for data sets larger than memory.
+ *
+ * A mapped matrix file is a 64-byte header followed by the values in row-major
+ * order as little-endian `f32` (the native byte order of every Metal device):
+ *
+ * | Offset | Size | Contents                       |
+ * |--------|------|--------------------------------|
+ * | 0      | 8    | Magic bytes `METALMAT`         |
+ * | 8      | 8    | Rows (`u64`, little-endian)    |
+ * | 16     | 8    | Columns (`u64`, little-endian) |
+ * | 24     | 4    | Data type (`u32`, 0 = `f32`)   |
+ * | 28     | 36   | Reserved (zero)                |
+ *
+ * The file is memory-mapped, so rows and row blocks are borrowed straight from the
+ * mapping without copying, and only the pages that are touched are read from disk.
+ * `matrix_multiply_mapped` streams row blocks of a mapped matrix through a backend,
+ * so neither operand nor result ever has to fit in memory as a whole.
+ */
+
+use crate::backend::Backend;
+use crate::matrix::Matrix;
+use anyhow::{bail, Context, Result};
+use memmap2::{Mmap, MmapMut};
+use std::fs::{File, OpenOptions};
+use std::path::Path;
+
+const MAGIC: &[u8; 8] = b"METALMAT";
+
+/// Size of the file header; the data that follows stays aligned for `f32`.
+const HEADER_SIZE: usize = 64;
+
+/// Data type code of `f32` values.
+const DTYPE_F32: u32 = 0;
+
+/// A read-only matrix backed by a memory-mapped file.
+///
+/// # Example
+///
+/// ```
+/// use metal_matrix::{MappedMatrix, Matrix};
+///
+/// let path = std::env::temp_dir().join("metal_matrix_doc_mapped.bin");
+/// let a = Matrix::with_data(3, 2, vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]).unwrap();
+/// MappedMatrix::write(&path, &a).unwrap();
+///
+/// let mapped = MappedMatrix::open(&path).unwrap();
+/// assert_eq!((mapped.rows, mapped.cols), (3, 2));
+/// assert_eq!(mapped.row(1), &[3.0, 4.0]);
+/// assert_eq!(mapped.row_block(1, 2), &[3.0, 4.0, 5.0, 6.0]);
+/// ```
+pub struct MappedMatrix {
+    /// Number of rows in the matrix
+    pub rows: usize,
+
+    /// Number of columns in the matrix
+    pub cols: usize,
+
+    mmap: Mmap,
+}
+
+/// A writable matrix backed by a memory-mapped file.
+///
+/// Changes are written back to the file by the operating system; call `flush` to
+/// write them synchronously, or `into_read_only` once the matrix is complete.
+pub struct MappedMatrixMut {
+    /// Number of rows in the matrix
+    pub rows: usize,
+
+    /// Number of columns in the matrix
+    pub cols: usize,
+
+    mmap: MmapMut,
+}
+
+impl MappedMatrix {
+    /// Opens and memory-maps a matrix file.
+    ///
+    /// The file must not be modified by another process while it is open.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the file
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the mapped matrix or an error.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be mapped, its header is invalid, or its
+    /// size does not match the dimensions in the header.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let file = File::open(path)
+            .with_context(|| format!("Failed to open matrix file: {}", path.display()))?;
+
+        // SAFETY: the mapping is read-only, and the caller is required not to modify
+        // the file while it is open.
+        let mmap = unsafe { Mmap::map(&file) }
+            .with_context(|| format!("Failed to map matrix file: {}", path.display()))?;
+        let (rows, cols) = read_header(&mmap)?;
+
+        Ok(Self { rows, cols, mmap })
+    }
+
+    /// Writes an in-memory matrix to a matrix file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the file to create
+    /// * `matrix` - The matrix to write
+    ///
+    /// # Returns
+    ///
+    /// A `Result` indicating success or an error.
+    pub fn write<P: AsRef<Path>>(path: P, matrix: &Matrix) -> Result<()> {
+        let mut mapped = MappedMatrixMut::create(path, matrix.rows, matrix.cols)?;
+        mapped.data_mut().copy_from_slice(&matrix.data);
+        mapped.flush()
+    }
+
+    /// Returns all values in row-major order without copying.
+    pub fn data(&self) -> &[f32] {
+        bytemuck::cast_slice(&self.mmap[HEADER_SIZE..])
+    }
+
+    /// Returns a row without copying.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `row` is out of bounds.
+    pub fn row(&self, row: usize) -> &[f32] {
+        self.row_block(row, 1)
+    }
+
+    /// Returns `count` consecutive rows starting at `start`, in row-major order,
+    /// without copying.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the rows are out of bounds.
+    pub fn row_block(&self, start: usize, count: usize) -> &[f32] {
+        assert!(start + count <= self.rows, "Row block out of bounds");
+        &self.data()[start * self.cols..(start + count) * self.cols]
+    }
+
+    /// Copies `count` consecutive rows starting at `start` into a matrix.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the rows are out of bounds.
+    pub fn block(&self, start: usize, count: usize) -> Matrix {
+        Matrix {
+            rows: count,
+            cols: self.cols,
+            data: self.row_block(start, count).to_vec(),
+        }
+    }
+
+    /// Copies the whole matrix into memory.
+    pub fn to_matrix(&self) -> Matrix {
+        self.block(0, self.rows)
+    }
+}
+
+impl MappedMatrixMut {
+    /// Creates a zero-filled matrix file and maps it for writing.
+    ///
+    /// An existing file at `path` is replaced.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the file to create
+    /// * `rows` - Number of rows
+    /// * `cols` - Number of columns
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the mapped matrix or an error.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use metal_matrix::MappedMatrixMut;
+    ///
+    /// let path = std::env::temp_dir().join("metal_matrix_doc_mapped_mut.bin");
+    /// let mut mapped = MappedMatrixMut::create(&path, 2, 3).unwrap();
+    /// mapped.row_mut(1).copy_from_slice(&[1.0, 2.0, 3.0]);
+    ///
+    /// let mapped = mapped.into_read_only().unwrap();
+    /// assert_eq!(mapped.data(), &[0.0, 0.0, 0.0, 1.0, 2.0, 3.0]);
+    /// ```
+    pub fn create<P: AsRef<Path>>(path: P, rows: usize, cols: usize) -> Result<Self> {
+        let path = path.as_ref();
+        let length = rows
+            .checked_mul(cols)
+            .and_then(|n| n.checked_mul(std::mem::size_of::<f32>()))
+            .and_then(|n| n.checked_add(HEADER_SIZE))
+            .context("Matrix dimensions too large")?;
+
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)
+            .with_context(|| format!("Failed to create matrix file: {}", path.display()))?;
+        file.set_len(length as u64)?;
+
+        // SAFETY: the file was just created by us, and the caller is required not to
+        // modify it through other means while it is mapped.
+        let mut mmap = unsafe { MmapMut::map_mut(&file) }
+            .with_context(|| format!("Failed to map matrix file: {}", path.display()))?;
+
+        mmap[..8].copy_from_slice(MAGIC);
+        mmap[8..16].copy_from_slice(&(rows as u64).to_le_bytes());
+        mmap[16..24].copy_from_slice(&(cols as u64).to_le_bytes());
+        mmap[24..28].copy_from_slice(&DTYPE_F32.to_le_bytes());
+
+        Ok(Self { rows, cols, mmap })
+    }
+
+    /// Returns all values in row-major order without copying.
+    pub fn data(&self) -> &[f32] {
+        bytemuck::cast_slice(&self.mmap[HEADER_SIZE..])
+    }
+
+    /// Returns all values in row-major order for writing.
+    pub fn data_mut(&mut self) -> &mut [f32] {
+        bytemuck::cast_slice_mut(&mut self.mmap[HEADER_SIZE..])
+    }
+
+    /// Returns a row for writing.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `row` is out of bounds.
+    pub fn row_mut(&mut self, row: usize) -> &mut [f32] {
+        self.row_block_mut(row, 1)
+    }
+
+    /// Returns `count` consecutive rows starting at `start` for writing.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the rows are out of bounds.
+    pub fn row_block_mut(&mut self, start: usize, count: usize) -> &mut [f32] {
+        assert!(start + count <= self.rows, "Row block out of bounds");
+        let cols = self.cols;
+        &mut self.data_mut()[start * cols..(start + count) * cols]
+    }
+
+    /// Writes all changes to the file.
+    pub fn flush(&self) -> Result<()> {
+        self.mmap.flush().context("Failed to flush matrix file")
+    }
+
+    /// Writes all changes to the file and converts the mapping to read-only.
+    pub fn into_read_only(self) -> Result<MappedMatrix> {
+        self.flush()?;
+        Ok(MappedMatrix {
+            rows: self.rows,
+            cols: self.cols,
+            mmap: self.mmap.make_read_only()?,
+        })
+    }
+}
+
+/// Multiplies a memory-mapped matrix by an in-memory matrix: C = A * B
+///
+/// A is processed in blocks of `block_rows` rows; each block is copied from the
+/// mapping, multiplied by B on the backend, and written to the matching rows of the
+/// mapped output, so memory use is bounded by the block size rather than the size
+/// of A or C.
+///
+/// # Arguments
+///
+/// * `backend` - The backend to multiply each block on
+/// * `a` - The mapped matrix (m × k)
+/// * `b` - The in-memory matrix (k × n)
+/// * `block_rows` - Number of rows of A per block
+/// * `output` - The mapped result matrix (m × n)
+///
+/// # Returns
+///
+/// A `Result` indicating success or an error.
+///
+/// # Errors
+///
+/// Returns an error if the dimensions are incompatible, `block_rows` is zero, or a
+/// block multiplication fails.
+///
+/// # Example
+///
+/// ```
+/// use metal_matrix::{matrix_multiply_mapped, CpuBackend, MappedMatrix, MappedMatrixMut, Matrix};
+///
+/// let dir = std::env::temp_dir();
+/// let a = Matrix::with_data(3, 2, vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]).unwrap();
+/// MappedMatrix::write(dir.join("metal_matrix_doc_a.bin"), &a).unwrap();
+///
+/// let a = MappedMatrix::open(dir.join("metal_matrix_doc_a.bin")).unwrap();
+/// let b = Matrix::identity(2);
+/// let mut c = MappedMatrixMut::create(dir.join("metal_matrix_doc_c.bin"), 3, 2).unwrap();
+///
+/// matrix_multiply_mapped(&CpuBackend, &a, &b, 2, &mut c).unwrap();
+/// assert_eq!(c.data(), a.data());
+/// ```
+pub fn matrix_multiply_mapped<B: Backend>(
+    backend: &B,
+    a: &MappedMatrix,
+    b: &Matrix,
+    block_rows: usize,
+    output: &mut MappedMatrixMut,
+) -> Result<()> {
+    if a.cols != b.rows {
+        bail!("Matrix dimensions incompatible for multiplication");
+    }
+    if output.rows != a.rows || output.cols != b.cols {
+        bail!("Output matrix dimensions do not match the product");
+    }
+    if block_rows == 0 {
+        bail!("Block size must be at least one row");
+    }
+
+    for start in (0..a.rows).step_by(block_rows) {
+        let count = block_rows.min(a.rows - start);
+        let product = backend.matrix_multiply(&a.block(start, count), b)?;
+        output
+            .row_block_mut(start, count)
+            .copy_from_slice(&product.data);
+    }
+
+    Ok(())
+}
+
+/// Validates the header and size of a mapped file, returning its dimensions.
+fn read_header(bytes: &[u8]) -> Result<(usize, usize)> {
+    if bytes.len() < HEADER_SIZE || &bytes[..8] != MAGIC {
+        bail!("Invalid matrix file: bad header");
+    }
+
+    let rows = u64::from_le_bytes(bytes[8..16].try_into().unwrap()) as usize;
+    let cols = u64::from_le_bytes(bytes[16..24].try_into().unwrap()) as usize;
+    let dtype = u32::from_le_bytes(bytes[24..28].try_into().unwrap());
+    if dtype != DTYPE_F32 {
+        bail!("Invalid matrix file: unsupported data type {}", dtype);
+    }
+
+    let length = rows
+        .checked_mul(cols)
+        .and_then(|n| n.checked_mul(std::mem::size_of::<f32>()))
+        .and_then(|n| n.checked_add(HEADER_SIZE));
+    if length != Some(bytes.len()) {
+        bail!(
+            "Invalid matrix file: size does not match a {}x{} matrix",
+            rows,
+            cols
+        );
+    }
+
+    Ok((rows, cols))
+}
@@ -196,11 +196,18 @@ impl Matrix {
     ///
     /// # Errors
     ///
-    /// Returns an error if the matrix is not a vector.
+    /// Returns an error if the matrix is not a vector or the index is out of bounds.
     pub fn vector_get(&self, index: usize) -> Result<f32> {
         if !self.is_vector() {
             anyhow::bail!("Not a vector");
         }
+        if index >= self.data.len() {
+            anyhow::bail!(
+                "Vector index {} out of bounds for length {}",
+                index,
+                self.data.len()
+            );
+        }
 
         if self.cols == 1 {
             Ok(self.get(index, 0))
@@ -223,6 +230,7 @@ impl Matrix {
     ///
     /// Panics if the row index is out of bounds.
     pub fn row(&self, row: usize) -> Self {
+        assert!(row < self.rows, "Row index {} out of bounds", row);
         let mut data = Vec::with_capacity(self.cols);
         for col in 0..self.cols {
             data.push(self.get(row, col));
@@ -248,6 +256,7 @@ impl Matrix {
     ///
     /// Panics if the column index is out of bounds.
     pub fn column(&self, col: usize) -> Self {
+        assert!(col < self.cols, "Column index {} out of bounds", col);
         let mut data = Vec::with_capacity(self.rows);
         for row in 0..self.rows {
             data.push(self.get(row, col));
@@ -258,4 +267,101 @@ impl Matrix {
             data,
         }
     }
+
+    /// Extract the lower triangle of the matrix.
+    ///
+    /// Elements on and below the `k`-th diagonal are kept and all others are set to zero.
+    /// `k = 0` is the main diagonal, `k > 0` is above it and `k < 0` is below it.
+    ///
+    /// # Arguments
+    ///
+    /// * `k` - Diagonal offset
+    ///
+    /// # Returns
+    ///
+    /// A new matrix of the same dimensions containing the lower triangle.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use metal_matrix::Matrix;
+    ///
+    /// let a = Matrix::with_data(2, 2, vec![1.0, 2.0, 3.0, 4.0]).unwrap();
+    /// assert_eq!(a.lower_triangle(0).data, vec![1.0, 0.0, 3.0, 4.0]);
+    /// assert_eq!(a.lower_triangle(-1).data, vec![0.0, 0.0, 3.0, 0.0]);
+    /// ```
+    pub fn lower_triangle(&self, k: isize) -> Self {
+        let mut result = self.clone();
+        for row in 0..self.rows {
+            for col in 0..self.cols {
+                if (col as isize) > (row as isize) + k {
+                    result.set(row, col, 0.0);
+                }
+            }
+        }
+        result
+    }
+
+    /// Extract the upper triangle of the matrix.
+    ///
+    /// Elements on and above the `k`-th diagonal are kept and all others are set to zero.
+    /// `k = 0` is the main diagonal, `k > 0` is above it and `k < 0` is below it.
+    ///
+    /// # Arguments
+    ///
+    /// * `k` - Diagonal offset
+    ///
+    /// # Returns
+    ///
+    /// A new matrix of the same dimensions containing the upper triangle.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use metal_matrix::Matrix;
+    ///
+    /// let a = Matrix::with_data(2, 2, vec![1.0, 2.0, 3.0, 4.0]).unwrap();
+    /// assert_eq!(a.upper_triangle(0).data, vec![1.0, 2.0, 0.0, 4.0]);
+    /// assert_eq!(a.upper_triangle(1).data, vec![0.0, 2.0, 0.0, 0.0]);
+    /// ```
+    pub fn upper_triangle(&self, k: isize) -> Self {
+        let mut result = self.clone();
+        for row in 0..self.rows {
+            for col in 0..self.cols {
+                if (col as isize) < (row as isize) + k {
+                    result.set(row, col, 0.0);
+                }
+            }
+        }
+        result
+    }
+
+    /// Copy a rectangular block out of the matrix.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the block extends past the matrix bounds.
+    pub(crate) fn submatrix(&self, row: usize, col: usize, rows: usize, cols: usize) -> Self {
+        assert!(row + rows <= self.rows && col + cols <= self.cols);
+        let mut data = Vec::with_capacity(rows * cols);
+        for r in row..row + rows {
+            let start = r * self.cols + col;
+            data.extend_from_slice(&self.data[start..start + cols]);
+        }
+        Self { rows, cols, data }
+    }
+
+    /// Overwrite a rectangular block of the matrix, with its top-left corner at (row, col).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the block extends past the matrix bounds.
+    pub(crate) fn set_submatrix(&mut self, row: usize, col: usize, block: &Matrix) {
+        assert!(row + block.rows <= self.rows && col + block.cols <= self.cols);
+        for r in 0..block.rows {
+            let start = (row + r) * self.cols + col;
+            self.data[start..start + block.cols]
+                .copy_from_slice(&block.data[r * block.cols..(r + 1) * block.cols]);
+        }
+    }
 }
@@ -0,0 +1,342 @@
+/*!
+ * # Matrix Functions
+ *
+ * This module provides functions of square matrices.
+ *
+ * ## Available Functions
+ *
+ * - Matrix exponential (`expm`), by scaling and squaring with Padé approximants
+ * - Matrix logarithm (`logm`), by inverse scaling and squaring
+ * - Matrix square root (`sqrtm`), by the product form of the Denman–Beavers iteration
+ * - Integer matrix power (`matrix_power`), by repeated squaring
+ *
+ * All functions are written against the `Backend` trait. The matrix products that
+ * dominate their cost go through `matrix_multiply` on the backend (the GPU when a
+ * `MetalContext` is passed), while the occasional linear solve or inverse uses the
+ * backend's `solve` and `inverse` (LU decomposition on the CPU, or LAPACK with
+ * `BlasBackend`).
+ */
+
+use crate::backend::Backend;
+use crate::cpu;
+use crate::matrix::Matrix;
+use anyhow::Result;
+
+/// Maximum 1-norm for which each Padé approximant of exp is accurate to single
+/// precision (Higham, "The Scaling and Squaring Method for the Matrix Exponential
+/// Revisited", 2005), paired with the approximant coefficients.
+const PADE_APPROXIMANTS: [(f32, &[f32]); 3] = [
+    (4.258_73e-1, &[120.0, 60.0, 12.0, 1.0]),
+    (1.880_152_7, &[30240.0, 15120.0, 3360.0, 420.0, 30.0, 1.0]),
+    (
+        3.925_724_7,
+        &[
+            17_297_280.0,
+            8_648_640.0,
+            1_995_840.0,
+            277_200.0,
+            25_200.0,
+            1512.0,
+            56.0,
+            1.0,
+        ],
+    ),
+];
+
+/// Gauss–Legendre nodes and weights on [0, 1] used for the Padé approximant of log(I + X).
+const GAUSS_LEGENDRE: [(f64, f64); 7] = [
+    (0.025_446_043_828_620_74, 0.064_742_483_084_434_85),
+    (0.129_234_407_200_302_78, 0.139_852_695_744_638_33),
+    (0.297_077_424_311_301_4, 0.190_915_025_252_559_47),
+    (0.5, 0.208_979_591_836_734_7),
+    (0.702_922_575_688_698_6, 0.190_915_025_252_559_47),
+    (0.870_765_592_799_697_2, 0.139_852_695_744_638_33),
+    (0.974_553_956_171_379_3, 0.064_742_483_084_434_85),
+];
+
+/// Maximum number of Denman–Beavers iterations before `sqrtm` gives up.
+const SQRTM_MAX_ITERATIONS: usize = 50;
+
+/// Error below which `sqrtm` accepts an iterate once the error stops decreasing.
+const STAGNATION_THRESHOLD: f32 = 1e-3;
+
+/// Maximum number of square roots `logm` takes to bring its argument close to I.
+const LOGM_MAX_SQUARE_ROOTS: usize = 40;
+
+/// Computes the matrix exponential e^A.
+///
+/// Uses scaling and squaring: A is scaled by 2^-s until its 1-norm is small enough for
+/// a Padé approximant of degree 3, 5 or 7 to be accurate, and the result is squared s
+/// times with `matrix_multiply`.
+///
+/// # Arguments
+///
+/// * `backend` - The backend the products run on (e.g. a `MetalContext`)
+/// * `a` - The input matrix (n × n)
+///
+/// # Returns
+///
+/// A `Result` containing e^A (n × n) or an error.
+///
+/// # Errors
+///
+/// Returns an error if `a` is not square or contains non-finite values.
+///
+/// # Example
+///
+/// ```
+/// use metal_matrix::{expm, CpuBackend, Matrix};
+///
+/// // The exponential of a rotation generator is a rotation
+/// let theta = 0.5f32;
+/// let generator = Matrix::with_data(2, 2, vec![0.0, -theta, theta, 0.0]).unwrap();
+///
+/// let rotation = expm(&CpuBackend, &generator).unwrap();
+/// assert!((rotation.get(0, 0) - theta.cos()).abs() < 1e-6);
+/// assert!((rotation.get(1, 0) - theta.sin()).abs() < 1e-6);
+/// ```
+pub fn expm<B: Backend>(backend: &B, a: &Matrix) -> Result<Matrix> {
+    check_square(a)?;
+    let norm = one_norm(a);
+    if !norm.is_finite() {
+        anyhow::bail!("Matrix exponential requires finite values");
+    }
+
+    let n = a.rows;
+    let (theta, coefficients) = PADE_APPROXIMANTS
+        .iter()
+        .find(|(theta, _)| norm <= *theta)
+        .copied()
+        .unwrap_or(PADE_APPROXIMANTS[PADE_APPROXIMANTS.len() - 1]);
+
+    let squarings = if norm > theta {
+        (norm / theta).log2().ceil() as i32
+    } else {
+        0
+    };
+    let a = backend.matrix_scalar_multiply(2f32.powi(-squarings), a)?;
+
+    // p(A) = V + U and q(A) = V - U, where U holds the odd and V the even powers
+    let a2 = backend.matrix_multiply(&a, &a)?;
+    let mut power = Matrix::identity(n);
+    let mut odd = backend.matrix_scalar_multiply(coefficients[1], &power)?;
+    let mut even = backend.matrix_scalar_multiply(coefficients[0], &power)?;
+    for pair in coefficients[2..].chunks(2) {
+        power = backend.matrix_multiply(&power, &a2)?;
+        even = backend.matrix_add(&even, &backend.matrix_scalar_multiply(pair[0], &power)?)?;
+        if let Some(&coefficient) = pair.get(1) {
+            odd =
+                backend.matrix_add(&odd, &backend.matrix_scalar_multiply(coefficient, &power)?)?;
+        }
+    }
+    let odd = backend.matrix_multiply(&a, &odd)?;
+
+    let p = backend.matrix_add(&even, &odd)?;
+    let q = backend.matrix_subtract(&even, &odd)?;
+    let mut result = backend.solve(&q, &p)?;
+
+    for _ in 0..squarings {
+        result = backend.matrix_multiply(&result, &result)?;
+    }
+
+    Ok(result)
+}
+
+/// Computes the principal matrix logarithm log(A).
+///
+/// Uses inverse scaling and squaring: square roots are taken with `sqrtm` until A is
+/// close to the identity, log(I + X) is evaluated with a Padé approximant, and the
+/// result is scaled back up.
+///
+/// # Arguments
+///
+/// * `backend` - The backend the products run on (e.g. a `MetalContext`)
+/// * `a` - The input matrix (n × n), with no eigenvalues on the closed negative real axis
+///
+/// # Returns
+///
+/// A `Result` containing log(A) (n × n) or an error.
+///
+/// # Errors
+///
+/// Returns an error if `a` is not square, or has no real principal logarithm
+/// (detected as a failure of the square root iteration).
+///
+/// # Example
+///
+/// ```
+/// use metal_matrix::{logm, CpuBackend, Matrix};
+///
+/// let e = std::f32::consts::E;
+/// let a = Matrix::with_data(2, 2, vec![e, 0.0, 0.0, e * e]).unwrap();
+///
+/// let log = logm(&CpuBackend, &a).unwrap();
+/// assert!((log.get(0, 0) - 1.0).abs() < 1e-5);
+/// assert!((log.get(1, 1) - 2.0).abs() < 1e-5);
+/// assert!(log.get(0, 1).abs() < 1e-5);
+/// ```
+pub fn logm<B: Backend>(backend: &B, a: &Matrix) -> Result<Matrix> {
+    check_square(a)?;
+
+    let n = a.rows;
+    let identity = Matrix::identity(n);
+    let mut root = a.clone();
+    let mut square_roots = 0;
+
+    while one_norm(&cpu::matrix_subtract(&root, &identity)?) > 0.25 {
+        if square_roots == LOGM_MAX_SQUARE_ROOTS {
+            anyhow::bail!("Matrix logarithm did not converge");
+        }
+        root = sqrtm(backend, &root)?;
+        square_roots += 1;
+    }
+
+    // log(I + X) = sum_j w_j * X * (I + x_j * X)^-1
+    let x = backend.matrix_subtract(&root, &identity)?;
+    let mut log = Matrix::new(n, n);
+    for (node, weight) in GAUSS_LEGENDRE {
+        let shifted =
+            backend.matrix_add(&identity, &backend.matrix_scalar_multiply(node as f32, &x)?)?;
+        let term = backend.solve(&shifted, &x)?;
+        log = backend.matrix_add(&log, &backend.matrix_scalar_multiply(weight as f32, &term)?)?;
+    }
+
+    backend.matrix_scalar_multiply(2f32.powi(square_roots as i32), &log)
+}
+
+/// Computes the principal matrix square root sqrt(A).
+///
+/// Uses the product form of the Denman–Beavers iteration, which needs one inverse and
+/// one `matrix_multiply` per step.
+///
+/// # Arguments
+///
+/// * `backend` - The backend the products run on (e.g. a `MetalContext`)
+/// * `a` - The input matrix (n × n), with no eigenvalues on the closed negative real axis
+///
+/// # Returns
+///
+/// A `Result` containing sqrt(A) (n × n) or an error.
+///
+/// # Errors
+///
+/// Returns an error if `a` is not square, an iterate becomes singular, or the
+/// iteration does not converge (e.g. because A has negative eigenvalues).
+///
+/// # Example
+///
+/// ```
+/// use metal_matrix::{sqrtm, CpuBackend, Matrix};
+///
+/// let a = Matrix::with_data(2, 2, vec![4.0, 0.0, 0.0, 9.0]).unwrap();
+///
+/// let root = sqrtm(&CpuBackend, &a).unwrap();
+/// assert!((root.get(0, 0) - 2.0).abs() < 1e-5);
+/// assert!((root.get(1, 1) - 3.0).abs() < 1e-5);
+/// ```
+pub fn sqrtm<B: Backend>(backend: &B, a: &Matrix) -> Result<Matrix> {
+    check_square(a)?;
+
+    let n = a.rows;
+    let identity = Matrix::identity(n);
+    let tolerance = (n.max(1) as f32) * 1e-6;
+
+    // M_k -> I and Y_k -> sqrt(A), with
+    // M_{k+1} = (I + (M_k + M_k^-1) / 2) / 2 and Y_{k+1} = Y_k * (I + M_k^-1) / 2
+    let mut m = a.clone();
+    let mut y = a.clone();
+    let mut previous_error = f32::INFINITY;
+
+    for _ in 0..SQRTM_MAX_ITERATIONS {
+        let m_inverse = backend.inverse(&m)?;
+        let half_sum = backend.matrix_scalar_multiply(0.5, &backend.matrix_add(&m, &m_inverse)?)?;
+        m = backend.matrix_scalar_multiply(0.5, &backend.matrix_add(&identity, &half_sum)?)?;
+        let factor =
+            backend.matrix_scalar_multiply(0.5, &backend.matrix_add(&identity, &m_inverse)?)?;
+        y = backend.matrix_multiply(&y, &factor)?;
+
+        let error = one_norm(&cpu::matrix_subtract(&m, &identity)?);
+        if !error.is_finite() {
+            break;
+        }
+        // Near convergence rounding can stall the error just above the tolerance
+        if error <= tolerance || (error < STAGNATION_THRESHOLD && error >= previous_error) {
+            return Ok(y);
+        }
+        previous_error = error;
+    }
+
+    anyhow::bail!("Matrix square root did not converge")
+}
+
+/// Computes the integer matrix power A^p.
+///
+/// Uses repeated squaring, so only O(log |p|) calls to `matrix_multiply` are made.
+/// Negative powers invert A first; A^0 is the identity.
+///
+/// # Arguments
+///
+/// * `backend` - The backend the products run on (e.g. a `MetalContext`)
+/// * `a` - The input matrix (n × n)
+/// * `p` - The exponent
+///
+/// # Returns
+///
+/// A `Result` containing A^p (n × n) or an error.
+///
+/// # Errors
+///
+/// Returns an error if `a` is not square, or `p` is negative and `a` is singular.
+///
+/// # Example
+///
+/// ```
+/// use metal_matrix::{matrix_power, CpuBackend, Matrix};
+///
+/// // Powers of the Fibonacci matrix
+/// let a = Matrix::with_data(2, 2, vec![1.0, 1.0, 1.0, 0.0]).unwrap();
+///
+/// let result = matrix_power(&CpuBackend, &a, 10).unwrap();
+/// assert_eq!(result.data, vec![89.0, 55.0, 55.0, 34.0]);
+/// ```
+pub fn matrix_power<B: Backend>(backend: &B, a: &Matrix, p: i32) -> Result<Matrix> {
+    check_square(a)?;
+
+    let mut base = if p < 0 {
+        backend.inverse(a)?
+    } else {
+        a.clone()
+    };
+    let mut exponent = p.unsigned_abs();
+    let mut result: Option<Matrix> = None;
+
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = Some(match result {
+                Some(r) => backend.matrix_multiply(&r, &base)?,
+                None => base.clone(),
+            });
+        }
+        exponent >>= 1;
+        if exponent > 0 {
+            base = backend.matrix_multiply(&base, &base)?;
+        }
+    }
+
+    Ok(result.unwrap_or_else(|| Matrix::identity(a.rows)))
+}
+
+fn check_square(a: &Matrix) -> Result<()> {
+    if a.rows != a.cols {
+        anyhow::bail!("Matrix function requires a square matrix");
+    }
+
+    Ok(())
+}
+
+/// The 1-norm (maximum absolute column sum) of a matrix.
+fn one_norm(a: &Matrix) -> f32 {
+    (0..a.cols)
+        .map(|col| (0..a.rows).map(|row| a.get(row, col).abs()).sum::<f32>())
+        .fold(0.0, f32::max)
+}
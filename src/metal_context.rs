@@ -8,9 +8,17 @@
  * compute operations.
  */
 
+use crate::autotune::TuningProfile;
+use crate::buffer_pool::{BufferPool, PoolStats};
+use crate::device::{DeviceInfo, DeviceSelector};
+use crate::limits::DeviceLimits;
 use anyhow::{Context, Result};
 use metal::*;
+use std::collections::HashMap;
+use std::ffi::c_void;
 use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex, RwLock};
 
 /// Manages the Metal context including device and command queue.
 ///
@@ -23,6 +31,18 @@ pub struct MetalContext {
 
     /// The command queue for submitting work to the GPU
     pub command_queue: CommandQueue,
+
+    /// Idle buffers kept for reuse, shared with pending results
+    pub(crate) buffer_pool: Arc<Mutex<BufferPool<Buffer>>>,
+
+    /// Pipelines loaded by `load_kernel`, by file path and function name
+    pipelines: Mutex<HashMap<(String, String), ComputePipelineState>>,
+
+    /// Pipelines compiled by `load_kernel_from_source`, by source and function name
+    source_pipelines: Mutex<HashMap<(String, String), ComputePipelineState>>,
+
+    /// Threadgroup sizes consulted when operations dispatch
+    tuning: RwLock<TuningProfile>,
 }
 
 impl MetalContext {
@@ -41,17 +61,77 @@ impl MetalContext {
     /// ```
     pub fn new() -> Result<Self> {
         let device = Device::system_default().context("No Metal device found")?;
+        Ok(Self::from_device(device))
+    }
+
+    /// Create a new Metal context on the device chosen by a selector.
+    ///
+    /// # Arguments
+    ///
+    /// * `selector` - How to choose among the devices returned by `list_devices`
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the new `MetalContext` or an error if no device matches.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use metal_matrix::device::DeviceSelector;
+    /// use metal_matrix::MetalContext;
+    ///
+    /// let context = MetalContext::with_device(DeviceSelector::HighestMemory).unwrap();
+    /// println!("Using {}", context.device_info().name);
+    /// ```
+    pub fn with_device(selector: DeviceSelector) -> Result<Self> {
+        let mut devices = Device::all();
+        let infos: Vec<DeviceInfo> = devices.iter().map(DeviceInfo::from_device).collect();
+        let index = selector
+            .select(&infos)
+            .with_context(|| format!("No Metal device matches {:?}", selector))?;
+
+        Ok(Self::from_device(devices.swap_remove(index)))
+    }
+
+    /// Create a new Metal context on the given device.
+    pub fn from_device(device: Device) -> Self {
         let command_queue = device.new_command_queue();
 
-        Ok(Self {
+        Self {
             device,
             command_queue,
-        })
+            buffer_pool: Arc::default(),
+            pipelines: Mutex::default(),
+            source_pipelines: Mutex::default(),
+            tuning: RwLock::default(),
+        }
+    }
+
+    /// List the Metal devices available on the system.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use metal_matrix::MetalContext;
+    ///
+    /// for device in MetalContext::list_devices() {
+    ///     println!("{} ({:#x})", device.name, device.registry_id);
+    /// }
+    /// ```
+    pub fn list_devices() -> Vec<DeviceInfo> {
+        Device::all().iter().map(DeviceInfo::from_device).collect()
+    }
+
+    /// Describe the device this context runs on.
+    pub fn device_info(&self) -> DeviceInfo {
+        DeviceInfo::from_device(&self.device)
     }
 
     /// Load a Metal kernel from a file.
     ///
     /// This method reads a Metal shader file, compiles it, and creates a compute pipeline.
+    /// Pipelines are cached, so later calls with the same file and function return the
+    /// pipeline without compiling again.
     ///
     /// # Arguments
     ///
@@ -66,19 +146,60 @@ impl MetalContext {
         file_path: &str,
         function_name: &str,
     ) -> Result<ComputePipelineState> {
+        let key = (file_path.to_string(), function_name.to_string());
+        if let Some(pipeline) = self.pipelines.lock().unwrap().get(&key) {
+            return Ok(pipeline.clone());
+        }
+
         let source = fs::read_to_string(file_path)
             .context(format!("Failed to read kernel file: {}", file_path))?;
 
+        let pipeline = self
+            .compile_kernel(&source, function_name)
+            .context(format!("Failed to load kernel file: {}", file_path))?;
+
+        self.pipelines.lock().unwrap().insert(key, pipeline.clone());
+        Ok(pipeline)
+    }
+
+    /// Compile a Metal kernel from source code.
+    ///
+    /// This is used for kernels generated at runtime, such as the fused kernels of a
+    /// lazy expression. Pipelines are cached by source and function name, so
+    /// evaluating the same expression again does not recompile its kernels.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - Metal shader source code
+    /// * `function_name` - Name of the kernel function to load
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the compute pipeline state or an error if compilation fails.
+    pub fn load_kernel_from_source(
+        &self,
+        source: &str,
+        function_name: &str,
+    ) -> Result<ComputePipelineState> {
+        let key = (source.to_string(), function_name.to_string());
+        if let Some(pipeline) = self.source_pipelines.lock().unwrap().get(&key) {
+            return Ok(pipeline.clone());
+        }
+
+        let pipeline = self.compile_kernel(source, function_name)?;
+        self.source_pipelines
+            .lock()
+            .unwrap()
+            .insert(key, pipeline.clone());
+        Ok(pipeline)
+    }
+
+    /// Compiles `function_name` from `source` without consulting either cache.
+    fn compile_kernel(&self, source: &str, function_name: &str) -> Result<ComputePipelineState> {
         let library = self
             .device
-            .new_library_with_source(&source, &CompileOptions::new())
-            .map_err(|e| {
-                anyhow::anyhow!(
-                    "Failed to create library from source: {} - {}",
-                    file_path,
-                    e
-                )
-            })?;
+            .new_library_with_source(source, &CompileOptions::new())
+            .map_err(|e| anyhow::anyhow!("Failed to create library from source: {}", e))?;
 
         let kernel = library
             .get_function(function_name, None)
@@ -139,6 +260,147 @@ impl MetalContext {
             .new_buffer(size, MTLResourceOptions::StorageModeShared)
     }
 
+    /// Take a buffer from the buffer pool, allocating one if none is idle.
+    ///
+    /// The buffer may be larger than requested. Pass it to `recycle_buffer` once the
+    /// GPU has finished with it so later operations can reuse it.
+    ///
+    /// # Arguments
+    ///
+    /// * `count` - Number of elements to allocate space for
+    ///
+    /// # Returns
+    ///
+    /// A Metal buffer with space for at least `count` elements of type `T`.
+    pub fn acquire_buffer<T>(&self, count: usize) -> Buffer {
+        let size = (count * std::mem::size_of::<T>()) as u64;
+        self.buffer_pool.lock().unwrap().acquire(size, |bucket| {
+            // A bucket past the device limit is not allocated; the exact size is,
+            // and the pool frees it on release since it is not a bucket size
+            let length = if bucket > self.device.max_buffer_length() {
+                size
+            } else {
+                bucket
+            };
+            self.device
+                .new_buffer(length, MTLResourceOptions::StorageModeShared)
+        })
+    }
+
+    /// Take a buffer from the buffer pool and copy data into it.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - Slice of data to copy into the buffer
+    ///
+    /// # Returns
+    ///
+    /// A Metal buffer whose leading bytes hold the data.
+    pub fn acquire_buffer_with_data<T: Copy>(&self, data: &[T]) -> Buffer {
+        let buffer = self.acquire_buffer::<T>(data.len());
+        unsafe {
+            std::ptr::copy_nonoverlapping(data.as_ptr(), buffer.contents() as *mut T, data.len());
+        }
+        buffer
+    }
+
+    /// Return a buffer to the buffer pool for reuse.
+    ///
+    /// The GPU must have finished with the buffer.
+    pub fn recycle_buffer(&self, buffer: Buffer) {
+        self.buffer_pool.lock().unwrap().release(buffer);
+    }
+
+    /// Returns the limits of the device, against which operations check shapes.
+    pub fn limits(&self) -> DeviceLimits {
+        DeviceLimits::from_device(&self.device)
+    }
+
+    /// Returns hit, miss and residency counters for the buffer pool.
+    pub fn buffer_pool_stats(&self) -> PoolStats {
+        self.buffer_pool.lock().unwrap().stats()
+    }
+
+    /// Sets the maximum number of bytes the buffer pool keeps in idle buffers.
+    pub fn set_buffer_pool_limit(&self, max_bytes: u64) {
+        self.buffer_pool.lock().unwrap().set_max_bytes(max_bytes);
+    }
+
+    /// Frees all idle buffers held by the buffer pool.
+    pub fn trim(&self) {
+        self.buffer_pool.lock().unwrap().trim();
+    }
+
+    /// Returns a copy of the threadgroup tuning profile.
+    pub fn tuning_profile(&self) -> TuningProfile {
+        self.tuning.read().unwrap().clone()
+    }
+
+    /// Replaces the threadgroup tuning profile consulted by operations.
+    pub fn set_tuning_profile(&self, profile: TuningProfile) {
+        *self.tuning.write().unwrap() = profile;
+    }
+
+    /// Loads a tuning profile saved with `TuningProfile::save` and uses it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or is not a valid profile.
+    pub fn load_tuning_profile<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        self.set_tuning_profile(TuningProfile::load(path)?);
+        Ok(())
+    }
+
+    pub(crate) fn update_tuning_profile<F>(&self, update: F)
+    where
+        F: FnOnce(&mut TuningProfile),
+    {
+        update(&mut self.tuning.write().unwrap());
+    }
+
+    /// Returns the threadgroup size for dispatching `kernel` over a `width` × `height`
+    /// grid: the tuned size for this device and shape if there is one, otherwise the
+    /// default heuristic.
+    pub fn threadgroup_size(
+        &self,
+        kernel: &str,
+        width: usize,
+        height: usize,
+        pipeline: &ComputePipelineStateRef,
+    ) -> MTLSize {
+        let size = self.tuning.read().unwrap().threadgroup_size(
+            kernel,
+            self.device.name(),
+            width,
+            height,
+            pipeline.max_total_threads_per_threadgroup(),
+        );
+        MTLSize::new(size.width, size.height, 1)
+    }
+
+    /// Returns the threadgroup size for dispatching `kernel`, in which each SIMD group
+    /// of `simd_width` threads works together, over a `width` × `height` grid. Like
+    /// `threadgroup_size`, but tuned sizes whose rows are not whole SIMD groups are
+    /// ignored.
+    pub fn simd_threadgroup_size(
+        &self,
+        kernel: &str,
+        width: usize,
+        height: usize,
+        simd_width: u64,
+        pipeline: &ComputePipelineStateRef,
+    ) -> MTLSize {
+        let size = self.tuning.read().unwrap().simd_threadgroup_size(
+            kernel,
+            self.device.name(),
+            width,
+            height,
+            pipeline.max_total_threads_per_threadgroup(),
+            simd_width,
+        );
+        MTLSize::new(size.width, size.height, 1)
+    }
+
     /// Execute a compute operation and wait for completion.
     ///
     /// This method creates a command buffer and encoder, calls the provided setup function,
@@ -181,3 +443,15 @@ impl MetalContext {
         Ok(())
     }
 }
+
+/// Bind a small constant argument (such as a `constant uint&` dimension) inline.
+///
+/// The value is copied into the command stream with `set_bytes`, so no buffer is
+/// allocated for it.
+pub(crate) fn set_constant<T: Copy>(encoder: &ComputeCommandEncoderRef, index: u64, value: T) {
+    encoder.set_bytes(
+        index,
+        std::mem::size_of::<T>() as u64,
+        &value as *const T as *const c_void,
+    );
+}
@@ -13,14 +13,27 @@
  * - Matrix subtraction (`matrix_subtract`)
  * - Matrix transpose (`matrix_transpose`)
  * - Scalar multiplication (`matrix_scalar_multiply`)
- * - Dot product (`dot_product`)
+ *
+ * Each operation also has an `_async` variant (e.g. `matrix_multiply_async`) that
+ * returns a `PendingMatrix` without waiting for the GPU.
  *
  * Each operation validates the input dimensions and returns appropriate errors
  * if the inputs are incompatible.
+ *
+ * Empty matrices (0×n or m×0) are valid inputs. Operations whose result is empty,
+ * and products with an inner dimension of zero, return a zero-filled result
+ * without creating buffers or touching the GPU.
+ *
+ * Shapes are checked against the kernel and device limits (see `limits`) before any
+ * buffer is created. Element-wise operations on matrices with more than 2^32 - 1
+ * elements run 64-bit-index kernel variants.
  */
 
 use crate::kernels;
+use crate::limits::IndexWidth;
 use crate::matrix::Matrix;
+use crate::metal_context::set_constant;
+use crate::pending::PendingMatrix;
 use crate::MetalContext;
 use anyhow::Result;
 use metal::*;
@@ -55,6 +68,29 @@ use metal::*;
 /// let result = matrix_multiply(&context, &a, &b).unwrap();
 /// ```
 pub fn matrix_multiply(context: &MetalContext, a: &Matrix, b: &Matrix) -> Result<Matrix> {
+    matrix_multiply_async(context, a, b)?.wait()
+}
+
+/// Starts matrix multiplication on the GPU: C = A * B, without waiting for the result
+///
+/// Encodes and commits the same computation as `matrix_multiply`, then returns while the GPU
+/// is still working. Await the returned `PendingMatrix` or call its `wait` method to
+/// collect the result.
+///
+/// # Returns
+///
+/// A `Result` containing the pending result or an error.
+///
+/// # Errors
+///
+/// Returns an error if the matrices have incompatible dimensions (a.cols != b.rows),
+/// or a `LimitError` if they exceed the kernel or device limits.
+/// Errors during execution are reported when the result is collected.
+pub fn matrix_multiply_async(
+    context: &MetalContext,
+    a: &Matrix,
+    b: &Matrix,
+) -> Result<PendingMatrix> {
     // Validate input
     if a.cols != b.rows {
         anyhow::bail!("Matrix dimensions incompatible for multiplication");
@@ -64,54 +100,43 @@ pub fn matrix_multiply(context: &MetalContext, a: &Matrix, b: &Matrix) -> Result
     let n = b.cols;
     let k = a.cols;
 
+    // An empty result, or a product of empty sums (k = 0), is all zeros
+    if m == 0 || n == 0 || k == 0 {
+        return Ok(PendingMatrix::ready(context, Matrix::new(m, n)));
+    }
+    context.limits().check_matrix_multiply(m, k, n)?;
+
     // Load kernel
     let pipeline =
         context.load_kernel(kernels::paths::MATRIX_MUL, kernels::functions::MATRIX_MUL)?;
 
     // Create buffers
-    let buffer_a = context.new_buffer_with_data(&a.data);
-    let buffer_b = context.new_buffer_with_data(&b.data);
-    let buffer_result = context.new_buffer::<f32>(m * n);
-
-    // Create dimension buffers
-    let m_val = m as u32;
-    let n_val = n as u32;
-    let k_val = k as u32;
-
-    let buffer_m = context.new_buffer_with_data(&[m_val]);
-    let buffer_n = context.new_buffer_with_data(&[n_val]);
-    let buffer_k = context.new_buffer_with_data(&[k_val]);
-
-    // Execute computation
-    context.execute_compute(|encoder| {
-        encoder.set_compute_pipeline_state(&pipeline);
-        encoder.set_buffer(0, Some(&buffer_a), 0);
-        encoder.set_buffer(1, Some(&buffer_b), 0);
-        encoder.set_buffer(2, Some(&buffer_result), 0);
-        encoder.set_buffer(3, Some(&buffer_m), 0);
-        encoder.set_buffer(4, Some(&buffer_n), 0);
-        encoder.set_buffer(5, Some(&buffer_k), 0);
-
-        let grid_size = MTLSize::new(n as u64, m as u64, 1);
-
-        // Calculate optimal threadgroup size
-        let max_threads = pipeline.max_total_threads_per_threadgroup();
-        let width = (n as u64).min(16);
-        let height = (max_threads / width).min(m as u64).max(1);
-
-        let threadgroup_size = MTLSize::new(width, height, 1);
-        encoder.dispatch_threads(grid_size, threadgroup_size);
-    })?;
-
-    // Read results
-    let result_ptr = buffer_result.contents() as *const f32;
-    let mut result_data = vec![0.0f32; m * n];
-
-    unsafe {
-        std::ptr::copy_nonoverlapping(result_ptr, result_data.as_mut_ptr(), m * n);
-    }
-
-    Matrix::with_data(m, n, result_data)
+    let buffer_a = context.acquire_buffer_with_data(&a.data);
+    let buffer_b = context.acquire_buffer_with_data(&b.data);
+    let buffer_result = context.acquire_buffer::<f32>(m * n);
+
+    // Submit computation
+    Ok(PendingMatrix::submit(
+        context,
+        &buffer_result,
+        &[&buffer_a, &buffer_b],
+        m,
+        n,
+        |encoder| {
+            encoder.set_compute_pipeline_state(&pipeline);
+            encoder.set_buffer(0, Some(&buffer_a), 0);
+            encoder.set_buffer(1, Some(&buffer_b), 0);
+            encoder.set_buffer(2, Some(&buffer_result), 0);
+            set_constant(encoder, 3, m as u32);
+            set_constant(encoder, 4, n as u32);
+            set_constant(encoder, 5, k as u32);
+
+            let grid_size = MTLSize::new(n as u64, m as u64, 1);
+            let threadgroup_size =
+                context.threadgroup_size(kernels::functions::MATRIX_MUL, n, m, &pipeline);
+            encoder.dispatch_threads(grid_size, threadgroup_size);
+        },
+    ))
 }
 
 /// Performs matrix addition on the GPU: C = A + B
@@ -144,6 +169,25 @@ pub fn matrix_multiply(context: &MetalContext, a: &Matrix, b: &Matrix) -> Result
 /// let result = matrix_add(&context, &a, &b).unwrap();
 /// ```
 pub fn matrix_add(context: &MetalContext, a: &Matrix, b: &Matrix) -> Result<Matrix> {
+    matrix_add_async(context, a, b)?.wait()
+}
+
+/// Starts matrix addition on the GPU: C = A + B, without waiting for the result
+///
+/// Encodes and commits the same computation as `matrix_add`, then returns while the GPU
+/// is still working. Await the returned `PendingMatrix` or call its `wait` method to
+/// collect the result.
+///
+/// # Returns
+///
+/// A `Result` containing the pending result or an error.
+///
+/// # Errors
+///
+/// Returns an error if the matrices have different dimensions, or a `LimitError` if
+/// they do not fit in a device buffer.
+/// Errors during execution are reported when the result is collected.
+pub fn matrix_add_async(context: &MetalContext, a: &Matrix, b: &Matrix) -> Result<PendingMatrix> {
     // Validate input
     if a.rows != b.rows || a.cols != b.cols {
         anyhow::bail!("Matrix dimensions must match for addition");
@@ -152,38 +196,39 @@ pub fn matrix_add(context: &MetalContext, a: &Matrix, b: &Matrix) -> Result<Matr
     let rows = a.rows;
     let cols = a.cols;
     let size = rows * cols;
+    if size == 0 {
+        return Ok(PendingMatrix::ready(context, Matrix::new(rows, cols)));
+    }
 
     // Load kernel
-    let pipeline =
-        context.load_kernel(kernels::paths::MATRIX_ADD, kernels::functions::MATRIX_ADD)?;
+    let width = context.limits().check_elementwise(rows, cols)?;
+    let function = match width {
+        IndexWidth::U32 => kernels::functions::MATRIX_ADD,
+        IndexWidth::U64 => kernels::functions::MATRIX_ADD_64,
+    };
+    let pipeline = context.load_kernel(kernels::paths::MATRIX_ADD, function)?;
 
     // Create buffers
-    let buffer_a = context.new_buffer_with_data(&a.data);
-    let buffer_b = context.new_buffer_with_data(&b.data);
-    let buffer_result = context.new_buffer::<f32>(size);
-
-    // Execute computation
-    context.execute_compute(|encoder| {
-        encoder.set_compute_pipeline_state(&pipeline);
-        encoder.set_buffer(0, Some(&buffer_a), 0);
-        encoder.set_buffer(1, Some(&buffer_b), 0);
-        encoder.set_buffer(2, Some(&buffer_result), 0);
-
-        let grid_size = MTLSize::new(size as u64, 1, 1);
-        let threadgroup_size =
-            MTLSize::new(pipeline.max_total_threads_per_threadgroup().min(256), 1, 1);
-        encoder.dispatch_threads(grid_size, threadgroup_size);
-    })?;
-
-    // Read results
-    let result_ptr = buffer_result.contents() as *const f32;
-    let mut result_data = vec![0.0f32; size];
-
-    unsafe {
-        std::ptr::copy_nonoverlapping(result_ptr, result_data.as_mut_ptr(), size);
-    }
-
-    Matrix::with_data(rows, cols, result_data)
+    let buffer_a = context.acquire_buffer_with_data(&a.data);
+    let buffer_b = context.acquire_buffer_with_data(&b.data);
+    let buffer_result = context.acquire_buffer::<f32>(size);
+
+    // Submit computation
+    Ok(PendingMatrix::submit(
+        context,
+        &buffer_result,
+        &[&buffer_a, &buffer_b],
+        rows,
+        cols,
+        |encoder| {
+            encoder.set_compute_pipeline_state(&pipeline);
+            encoder.set_buffer(0, Some(&buffer_a), 0);
+            encoder.set_buffer(1, Some(&buffer_b), 0);
+            encoder.set_buffer(2, Some(&buffer_result), 0);
+
+            dispatch_elementwise(context, encoder, &pipeline, function, width, size, 3);
+        },
+    ))
 }
 
 /// Performs matrix subtraction on the GPU: C = A - B
@@ -216,6 +261,29 @@ pub fn matrix_add(context: &MetalContext, a: &Matrix, b: &Matrix) -> Result<Matr
 /// let result = matrix_subtract(&context, &a, &b).unwrap();
 /// ```
 pub fn matrix_subtract(context: &MetalContext, a: &Matrix, b: &Matrix) -> Result<Matrix> {
+    matrix_subtract_async(context, a, b)?.wait()
+}
+
+/// Starts matrix subtraction on the GPU: C = A - B, without waiting for the result
+///
+/// Encodes and commits the same computation as `matrix_subtract`, then returns while the GPU
+/// is still working. Await the returned `PendingMatrix` or call its `wait` method to
+/// collect the result.
+///
+/// # Returns
+///
+/// A `Result` containing the pending result or an error.
+///
+/// # Errors
+///
+/// Returns an error if the matrices have different dimensions, or a `LimitError` if
+/// they do not fit in a device buffer.
+/// Errors during execution are reported when the result is collected.
+pub fn matrix_subtract_async(
+    context: &MetalContext,
+    a: &Matrix,
+    b: &Matrix,
+) -> Result<PendingMatrix> {
     // Validate input
     if a.rows != b.rows || a.cols != b.cols {
         anyhow::bail!("Matrix dimensions must match for subtraction");
@@ -224,38 +292,39 @@ pub fn matrix_subtract(context: &MetalContext, a: &Matrix, b: &Matrix) -> Result
     let rows = a.rows;
     let cols = a.cols;
     let size = rows * cols;
+    if size == 0 {
+        return Ok(PendingMatrix::ready(context, Matrix::new(rows, cols)));
+    }
 
     // Load kernel
-    let pipeline =
-        context.load_kernel(kernels::paths::MATRIX_SUB, kernels::functions::MATRIX_SUB)?;
+    let width = context.limits().check_elementwise(rows, cols)?;
+    let function = match width {
+        IndexWidth::U32 => kernels::functions::MATRIX_SUB,
+        IndexWidth::U64 => kernels::functions::MATRIX_SUB_64,
+    };
+    let pipeline = context.load_kernel(kernels::paths::MATRIX_SUB, function)?;
 
     // Create buffers
-    let buffer_a = context.new_buffer_with_data(&a.data);
-    let buffer_b = context.new_buffer_with_data(&b.data);
-    let buffer_result = context.new_buffer::<f32>(size);
-
-    // Execute computation
-    context.execute_compute(|encoder| {
-        encoder.set_compute_pipeline_state(&pipeline);
-        encoder.set_buffer(0, Some(&buffer_a), 0);
-        encoder.set_buffer(1, Some(&buffer_b), 0);
-        encoder.set_buffer(2, Some(&buffer_result), 0);
-
-        let grid_size = MTLSize::new(size as u64, 1, 1);
-        let threadgroup_size =
-            MTLSize::new(pipeline.max_total_threads_per_threadgroup().min(256), 1, 1);
-        encoder.dispatch_threads(grid_size, threadgroup_size);
-    })?;
-
-    // Read results
-    let result_ptr = buffer_result.contents() as *const f32;
-    let mut result_data = vec![0.0f32; size];
-
-    unsafe {
-        std::ptr::copy_nonoverlapping(result_ptr, result_data.as_mut_ptr(), size);
-    }
-
-    Matrix::with_data(rows, cols, result_data)
+    let buffer_a = context.acquire_buffer_with_data(&a.data);
+    let buffer_b = context.acquire_buffer_with_data(&b.data);
+    let buffer_result = context.acquire_buffer::<f32>(size);
+
+    // Submit computation
+    Ok(PendingMatrix::submit(
+        context,
+        &buffer_result,
+        &[&buffer_a, &buffer_b],
+        rows,
+        cols,
+        |encoder| {
+            encoder.set_compute_pipeline_state(&pipeline);
+            encoder.set_buffer(0, Some(&buffer_a), 0);
+            encoder.set_buffer(1, Some(&buffer_b), 0);
+            encoder.set_buffer(2, Some(&buffer_result), 0);
+
+            dispatch_elementwise(context, encoder, &pipeline, function, width, size, 3);
+        },
+    ))
 }
 
 /// Performs matrix transpose on the GPU: B = A^T
@@ -284,8 +353,31 @@ pub fn matrix_subtract(context: &MetalContext, a: &Matrix, b: &Matrix) -> Result
 /// assert_eq!(result.cols, 2);
 /// ```
 pub fn matrix_transpose(context: &MetalContext, a: &Matrix) -> Result<Matrix> {
+    matrix_transpose_async(context, a)?.wait()
+}
+
+/// Starts matrix transpose on the GPU: B = A^T, without waiting for the result
+///
+/// Encodes and commits the same computation as `matrix_transpose`, then returns while the GPU
+/// is still working. Await the returned `PendingMatrix` or call its `wait` method to
+/// collect the result.
+///
+/// # Returns
+///
+/// A `Result` containing the pending result or an error.
+///
+/// # Errors
+///
+/// Returns a `LimitError` if the matrix exceeds the kernel or device limits, or an
+/// error if the kernel cannot be loaded. Errors during execution are reported when
+/// the result is collected.
+pub fn matrix_transpose_async(context: &MetalContext, a: &Matrix) -> Result<PendingMatrix> {
     let rows = a.rows;
     let cols = a.cols;
+    if rows == 0 || cols == 0 {
+        return Ok(PendingMatrix::ready(context, Matrix::new(cols, rows)));
+    }
+    context.limits().check_transpose(rows, cols)?;
 
     // Load kernel
     let pipeline = context.load_kernel(
@@ -294,44 +386,33 @@ pub fn matrix_transpose(context: &MetalContext, a: &Matrix) -> Result<Matrix> {
     )?;
 
     // Create buffers
-    let buffer_a = context.new_buffer_with_data(&a.data);
-    let buffer_result = context.new_buffer::<f32>(rows * cols);
-
-    // Create dimension buffers
-    let rows_val = rows as u32;
-    let cols_val = cols as u32;
-
-    let buffer_rows = context.new_buffer_with_data(&[rows_val]);
-    let buffer_cols = context.new_buffer_with_data(&[cols_val]);
-
-    // Execute computation
-    context.execute_compute(|encoder| {
-        encoder.set_compute_pipeline_state(&pipeline);
-        encoder.set_buffer(0, Some(&buffer_a), 0);
-        encoder.set_buffer(1, Some(&buffer_result), 0);
-        encoder.set_buffer(2, Some(&buffer_rows), 0);
-        encoder.set_buffer(3, Some(&buffer_cols), 0);
-
-        let grid_size = MTLSize::new(cols as u64, rows as u64, 1);
-
-        // Calculate optimal threadgroup size
-        let max_threads = pipeline.max_total_threads_per_threadgroup();
-        let width = (cols as u64).min(16);
-        let height = (max_threads / width).min(rows as u64).max(1);
-
-        let threadgroup_size = MTLSize::new(width, height, 1);
-        encoder.dispatch_threads(grid_size, threadgroup_size);
-    })?;
-
-    // Read results
-    let result_ptr = buffer_result.contents() as *const f32;
-    let mut result_data = vec![0.0f32; rows * cols];
-
-    unsafe {
-        std::ptr::copy_nonoverlapping(result_ptr, result_data.as_mut_ptr(), rows * cols);
-    }
-
-    Matrix::with_data(cols, rows, result_data)
+    let buffer_a = context.acquire_buffer_with_data(&a.data);
+    let buffer_result = context.acquire_buffer::<f32>(rows * cols);
+
+    // Submit computation
+    Ok(PendingMatrix::submit(
+        context,
+        &buffer_result,
+        &[&buffer_a],
+        cols,
+        rows,
+        |encoder| {
+            encoder.set_compute_pipeline_state(&pipeline);
+            encoder.set_buffer(0, Some(&buffer_a), 0);
+            encoder.set_buffer(1, Some(&buffer_result), 0);
+            set_constant(encoder, 2, rows as u32);
+            set_constant(encoder, 3, cols as u32);
+
+            let grid_size = MTLSize::new(cols as u64, rows as u64, 1);
+            let threadgroup_size = context.threadgroup_size(
+                kernels::functions::MATRIX_TRANSPOSE,
+                cols,
+                rows,
+                &pipeline,
+            );
+            encoder.dispatch_threads(grid_size, threadgroup_size);
+        },
+    ))
 }
 
 /// Performs scalar multiplication on the GPU: B = scalar * A
@@ -359,41 +440,90 @@ pub fn matrix_transpose(context: &MetalContext, a: &Matrix) -> Result<Matrix> {
 /// let result = matrix_scalar_multiply(&context, 2.5, &a).unwrap();
 /// ```
 pub fn matrix_scalar_multiply(context: &MetalContext, scalar: f32, a: &Matrix) -> Result<Matrix> {
+    matrix_scalar_multiply_async(context, scalar, a)?.wait()
+}
+
+/// Starts scalar multiplication on the GPU: B = scalar * A, without waiting for the result
+///
+/// Encodes and commits the same computation as `matrix_scalar_multiply`, then returns while the GPU
+/// is still working. Await the returned `PendingMatrix` or call its `wait` method to
+/// collect the result.
+///
+/// # Returns
+///
+/// A `Result` containing the pending result or an error.
+///
+/// # Errors
+///
+/// Returns a `LimitError` if the matrix does not fit in a device buffer, or an
+/// error if the kernel cannot be loaded. Errors during execution are reported when
+/// the result is collected.
+pub fn matrix_scalar_multiply_async(
+    context: &MetalContext,
+    scalar: f32,
+    a: &Matrix,
+) -> Result<PendingMatrix> {
     let rows = a.rows;
     let cols = a.cols;
     let size = rows * cols;
+    if size == 0 {
+        return Ok(PendingMatrix::ready(context, Matrix::new(rows, cols)));
+    }
 
     // Load kernel
-    let pipeline = context.load_kernel(
-        kernels::paths::MATRIX_SCALAR_MUL,
-        kernels::functions::MATRIX_SCALAR_MUL,
-    )?;
+    let width = context.limits().check_elementwise(rows, cols)?;
+    let function = match width {
+        IndexWidth::U32 => kernels::functions::MATRIX_SCALAR_MUL,
+        IndexWidth::U64 => kernels::functions::MATRIX_SCALAR_MUL_64,
+    };
+    let pipeline = context.load_kernel(kernels::paths::MATRIX_SCALAR_MUL, function)?;
 
     // Create buffers
-    let buffer_a = context.new_buffer_with_data(&a.data);
-    let buffer_scalar = context.new_buffer_with_data(&[scalar]);
-    let buffer_result = context.new_buffer::<f32>(size);
-
-    // Execute computation
-    context.execute_compute(|encoder| {
-        encoder.set_compute_pipeline_state(&pipeline);
-        encoder.set_buffer(0, Some(&buffer_a), 0);
-        encoder.set_buffer(1, Some(&buffer_scalar), 0);
-        encoder.set_buffer(2, Some(&buffer_result), 0);
-
-        let grid_size = MTLSize::new(size as u64, 1, 1);
-        let threadgroup_size =
-            MTLSize::new(pipeline.max_total_threads_per_threadgroup().min(256), 1, 1);
-        encoder.dispatch_threads(grid_size, threadgroup_size);
-    })?;
-
-    // Read results
-    let result_ptr = buffer_result.contents() as *const f32;
-    let mut result_data = vec![0.0f32; size];
-
-    unsafe {
-        std::ptr::copy_nonoverlapping(result_ptr, result_data.as_mut_ptr(), size);
-    }
+    let buffer_a = context.acquire_buffer_with_data(&a.data);
+    let buffer_result = context.acquire_buffer::<f32>(size);
+
+    // Submit computation
+    Ok(PendingMatrix::submit(
+        context,
+        &buffer_result,
+        &[&buffer_a],
+        rows,
+        cols,
+        |encoder| {
+            encoder.set_compute_pipeline_state(&pipeline);
+            encoder.set_buffer(0, Some(&buffer_a), 0);
+            set_constant(encoder, 1, scalar);
+            encoder.set_buffer(2, Some(&buffer_result), 0);
+
+            dispatch_elementwise(context, encoder, &pipeline, function, width, size, 3);
+        },
+    ))
+}
 
-    Matrix::with_data(rows, cols, result_data)
+/// Threads dispatched for the 64-bit-index kernels, each striding over the elements.
+const STRIDED_THREADS: usize = 1 << 24;
+
+/// Dispatches an element-wise kernel over `size` elements: one thread per element
+/// for the 32-bit-index variant, or a fixed grid given the element count (bound at
+/// `count_index`) for the 64-bit-index variant.
+fn dispatch_elementwise(
+    context: &MetalContext,
+    encoder: &ComputeCommandEncoderRef,
+    pipeline: &ComputePipelineStateRef,
+    function: &str,
+    width: IndexWidth,
+    size: usize,
+    count_index: u64,
+) {
+    let threads = match width {
+        IndexWidth::U32 => size,
+        IndexWidth::U64 => {
+            set_constant(encoder, count_index, size as u64);
+            size.min(STRIDED_THREADS)
+        }
+    };
+
+    let grid_size = MTLSize::new(threads as u64, 1, 1);
+    let threadgroup_size = context.threadgroup_size(function, threads, 1, pipeline);
+    encoder.dispatch_threads(grid_size, threadgroup_size);
 }
@@ -0,0 +1,226 @@
+/*!
+ * # Asynchronous Execution
+ *
+ * This module provides `PendingMatrix`, the result of a GPU operation that has been
+ * submitted but not waited on.
+ *
+ * The `*_async` operations encode and commit their command buffer, then return
+ * immediately so the calling thread can do other work while the GPU computes. The
+ * result is collected either by awaiting the `PendingMatrix`, which is woken from the
+ * command buffer's completion handler, or by calling `PendingMatrix::wait`, which
+ * blocks. The future does not depend on any particular async runtime.
+ */
+
+use crate::buffer_pool::BufferPool;
+use crate::matrix::Matrix;
+use crate::metal_context::MetalContext;
+use anyhow::{anyhow, bail, Result};
+use block::ConcreteBlock;
+use metal::*;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+/// Completion state shared with the command buffer's completion handler.
+#[derive(Default)]
+struct Completion {
+    state: Mutex<CompletionState>,
+}
+
+#[derive(Default)]
+struct CompletionState {
+    complete: bool,
+    waker: Option<Waker>,
+}
+
+impl Completion {
+    fn complete(&self) {
+        let waker = {
+            let mut state = self.state.lock().unwrap();
+            state.complete = true;
+            state.waker.take()
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+
+    fn is_complete(&self) -> bool {
+        self.state.lock().unwrap().complete
+    }
+
+    /// Returns `true` if complete, otherwise stores the waker to be woken on completion.
+    fn register(&self, waker: &Waker) -> bool {
+        let mut state = self.state.lock().unwrap();
+        if !state.complete {
+            match &state.waker {
+                Some(existing) if existing.will_wake(waker) => {}
+                _ => state.waker = Some(waker.clone()),
+            }
+        }
+        state.complete
+    }
+}
+
+/// A matrix being computed on the GPU.
+///
+/// Awaiting a `PendingMatrix` yields the result once the GPU has finished, without
+/// blocking the thread. `wait` blocks until then instead.
+pub struct PendingMatrix {
+    /// `None` for a result known without running anything on the GPU
+    command_buffer: Option<CommandBuffer>,
+    /// The output buffer followed by the input buffers, recycled once read back
+    buffers: Vec<Buffer>,
+    pool: Arc<Mutex<BufferPool<Buffer>>>,
+    rows: usize,
+    cols: usize,
+    ready: Option<Matrix>,
+    completion: Arc<Completion>,
+}
+
+impl PendingMatrix {
+    /// Encodes a compute operation, commits it and returns without waiting.
+    ///
+    /// `output` and `inputs` are returned to the context's buffer pool when the result
+    /// is read back. If the `PendingMatrix` is dropped first they are freed instead,
+    /// once the command buffer (which retains them) completes.
+    pub(crate) fn submit<F>(
+        context: &MetalContext,
+        output: &BufferRef,
+        inputs: &[&BufferRef],
+        rows: usize,
+        cols: usize,
+        encoder_setup: F,
+    ) -> Self
+    where
+        F: FnOnce(&ComputeCommandEncoderRef),
+    {
+        let command_buffer = context.command_queue.new_command_buffer().to_owned();
+        let encoder = command_buffer.new_compute_command_encoder();
+
+        encoder_setup(encoder);
+        encoder.end_encoding();
+
+        let completion = Arc::new(Completion::default());
+        let handler = {
+            let completion = Arc::clone(&completion);
+            ConcreteBlock::new(move |_: &CommandBufferRef| completion.complete()).copy()
+        };
+        command_buffer.add_completed_handler(&handler);
+        command_buffer.commit();
+
+        Self {
+            command_buffer: Some(command_buffer),
+            buffers: std::iter::once(output)
+                .chain(inputs.iter().copied())
+                .map(ToOwned::to_owned)
+                .collect(),
+            pool: Arc::clone(&context.buffer_pool),
+            rows,
+            cols,
+            ready: None,
+            completion,
+        }
+    }
+
+    /// Wraps a result that needs no GPU work, such as the all-zero result of an
+    /// operation on an empty matrix. No command buffer or Metal buffers are created.
+    pub(crate) fn ready(context: &MetalContext, matrix: Matrix) -> Self {
+        let completion = Arc::new(Completion::default());
+        completion.complete();
+
+        Self {
+            command_buffer: None,
+            buffers: Vec::new(),
+            pool: Arc::clone(&context.buffer_pool),
+            rows: matrix.rows,
+            cols: matrix.cols,
+            ready: Some(matrix),
+            completion,
+        }
+    }
+
+    /// Returns `true` once the GPU has finished computing the matrix.
+    pub fn is_complete(&self) -> bool {
+        self.completion.is_complete()
+    }
+
+    /// Blocks the calling thread until the matrix has been computed.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the computed matrix or an error.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the GPU failed to execute the command buffer.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use metal_matrix::{MetalContext, Matrix, matrix_multiply_async};
+    ///
+    /// let context = MetalContext::new().unwrap();
+    /// let a = Matrix::identity(256);
+    ///
+    /// let pending = matrix_multiply_async(&context, &a, &a).unwrap();
+    /// // ... other work on the CPU while the GPU computes ...
+    /// let product = pending.wait().unwrap();
+    /// ```
+    pub fn wait(mut self) -> Result<Matrix> {
+        if let Some(command_buffer) = &self.command_buffer {
+            command_buffer.wait_until_completed();
+        }
+        self.read()
+    }
+
+    /// Copies the result out and recycles the buffers. Must only be called once the
+    /// command buffer has completed.
+    fn read(&mut self) -> Result<Matrix> {
+        if let Some(matrix) = self.ready.take() {
+            return Ok(matrix);
+        }
+        if self.buffers.is_empty() {
+            bail!("PendingMatrix polled after completion");
+        }
+
+        let buffers = std::mem::take(&mut self.buffers);
+        let failed = self
+            .command_buffer
+            .as_ref()
+            .is_some_and(|command_buffer| command_buffer.status() == MTLCommandBufferStatus::Error);
+        let result = if failed {
+            Err(anyhow!("GPU command buffer failed"))
+        } else {
+            let size = self.rows * self.cols;
+            let result_ptr = buffers[0].contents() as *const f32;
+            let mut result_data = vec![0.0f32; size];
+
+            unsafe {
+                std::ptr::copy_nonoverlapping(result_ptr, result_data.as_mut_ptr(), size);
+            }
+
+            Matrix::with_data(self.rows, self.cols, result_data)
+        };
+
+        let mut pool = self.pool.lock().unwrap();
+        for buffer in buffers {
+            pool.release(buffer);
+        }
+        result
+    }
+}
+
+impl Future for PendingMatrix {
+    type Output = Result<Matrix>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        if this.completion.register(cx.waker()) {
+            Poll::Ready(this.read())
+        } else {
+            Poll::Pending
+        }
+    }
+}
@@ -0,0 +1,652 @@
+/*!
+ * # Half and Mixed Precision
+ *
+ * This module stores matrices in 16-bit floating point and multiplies them on the GPU
+ * with f32 accumulation, halving the memory traffic of bandwidth-bound products.
+ *
+ * A `HalfMatrix` holds f16 (IEEE binary16) or bf16 (the upper half of an f32) values
+ * as raw bits. `to_half` and `from_half` convert between it and an f32 `Matrix` on
+ * the GPU; `HalfMatrix::from_matrix` and `HalfMatrix::to_matrix` do the same on the
+ * CPU with the software conversions in this module (`f32_to_f16` and friends), which
+ * serve as the reference for the GPU kernels. Every conversion to 16 bits rounds to
+ * nearest, ties to even.
+ *
+ * `mixed_matrix_multiply` multiplies two half-precision matrices of the same format,
+ * accumulating in f32, and returns the product in the requested output `Precision`
+ * as a `MixedMatrix`. `cpu::mixed_matrix_multiply` is its CPU reference.
+ *
+ * `mixed_precision_report` measures the accuracy of the mixed-precision path against
+ * the f32 `matrix_multiply`, using the `Report` of the `verify` module with a
+ * tolerance derived from the input and output precisions.
+ *
+ * ```
+ * use metal_matrix::precision::{HalfFormat, HalfMatrix};
+ * use metal_matrix::Matrix;
+ *
+ * let a = Matrix::with_data(1, 3, vec![1.0, 0.1, 65520.0]).unwrap();
+ *
+ * // 0.1 is not representable; 65520 rounds up past the largest f16 to infinity
+ * let half = HalfMatrix::from_matrix(&a, HalfFormat::F16);
+ * assert_eq!(half.data, vec![0x3c00, 0x2e66, 0x7c00]);
+ * assert_eq!(half.to_matrix().data, vec![1.0, 0.099975586, f32::INFINITY]);
+ * ```
+ */
+
+use crate::kernels;
+use crate::limits::check_32bit;
+use crate::matrix::Matrix;
+use crate::metal_context::set_constant;
+use crate::operations::matrix_multiply;
+use crate::verify::{compare_elements, Report, Tolerance};
+use crate::MetalContext;
+use anyhow::{bail, Result};
+use metal::*;
+
+/// A 16-bit floating-point format.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum HalfFormat {
+    /// IEEE 754 binary16: 5 exponent and 10 mantissa bits
+    F16,
+
+    /// bfloat16: the 8 exponent bits of an f32 and 7 mantissa bits
+    Bf16,
+}
+
+impl HalfFormat {
+    /// The name of the format: "f16" or "bf16".
+    pub fn name(&self) -> &'static str {
+        match self {
+            HalfFormat::F16 => "f16",
+            HalfFormat::Bf16 => "bf16",
+        }
+    }
+
+    /// The difference between 1 and the next representable value.
+    pub fn epsilon(&self) -> f32 {
+        match self {
+            HalfFormat::F16 => 2f32.powi(-10),
+            HalfFormat::Bf16 => 2f32.powi(-7),
+        }
+    }
+
+    /// Rounds an f32 to this format, returning its bits.
+    pub fn from_f32(&self, x: f32) -> u16 {
+        match self {
+            HalfFormat::F16 => f32_to_f16(x),
+            HalfFormat::Bf16 => f32_to_bf16(x),
+        }
+    }
+
+    /// Converts bits in this format to f32 (exactly).
+    pub fn to_f32(&self, bits: u16) -> f32 {
+        match self {
+            HalfFormat::F16 => f16_to_f32(bits),
+            HalfFormat::Bf16 => bf16_to_f32(bits),
+        }
+    }
+}
+
+/// The precision of a mixed-precision product's output.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Precision {
+    /// f32, as accumulated
+    F32,
+
+    /// The accumulated f32 rounded once to a 16-bit format
+    Half(HalfFormat),
+}
+
+impl Precision {
+    /// The name of the precision: "f32", "f16" or "bf16".
+    pub fn name(&self) -> &'static str {
+        match self {
+            Precision::F32 => "f32",
+            Precision::Half(format) => format.name(),
+        }
+    }
+
+    /// The difference between 1 and the next representable value.
+    pub fn epsilon(&self) -> f32 {
+        match self {
+            Precision::F32 => f32::EPSILON,
+            Precision::Half(format) => format.epsilon(),
+        }
+    }
+}
+
+/// Rounds an f32 to the nearest f16 (ties to even) and returns its bits.
+///
+/// Values beyond the f16 range become infinities, values below half the smallest
+/// subnormal become zeros, and NaNs stay quiet NaNs.
+pub fn f32_to_f16(x: f32) -> u16 {
+    let bits = x.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x7f_ffff;
+
+    if exponent == 0xff {
+        let nan = if mantissa != 0 {
+            0x0200 | (mantissa >> 13) as u16
+        } else {
+            0
+        };
+        return sign | 0x7c00 | nan;
+    }
+
+    // The f16 biased exponent
+    let exponent = exponent - 127 + 15;
+    if exponent >= 0x1f {
+        return sign | 0x7c00;
+    }
+
+    // Keep the top bits of the significand and round on the dropped ones; a carry
+    // out of the mantissa correctly increments the exponent (up to infinity)
+    let (kept, dropped, shift) = if exponent <= 0 {
+        // Subnormal: the implicit bit becomes explicit and the point moves left
+        if exponent < -10 {
+            return sign;
+        }
+        let significand = mantissa | 0x80_0000;
+        let shift = (14 - exponent) as u32;
+        (
+            significand >> shift,
+            significand & ((1 << shift) - 1),
+            shift,
+        )
+    } else {
+        (
+            ((exponent as u32) << 10) | (mantissa >> 13),
+            mantissa & 0x1fff,
+            13,
+        )
+    };
+
+    let halfway = 1 << (shift - 1);
+    let round_up = dropped > halfway || (dropped == halfway && kept & 1 == 1);
+    sign | (kept + round_up as u32) as u16
+}
+
+/// Converts f16 bits to f32. Every f16 value is exactly representable.
+pub fn f16_to_f32(bits: u16) -> f32 {
+    let sign = ((bits & 0x8000) as u32) << 16;
+    let exponent = ((bits >> 10) & 0x1f) as u32;
+    let mantissa = (bits & 0x3ff) as u32;
+
+    let bits = match (exponent, mantissa) {
+        (0, 0) => sign,
+        (0, _) => {
+            // Subnormal: mantissa * 2^-24
+            let magnitude = mantissa as f32 * 2f32.powi(-24);
+            return if sign != 0 { -magnitude } else { magnitude };
+        }
+        (0x1f, 0) => sign | 0x7f80_0000,
+        (0x1f, _) => sign | 0x7fc0_0000 | (mantissa << 13),
+        _ => sign | ((exponent + 127 - 15) << 23) | (mantissa << 13),
+    };
+    f32::from_bits(bits)
+}
+
+/// Rounds an f32 to the nearest bf16 (ties to even) and returns its bits.
+///
+/// NaNs stay quiet NaNs.
+pub fn f32_to_bf16(x: f32) -> u16 {
+    let bits = x.to_bits();
+    if bits & 0x7fff_ffff > 0x7f80_0000 {
+        return ((bits >> 16) | 0x40) as u16;
+    }
+    (bits.wrapping_add(0x7fff + ((bits >> 16) & 1)) >> 16) as u16
+}
+
+/// Converts bf16 bits to f32. Every bf16 value is exactly representable.
+pub fn bf16_to_f32(bits: u16) -> f32 {
+    f32::from_bits((bits as u32) << 16)
+}
+
+/// A matrix of 16-bit floating-point values, stored row-major as raw bits.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HalfMatrix {
+    /// Number of rows in the matrix
+    pub rows: usize,
+
+    /// Number of columns in the matrix
+    pub cols: usize,
+
+    /// The format of the elements
+    pub format: HalfFormat,
+
+    /// The bits of the elements in row-major order
+    pub data: Vec<u16>,
+}
+
+impl HalfMatrix {
+    /// Creates a half-precision matrix from element bits.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the data length doesn't match the dimensions.
+    pub fn with_data(rows: usize, cols: usize, format: HalfFormat, data: Vec<u16>) -> Result<Self> {
+        if data.len() != rows * cols {
+            bail!("Data length does not match matrix dimensions");
+        }
+        Ok(Self {
+            rows,
+            cols,
+            format,
+            data,
+        })
+    }
+
+    /// Rounds an f32 matrix to a half-precision format on the CPU.
+    pub fn from_matrix(matrix: &Matrix, format: HalfFormat) -> Self {
+        Self {
+            rows: matrix.rows,
+            cols: matrix.cols,
+            format,
+            data: matrix.data.iter().map(|&x| format.from_f32(x)).collect(),
+        }
+    }
+
+    /// Converts the matrix to f32 on the CPU (exactly).
+    pub fn to_matrix(&self) -> Matrix {
+        Matrix {
+            rows: self.rows,
+            cols: self.cols,
+            data: self.data.iter().map(|&x| self.format.to_f32(x)).collect(),
+        }
+    }
+}
+
+/// The result of a mixed-precision product, in its output precision.
+#[derive(Clone, Debug)]
+pub enum MixedMatrix {
+    /// An f32 result
+    F32(Matrix),
+
+    /// A half-precision result
+    Half(HalfMatrix),
+}
+
+impl MixedMatrix {
+    /// An all-zero matrix in the given precision.
+    pub(crate) fn zeros(rows: usize, cols: usize, precision: Precision) -> Self {
+        match precision {
+            Precision::F32 => MixedMatrix::F32(Matrix::new(rows, cols)),
+            // Zero has all bits clear in both formats
+            Precision::Half(format) => MixedMatrix::Half(HalfMatrix {
+                rows,
+                cols,
+                format,
+                data: vec![0; rows * cols],
+            }),
+        }
+    }
+
+    /// The precision of the elements.
+    pub fn precision(&self) -> Precision {
+        match self {
+            MixedMatrix::F32(_) => Precision::F32,
+            MixedMatrix::Half(m) => Precision::Half(m.format),
+        }
+    }
+
+    /// Converts the result to an f32 matrix (exactly).
+    pub fn to_matrix(&self) -> Matrix {
+        match self {
+            MixedMatrix::F32(m) => m.clone(),
+            MixedMatrix::Half(m) => m.to_matrix(),
+        }
+    }
+}
+
+/// Checks the operands of a mixed-precision product.
+pub(crate) fn validate(a: &HalfMatrix, b: &HalfMatrix) -> Result<()> {
+    if a.cols != b.rows {
+        bail!("Matrix dimensions incompatible for multiplication");
+    }
+    if a.format != b.format {
+        bail!(
+            "Operands must have the same format, not {} and {}",
+            a.format.name(),
+            b.format.name()
+        );
+    }
+    Ok(())
+}
+
+/// The kernel function multiplying `format` inputs into `output`.
+fn multiply_kernel(format: HalfFormat, output: Precision) -> &'static str {
+    use kernels::functions::*;
+    use HalfFormat::{Bf16, F16};
+
+    match (format, output) {
+        (F16, Precision::F32) => MATRIX_MUL_F16_F32,
+        (F16, Precision::Half(F16)) => MATRIX_MUL_F16_F16,
+        (F16, Precision::Half(Bf16)) => MATRIX_MUL_F16_BF16,
+        (Bf16, Precision::F32) => MATRIX_MUL_BF16_F32,
+        (Bf16, Precision::Half(F16)) => MATRIX_MUL_BF16_F16,
+        (Bf16, Precision::Half(Bf16)) => MATRIX_MUL_BF16_BF16,
+    }
+}
+
+/// Runs a conversion kernel over `count` elements, one thread per element.
+fn convert(
+    context: &MetalContext,
+    function: &str,
+    input: &BufferRef,
+    output: &BufferRef,
+    count: usize,
+) -> Result<()> {
+    let pipeline = context.load_kernel(kernels::paths::MIXED_PRECISION, function)?;
+
+    context.execute_compute(|encoder| {
+        encoder.set_compute_pipeline_state(&pipeline);
+        encoder.set_buffer(0, Some(input), 0);
+        encoder.set_buffer(1, Some(output), 0);
+
+        let grid_size = MTLSize::new(count as u64, 1, 1);
+        let threadgroup_size = context.threadgroup_size(function, count, 1, &pipeline);
+        encoder.dispatch_threads(grid_size, threadgroup_size);
+    })
+}
+
+/// Copies `count` elements out of a buffer.
+fn read_buffer<T: Copy + Default>(buffer: &BufferRef, count: usize) -> Vec<T> {
+    let mut data = vec![T::default(); count];
+    unsafe {
+        std::ptr::copy_nonoverlapping(buffer.contents() as *const T, data.as_mut_ptr(), count);
+    }
+    data
+}
+
+/// Rounds an f32 matrix to a half-precision format on the GPU.
+///
+/// # Arguments
+///
+/// * `context` - The Metal context for GPU computation
+/// * `a` - The matrix to convert
+/// * `format` - The half-precision format
+///
+/// # Returns
+///
+/// A `Result` containing the half-precision matrix or an error.
+///
+/// # Errors
+///
+/// Returns a `LimitError` if the matrix exceeds the kernel or device limits.
+///
+/// # Example
+///
+/// ```no_run
+/// use metal_matrix::precision::{self, HalfFormat, HalfMatrix};
+/// use metal_matrix::{Matrix, MetalContext};
+///
+/// let context = MetalContext::new().unwrap();
+/// let a = Matrix::with_data(2, 2, vec![1.0, 0.1, -2.5, 1e-6]).unwrap();
+///
+/// let half = precision::to_half(&context, &a, HalfFormat::F16).unwrap();
+/// assert_eq!(half, HalfMatrix::from_matrix(&a, HalfFormat::F16));
+/// ```
+pub fn to_half(context: &MetalContext, a: &Matrix, format: HalfFormat) -> Result<HalfMatrix> {
+    let count = a.data.len();
+    if count == 0 {
+        return HalfMatrix::with_data(a.rows, a.cols, format, Vec::new());
+    }
+    check_32bit(a.rows, a.cols)?;
+    context.limits().check_buffer(count)?;
+
+    let function = match format {
+        HalfFormat::F16 => kernels::functions::CONVERT_F32_TO_F16,
+        HalfFormat::Bf16 => kernels::functions::CONVERT_F32_TO_BF16,
+    };
+    let input = context.acquire_buffer_with_data(&a.data);
+    let output = context.acquire_buffer::<u16>(count);
+    let result = convert(context, function, &input, &output, count);
+
+    let data = read_buffer(&output, count);
+    context.recycle_buffer(input);
+    context.recycle_buffer(output);
+    result?;
+
+    HalfMatrix::with_data(a.rows, a.cols, format, data)
+}
+
+/// Converts a half-precision matrix to f32 on the GPU.
+///
+/// # Arguments
+///
+/// * `context` - The Metal context for GPU computation
+/// * `a` - The matrix to convert
+///
+/// # Returns
+///
+/// A `Result` containing the f32 matrix or an error.
+///
+/// # Errors
+///
+/// Returns a `LimitError` if the matrix exceeds the kernel or device limits.
+pub fn from_half(context: &MetalContext, a: &HalfMatrix) -> Result<Matrix> {
+    let count = a.data.len();
+    if count == 0 {
+        return Ok(Matrix::new(a.rows, a.cols));
+    }
+    check_32bit(a.rows, a.cols)?;
+    context.limits().check_buffer(count)?;
+
+    let function = match a.format {
+        HalfFormat::F16 => kernels::functions::CONVERT_F16_TO_F32,
+        HalfFormat::Bf16 => kernels::functions::CONVERT_BF16_TO_F32,
+    };
+    let input = context.acquire_buffer_with_data(&a.data);
+    let output = context.acquire_buffer::<f32>(count);
+    let result = convert(context, function, &input, &output, count);
+
+    let data = read_buffer(&output, count);
+    context.recycle_buffer(input);
+    context.recycle_buffer(output);
+    result?;
+
+    Matrix::with_data(a.rows, a.cols, data)
+}
+
+/// Performs mixed-precision matrix multiplication on the GPU: C = A * B
+///
+/// Reads half-precision inputs, accumulates each element in f32 and rounds it once
+/// to the output precision.
+///
+/// # Arguments
+///
+/// * `context` - The Metal context for GPU computation
+/// * `a` - The first matrix (m × k)
+/// * `b` - The second matrix (k × n), in the same format as `a`
+/// * `output` - The precision of the result
+///
+/// # Returns
+///
+/// A `Result` containing the product matrix (m × n) or an error.
+///
+/// # Errors
+///
+/// Returns an error if the matrices have incompatible dimensions (a.cols != b.rows)
+/// or different formats, or a `LimitError` if they exceed the kernel or device limits.
+///
+/// # Example
+///
+/// ```no_run
+/// use metal_matrix::precision::{self, HalfFormat, HalfMatrix, Precision};
+/// use metal_matrix::{Matrix, MetalContext};
+///
+/// let context = MetalContext::new().unwrap();
+/// let a = HalfMatrix::from_matrix(&Matrix::identity(64), HalfFormat::Bf16);
+///
+/// let c = precision::mixed_matrix_multiply(&context, &a, &a, Precision::F32).unwrap();
+/// assert_eq!(c.to_matrix().data, Matrix::identity(64).data);
+/// ```
+pub fn mixed_matrix_multiply(
+    context: &MetalContext,
+    a: &HalfMatrix,
+    b: &HalfMatrix,
+    output: Precision,
+) -> Result<MixedMatrix> {
+    validate(a, b)?;
+
+    let m = a.rows;
+    let n = b.cols;
+    let k = a.cols;
+
+    // An empty result, or a product of empty sums (k = 0), is all zeros
+    if m == 0 || n == 0 || k == 0 {
+        return Ok(MixedMatrix::zeros(m, n, output));
+    }
+    context.limits().check_matrix_multiply(m, k, n)?;
+
+    // Load kernel
+    let function = multiply_kernel(a.format, output);
+    let pipeline = context.load_kernel(kernels::paths::MIXED_PRECISION, function)?;
+
+    // Create buffers
+    let buffer_a = context.acquire_buffer_with_data(&a.data);
+    let buffer_b = context.acquire_buffer_with_data(&b.data);
+    let buffer_result = match output {
+        Precision::F32 => context.acquire_buffer::<f32>(m * n),
+        Precision::Half(_) => context.acquire_buffer::<u16>(m * n),
+    };
+
+    // Execute computation
+    let result = context.execute_compute(|encoder| {
+        encoder.set_compute_pipeline_state(&pipeline);
+        encoder.set_buffer(0, Some(&buffer_a), 0);
+        encoder.set_buffer(1, Some(&buffer_b), 0);
+        encoder.set_buffer(2, Some(&buffer_result), 0);
+        set_constant(encoder, 3, m as u32);
+        set_constant(encoder, 4, n as u32);
+        set_constant(encoder, 5, k as u32);
+
+        let grid_size = MTLSize::new(n as u64, m as u64, 1);
+        let threadgroup_size = context.threadgroup_size(function, n, m, &pipeline);
+        encoder.dispatch_threads(grid_size, threadgroup_size);
+    });
+
+    // Read results
+    let product = match output {
+        Precision::F32 => {
+            MixedMatrix::F32(Matrix::with_data(m, n, read_buffer(&buffer_result, m * n))?)
+        }
+        Precision::Half(format) => MixedMatrix::Half(HalfMatrix::with_data(
+            m,
+            n,
+            format,
+            read_buffer(&buffer_result, m * n),
+        )?),
+    };
+    for buffer in [buffer_a, buffer_b, buffer_result] {
+        context.recycle_buffer(buffer);
+    }
+    result?;
+
+    Ok(product)
+}
+
+/// The tolerance for a mixed-precision product of `a` and `b` compared with the f32
+/// product.
+///
+/// Rounding the inputs to `format` perturbs each term `a[i][p] * b[p][j]` by a
+/// relative error of up to `format.epsilon()` (half an ulp from each factor), f32
+/// accumulation adds up to K roundings, and the output adds one rounding in its own
+/// precision. As for `Operation::MatrixMultiply`, the absolute bound, for elements
+/// that cancel to near zero, allows each of the K terms one rounding of each kind at
+/// the largest possible term `max|a| * max|b|`, so it grows linearly with K.
+pub fn tolerance(a: &Matrix, b: &Matrix, format: HalfFormat, output: Precision) -> Tolerance {
+    let k = a.cols.max(1) as f64;
+    let (input_eps, f32_eps, output_eps) = (
+        format.epsilon() as f64,
+        f32::EPSILON as f64,
+        output.epsilon() as f64 / 2.0,
+    );
+    let max_abs = |m: &Matrix| {
+        m.data
+            .iter()
+            .fold(0.0f64, |max, &x| max.max((x as f64).abs()))
+    };
+
+    Tolerance {
+        abs: (input_eps + f32_eps + output_eps) * k * max_abs(a) * max_abs(b),
+        rel: input_eps + k * f32_eps + output_eps,
+    }
+}
+
+/// Compares a mixed-precision product of `a` and `b` (rounded to `format`) with the
+/// f32 product, using `tolerance`.
+///
+/// # Errors
+///
+/// Returns an error if the results have different dimensions.
+pub fn accuracy_report(
+    result: &MixedMatrix,
+    reference: &Matrix,
+    a: &Matrix,
+    b: &Matrix,
+    format: HalfFormat,
+) -> Result<Report> {
+    let output = result.precision();
+    let errors = compare_elements(&result.to_matrix(), reference)?;
+
+    Ok(Report::new(
+        multiply_kernel(format, output),
+        (reference.rows, reference.cols),
+        &errors,
+        tolerance(a, b, format, output),
+    ))
+}
+
+/// Measures the accuracy of mixed-precision multiplication on the GPU.
+///
+/// Converts `a` and `b` to `format`, multiplies them with `mixed_matrix_multiply` and
+/// compares the result with `matrix_multiply` on the f32 inputs.
+///
+/// # Arguments
+///
+/// * `context` - The Metal context for GPU computation
+/// * `a` - The first matrix (m × k)
+/// * `b` - The second matrix (k × n)
+/// * `format` - The format the inputs are rounded to
+/// * `output` - The precision of the mixed-precision result
+///
+/// # Returns
+///
+/// A `Result` containing the comparison report or an error.
+///
+/// # Errors
+///
+/// Returns an error if either product fails. A comparison outside the tolerance is not
+/// an error; check `Report::passed`.
+///
+/// # Example
+///
+/// ```no_run
+/// use metal_matrix::precision::{self, HalfFormat, Precision};
+/// use metal_matrix::verify::sample_matrix;
+/// use metal_matrix::MetalContext;
+///
+/// let context = MetalContext::new().unwrap();
+/// let a = sample_matrix(256, 512, 1);
+/// let b = sample_matrix(512, 128, 2);
+///
+/// let report =
+///     precision::mixed_precision_report(&context, &a, &b, HalfFormat::F16, Precision::F32)
+///         .unwrap();
+/// println!("{}", report);
+/// ```
+pub fn mixed_precision_report(
+    context: &MetalContext,
+    a: &Matrix,
+    b: &Matrix,
+    format: HalfFormat,
+    output: Precision,
+) -> Result<Report> {
+    let half_a = to_half(context, a, format)?;
+    let half_b = to_half(context, b, format)?;
+    let result = mixed_matrix_multiply(context, &half_a, &half_b, output)?;
+    let reference = matrix_multiply(context, a, b)?;
+
+    accuracy_report(&result, &reference, a, b, format)
+}
@@ -0,0 +1,430 @@
+/*!
+ * # Quantization
+ *
+ * This module stores weight matrices with weight-only quantization, so that larger
+ * models fit in GPU memory, and multiplies by them on the GPU.
+ *
+ * `quantize` maps every element of a matrix to an unsigned integer code with an
+ * affine scheme: each block of elements shares a `scale` and a `zero_point`, chosen so
+ * that the block's range (extended to include zero) spans all codes and zero is exactly
+ * representable. An element is recovered as `(code - zero_point) * scale`.
+ *
+ * Two `Scheme`s are supported:
+ * - `Int8PerRow`: 8-bit codes, one byte each, with one scale per row (per output
+ *   channel of a weight matrix)
+ * - `Int4Group(g)`: 4-bit codes packed two per byte, with one scale per `g`
+ *   consecutive elements of a row
+ *
+ * `quantized_matrix_multiply` computes `A * Wᵀ` for a dense `A` and a quantized
+ * weight matrix `W` stored as output channels × input features, the layout of
+ * linear-layer weights. The kernels dequantize each weight inside the inner loop of
+ * the product with the same integer arithmetic as `dequantize`, so the dequantized
+ * weights are bit-identical on the CPU and the GPU; `cpu::quantized_matrix_multiply`
+ * is the reference.
+ *
+ * Quantization itself runs on the CPU, once per weight matrix.
+ *
+ * ```
+ * use metal_matrix::quantization::{dequantize, quantize, Scheme};
+ * use metal_matrix::Matrix;
+ *
+ * let w = Matrix::with_data(1, 4, vec![-1.0, 0.0, 2.0, 6.5]).unwrap();
+ *
+ * // The range [-1, 6.5] spans the 15 steps of a 4-bit code with a scale of 0.5
+ * let q = quantize(&w, Scheme::Int4Group(4)).unwrap();
+ * assert_eq!((q.scales.clone(), q.zero_points.clone()), (vec![0.5], vec![2]));
+ * assert_eq!(q.data, vec![0x20, 0xf6]);
+ * assert_eq!(dequantize(&q).data, w.data);
+ * ```
+ */
+
+use crate::kernels;
+use crate::matrix::Matrix;
+use crate::metal_context::set_constant;
+use crate::MetalContext;
+use anyhow::{anyhow, bail, Result};
+use metal::*;
+
+/// How a matrix is quantized.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Scheme {
+    /// 8-bit codes with one scale and zero point per row
+    Int8PerRow,
+
+    /// 4-bit codes with one scale and zero point per group of this many consecutive
+    /// elements of a row
+    Int4Group(usize),
+}
+
+impl Scheme {
+    /// The number of bits per code.
+    pub fn bits(&self) -> u32 {
+        match self {
+            Scheme::Int8PerRow => 8,
+            Scheme::Int4Group(_) => 4,
+        }
+    }
+
+    /// The largest code.
+    pub fn max_code(&self) -> u8 {
+        ((1u32 << self.bits()) - 1) as u8
+    }
+
+    /// The number of elements sharing a scale in a row of `cols` elements.
+    pub fn group_size(&self, cols: usize) -> usize {
+        match *self {
+            Scheme::Int8PerRow => cols,
+            Scheme::Int4Group(size) => size,
+        }
+    }
+
+    /// The number of groups in a row of `cols` elements.
+    pub fn groups_per_row(&self, cols: usize) -> usize {
+        match self.group_size(cols) {
+            0 => 0,
+            size => cols.div_ceil(size),
+        }
+    }
+
+    /// The number of bytes holding the codes of a row of `cols` elements.
+    pub fn row_bytes(&self, cols: usize) -> usize {
+        match self {
+            Scheme::Int8PerRow => cols,
+            Scheme::Int4Group(_) => cols.div_ceil(2),
+        }
+    }
+}
+
+/// A matrix stored as integer codes with per-row or per-group scales and zero points.
+#[derive(Clone, Debug, PartialEq)]
+pub struct QuantizedMatrix {
+    /// Number of rows in the matrix
+    pub rows: usize,
+
+    /// Number of columns in the matrix
+    pub cols: usize,
+
+    /// How the matrix is quantized
+    pub scheme: Scheme,
+
+    /// The codes, row by row; 4-bit codes are packed two per byte with the even
+    /// column in the low nibble, and each row starts on a new byte
+    pub data: Vec<u8>,
+
+    /// The scale of each group, row by row
+    pub scales: Vec<f32>,
+
+    /// The code of zero in each group, row by row
+    pub zero_points: Vec<u8>,
+}
+
+impl QuantizedMatrix {
+    /// Returns the code of the element at (row, col).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the position is out of bounds.
+    pub fn code(&self, row: usize, col: usize) -> u8 {
+        assert!(
+            row < self.rows && col < self.cols,
+            "Index ({}, {}) out of bounds",
+            row,
+            col
+        );
+
+        let start = row * self.scheme.row_bytes(self.cols);
+        match self.scheme {
+            Scheme::Int8PerRow => self.data[start + col],
+            Scheme::Int4Group(_) => (self.data[start + col / 2] >> (4 * (col % 2))) & 0xf,
+        }
+    }
+
+    /// Returns the index into `scales` and `zero_points` of the element at (row, col).
+    pub fn group(&self, row: usize, col: usize) -> usize {
+        row * self.scheme.groups_per_row(self.cols) + col / self.scheme.group_size(self.cols)
+    }
+
+    /// Returns the dequantized value of the element at (row, col).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the position is out of bounds.
+    pub fn get(&self, row: usize, col: usize) -> f32 {
+        let group = self.group(row, col);
+        dequantize_code(
+            self.code(row, col),
+            self.zero_points[group],
+            self.scales[group],
+        )
+    }
+
+    /// The memory used by the codes, scales and zero points, in bytes.
+    pub fn size_in_bytes(&self) -> usize {
+        self.data.len() + self.scales.len() * size_of::<f32>() + self.zero_points.len()
+    }
+}
+
+/// The value of a code: the integer offset from the zero point, times the scale.
+fn dequantize_code(code: u8, zero_point: u8, scale: f32) -> f32 {
+    (code as i32 - zero_point as i32) as f32 * scale
+}
+
+/// Chooses the scale and zero point of a group of finite values.
+///
+/// The range is extended to include zero, so the zero point is a valid code. A group
+/// without a nonzero f32 scale (all zeros, or values too close to zero) gets scale 1
+/// and zero point 0.
+fn group_parameters(values: &[f32], max_code: u8) -> (f32, u8) {
+    let min = values.iter().fold(0.0f32, |min, &x| min.min(x)) as f64;
+    let max = values.iter().fold(0.0f32, |max, &x| max.max(x)) as f64;
+
+    let scale = ((max - min) / max_code as f64) as f32;
+    if scale == 0.0 {
+        return (1.0, 0);
+    }
+    let zero_point = (-min / scale as f64).round().clamp(0.0, max_code as f64);
+    (scale, zero_point as u8)
+}
+
+/// Quantizes a value: rounded to nearest (ties away from zero) and clamped to the
+/// code range.
+fn quantize_value(x: f32, scale: f32, zero_point: u8, max_code: u8) -> u8 {
+    let code = (x as f64 / scale as f64).round() + zero_point as f64;
+    code.clamp(0.0, max_code as f64) as u8
+}
+
+/// Quantizes a matrix.
+///
+/// # Arguments
+///
+/// * `matrix` - The matrix to quantize (typically weights, one row per output channel)
+/// * `scheme` - The code width and grouping
+///
+/// # Returns
+///
+/// A `Result` containing the quantized matrix or an error.
+///
+/// # Errors
+///
+/// Returns an error if the matrix contains NaN or infinite values, or the group size
+/// of `Int4Group` is zero.
+pub fn quantize(matrix: &Matrix, scheme: Scheme) -> Result<QuantizedMatrix> {
+    if scheme == Scheme::Int4Group(0) {
+        bail!("Quantization group size must be positive");
+    }
+    if matrix.data.iter().any(|x| !x.is_finite()) {
+        bail!("Cannot quantize a matrix with NaN or infinite values");
+    }
+
+    let cols = matrix.cols;
+    let max_code = scheme.max_code();
+    let group_size = scheme.group_size(cols).max(1);
+    let row_bytes = scheme.row_bytes(cols);
+    let groups = matrix.rows * scheme.groups_per_row(cols);
+
+    let mut quantized = QuantizedMatrix {
+        rows: matrix.rows,
+        cols,
+        scheme,
+        data: vec![0; matrix.rows * row_bytes],
+        scales: Vec::with_capacity(groups),
+        zero_points: Vec::with_capacity(groups),
+    };
+
+    for (row, values) in matrix.data.chunks(cols.max(1)).enumerate() {
+        let codes = &mut quantized.data[row * row_bytes..(row + 1) * row_bytes];
+        for (group, values) in values.chunks(group_size).enumerate() {
+            let (scale, zero_point) = group_parameters(values, max_code);
+            quantized.scales.push(scale);
+            quantized.zero_points.push(zero_point);
+
+            for (i, &x) in values.iter().enumerate() {
+                let code = quantize_value(x, scale, zero_point, max_code);
+                let col = group * group_size + i;
+                match scheme {
+                    Scheme::Int8PerRow => codes[col] = code,
+                    Scheme::Int4Group(_) => codes[col / 2] |= code << (4 * (col % 2)),
+                }
+            }
+        }
+    }
+
+    Ok(quantized)
+}
+
+/// Dequantizes a matrix on the CPU, with the same arithmetic as the GPU kernels.
+///
+/// # Example
+///
+/// ```
+/// use metal_matrix::quantization::{dequantize, quantize, Scheme};
+/// use metal_matrix::Matrix;
+///
+/// let w = Matrix::with_data(2, 2, vec![0.1, -0.2, 3.0, 0.0]).unwrap();
+/// let q = quantize(&w, Scheme::Int8PerRow).unwrap();
+///
+/// // Each element is within half a step of the original
+/// let restored = dequantize(&q);
+/// for row in 0..2 {
+///     for col in 0..2 {
+///         let error = (restored.get(row, col) - w.get(row, col)).abs();
+///         assert!(error <= q.scales[row] / 2.0 * 1.001);
+///     }
+/// }
+/// ```
+pub fn dequantize(quantized: &QuantizedMatrix) -> Matrix {
+    let mut matrix = Matrix::new(quantized.rows, quantized.cols);
+    for row in 0..quantized.rows {
+        for col in 0..quantized.cols {
+            matrix.set(row, col, quantized.get(row, col));
+        }
+    }
+    matrix
+}
+
+/// Checks the operands of a quantized product, including that the arrays of `w`
+/// have the lengths its shape and scheme imply, since its fields are public.
+pub(crate) fn validate(a: &Matrix, w: &QuantizedMatrix) -> Result<()> {
+    if a.cols != w.cols {
+        bail!("Matrix dimensions incompatible for multiplication");
+    }
+    if w.scheme == Scheme::Int4Group(0) {
+        bail!("Quantization group size must be positive");
+    }
+
+    let expected = |per_row: usize| {
+        w.rows
+            .checked_mul(per_row)
+            .ok_or_else(|| anyhow!("Quantized matrix is too large"))
+    };
+    let data = expected(w.scheme.row_bytes(w.cols))?;
+    let groups = expected(w.scheme.groups_per_row(w.cols))?;
+    for (name, len, expected) in [
+        ("data bytes", w.data.len(), data),
+        ("scales", w.scales.len(), groups),
+        ("zero points", w.zero_points.len(), groups),
+    ] {
+        if len != expected {
+            bail!(
+                "Quantized matrix has {} {}, expected {} for a {}x{} {:?} matrix",
+                len,
+                name,
+                expected,
+                w.rows,
+                w.cols,
+                w.scheme
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Performs matrix multiplication by quantized weights on the GPU: C = A * Wᵀ
+///
+/// Dequantizes each weight inside the inner loop and accumulates in f32; only the
+/// codes, scales and zero points of `W` are uploaded.
+///
+/// # Arguments
+///
+/// * `context` - The Metal context for GPU computation
+/// * `a` - The input matrix (m × k)
+/// * `w` - The quantized weight matrix (n × k)
+///
+/// # Returns
+///
+/// A `Result` containing the product matrix (m × n) or an error.
+///
+/// # Errors
+///
+/// Returns an error if the matrices have incompatible dimensions (a.cols != w.cols)
+/// or the arrays of `w` do not match its shape and scheme, or a `LimitError` if they
+/// exceed the kernel or device limits.
+///
+/// # Example
+///
+/// ```no_run
+/// use metal_matrix::quantization::{self, Scheme};
+/// use metal_matrix::verify::sample_matrix;
+/// use metal_matrix::{cpu, Matrix, MetalContext};
+///
+/// let context = MetalContext::new().unwrap();
+/// let x = sample_matrix(8, 4096, 1);
+/// let w = quantization::quantize(&sample_matrix(1024, 4096, 2), Scheme::Int4Group(128)).unwrap();
+///
+/// let y = quantization::quantized_matrix_multiply(&context, &x, &w).unwrap();
+/// assert_eq!((y.rows, y.cols), (8, 1024));
+/// ```
+pub fn quantized_matrix_multiply(
+    context: &MetalContext,
+    a: &Matrix,
+    w: &QuantizedMatrix,
+) -> Result<Matrix> {
+    validate(a, w)?;
+
+    let m = a.rows;
+    let n = w.rows;
+    let k = a.cols;
+
+    // An empty result, or a product of empty sums (k = 0), is all zeros
+    if m == 0 || n == 0 || k == 0 {
+        return Ok(Matrix::new(m, n));
+    }
+    // W (n × k) has as many elements as the k × n operand of a plain product
+    context.limits().check_matrix_multiply(m, k, n)?;
+
+    // Load kernel
+    let function = match w.scheme {
+        Scheme::Int8PerRow => kernels::functions::QUANTIZED_MATRIX_MUL_INT8,
+        Scheme::Int4Group(_) => kernels::functions::QUANTIZED_MATRIX_MUL_INT4,
+    };
+    let pipeline = context.load_kernel(kernels::paths::QUANTIZED, function)?;
+
+    // Create buffers
+    let buffer_a = context.acquire_buffer_with_data(&a.data);
+    let buffer_w = context.acquire_buffer_with_data(&w.data);
+    let buffer_scales = context.acquire_buffer_with_data(&w.scales);
+    let buffer_zero_points = context.acquire_buffer_with_data(&w.zero_points);
+    let buffer_result = context.acquire_buffer::<f32>(m * n);
+
+    // Execute computation
+    let result = context.execute_compute(|encoder| {
+        encoder.set_compute_pipeline_state(&pipeline);
+        encoder.set_buffer(0, Some(&buffer_a), 0);
+        encoder.set_buffer(1, Some(&buffer_w), 0);
+        encoder.set_buffer(2, Some(&buffer_scales), 0);
+        encoder.set_buffer(3, Some(&buffer_zero_points), 0);
+        encoder.set_buffer(4, Some(&buffer_result), 0);
+        set_constant(encoder, 5, m as u32);
+        set_constant(encoder, 6, n as u32);
+        set_constant(encoder, 7, k as u32);
+        if let Scheme::Int4Group(size) = w.scheme {
+            set_constant(encoder, 8, size.min(k) as u32);
+        }
+
+        let grid_size = MTLSize::new(n as u64, m as u64, 1);
+        let threadgroup_size = context.threadgroup_size(function, n, m, &pipeline);
+        encoder.dispatch_threads(grid_size, threadgroup_size);
+    });
+
+    // Read results
+    let mut product = Matrix::new(m, n);
+    unsafe {
+        std::ptr::copy_nonoverlapping(
+            buffer_result.contents() as *const f32,
+            product.data.as_mut_ptr(),
+            m * n,
+        );
+    }
+    for buffer in [
+        buffer_a,
+        buffer_w,
+        buffer_scales,
+        buffer_zero_points,
+        buffer_result,
+    ] {
+        context.recycle_buffer(buffer);
+    }
+    result?;
+
+    Ok(product)
+}
@@ -0,0 +1,124 @@
+/*!
+ * # Serialization
+ *
+ * This module implements `Serialize` and `Deserialize` for `Matrix` when the `serde`
+ * feature is enabled.
+ *
+ * A matrix is encoded as a struct with `rows`, `cols` and `data` fields. The layout
+ * of `data` depends on the format:
+ * - human-readable formats (JSON, YAML, TOML) use nested rows, e.g.
+ *   `{"rows": 2, "cols": 2, "data": [[1.0, 2.0], [3.0, 4.0]]}`, and also accept a
+ *   flat row-major array when reading
+ * - binary formats (bincode, postcard) use the flat row-major array, so the encoding
+ *   is the two dimensions followed by the values
+ *
+ * Deserialization enforces the same invariant as `Matrix::with_data`: the data must
+ * hold exactly `rows * cols` values.
+ */
+
+use crate::matrix::Matrix;
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+#[derive(Serialize)]
+#[serde(rename = "Matrix")]
+struct FlatRef<'a> {
+    rows: usize,
+    cols: usize,
+    data: &'a [f32],
+}
+
+#[derive(Serialize)]
+#[serde(rename = "Matrix")]
+struct NestedRef<'a> {
+    rows: usize,
+    cols: usize,
+    data: Vec<&'a [f32]>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "Matrix")]
+struct Flat {
+    rows: usize,
+    cols: usize,
+    data: Vec<f32>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "Matrix")]
+struct Readable {
+    rows: usize,
+    cols: usize,
+    data: Data,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Data {
+    Nested(Vec<Vec<f32>>),
+    Flat(Vec<f32>),
+}
+
+impl Serialize for Matrix {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            NestedRef {
+                rows: self.rows,
+                cols: self.cols,
+                data: (0..self.rows)
+                    .map(|row| &self.data[row * self.cols..(row + 1) * self.cols])
+                    .collect(),
+            }
+            .serialize(serializer)
+        } else {
+            FlatRef {
+                rows: self.rows,
+                cols: self.cols,
+                data: &self.data,
+            }
+            .serialize(serializer)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Matrix {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let (rows, cols, data) = if deserializer.is_human_readable() {
+            let matrix = Readable::deserialize(deserializer)?;
+            let data = match matrix.data {
+                // An empty array is also the flat encoding of an empty matrix
+                Data::Nested(nested) if nested.is_empty() => Vec::new(),
+                Data::Nested(nested) => {
+                    if nested.len() != matrix.rows {
+                        return Err(D::Error::custom(format!(
+                            "expected {} rows, found {}",
+                            matrix.rows,
+                            nested.len()
+                        )));
+                    }
+                    if let Some(row) = nested.iter().position(|r| r.len() != matrix.cols) {
+                        return Err(D::Error::custom(format!(
+                            "expected {} columns in row {}, found {}",
+                            matrix.cols,
+                            row,
+                            nested[row].len()
+                        )));
+                    }
+                    nested.concat()
+                }
+                Data::Flat(data) => data,
+            };
+            (matrix.rows, matrix.cols, data)
+        } else {
+            let matrix = Flat::deserialize(deserializer)?;
+            (matrix.rows, matrix.cols, matrix.data)
+        };
+
+        if rows.checked_mul(cols) != Some(data.len()) {
+            return Err(D::Error::custom(
+                "Data length does not match matrix dimensions",
+            ));
+        }
+        Ok(Matrix { rows, cols, data })
+    }
+}
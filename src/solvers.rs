@@ -0,0 +1,769 @@
+/*!
+ * # Iterative Solvers
+ *
+ * This module provides Krylov subspace solvers for large linear systems A * x = b
+ * where a factorisation would be too expensive.
+ *
+ * ## Available Solvers
+ *
+ * - Conjugate gradient (`conjugate_gradient`) for symmetric positive definite systems
+ * - Preconditioned conjugate gradient (`preconditioned_conjugate_gradient`)
+ * - BiCGSTAB (`bicgstab`) for general non-symmetric systems
+ * - Restarted GMRES (`gmres`) for general non-symmetric systems
+ *
+ * The solvers only access the system through the `LinearOperator` trait. A `Matrix`
+ * is applied with `matrix_multiply` on the given backend (so on the GPU with a
+ * `MetalContext`), and any closure can be used through `FnOperator`. The vector
+ * updates between operator applications are cheap and run on the CPU.
+ */
+
+use crate::backend::Backend;
+use crate::cpu;
+use crate::matrix::Matrix;
+use crate::triangular::{Diag, Side, Transpose, Uplo};
+use anyhow::Result;
+
+/// A square linear operator y = A * x.
+pub trait LinearOperator {
+    /// Dimension n of the operator (it maps n×1 vectors to n×1 vectors).
+    fn size(&self) -> usize;
+
+    /// Applies the operator to a column vector.
+    ///
+    /// # Arguments
+    ///
+    /// * `backend` - The backend to run any matrix operations on
+    /// * `x` - The input vector (n × 1)
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the output vector (n × 1) or an error.
+    fn apply<B: Backend>(&self, backend: &B, x: &Matrix) -> Result<Matrix>;
+}
+
+impl LinearOperator for Matrix {
+    fn size(&self) -> usize {
+        self.rows
+    }
+
+    fn apply<B: Backend>(&self, backend: &B, x: &Matrix) -> Result<Matrix> {
+        backend.matrix_multiply(self, x)
+    }
+}
+
+/// A linear operator defined by a closure.
+///
+/// # Example
+///
+/// ```
+/// use metal_matrix::{FnOperator, Matrix};
+///
+/// // The operator x -> 2x on vectors of length 3
+/// let double = FnOperator::new(3, |x: &Matrix| {
+///     Ok(Matrix::vector(x.data.iter().map(|v| 2.0 * v).collect()))
+/// });
+/// ```
+pub struct FnOperator<F> {
+    size: usize,
+    function: F,
+}
+
+impl<F> FnOperator<F>
+where
+    F: Fn(&Matrix) -> Result<Matrix>,
+{
+    /// Create an operator of dimension `size` from a closure computing A * x.
+    pub fn new(size: usize, function: F) -> Self {
+        Self { size, function }
+    }
+}
+
+impl<F> LinearOperator for FnOperator<F>
+where
+    F: Fn(&Matrix) -> Result<Matrix>,
+{
+    fn size(&self) -> usize {
+        self.size
+    }
+
+    fn apply<B: Backend>(&self, _backend: &B, x: &Matrix) -> Result<Matrix> {
+        (self.function)(x)
+    }
+}
+
+/// A preconditioner M ≈ A, applied as z = M^-1 * r.
+pub trait Preconditioner {
+    /// Applies the inverse of the preconditioner to a column vector.
+    fn apply(&self, r: &Matrix) -> Result<Matrix>;
+}
+
+/// The identity preconditioner, used by the unpreconditioned solvers.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct IdentityPreconditioner;
+
+impl Preconditioner for IdentityPreconditioner {
+    fn apply(&self, r: &Matrix) -> Result<Matrix> {
+        Ok(r.clone())
+    }
+}
+
+/// Jacobi (diagonal) preconditioner: M = diag(A).
+#[derive(Clone, Debug)]
+pub struct Jacobi {
+    inverse_diagonal: Vec<f32>,
+}
+
+impl Jacobi {
+    /// Build a Jacobi preconditioner from the diagonal of `a`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `a` is not square or has a zero on its diagonal.
+    pub fn new(a: &Matrix) -> Result<Self> {
+        if a.rows != a.cols {
+            anyhow::bail!("Preconditioner matrix must be square");
+        }
+
+        let mut inverse_diagonal = Vec::with_capacity(a.rows);
+        for i in 0..a.rows {
+            let value = a.get(i, i);
+            if value == 0.0 {
+                anyhow::bail!("Jacobi preconditioner requires a non-zero diagonal");
+            }
+            inverse_diagonal.push(1.0 / value);
+        }
+
+        Ok(Self { inverse_diagonal })
+    }
+}
+
+impl Preconditioner for Jacobi {
+    fn apply(&self, r: &Matrix) -> Result<Matrix> {
+        check_vector(r, self.inverse_diagonal.len())?;
+        let data = r
+            .data
+            .iter()
+            .zip(&self.inverse_diagonal)
+            .map(|(value, inverse)| value * inverse)
+            .collect();
+        Ok(Matrix::vector(data))
+    }
+}
+
+/// Incomplete Cholesky preconditioner with zero fill-in: M = L * L^T.
+///
+/// `L` keeps the sparsity pattern of the lower triangle of `A`, so for a matrix
+/// without zeros it is the exact Cholesky factor.
+#[derive(Clone, Debug)]
+pub struct IncompleteCholesky {
+    factor: Matrix,
+}
+
+impl IncompleteCholesky {
+    /// Compute the incomplete Cholesky factor of a symmetric positive definite matrix.
+    ///
+    /// Only the lower triangle of `a` is read.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `a` is not square or a non-positive pivot is encountered.
+    pub fn new(a: &Matrix) -> Result<Self> {
+        if a.rows != a.cols {
+            anyhow::bail!("Preconditioner matrix must be square");
+        }
+
+        let n = a.rows;
+        let mut l = a.lower_triangle(0);
+
+        for k in 0..n {
+            let pivot = l.get(k, k);
+            if pivot <= 0.0 {
+                anyhow::bail!("Incomplete Cholesky breakdown: matrix is not positive definite");
+            }
+            let pivot = pivot.sqrt();
+            l.set(k, k, pivot);
+
+            for i in k + 1..n {
+                if a.get(i, k) != 0.0 {
+                    l.set(i, k, l.get(i, k) / pivot);
+                }
+            }
+
+            for j in k + 1..n {
+                let l_jk = l.get(j, k);
+                if l_jk == 0.0 {
+                    continue;
+                }
+                for i in j..n {
+                    if a.get(i, j) != 0.0 {
+                        l.set(i, j, l.get(i, j) - l.get(i, k) * l_jk);
+                    }
+                }
+            }
+        }
+
+        Ok(Self { factor: l })
+    }
+
+    /// The lower triangular factor L.
+    pub fn factor(&self) -> &Matrix {
+        &self.factor
+    }
+}
+
+impl Preconditioner for IncompleteCholesky {
+    fn apply(&self, r: &Matrix) -> Result<Matrix> {
+        check_vector(r, self.factor.rows)?;
+        let y = cpu::triangular_solve(
+            &self.factor,
+            r,
+            Uplo::Lower,
+            Transpose::NoTrans,
+            Diag::NonUnit,
+            Side::Left,
+        )?;
+        cpu::triangular_solve(
+            &self.factor,
+            &y,
+            Uplo::Lower,
+            Transpose::Trans,
+            Diag::NonUnit,
+            Side::Left,
+        )
+    }
+}
+
+/// Stopping criteria for the iterative solvers.
+#[derive(Clone, Debug)]
+pub struct SolverOptions {
+    /// Relative residual ||b - A * x|| / ||b|| at which the solver stops
+    pub tolerance: f32,
+
+    /// Maximum number of iterations; a BiCGSTAB iteration applies the operator twice and
+    /// a GMRES iteration is one Arnoldi step
+    pub max_iterations: usize,
+}
+
+impl Default for SolverOptions {
+    fn default() -> Self {
+        Self {
+            tolerance: 1e-6,
+            max_iterations: 1000,
+        }
+    }
+}
+
+/// Why an iterative solver stopped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SolverStatus {
+    /// The relative residual dropped below the tolerance
+    Converged,
+
+    /// The iteration limit was reached before converging
+    MaxIterations,
+
+    /// The method broke down (a division by zero in the recurrence)
+    Breakdown,
+}
+
+/// The outcome of an iterative solve.
+#[derive(Clone, Debug)]
+pub struct SolverResult {
+    /// The final iterate x (n × 1)
+    pub solution: Matrix,
+
+    /// Number of iterations performed
+    pub iterations: usize,
+
+    /// Relative residual after each iteration, starting with the initial residual
+    pub residual_history: Vec<f32>,
+
+    /// Why the solver stopped
+    pub status: SolverStatus,
+}
+
+impl SolverResult {
+    /// Whether the solver reached the requested tolerance.
+    pub fn converged(&self) -> bool {
+        self.status == SolverStatus::Converged
+    }
+}
+
+/// Solves A * x = b with the conjugate gradient method.
+///
+/// `A` must be symmetric positive definite.
+///
+/// # Arguments
+///
+/// * `backend` - The backend the operator is applied on (e.g. a `MetalContext`)
+/// * `operator` - The system matrix A
+/// * `b` - The right-hand side (n × 1)
+/// * `options` - Tolerance and iteration limit
+///
+/// # Returns
+///
+/// A `Result` containing the solution, iteration count, residual history and status.
+///
+/// # Errors
+///
+/// Returns an error if `b` is not an n×1 vector or applying the operator fails.
+///
+/// # Example
+///
+/// ```
+/// use metal_matrix::{conjugate_gradient, CpuBackend, Matrix, SolverOptions};
+///
+/// let a = Matrix::with_data(2, 2, vec![4.0, 1.0, 1.0, 3.0]).unwrap();
+/// let b = Matrix::vector(vec![1.0, 2.0]);
+///
+/// let result = conjugate_gradient(&CpuBackend, &a, &b, &SolverOptions::default()).unwrap();
+/// assert!(result.converged());
+/// assert!((result.solution.data[0] - 1.0 / 11.0).abs() < 1e-5);
+/// assert!((result.solution.data[1] - 7.0 / 11.0).abs() < 1e-5);
+/// ```
+pub fn conjugate_gradient<B, A>(
+    backend: &B,
+    operator: &A,
+    b: &Matrix,
+    options: &SolverOptions,
+) -> Result<SolverResult>
+where
+    B: Backend,
+    A: LinearOperator,
+{
+    preconditioned_conjugate_gradient(backend, operator, b, &IdentityPreconditioner, options)
+}
+
+/// Solves A * x = b with the preconditioned conjugate gradient method.
+///
+/// `A` and the preconditioner must both be symmetric positive definite.
+///
+/// # Arguments
+///
+/// * `backend` - The backend the operator is applied on (e.g. a `MetalContext`)
+/// * `operator` - The system matrix A
+/// * `b` - The right-hand side (n × 1)
+/// * `preconditioner` - The preconditioner M ≈ A
+/// * `options` - Tolerance and iteration limit
+///
+/// # Returns
+///
+/// A `Result` containing the solution, iteration count, residual history and status.
+///
+/// # Errors
+///
+/// Returns an error if `b` is not an n×1 vector or applying the operator or
+/// preconditioner fails.
+///
+/// # Example
+///
+/// ```
+/// use metal_matrix::{
+///     preconditioned_conjugate_gradient, CpuBackend, IncompleteCholesky, Matrix, SolverOptions,
+/// };
+///
+/// let a = Matrix::with_data(3, 3, vec![4.0, 1.0, 0.0, 1.0, 4.0, 1.0, 0.0, 1.0, 4.0]).unwrap();
+/// let b = Matrix::vector(vec![1.0, 2.0, 3.0]);
+///
+/// // The incomplete factor of a tridiagonal matrix is exact, so one iteration suffices
+/// let ic = IncompleteCholesky::new(&a).unwrap();
+/// let result =
+///     preconditioned_conjugate_gradient(&CpuBackend, &a, &b, &ic, &SolverOptions::default())
+///         .unwrap();
+/// assert!(result.converged());
+/// assert_eq!(result.iterations, 1);
+/// ```
+pub fn preconditioned_conjugate_gradient<B, A, P>(
+    backend: &B,
+    operator: &A,
+    b: &Matrix,
+    preconditioner: &P,
+    options: &SolverOptions,
+) -> Result<SolverResult>
+where
+    B: Backend,
+    A: LinearOperator,
+    P: Preconditioner,
+{
+    let n = operator.size();
+    check_vector(b, n)?;
+
+    let b_norm = norm(&b.data);
+    let mut x = vec![0.0f32; n];
+    if b_norm == 0.0 {
+        return Ok(finish(x, 0, vec![0.0], SolverStatus::Converged));
+    }
+
+    let mut r = b.data.clone();
+    let mut z = preconditioner.apply(b)?.data;
+    let mut p = z.clone();
+    let mut rz = dot(&r, &z);
+    let mut history = vec![1.0];
+    // A singular or indefinite preconditioner can make rᵀ * z zero or non-finite
+    if rz == 0.0 || !rz.is_finite() {
+        return Ok(finish(x, 0, history, SolverStatus::Breakdown));
+    }
+
+    for iteration in 1..=options.max_iterations {
+        let ap = apply(backend, operator, &p)?;
+        let pap = dot(&p, &ap);
+        if pap.is_nan() || pap <= 0.0 {
+            return Ok(finish(x, iteration, history, SolverStatus::Breakdown));
+        }
+
+        let alpha = rz / pap;
+        axpy(alpha, &p, &mut x);
+        axpy(-alpha, &ap, &mut r);
+
+        let residual = norm(&r) / b_norm;
+        history.push(residual);
+        if residual <= options.tolerance {
+            return Ok(finish(x, iteration, history, SolverStatus::Converged));
+        }
+
+        z = preconditioner.apply(&Matrix::vector(r.clone()))?.data;
+        let rz_next = dot(&r, &z);
+        if rz_next == 0.0 || !rz_next.is_finite() {
+            return Ok(finish(x, iteration, history, SolverStatus::Breakdown));
+        }
+        let beta = rz_next / rz;
+        for (p_i, z_i) in p.iter_mut().zip(&z) {
+            *p_i = z_i + beta * *p_i;
+        }
+        rz = rz_next;
+    }
+
+    Ok(finish(
+        x,
+        options.max_iterations,
+        history,
+        SolverStatus::MaxIterations,
+    ))
+}
+
+/// Solves A * x = b with the stabilised bi-conjugate gradient method (BiCGSTAB).
+///
+/// Works for general non-symmetric `A`. Each iteration applies the operator twice.
+///
+/// # Arguments
+///
+/// * `backend` - The backend the operator is applied on (e.g. a `MetalContext`)
+/// * `operator` - The system matrix A
+/// * `b` - The right-hand side (n × 1)
+/// * `options` - Tolerance and iteration limit
+///
+/// # Returns
+///
+/// A `Result` containing the solution, iteration count, residual history and status.
+///
+/// # Errors
+///
+/// Returns an error if `b` is not an n×1 vector or applying the operator fails.
+///
+/// # Example
+///
+/// ```
+/// use metal_matrix::{bicgstab, CpuBackend, Matrix, SolverOptions};
+///
+/// let a = Matrix::with_data(2, 2, vec![3.0, 1.0, -1.0, 2.0]).unwrap();
+/// let b = Matrix::vector(vec![5.0, 3.0]);
+///
+/// let result = bicgstab(&CpuBackend, &a, &b, &SolverOptions::default()).unwrap();
+/// assert!(result.converged());
+/// assert!((result.solution.data[0] - 1.0).abs() < 1e-4);
+/// assert!((result.solution.data[1] - 2.0).abs() < 1e-4);
+/// ```
+pub fn bicgstab<B, A>(
+    backend: &B,
+    operator: &A,
+    b: &Matrix,
+    options: &SolverOptions,
+) -> Result<SolverResult>
+where
+    B: Backend,
+    A: LinearOperator,
+{
+    let n = operator.size();
+    check_vector(b, n)?;
+
+    let b_norm = norm(&b.data);
+    let mut x = vec![0.0f32; n];
+    if b_norm == 0.0 {
+        return Ok(finish(x, 0, vec![0.0], SolverStatus::Converged));
+    }
+
+    let mut r = b.data.clone();
+    let r_hat = r.clone();
+    let mut p = vec![0.0f32; n];
+    let mut v = vec![0.0f32; n];
+    let (mut rho, mut alpha, mut omega) = (1.0f32, 1.0f32, 1.0f32);
+    let mut history = vec![1.0];
+
+    for iteration in 1..=options.max_iterations {
+        let rho_next = dot(&r_hat, &r);
+        if rho_next == 0.0 {
+            return Ok(finish(x, iteration, history, SolverStatus::Breakdown));
+        }
+
+        let beta = (rho_next / rho) * (alpha / omega);
+        for ((p_i, r_i), v_i) in p.iter_mut().zip(&r).zip(&v) {
+            *p_i = r_i + beta * (*p_i - omega * v_i);
+        }
+
+        v = apply(backend, operator, &p)?;
+        let r_hat_v = dot(&r_hat, &v);
+        if r_hat_v == 0.0 {
+            return Ok(finish(x, iteration, history, SolverStatus::Breakdown));
+        }
+        alpha = rho_next / r_hat_v;
+
+        let mut s = r.clone();
+        axpy(-alpha, &v, &mut s);
+        let s_residual = norm(&s) / b_norm;
+        if s_residual <= options.tolerance {
+            axpy(alpha, &p, &mut x);
+            history.push(s_residual);
+            return Ok(finish(x, iteration, history, SolverStatus::Converged));
+        }
+
+        let t = apply(backend, operator, &s)?;
+        let tt = dot(&t, &t);
+        if tt == 0.0 {
+            return Ok(finish(x, iteration, history, SolverStatus::Breakdown));
+        }
+        omega = dot(&t, &s) / tt;
+
+        axpy(alpha, &p, &mut x);
+        axpy(omega, &s, &mut x);
+        r = s;
+        axpy(-omega, &t, &mut r);
+
+        let residual = norm(&r) / b_norm;
+        history.push(residual);
+        if residual <= options.tolerance {
+            return Ok(finish(x, iteration, history, SolverStatus::Converged));
+        }
+        if omega == 0.0 {
+            return Ok(finish(x, iteration, history, SolverStatus::Breakdown));
+        }
+
+        rho = rho_next;
+    }
+
+    Ok(finish(
+        x,
+        options.max_iterations,
+        history,
+        SolverStatus::MaxIterations,
+    ))
+}
+
+/// Solves A * x = b with the restarted generalised minimal residual method, GMRES(m).
+///
+/// Works for general non-symmetric `A`. The Krylov basis is rebuilt from the current
+/// residual every `restart` iterations, bounding memory to `restart + 1` vectors.
+///
+/// If `A` is singular on the Krylov subspace, the solver stops with
+/// `SolverStatus::Breakdown` and the best iterate found before the singular direction.
+///
+/// # Arguments
+///
+/// * `backend` - The backend the operator is applied on (e.g. a `MetalContext`)
+/// * `operator` - The system matrix A
+/// * `b` - The right-hand side (n × 1)
+/// * `restart` - Krylov subspace dimension m between restarts
+/// * `options` - Tolerance and iteration limit
+///
+/// # Returns
+///
+/// A `Result` containing the solution, iteration count, residual history and status.
+///
+/// # Errors
+///
+/// Returns an error if `b` is not an n×1 vector, `restart` is zero, or applying the
+/// operator fails.
+///
+/// # Example
+///
+/// ```
+/// use metal_matrix::{gmres, CpuBackend, Matrix, SolverOptions};
+///
+/// let a = Matrix::with_data(3, 3, vec![2.0, 1.0, 0.0, 0.0, 3.0, 1.0, 1.0, 0.0, 4.0]).unwrap();
+/// let b = Matrix::vector(vec![3.0, 4.0, 5.0]);
+///
+/// let result = gmres(&CpuBackend, &a, &b, 3, &SolverOptions::default()).unwrap();
+/// assert!(result.converged());
+/// for value in &result.solution.data {
+///     assert!((value - 1.0).abs() < 1e-4);
+/// }
+/// ```
+pub fn gmres<B, A>(
+    backend: &B,
+    operator: &A,
+    b: &Matrix,
+    restart: usize,
+    options: &SolverOptions,
+) -> Result<SolverResult>
+where
+    B: Backend,
+    A: LinearOperator,
+{
+    let n = operator.size();
+    check_vector(b, n)?;
+    if restart == 0 {
+        anyhow::bail!("GMRES restart length must be at least 1");
+    }
+
+    let b_norm = norm(&b.data);
+    let mut x = vec![0.0f32; n];
+    if b_norm == 0.0 {
+        return Ok(finish(x, 0, vec![0.0], SolverStatus::Converged));
+    }
+
+    let mut history = vec![1.0];
+    let mut iterations = 0;
+
+    while iterations < options.max_iterations {
+        // r = b - A * x
+        let mut r = b.data.clone();
+        axpy(-1.0, &apply(backend, operator, &x)?, &mut r);
+        let beta = norm(&r);
+        if beta / b_norm <= options.tolerance {
+            return Ok(finish(x, iterations, history, SolverStatus::Converged));
+        }
+
+        // Arnoldi process with Givens rotations applied to the Hessenberg matrix
+        let mut basis = vec![r.iter().map(|value| value / beta).collect::<Vec<f32>>()];
+        let mut hessenberg = Matrix::new(restart + 1, restart);
+        let mut cosines = vec![0.0f32; restart];
+        let mut sines = vec![0.0f32; restart];
+        let mut g = vec![0.0f32; restart + 1];
+        g[0] = beta;
+
+        let mut steps = 0;
+        let mut residual = beta / b_norm;
+        let mut happy_breakdown = false;
+        let mut breakdown = false;
+
+        for j in 0..restart {
+            let mut w = apply(backend, operator, &basis[j])?;
+            iterations += 1;
+            steps = j + 1;
+
+            // Modified Gram-Schmidt
+            for (i, v) in basis.iter().enumerate() {
+                let h = dot(&w, v);
+                hessenberg.set(i, j, h);
+                axpy(-h, v, &mut w);
+            }
+            let h_next = norm(&w);
+            hessenberg.set(j + 1, j, h_next);
+
+            for i in 0..j {
+                let (upper, lower) = (hessenberg.get(i, j), hessenberg.get(i + 1, j));
+                hessenberg.set(i, j, cosines[i] * upper + sines[i] * lower);
+                hessenberg.set(i + 1, j, -sines[i] * upper + cosines[i] * lower);
+            }
+
+            let (diagonal, below) = (hessenberg.get(j, j), hessenberg.get(j + 1, j));
+            let radius = diagonal.hypot(below);
+            if radius == 0.0 {
+                // A maps the new basis vector into the span of the previous ones: the
+                // operator is singular on the Krylov subspace and H cannot be reduced
+                steps = j;
+                breakdown = true;
+                break;
+            }
+            cosines[j] = diagonal / radius;
+            sines[j] = below / radius;
+            hessenberg.set(j, j, radius);
+            hessenberg.set(j + 1, j, 0.0);
+            g[j + 1] = -sines[j] * g[j];
+            g[j] *= cosines[j];
+
+            residual = g[j + 1].abs() / b_norm;
+            history.push(residual);
+
+            if h_next == 0.0 {
+                happy_breakdown = true;
+                break;
+            }
+            if residual <= options.tolerance || iterations >= options.max_iterations {
+                break;
+            }
+            basis.push(w.iter().map(|value| value / h_next).collect());
+        }
+
+        // Minimise the residual over the Krylov subspace: x += V * H^-1 * g
+        let h = hessenberg.submatrix(0, 0, steps, steps);
+        let y = cpu::triangular_solve(
+            &h,
+            &Matrix::vector(g[..steps].to_vec()),
+            Uplo::Upper,
+            Transpose::NoTrans,
+            Diag::NonUnit,
+            Side::Left,
+        )?;
+        for (v, y_i) in basis.iter().zip(&y.data) {
+            axpy(*y_i, v, &mut x);
+        }
+
+        if breakdown {
+            return Ok(finish(x, iterations, history, SolverStatus::Breakdown));
+        }
+        if residual <= options.tolerance || happy_breakdown {
+            return Ok(finish(x, iterations, history, SolverStatus::Converged));
+        }
+    }
+
+    Ok(finish(x, iterations, history, SolverStatus::MaxIterations))
+}
+
+/// Checks that `v` is an n×1 column vector.
+fn check_vector(v: &Matrix, n: usize) -> Result<()> {
+    if v.rows != n || v.cols != 1 {
+        anyhow::bail!(
+            "Expected a {}x1 vector, got a {}x{} matrix",
+            n,
+            v.rows,
+            v.cols
+        );
+    }
+
+    Ok(())
+}
+
+/// Applies the operator to a plain vector, checking the size of the result.
+fn apply<B: Backend, A: LinearOperator>(backend: &B, operator: &A, x: &[f32]) -> Result<Vec<f32>> {
+    let y = operator.apply(backend, &Matrix::vector(x.to_vec()))?;
+    check_vector(&y, x.len())?;
+    Ok(y.data)
+}
+
+fn finish(x: Vec<f32>, iterations: usize, history: Vec<f32>, status: SolverStatus) -> SolverResult {
+    SolverResult {
+        solution: Matrix::vector(x),
+        iterations,
+        residual_history: history,
+        status,
+    }
+}
+
+/// Dot product accumulated in double precision.
+fn dot(x: &[f32], y: &[f32]) -> f32 {
+    x.iter()
+        .zip(y)
+        .map(|(a, b)| *a as f64 * *b as f64)
+        .sum::<f64>() as f32
+}
+
+fn norm(x: &[f32]) -> f32 {
+    dot(x, x).sqrt()
+}
+
+/// y += alpha * x
+fn axpy(alpha: f32, x: &[f32], y: &mut [f32]) {
+    for (y_i, x_i) in y.iter_mut().zip(x) {
+        *y_i += alpha * x_i;
+    }
+}
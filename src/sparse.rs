@@ -0,0 +1,832 @@
+/*!
+ * # Sparse Matrices
+ *
+ * This module provides sparse matrix types for matrices that are mostly zeros,
+ * such as graph adjacency and finite-element matrices.
+ *
+ * - `CooMatrix`: coordinate (triplet) format, convenient for building a matrix
+ * - `CsrMatrix`: compressed sparse row format, used for computation
+ * - `CscMatrix`: compressed sparse column format
+ *
+ * Sparse-dense products run on the GPU with `spmv` (matrix-vector) and `spmm`
+ * (matrix-matrix). Two kernel strategies are available, selected automatically
+ * from the average number of non-zeros per row or explicitly with `SparseKernel`.
+ * CPU references are provided in `cpu`.
+ *
+ * Indices are stored as `u32` so that they can be uploaded to Metal unchanged.
+ */
+
+use crate::kernels;
+use crate::limits::{check_32bit, MAX_32BIT_INDEX};
+use crate::matrix::Matrix;
+use crate::metal_context::set_constant;
+use crate::MetalContext;
+use anyhow::Result;
+use metal::*;
+
+/// Width of a SIMD group, the number of threads sharing a row in the vector kernels.
+const SIMD_WIDTH: u64 = 32;
+
+/// Average non-zeros per row above which the vector kernels are used.
+const VECTOR_KERNEL_THRESHOLD: usize = 16;
+
+/// A sparse matrix in coordinate (triplet) format.
+///
+/// Entries can be pushed in any order; duplicates are summed when converting to
+/// a compressed format.
+///
+/// # Example
+///
+/// ```
+/// use metal_matrix::CooMatrix;
+///
+/// let mut coo = CooMatrix::new(2, 3);
+/// coo.push(0, 2, 1.0).unwrap();
+/// coo.push(1, 0, 2.0).unwrap();
+/// coo.push(0, 2, 0.5).unwrap();
+///
+/// let csr = coo.to_csr().unwrap();
+/// assert_eq!(csr.nnz(), 2);
+/// assert_eq!(csr.get(0, 2), 1.5);
+/// ```
+#[derive(Clone, Debug)]
+pub struct CooMatrix {
+    /// Number of rows in the matrix
+    pub rows: usize,
+
+    /// Number of columns in the matrix
+    pub cols: usize,
+
+    /// Row index of each entry
+    pub row_indices: Vec<usize>,
+
+    /// Column index of each entry
+    pub col_indices: Vec<usize>,
+
+    /// Value of each entry
+    pub values: Vec<f32>,
+}
+
+impl CooMatrix {
+    /// Create an empty sparse matrix with given dimensions.
+    pub fn new(rows: usize, cols: usize) -> Self {
+        Self {
+            rows,
+            cols,
+            row_indices: Vec::new(),
+            col_indices: Vec::new(),
+            values: Vec::new(),
+        }
+    }
+
+    /// Add an entry at position (row, col).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the position is outside the matrix.
+    pub fn push(&mut self, row: usize, col: usize, value: f32) -> Result<()> {
+        if row >= self.rows || col >= self.cols {
+            anyhow::bail!(
+                "Entry ({}, {}) is outside a {}x{} matrix",
+                row,
+                col,
+                self.rows,
+                self.cols
+            );
+        }
+
+        self.row_indices.push(row);
+        self.col_indices.push(col);
+        self.values.push(value);
+        Ok(())
+    }
+
+    /// Number of stored entries (including duplicates).
+    pub fn nnz(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Convert to compressed sparse row format, summing duplicate entries.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an entry is outside the matrix or the matrix is too large
+    /// for 32-bit indices.
+    pub fn to_csr(&self) -> Result<CsrMatrix> {
+        if self.row_indices.len() != self.values.len()
+            || self.col_indices.len() != self.values.len()
+        {
+            anyhow::bail!("Coordinate arrays must have the same length");
+        }
+        if let Some(i) = (0..self.nnz())
+            .find(|&i| self.row_indices[i] >= self.rows || self.col_indices[i] >= self.cols)
+        {
+            anyhow::bail!(
+                "Entry ({}, {}) is outside a {}x{} matrix",
+                self.row_indices[i],
+                self.col_indices[i],
+                self.rows,
+                self.cols
+            );
+        }
+        to_index(self.cols)?;
+        to_index(self.nnz())?;
+
+        let mut order: Vec<usize> = (0..self.nnz()).collect();
+        order.sort_by_key(|&i| (self.row_indices[i], self.col_indices[i]));
+
+        let mut row_offsets = vec![0u32; self.rows + 1];
+        let mut col_indices: Vec<u32> = Vec::with_capacity(self.nnz());
+        let mut values: Vec<f32> = Vec::with_capacity(self.nnz());
+        let mut last: Option<(usize, usize)> = None;
+
+        for i in order {
+            let position = (self.row_indices[i], self.col_indices[i]);
+            if last == Some(position) {
+                *values.last_mut().unwrap() += self.values[i];
+                continue;
+            }
+            last = Some(position);
+            col_indices.push(position.1 as u32);
+            values.push(self.values[i]);
+            row_offsets[position.0 + 1] += 1;
+        }
+
+        for row in 0..self.rows {
+            row_offsets[row + 1] += row_offsets[row];
+        }
+
+        Ok(CsrMatrix {
+            rows: self.rows,
+            cols: self.cols,
+            row_offsets,
+            col_indices,
+            values,
+        })
+    }
+
+    /// Convert to compressed sparse column format, summing duplicate entries.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an entry is outside the matrix or the matrix is too large
+    /// for 32-bit indices.
+    pub fn to_csc(&self) -> Result<CscMatrix> {
+        Ok(self.to_csr()?.to_csc())
+    }
+}
+
+/// A sparse matrix in compressed sparse row (CSR) format.
+///
+/// The non-zeros of row `i` are `values[row_offsets[i]..row_offsets[i + 1]]`, at the
+/// columns given by the same range of `col_indices`. Column indices are strictly
+/// increasing within each row.
+///
+/// # Example
+///
+/// ```
+/// use metal_matrix::{CsrMatrix, Matrix};
+///
+/// let dense = Matrix::with_data(2, 3, vec![1.0, 0.0, 2.0, 0.0, 0.0, 3.0]).unwrap();
+/// let csr = CsrMatrix::from_dense(&dense).unwrap();
+///
+/// assert_eq!(csr.row_offsets, vec![0, 2, 3]);
+/// assert_eq!(csr.col_indices, vec![0, 2, 2]);
+/// assert_eq!(csr.values, vec![1.0, 2.0, 3.0]);
+/// assert_eq!(csr.to_dense().data, dense.data);
+/// ```
+#[derive(Clone, Debug)]
+pub struct CsrMatrix {
+    /// Number of rows in the matrix
+    pub rows: usize,
+
+    /// Number of columns in the matrix
+    pub cols: usize,
+
+    /// Offset of the first non-zero of each row, followed by the number of non-zeros
+    pub row_offsets: Vec<u32>,
+
+    /// Column index of each non-zero
+    pub col_indices: Vec<u32>,
+
+    /// Value of each non-zero
+    pub values: Vec<f32>,
+}
+
+impl CsrMatrix {
+    /// Create a sparse matrix from its CSR arrays.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the arrays are inconsistent with each other or with the
+    /// dimensions, or if the column indices of a row are not strictly increasing.
+    pub fn new(
+        rows: usize,
+        cols: usize,
+        row_offsets: Vec<u32>,
+        col_indices: Vec<u32>,
+        values: Vec<f32>,
+    ) -> Result<Self> {
+        if row_offsets.len() != rows + 1 || row_offsets[0] != 0 {
+            anyhow::bail!("Row offsets must have rows + 1 entries starting at 0");
+        }
+        if col_indices.len() != values.len() || row_offsets[rows] as usize != values.len() {
+            anyhow::bail!("Row offsets do not match the number of non-zeros");
+        }
+        // Non-decreasing offsets that end at the number of non-zeros stay within the
+        // arrays, so each row can be sliced safely below
+        if row_offsets.windows(2).any(|pair| pair[0] > pair[1]) {
+            anyhow::bail!("Row offsets must be non-decreasing");
+        }
+        for row in 0..rows {
+            let (start, end) = (row_offsets[row], row_offsets[row + 1]);
+            let columns = &col_indices[start as usize..end as usize];
+            if columns.windows(2).any(|pair| pair[0] >= pair[1]) {
+                anyhow::bail!(
+                    "Column indices must be strictly increasing within row {}",
+                    row
+                );
+            }
+            if columns.last().is_some_and(|&col| col as usize >= cols) {
+                anyhow::bail!("Column index out of bounds in row {}", row);
+            }
+        }
+
+        Ok(Self {
+            rows,
+            cols,
+            row_offsets,
+            col_indices,
+            values,
+        })
+    }
+
+    /// Create an all-zero sparse matrix with given dimensions.
+    pub fn zeros(rows: usize, cols: usize) -> Self {
+        Self {
+            rows,
+            cols,
+            row_offsets: vec![0; rows + 1],
+            col_indices: Vec::new(),
+            values: Vec::new(),
+        }
+    }
+
+    /// Convert a dense matrix, keeping its non-zero elements.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the matrix is too large for 32-bit indices.
+    pub fn from_dense(matrix: &Matrix) -> Result<Self> {
+        to_index(matrix.cols)?;
+
+        let mut row_offsets = Vec::with_capacity(matrix.rows + 1);
+        let mut col_indices = Vec::new();
+        let mut values = Vec::new();
+        row_offsets.push(0);
+
+        for row in 0..matrix.rows {
+            for col in 0..matrix.cols {
+                let value = matrix.get(row, col);
+                if value != 0.0 {
+                    col_indices.push(col as u32);
+                    values.push(value);
+                }
+            }
+            row_offsets.push(to_index(values.len())?);
+        }
+
+        Ok(Self {
+            rows: matrix.rows,
+            cols: matrix.cols,
+            row_offsets,
+            col_indices,
+            values,
+        })
+    }
+
+    /// Convert to a dense matrix.
+    pub fn to_dense(&self) -> Matrix {
+        let mut matrix = Matrix::new(self.rows, self.cols);
+        for row in 0..self.rows {
+            for i in self.row_range(row) {
+                matrix.set(row, self.col_indices[i] as usize, self.values[i]);
+            }
+        }
+        matrix
+    }
+
+    /// Convert to coordinate format.
+    pub fn to_coo(&self) -> CooMatrix {
+        let mut coo = CooMatrix::new(self.rows, self.cols);
+        for row in 0..self.rows {
+            for i in self.row_range(row) {
+                coo.row_indices.push(row);
+                coo.col_indices.push(self.col_indices[i] as usize);
+                coo.values.push(self.values[i]);
+            }
+        }
+        coo
+    }
+
+    /// Convert to compressed sparse column format.
+    pub fn to_csc(&self) -> CscMatrix {
+        // The CSC arrays of A are the CSR arrays of A^T
+        let transpose = self.transpose();
+        CscMatrix {
+            rows: self.rows,
+            cols: self.cols,
+            col_offsets: transpose.row_offsets,
+            row_indices: transpose.col_indices,
+            values: transpose.values,
+        }
+    }
+
+    /// Number of stored non-zeros.
+    pub fn nnz(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Get element at position (row, col), which is zero if it is not stored.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the row index is out of bounds.
+    pub fn get(&self, row: usize, col: usize) -> f32 {
+        let range = self.row_range(row);
+        match self.col_indices[range.clone()].binary_search(&(col as u32)) {
+            Ok(i) => self.values[range.start + i],
+            Err(_) => 0.0,
+        }
+    }
+
+    /// Computes the sparse transpose.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use metal_matrix::{CsrMatrix, Matrix};
+    ///
+    /// let dense = Matrix::with_data(2, 3, vec![1.0, 0.0, 2.0, 0.0, 0.0, 3.0]).unwrap();
+    /// let transpose = CsrMatrix::from_dense(&dense).unwrap().transpose();
+    ///
+    /// assert_eq!((transpose.rows, transpose.cols), (3, 2));
+    /// assert_eq!(transpose.to_dense().data, vec![1.0, 0.0, 0.0, 0.0, 2.0, 3.0]);
+    /// ```
+    pub fn transpose(&self) -> CsrMatrix {
+        // Counting sort of the non-zeros by column
+        let mut row_offsets = vec![0u32; self.cols + 1];
+        for &col in &self.col_indices {
+            row_offsets[col as usize + 1] += 1;
+        }
+        for col in 0..self.cols {
+            row_offsets[col + 1] += row_offsets[col];
+        }
+
+        let mut next = row_offsets.clone();
+        let mut col_indices = vec![0u32; self.nnz()];
+        let mut values = vec![0.0f32; self.nnz()];
+        for row in 0..self.rows {
+            for i in self.row_range(row) {
+                let col = self.col_indices[i] as usize;
+                let slot = next[col] as usize;
+                col_indices[slot] = row as u32;
+                values[slot] = self.values[i];
+                next[col] += 1;
+            }
+        }
+
+        CsrMatrix {
+            rows: self.cols,
+            cols: self.rows,
+            row_offsets,
+            col_indices,
+            values,
+        }
+    }
+
+    /// Range of `col_indices`/`values` holding the non-zeros of a row.
+    pub(crate) fn row_range(&self, row: usize) -> std::ops::Range<usize> {
+        self.row_offsets[row] as usize..self.row_offsets[row + 1] as usize
+    }
+}
+
+/// A sparse matrix in compressed sparse column (CSC) format.
+///
+/// The non-zeros of column `j` are `values[col_offsets[j]..col_offsets[j + 1]]`, at the
+/// rows given by the same range of `row_indices`.
+#[derive(Clone, Debug)]
+pub struct CscMatrix {
+    /// Number of rows in the matrix
+    pub rows: usize,
+
+    /// Number of columns in the matrix
+    pub cols: usize,
+
+    /// Offset of the first non-zero of each column, followed by the number of non-zeros
+    pub col_offsets: Vec<u32>,
+
+    /// Row index of each non-zero
+    pub row_indices: Vec<u32>,
+
+    /// Value of each non-zero
+    pub values: Vec<f32>,
+}
+
+impl CscMatrix {
+    /// Convert to compressed sparse row format.
+    pub fn to_csr(&self) -> CsrMatrix {
+        let transpose = CsrMatrix {
+            rows: self.cols,
+            cols: self.rows,
+            row_offsets: self.col_offsets.clone(),
+            col_indices: self.row_indices.clone(),
+            values: self.values.clone(),
+        };
+        transpose.transpose()
+    }
+
+    /// Number of stored non-zeros.
+    pub fn nnz(&self) -> usize {
+        self.values.len()
+    }
+}
+
+/// Strategy used by the sparse product kernels.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SparseKernel {
+    /// One thread per output element, best for rows with few non-zeros
+    Scalar,
+
+    /// One SIMD group per output element, best for rows with many non-zeros
+    Vector,
+}
+
+impl SparseKernel {
+    /// Choose a strategy from the average number of non-zeros per row.
+    pub fn for_matrix(a: &CsrMatrix) -> Self {
+        if a.nnz() >= VECTOR_KERNEL_THRESHOLD * a.rows.max(1) {
+            SparseKernel::Vector
+        } else {
+            SparseKernel::Scalar
+        }
+    }
+
+    /// The strategy to use for a product `width` elements wide (the rows of `spmv`,
+    /// the columns of `spmm`): the vector kernels launch `SIMD_WIDTH` (32) threads per
+    /// element, so they fall back to the scalar kernels when `width * 32` exceeds the
+    /// 32-bit thread index.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use metal_matrix::limits::MAX_32BIT_INDEX;
+    /// use metal_matrix::SparseKernel;
+    ///
+    /// assert_eq!(SparseKernel::Vector.within_32bit(1000), SparseKernel::Vector);
+    /// let too_wide = MAX_32BIT_INDEX as usize / 16;
+    /// assert_eq!(SparseKernel::Vector.within_32bit(too_wide), SparseKernel::Scalar);
+    /// ```
+    pub fn within_32bit(self, width: usize) -> Self {
+        match self {
+            SparseKernel::Vector if width as u64 * SIMD_WIDTH > MAX_32BIT_INDEX => {
+                SparseKernel::Scalar
+            }
+            kernel => kernel,
+        }
+    }
+}
+
+/// Computes the sum of two sparse matrices: C = A + B
+///
+/// Entries that cancel to exactly zero are not stored in the result.
+///
+/// # Errors
+///
+/// Returns an error if the matrices have different dimensions.
+///
+/// # Example
+///
+/// ```
+/// use metal_matrix::{sparse_add, CsrMatrix, Matrix};
+///
+/// let a = CsrMatrix::from_dense(&Matrix::with_data(1, 3, vec![1.0, 0.0, 2.0]).unwrap()).unwrap();
+/// let b = CsrMatrix::from_dense(&Matrix::with_data(1, 3, vec![0.0, 3.0, -2.0]).unwrap()).unwrap();
+///
+/// let sum = sparse_add(&a, &b).unwrap();
+/// assert_eq!(sum.col_indices, vec![0, 1]);
+/// assert_eq!(sum.values, vec![1.0, 3.0]);
+/// ```
+pub fn sparse_add(a: &CsrMatrix, b: &CsrMatrix) -> Result<CsrMatrix> {
+    if a.rows != b.rows || a.cols != b.cols {
+        anyhow::bail!("Matrix dimensions must match for addition");
+    }
+
+    let mut row_offsets = Vec::with_capacity(a.rows + 1);
+    let mut col_indices = Vec::with_capacity(a.nnz() + b.nnz());
+    let mut values = Vec::with_capacity(a.nnz() + b.nnz());
+    row_offsets.push(0);
+
+    for row in 0..a.rows {
+        let (mut i, end_a) = (a.row_offsets[row] as usize, a.row_offsets[row + 1] as usize);
+        let (mut j, end_b) = (b.row_offsets[row] as usize, b.row_offsets[row + 1] as usize);
+
+        // Merge the two sorted rows; u32::MAX marks an exhausted row
+        while i < end_a || j < end_b {
+            let col_a = if i < end_a {
+                a.col_indices[i]
+            } else {
+                u32::MAX
+            };
+            let col_b = if j < end_b {
+                b.col_indices[j]
+            } else {
+                u32::MAX
+            };
+            let col = col_a.min(col_b);
+
+            let mut value = 0.0;
+            if col_a == col {
+                value += a.values[i];
+                i += 1;
+            }
+            if col_b == col {
+                value += b.values[j];
+                j += 1;
+            }
+            if value != 0.0 {
+                col_indices.push(col);
+                values.push(value);
+            }
+        }
+        row_offsets.push(to_index(values.len())?);
+    }
+
+    Ok(CsrMatrix {
+        rows: a.rows,
+        cols: a.cols,
+        row_offsets,
+        col_indices,
+        values,
+    })
+}
+
+/// Performs sparse matrix-vector multiplication on the GPU: y = A * x
+///
+/// The kernel strategy is chosen with `SparseKernel::for_matrix`.
+///
+/// # Arguments
+///
+/// * `context` - The Metal context for GPU computation
+/// * `a` - The sparse matrix (m × k)
+/// * `x` - The dense column vector (k × 1)
+///
+/// # Returns
+///
+/// A `Result` containing the product vector (m × 1) or an error.
+///
+/// # Errors
+///
+/// Returns an error if `x` is not a k×1 vector.
+///
+/// # Example
+///
+/// ```
+/// use metal_matrix::{spmv, CsrMatrix, Matrix, MetalContext};
+///
+/// let context = MetalContext::new().unwrap();
+/// let dense = Matrix::with_data(2, 3, vec![1.0, 0.0, 2.0, 0.0, 0.0, 3.0]).unwrap();
+/// let a = CsrMatrix::from_dense(&dense).unwrap();
+///
+/// let y = spmv(&context, &a, &Matrix::vector(vec![1.0, 1.0, 1.0])).unwrap();
+/// assert_eq!(y.data, vec![3.0, 3.0]);
+/// ```
+pub fn spmv(context: &MetalContext, a: &CsrMatrix, x: &Matrix) -> Result<Matrix> {
+    spmv_with_kernel(context, a, x, SparseKernel::for_matrix(a))
+}
+
+/// Performs sparse matrix-vector multiplication on the GPU with a given kernel strategy.
+///
+/// See `spmv`. A vector `kernel` too wide for 32-bit thread indices runs as the scalar
+/// kernel instead; see `SparseKernel::within_32bit`.
+///
+/// # Errors
+///
+/// Returns an error if `x` is not a k×1 vector, or a `LimitError` if the result
+/// exceeds the 32-bit kernel limits or an operand does not fit in a device buffer.
+pub fn spmv_with_kernel(
+    context: &MetalContext,
+    a: &CsrMatrix,
+    x: &Matrix,
+    kernel: SparseKernel,
+) -> Result<Matrix> {
+    // Validate input
+    if x.rows != a.cols || x.cols != 1 {
+        anyhow::bail!("Matrix dimensions incompatible for sparse multiplication");
+    }
+
+    let rows = a.rows;
+    if a.nnz() == 0 {
+        return Ok(Matrix::new(rows, 1));
+    }
+    check_32bit(rows, 1)?;
+    check_32bit(a.cols, 1)?;
+    let kernel = kernel.within_32bit(rows);
+    let limits = context.limits();
+    for elements in [a.nnz(), x.data.len(), rows + 1] {
+        limits.check_buffer(elements)?;
+    }
+
+    // Load kernel
+    let function = match kernel {
+        SparseKernel::Scalar => kernels::functions::SPMV_SCALAR,
+        SparseKernel::Vector => kernels::functions::SPMV_VECTOR,
+    };
+    let pipeline = context.load_kernel(kernels::paths::SPARSE, function)?;
+
+    // Create buffers
+    let buffer_offsets = context.acquire_buffer_with_data(&a.row_offsets);
+    let buffer_indices = context.acquire_buffer_with_data(&a.col_indices);
+    let buffer_values = context.acquire_buffer_with_data(&a.values);
+    let buffer_x = context.acquire_buffer_with_data(&x.data);
+    let buffer_result = context.acquire_buffer::<f32>(rows);
+
+    // Execute computation
+    let result = context.execute_compute(|encoder| {
+        encoder.set_compute_pipeline_state(&pipeline);
+        encoder.set_buffer(0, Some(&buffer_offsets), 0);
+        encoder.set_buffer(1, Some(&buffer_indices), 0);
+        encoder.set_buffer(2, Some(&buffer_values), 0);
+        encoder.set_buffer(3, Some(&buffer_x), 0);
+        encoder.set_buffer(4, Some(&buffer_result), 0);
+        set_constant(encoder, 5, rows as u32);
+
+        let (grid_width, threadgroup_size) = match kernel {
+            SparseKernel::Scalar => (rows, context.threadgroup_size(function, rows, 1, &pipeline)),
+            SparseKernel::Vector => {
+                let width = rows * SIMD_WIDTH as usize;
+                let size = context.simd_threadgroup_size(function, width, 1, SIMD_WIDTH, &pipeline);
+                (width, size)
+            }
+        };
+        let grid_size = MTLSize::new(grid_width as u64, 1, 1);
+        encoder.dispatch_threads(grid_size, threadgroup_size);
+    });
+
+    // Read results
+    let result_ptr = buffer_result.contents() as *const f32;
+    let mut result_data = vec![0.0f32; rows];
+
+    unsafe {
+        std::ptr::copy_nonoverlapping(result_ptr, result_data.as_mut_ptr(), rows);
+    }
+    for buffer in [
+        buffer_offsets,
+        buffer_indices,
+        buffer_values,
+        buffer_x,
+        buffer_result,
+    ] {
+        context.recycle_buffer(buffer);
+    }
+    result?;
+
+    Matrix::with_data(rows, 1, result_data)
+}
+
+/// Performs sparse-dense matrix multiplication on the GPU: C = A * B
+///
+/// The kernel strategy is chosen with `SparseKernel::for_matrix`.
+///
+/// # Arguments
+///
+/// * `context` - The Metal context for GPU computation
+/// * `a` - The sparse matrix (m × k)
+/// * `b` - The dense matrix (k × n)
+///
+/// # Returns
+///
+/// A `Result` containing the dense product matrix (m × n) or an error.
+///
+/// # Errors
+///
+/// Returns an error if the matrices have incompatible dimensions (a.cols != b.rows).
+///
+/// # Example
+///
+/// ```
+/// use metal_matrix::{spmm, CsrMatrix, Matrix, MetalContext};
+///
+/// let context = MetalContext::new().unwrap();
+/// let a = CsrMatrix::from_dense(&Matrix::identity(3)).unwrap();
+/// let b = Matrix::with_data(3, 2, vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]).unwrap();
+///
+/// let c = spmm(&context, &a, &b).unwrap();
+/// assert_eq!(c.data, b.data);
+/// ```
+pub fn spmm(context: &MetalContext, a: &CsrMatrix, b: &Matrix) -> Result<Matrix> {
+    spmm_with_kernel(context, a, b, SparseKernel::for_matrix(a))
+}
+
+/// Performs sparse-dense matrix multiplication on the GPU with a given kernel strategy.
+///
+/// See `spmm`. A vector `kernel` too wide for 32-bit thread indices runs as the scalar
+/// kernel instead; see `SparseKernel::within_32bit`.
+///
+/// # Errors
+///
+/// Returns an error if the matrices have incompatible dimensions (a.cols != b.rows),
+/// or a `LimitError` if an operand or the result exceeds the 32-bit kernel limits or
+/// an operand does not fit in a device buffer.
+pub fn spmm_with_kernel(
+    context: &MetalContext,
+    a: &CsrMatrix,
+    b: &Matrix,
+    kernel: SparseKernel,
+) -> Result<Matrix> {
+    // Validate input
+    if a.cols != b.rows {
+        anyhow::bail!("Matrix dimensions incompatible for sparse multiplication");
+    }
+
+    let m = a.rows;
+    let n = b.cols;
+    if a.nnz() == 0 || n == 0 {
+        return Ok(Matrix::new(m, n));
+    }
+    check_32bit(m, n)?;
+    check_32bit(a.cols, n)?;
+    let kernel = kernel.within_32bit(n);
+    let limits = context.limits();
+    for elements in [a.nnz(), b.data.len(), m * n] {
+        limits.check_buffer(elements)?;
+    }
+
+    // Load kernel
+    let function = match kernel {
+        SparseKernel::Scalar => kernels::functions::SPMM_SCALAR,
+        SparseKernel::Vector => kernels::functions::SPMM_VECTOR,
+    };
+    let pipeline = context.load_kernel(kernels::paths::SPARSE, function)?;
+
+    // Create buffers
+    let buffer_offsets = context.acquire_buffer_with_data(&a.row_offsets);
+    let buffer_indices = context.acquire_buffer_with_data(&a.col_indices);
+    let buffer_values = context.acquire_buffer_with_data(&a.values);
+    let buffer_b = context.acquire_buffer_with_data(&b.data);
+    let buffer_result = context.acquire_buffer::<f32>(m * n);
+
+    // Execute computation
+    let result = context.execute_compute(|encoder| {
+        encoder.set_compute_pipeline_state(&pipeline);
+        encoder.set_buffer(0, Some(&buffer_offsets), 0);
+        encoder.set_buffer(1, Some(&buffer_indices), 0);
+        encoder.set_buffer(2, Some(&buffer_values), 0);
+        encoder.set_buffer(3, Some(&buffer_b), 0);
+        encoder.set_buffer(4, Some(&buffer_result), 0);
+        set_constant(encoder, 5, m as u32);
+        set_constant(encoder, 6, n as u32);
+
+        let (grid_size, threadgroup_size) = match kernel {
+            SparseKernel::Scalar => (
+                MTLSize::new(n as u64, m as u64, 1),
+                context.threadgroup_size(function, n, m, &pipeline),
+            ),
+            SparseKernel::Vector => {
+                // Rows of one threadgroup are whole SIMD groups, each owning one element
+                let width = n * SIMD_WIDTH as usize;
+                (
+                    MTLSize::new(width as u64, m as u64, 1),
+                    context.simd_threadgroup_size(function, width, m, SIMD_WIDTH, &pipeline),
+                )
+            }
+        };
+        encoder.dispatch_threads(grid_size, threadgroup_size);
+    });
+
+    // Read results
+    let result_ptr = buffer_result.contents() as *const f32;
+    let mut result_data = vec![0.0f32; m * n];
+
+    unsafe {
+        std::ptr::copy_nonoverlapping(result_ptr, result_data.as_mut_ptr(), m * n);
+    }
+    for buffer in [
+        buffer_offsets,
+        buffer_indices,
+        buffer_values,
+        buffer_b,
+        buffer_result,
+    ] {
+        context.recycle_buffer(buffer);
+    }
+    result?;
+
+    Matrix::with_data(m, n, result_data)
+}
+
+/// Converts a count or index to the 32-bit type used by the sparse formats.
+fn to_index(value: usize) -> Result<u32> {
+    u32::try_from(value)
+        .map_err(|_| anyhow::anyhow!("Sparse matrix is too large for 32-bit indices"))
+}
@@ -0,0 +1,280 @@
+/*!
+ * # Triangular Operations
+ *
+ * This module provides triangular solves (TRSM) and triangular matrix
+ * multiplication (TRMM), the building blocks used after a factorisation.
+ *
+ * Both operations are written against the `Backend` trait, so they run on the GPU
+ * when called with a `MetalContext` and on the CPU when called with `CpuBackend`.
+ * Straightforward substitution-based references live in `cpu`.
+ *
+ * The shape of the problem is described with BLAS-style flags:
+ * - `Uplo`: which triangle of `T` is referenced
+ * - `Transpose`: whether `op(T)` is `T` or `T^T`
+ * - `Diag`: whether the diagonal of `T` is read or assumed to be all ones
+ * - `Side`: whether `op(T)` appears on the left or the right of `X`
+ */
+
+use crate::backend::Backend;
+use crate::cpu;
+use crate::matrix::Matrix;
+use anyhow::Result;
+
+/// Number of rows solved per diagonal block in `triangular_solve`.
+const BLOCK_SIZE: usize = 64;
+
+/// Which triangle of a triangular matrix is referenced.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Uplo {
+    /// The upper triangle (on and above the diagonal)
+    Upper,
+
+    /// The lower triangle (on and below the diagonal)
+    Lower,
+}
+
+/// Whether a triangular matrix is used as is or transposed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Transpose {
+    /// op(T) = T
+    NoTrans,
+
+    /// op(T) = T^T
+    Trans,
+}
+
+/// Whether the diagonal of a triangular matrix is read or assumed to be one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Diag {
+    /// The diagonal is read from the matrix
+    NonUnit,
+
+    /// The diagonal is assumed to be all ones and is not read
+    Unit,
+}
+
+/// Which side of the unknown matrix a triangular matrix appears on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Side {
+    /// op(T) * X
+    Left,
+
+    /// X * op(T)
+    Right,
+}
+
+/// Solves a triangular system with many right-hand sides: op(T) * X = B or X * op(T) = B
+///
+/// Only the triangle of `t` selected by `uplo` is read. The solve is blocked: each
+/// diagonal block is solved by substitution on the CPU, and the remaining right-hand
+/// sides are updated with `matrix_multiply` and `matrix_subtract` on the backend, so
+/// almost all of the work runs on the GPU when `backend` is a `MetalContext`.
+///
+/// # Arguments
+///
+/// * `backend` - The backend used for the block updates (e.g. a `MetalContext`)
+/// * `t` - The triangular matrix (n × n)
+/// * `b` - The right-hand sides (n × r for `Side::Left`, r × n for `Side::Right`)
+/// * `uplo` - Which triangle of `t` is referenced
+/// * `trans` - Whether to solve with `t` or its transpose
+/// * `diag` - Whether the diagonal of `t` is assumed to be one
+/// * `side` - Whether op(T) multiplies X from the left or the right
+///
+/// # Returns
+///
+/// A `Result` containing the solution X (same dimensions as `b`) or an error.
+///
+/// # Errors
+///
+/// Returns an error if `t` is not square, the dimensions of `t` and `b` are
+/// incompatible, or `t` has a zero on its diagonal (with `Diag::NonUnit`).
+///
+/// # Example
+///
+/// ```
+/// use metal_matrix::{triangular_solve, CpuBackend, Diag, Matrix, Side, Transpose, Uplo};
+///
+/// let l = Matrix::with_data(2, 2, vec![2.0, 0.0, 1.0, 4.0]).unwrap();
+/// let b = Matrix::with_data(2, 1, vec![2.0, 9.0]).unwrap();
+///
+/// let x = triangular_solve(
+///     &CpuBackend, &l, &b, Uplo::Lower, Transpose::NoTrans, Diag::NonUnit, Side::Left,
+/// )
+/// .unwrap();
+/// assert_eq!(x.data, vec![1.0, 2.0]);
+/// ```
+pub fn triangular_solve<B: Backend>(
+    backend: &B,
+    t: &Matrix,
+    b: &Matrix,
+    uplo: Uplo,
+    trans: Transpose,
+    diag: Diag,
+    side: Side,
+) -> Result<Matrix> {
+    validate(t, b, side)?;
+    check_nonsingular(t, diag)?;
+
+    if b.data.is_empty() {
+        return Ok(b.clone());
+    }
+
+    // Reduce every case to a left-side, non-transposed solve:
+    // X * op(T) = B  <=>  op(T)^T * X^T = B^T
+    let transpose_t = (trans == Transpose::Trans) != (side == Side::Right);
+    let t = if transpose_t {
+        backend.matrix_transpose(t)?
+    } else {
+        t.clone()
+    };
+    let lower = (uplo == Uplo::Lower) != transpose_t;
+    let mut x = match side {
+        Side::Left => b.clone(),
+        Side::Right => backend.matrix_transpose(b)?,
+    };
+
+    let n = t.rows;
+    let rhs = x.cols;
+    let block_uplo = if lower { Uplo::Lower } else { Uplo::Upper };
+
+    let mut starts: Vec<usize> = (0..n).step_by(BLOCK_SIZE).collect();
+    if !lower {
+        starts.reverse();
+    }
+
+    for start in starts {
+        let len = BLOCK_SIZE.min(n - start);
+
+        // Solve the diagonal block
+        let t_block = t.submatrix(start, start, len, len);
+        let b_block = x.submatrix(start, 0, len, rhs);
+        let x_block = cpu::triangular_solve(
+            &t_block,
+            &b_block,
+            block_uplo,
+            Transpose::NoTrans,
+            diag,
+            Side::Left,
+        )?;
+        x.set_submatrix(start, 0, &x_block);
+
+        // Eliminate the solved block from the rows still to be solved
+        let (rest_start, rest_len) = if lower {
+            (start + len, n - start - len)
+        } else {
+            (0, start)
+        };
+        if rest_len == 0 {
+            continue;
+        }
+
+        let t_panel = t.submatrix(rest_start, start, rest_len, len);
+        let update = backend.matrix_multiply(&t_panel, &x_block)?;
+        let b_rest = x.submatrix(rest_start, 0, rest_len, rhs);
+        x.set_submatrix(rest_start, 0, &backend.matrix_subtract(&b_rest, &update)?);
+    }
+
+    match side {
+        Side::Left => Ok(x),
+        Side::Right => backend.matrix_transpose(&x),
+    }
+}
+
+/// Multiplies by a triangular matrix: op(T) * B or B * op(T)
+///
+/// Only the triangle of `t` selected by `uplo` is read. The referenced triangle is
+/// copied into a dense matrix (with ones on the diagonal for `Diag::Unit`) and the
+/// product is computed with a single `matrix_multiply` on the backend.
+///
+/// # Arguments
+///
+/// * `backend` - The backend used for the product (e.g. a `MetalContext`)
+/// * `t` - The triangular matrix (n × n)
+/// * `b` - The other factor (n × r for `Side::Left`, r × n for `Side::Right`)
+/// * `uplo` - Which triangle of `t` is referenced
+/// * `trans` - Whether to multiply by `t` or its transpose
+/// * `diag` - Whether the diagonal of `t` is assumed to be one
+/// * `side` - Whether op(T) multiplies B from the left or the right
+///
+/// # Returns
+///
+/// A `Result` containing the product (same dimensions as `b`) or an error.
+///
+/// # Errors
+///
+/// Returns an error if `t` is not square or the dimensions of `t` and `b` are incompatible.
+///
+/// # Example
+///
+/// ```
+/// use metal_matrix::{triangular_multiply, CpuBackend, Diag, Matrix, Side, Transpose, Uplo};
+///
+/// // Only the upper triangle is read, so the 9.0 is ignored
+/// let u = Matrix::with_data(2, 2, vec![1.0, 2.0, 9.0, 3.0]).unwrap();
+/// let b = Matrix::with_data(2, 1, vec![1.0, 1.0]).unwrap();
+///
+/// let result = triangular_multiply(
+///     &CpuBackend, &u, &b, Uplo::Upper, Transpose::NoTrans, Diag::NonUnit, Side::Left,
+/// )
+/// .unwrap();
+/// assert_eq!(result.data, vec![3.0, 3.0]);
+/// ```
+pub fn triangular_multiply<B: Backend>(
+    backend: &B,
+    t: &Matrix,
+    b: &Matrix,
+    uplo: Uplo,
+    trans: Transpose,
+    diag: Diag,
+    side: Side,
+) -> Result<Matrix> {
+    validate(t, b, side)?;
+
+    if b.data.is_empty() {
+        return Ok(b.clone());
+    }
+
+    let mut triangle = match uplo {
+        Uplo::Lower => t.lower_triangle(0),
+        Uplo::Upper => t.upper_triangle(0),
+    };
+    if diag == Diag::Unit {
+        for i in 0..triangle.rows {
+            triangle.set(i, i, 1.0);
+        }
+    }
+    if trans == Transpose::Trans {
+        triangle = backend.matrix_transpose(&triangle)?;
+    }
+
+    match side {
+        Side::Left => backend.matrix_multiply(&triangle, b),
+        Side::Right => backend.matrix_multiply(b, &triangle),
+    }
+}
+
+/// Checks that `t` is square and compatible with `b` for the given side.
+pub(crate) fn validate(t: &Matrix, b: &Matrix, side: Side) -> Result<()> {
+    if t.rows != t.cols {
+        anyhow::bail!("Triangular matrix must be square");
+    }
+
+    let compatible = match side {
+        Side::Left => t.rows == b.rows,
+        Side::Right => t.cols == b.cols,
+    };
+    if !compatible {
+        anyhow::bail!("Matrix dimensions incompatible for triangular operation");
+    }
+
+    Ok(())
+}
+
+/// Checks that a non-unit triangular matrix has no zeros on its diagonal.
+pub(crate) fn check_nonsingular(t: &Matrix, diag: Diag) -> Result<()> {
+    if diag == Diag::NonUnit && (0..t.rows).any(|i| t.get(i, i) == 0.0) {
+        anyhow::bail!("Triangular matrix is singular");
+    }
+
+    Ok(())
+}
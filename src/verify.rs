@@ -0,0 +1,588 @@
+/*!
+ * # Cross-Backend Verification
+ *
+ * This module checks that two backends compute the same results, for example the
+ * Metal kernels against the CPU implementations, or the f32 CPU implementations
+ * against `F64Backend`, which computes in double precision and rounds once.
+ *
+ * `verify` runs an `Operation` on both backends and compares the results element by
+ * element, reporting the maximum absolute error, maximum relative error and maximum
+ * distance in units in the last place (ULPs), and counting elements where one result
+ * is NaN or infinite and the other is not. Each operation has a built-in `Tolerance`:
+ * exact for transposes, one rounding for element-wise operations, and growing with
+ * the inner dimension K for products.
+ *
+ * The example binary exposes the same check as a subcommand:
+ * `cargo run --bin example -- verify matrix_multiply 256 metal f64`.
+ */
+
+use crate::backend::Backend;
+use crate::cpu;
+use crate::matrix::Matrix;
+use anyhow::{bail, Result};
+use std::fmt;
+
+/// An operation that can be verified across backends.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Operation {
+    /// `matrix_multiply(a, b)`
+    MatrixMultiply,
+    /// `matrix_add(a, b)`
+    MatrixAdd,
+    /// `matrix_subtract(a, b)`
+    MatrixSubtract,
+    /// `matrix_transpose(a)`
+    MatrixTranspose,
+    /// `matrix_scalar_multiply(scalar, a)`
+    MatrixScalarMultiply(f32),
+}
+
+impl Operation {
+    /// Names accepted by `from_name`.
+    pub const NAMES: [&'static str; 5] = [
+        "matrix_multiply",
+        "matrix_add",
+        "matrix_subtract",
+        "matrix_transpose",
+        "matrix_scalar_multiply",
+    ];
+
+    /// Parses an operation name, with or without the `matrix_` prefix. Scalar
+    /// multiplication uses a scalar of 1.5.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the name is unknown.
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name.strip_prefix("matrix_").unwrap_or(name) {
+            "multiply" => Ok(Operation::MatrixMultiply),
+            "add" => Ok(Operation::MatrixAdd),
+            "subtract" => Ok(Operation::MatrixSubtract),
+            "transpose" => Ok(Operation::MatrixTranspose),
+            "scalar_multiply" => Ok(Operation::MatrixScalarMultiply(1.5)),
+            _ => bail!(
+                "Unknown operation '{}' (expected one of: {})",
+                name,
+                Self::NAMES.join(", ")
+            ),
+        }
+    }
+
+    /// The name of the operation.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Operation::MatrixMultiply => Self::NAMES[0],
+            Operation::MatrixAdd => Self::NAMES[1],
+            Operation::MatrixSubtract => Self::NAMES[2],
+            Operation::MatrixTranspose => Self::NAMES[3],
+            Operation::MatrixScalarMultiply(_) => Self::NAMES[4],
+        }
+    }
+
+    /// Runs the operation on a backend. Unary operations ignore `b`.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error from the backend.
+    pub fn run<B: Backend>(&self, backend: &B, a: &Matrix, b: &Matrix) -> Result<Matrix> {
+        match *self {
+            Operation::MatrixMultiply => backend.matrix_multiply(a, b),
+            Operation::MatrixAdd => backend.matrix_add(a, b),
+            Operation::MatrixSubtract => backend.matrix_subtract(a, b),
+            Operation::MatrixTranspose => backend.matrix_transpose(a),
+            Operation::MatrixScalarMultiply(scalar) => backend.matrix_scalar_multiply(scalar, a),
+        }
+    }
+
+    /// The tolerance for comparing results of the operation on `a` and `b`.
+    ///
+    /// - Transposes move values without arithmetic and must match exactly.
+    /// - Element-wise operations round once, so results may differ by one rounding
+    ///   (a relative error of `f32::EPSILON`) from a reference that rounds differently.
+    /// - A product accumulates K roundings per element; its error is allowed to grow
+    ///   linearly with K, relative to the element and, for elements that cancel to
+    ///   near zero, to the largest possible term `max|a| * max|b|`.
+    pub fn tolerance(&self, a: &Matrix, b: &Matrix) -> Tolerance {
+        let epsilon = f32::EPSILON as f64;
+        match self {
+            Operation::MatrixTranspose => Tolerance::exact(),
+            Operation::MatrixAdd
+            | Operation::MatrixSubtract
+            | Operation::MatrixScalarMultiply(_) => Tolerance {
+                abs: f32::MIN_POSITIVE as f64,
+                rel: epsilon,
+            },
+            Operation::MatrixMultiply => {
+                let k = a.cols.max(1) as f64;
+                Tolerance {
+                    abs: k * epsilon * max_abs(a) * max_abs(b),
+                    rel: k * epsilon,
+                }
+            }
+        }
+    }
+}
+
+/// Largest absolute value in a matrix (0 if it is empty).
+fn max_abs(m: &Matrix) -> f64 {
+    m.data
+        .iter()
+        .fold(0.0f64, |max, &x| max.max((x as f64).abs()))
+}
+
+/// How far a result may be from the reference.
+///
+/// An element passes if its absolute error is at most `abs` or its relative error is
+/// at most `rel`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Tolerance {
+    /// Allowed absolute error
+    pub abs: f64,
+
+    /// Allowed error relative to the reference value
+    pub rel: f64,
+}
+
+impl Tolerance {
+    /// A tolerance only bit-identical (or both NaN) results meet.
+    pub fn exact() -> Self {
+        Self { abs: 0.0, rel: 0.0 }
+    }
+}
+
+/// Distance between two floats in units in the last place: the number of
+/// representable f32 values between them.
+///
+/// Zeros of either sign are 0 ULPs apart, as are two NaNs; a NaN and a number are
+/// `u64::MAX` apart.
+///
+/// # Example
+///
+/// ```
+/// use metal_matrix::verify::ulp_distance;
+///
+/// assert_eq!(ulp_distance(1.0, 1.0 + f32::EPSILON), 1);
+/// assert_eq!(ulp_distance(-0.0, 0.0), 0);
+/// assert_eq!(ulp_distance(-f32::from_bits(1), f32::from_bits(1)), 2);
+/// ```
+pub fn ulp_distance(a: f32, b: f32) -> u64 {
+    match (a.is_nan(), b.is_nan()) {
+        (true, true) => return 0,
+        (true, false) | (false, true) => return u64::MAX,
+        _ => {}
+    }
+
+    // Map the bits onto a line where adjacent floats are adjacent integers
+    let ordered = |x: f32| {
+        let bits = x.to_bits();
+        let magnitude = (bits & 0x7fff_ffff) as i64;
+        if bits >> 31 == 1 {
+            -magnitude
+        } else {
+            magnitude
+        }
+    };
+    (ordered(a) - ordered(b)).unsigned_abs()
+}
+
+/// The comparison of one element of a result with the reference.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ElementError {
+    /// Row of the element
+    pub row: usize,
+
+    /// Column of the element
+    pub col: usize,
+
+    /// Value computed by the backend under test
+    pub actual: f32,
+
+    /// Value computed by the reference backend
+    pub expected: f32,
+
+    /// `|actual - expected|`
+    pub abs_error: f64,
+
+    /// `abs_error / |expected|` (infinite if `expected` is 0 and `actual` is not)
+    pub rel_error: f64,
+
+    /// `ulp_distance(actual, expected)`
+    pub ulps: u64,
+
+    /// One value is NaN or infinite and the other is not (or they are different
+    /// non-finite values)
+    pub nonfinite_mismatch: bool,
+}
+
+impl ElementError {
+    fn new(row: usize, col: usize, actual: f32, expected: f32) -> Self {
+        let same_nonfinite =
+            (actual.is_nan() && expected.is_nan()) || (actual.is_infinite() && actual == expected);
+        let nonfinite_mismatch = (!actual.is_finite() || !expected.is_finite()) && !same_nonfinite;
+
+        let (abs_error, rel_error) = if same_nonfinite {
+            (0.0, 0.0)
+        } else {
+            let abs_error = (actual as f64 - expected as f64).abs();
+            let rel_error = if abs_error == 0.0 {
+                0.0
+            } else if expected == 0.0 {
+                f64::INFINITY
+            } else {
+                abs_error / (expected as f64).abs()
+            };
+            (abs_error, rel_error)
+        };
+
+        Self {
+            row,
+            col,
+            actual,
+            expected,
+            abs_error,
+            rel_error,
+            ulps: ulp_distance(actual, expected),
+            nonfinite_mismatch,
+        }
+    }
+
+    /// Whether the element is within `tolerance` of the reference.
+    pub fn within(&self, tolerance: &Tolerance) -> bool {
+        !self.nonfinite_mismatch
+            && (self.abs_error <= tolerance.abs || self.rel_error <= tolerance.rel)
+    }
+}
+
+/// Compares a result with the reference element by element.
+///
+/// # Errors
+///
+/// Returns an error if the matrices have different dimensions.
+pub fn compare_elements(actual: &Matrix, expected: &Matrix) -> Result<Vec<ElementError>> {
+    if (actual.rows, actual.cols) != (expected.rows, expected.cols) {
+        bail!(
+            "Result is {}x{} but the reference is {}x{}",
+            actual.rows,
+            actual.cols,
+            expected.rows,
+            expected.cols
+        );
+    }
+
+    let cols = actual.cols.max(1);
+    Ok(actual
+        .data
+        .iter()
+        .zip(&expected.data)
+        .enumerate()
+        .map(|(i, (&x, &y))| ElementError::new(i / cols, i % cols, x, y))
+        .collect())
+}
+
+/// Summary of a comparison between a result and the reference.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Report {
+    /// Name of the operation compared
+    pub operation: &'static str,
+
+    /// Rows and columns of the result
+    pub shape: (usize, usize),
+
+    /// Largest absolute error
+    pub max_abs_error: f64,
+
+    /// Largest relative error
+    pub max_rel_error: f64,
+
+    /// Largest ULP distance
+    pub max_ulps: u64,
+
+    /// Elements where one result is NaN or infinite and the other is not
+    pub nonfinite_mismatches: usize,
+
+    /// Elements outside the tolerance (including non-finite mismatches)
+    pub failures: usize,
+
+    /// The first element with the largest absolute error outside the tolerance, if any
+    pub worst: Option<ElementError>,
+
+    /// The tolerance applied
+    pub tolerance: Tolerance,
+}
+
+impl Report {
+    /// Summarizes element comparisons against a tolerance.
+    pub fn new(
+        operation: &'static str,
+        shape: (usize, usize),
+        errors: &[ElementError],
+        tolerance: Tolerance,
+    ) -> Self {
+        let mut report = Self {
+            operation,
+            shape,
+            max_abs_error: 0.0,
+            max_rel_error: 0.0,
+            max_ulps: 0,
+            nonfinite_mismatches: 0,
+            failures: 0,
+            worst: None,
+            tolerance,
+        };
+
+        for error in errors {
+            if error.nonfinite_mismatch {
+                report.nonfinite_mismatches += 1;
+            } else {
+                report.max_abs_error = report.max_abs_error.max(error.abs_error);
+                report.max_rel_error = report.max_rel_error.max(error.rel_error);
+                report.max_ulps = report.max_ulps.max(error.ulps);
+            }
+
+            if !error.within(&tolerance) {
+                report.failures += 1;
+                let worse = report.worst.is_none_or(|worst| {
+                    error.nonfinite_mismatch && !worst.nonfinite_mismatch
+                        || error.nonfinite_mismatch == worst.nonfinite_mismatch
+                            && error.abs_error > worst.abs_error
+                });
+                if worse {
+                    report.worst = Some(*error);
+                }
+            }
+        }
+
+        report
+    }
+
+    /// Whether every element is within the tolerance.
+    pub fn passed(&self) -> bool {
+        self.failures == 0
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} ({}x{}): {}",
+            self.operation,
+            self.shape.0,
+            self.shape.1,
+            if self.passed() { "passed" } else { "FAILED" }
+        )?;
+        writeln!(f, "  max absolute error: {:e}", self.max_abs_error)?;
+        writeln!(f, "  max relative error: {:e}", self.max_rel_error)?;
+        writeln!(f, "  max ULP distance:   {}", self.max_ulps)?;
+        writeln!(f, "  NaN/Inf mismatches: {}", self.nonfinite_mismatches)?;
+        write!(
+            f,
+            "  outside tolerance:  {} of {} (abs {:e}, rel {:e})",
+            self.failures,
+            self.shape.0 * self.shape.1,
+            self.tolerance.abs,
+            self.tolerance.rel
+        )?;
+        if let Some(worst) = &self.worst {
+            write!(
+                f,
+                "\n  worst element:      ({}, {}) = {} but expected {}",
+                worst.row, worst.col, worst.actual, worst.expected
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Runs an operation on two backends and compares the results with the operation's
+/// tolerance.
+///
+/// # Arguments
+///
+/// * `operation` - The operation to run
+/// * `tested` - The backend under test
+/// * `reference` - The backend whose results are taken as correct
+/// * `a` - First operand
+/// * `b` - Second operand (ignored by unary operations)
+///
+/// # Returns
+///
+/// A `Result` containing the comparison report or an error.
+///
+/// # Errors
+///
+/// Returns an error if either backend fails or the results have different dimensions.
+/// A comparison outside the tolerance is not an error; check `Report::passed`.
+///
+/// # Example
+///
+/// ```
+/// use metal_matrix::verify::{self, F64Backend, Operation};
+/// use metal_matrix::CpuBackend;
+///
+/// let a = verify::sample_matrix(64, 300, 1);
+/// let b = verify::sample_matrix(300, 32, 2);
+///
+/// let report = verify::verify(Operation::MatrixMultiply, &CpuBackend, &F64Backend, &a, &b).unwrap();
+/// assert!(report.passed(), "{}", report);
+/// ```
+pub fn verify<T: Backend, R: Backend>(
+    operation: Operation,
+    tested: &T,
+    reference: &R,
+    a: &Matrix,
+    b: &Matrix,
+) -> Result<Report> {
+    let actual = operation.run(tested, a, b)?;
+    let expected = operation.run(reference, a, b)?;
+    let errors = compare_elements(&actual, &expected)?;
+
+    Ok(Report::new(
+        operation.name(),
+        (expected.rows, expected.cols),
+        &errors,
+        operation.tolerance(a, b),
+    ))
+}
+
+/// A reproducible matrix of values in [-1, 1) for verification inputs.
+pub fn sample_matrix(rows: usize, cols: usize, seed: u64) -> Matrix {
+    // SplitMix64
+    let mut state = seed;
+    let data = (0..rows * cols)
+        .map(|_| {
+            state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+            let mut z = state;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            z ^= z >> 31;
+            ((z >> 40) as f32 / (1u64 << 24) as f32) * 2.0 - 1.0
+        })
+        .collect();
+
+    Matrix { rows, cols, data }
+}
+
+/// Backend computing in f64 and rounding each result to f32 once, as a reference for
+/// the f32 backends.
+///
+/// # Example
+///
+/// ```
+/// use metal_matrix::verify::F64Backend;
+/// use metal_matrix::{Backend, Matrix};
+///
+/// // 1 + 1e-8 - 1 is 0 in f32 arithmetic, but not in f64
+/// let a = Matrix::with_data(1, 3, vec![1.0, 1e-8, -1.0]).unwrap();
+/// let ones = Matrix::with_data(3, 1, vec![1.0; 3]).unwrap();
+/// assert_eq!(F64Backend.matrix_multiply(&a, &ones).unwrap().data, vec![1e-8]);
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+pub struct F64Backend;
+
+impl Backend for F64Backend {
+    fn matrix_multiply(&self, a: &Matrix, b: &Matrix) -> Result<Matrix> {
+        if a.cols != b.rows {
+            bail!("Matrix dimensions incompatible for multiplication");
+        }
+
+        let mut result = Matrix::new(a.rows, b.cols);
+        for row in 0..a.rows {
+            for col in 0..b.cols {
+                let sum: f64 = (0..a.cols)
+                    .map(|i| a.get(row, i) as f64 * b.get(i, col) as f64)
+                    .sum();
+                result.set(row, col, sum as f32);
+            }
+        }
+
+        Ok(result)
+    }
+
+    fn matrix_add(&self, a: &Matrix, b: &Matrix) -> Result<Matrix> {
+        if a.rows != b.rows || a.cols != b.cols {
+            bail!("Matrix dimensions must match for addition");
+        }
+
+        let data = a
+            .data
+            .iter()
+            .zip(&b.data)
+            .map(|(&x, &y)| (x as f64 + y as f64) as f32);
+        Matrix::with_data(a.rows, a.cols, data.collect())
+    }
+
+    fn matrix_subtract(&self, a: &Matrix, b: &Matrix) -> Result<Matrix> {
+        if a.rows != b.rows || a.cols != b.cols {
+            bail!("Matrix dimensions must match for subtraction");
+        }
+
+        let data = a
+            .data
+            .iter()
+            .zip(&b.data)
+            .map(|(&x, &y)| (x as f64 - y as f64) as f32);
+        Matrix::with_data(a.rows, a.cols, data.collect())
+    }
+
+    fn matrix_transpose(&self, a: &Matrix) -> Result<Matrix> {
+        cpu::matrix_transpose(a)
+    }
+
+    fn matrix_scalar_multiply(&self, scalar: f32, a: &Matrix) -> Result<Matrix> {
+        let data = a.data.iter().map(|&x| (scalar as f64 * x as f64) as f32);
+        Matrix::with_data(a.rows, a.cols, data.collect())
+    }
+
+    fn solve(&self, a: &Matrix, b: &Matrix) -> Result<Matrix> {
+        if a.rows != a.cols {
+            bail!("Coefficient matrix must be square");
+        }
+        if a.rows != b.rows {
+            bail!("Matrix dimensions incompatible for solve");
+        }
+
+        // Gaussian elimination with partial pivoting on [A | B]
+        let (n, m) = (a.rows, b.cols);
+        let mut lu: Vec<f64> = a.data.iter().map(|&x| x as f64).collect();
+        let mut x: Vec<f64> = b.data.iter().map(|&x| x as f64).collect();
+
+        for k in 0..n {
+            let pivot_row = (k..n)
+                .max_by(|&i, &j| lu[i * n + k].abs().total_cmp(&lu[j * n + k].abs()))
+                .unwrap_or(k);
+            if lu[pivot_row * n + k] == 0.0 {
+                bail!("Matrix is singular");
+            }
+            for col in 0..n {
+                lu.swap(k * n + col, pivot_row * n + col);
+            }
+            for col in 0..m {
+                x.swap(k * m + col, pivot_row * m + col);
+            }
+
+            for i in k + 1..n {
+                let factor = lu[i * n + k] / lu[k * n + k];
+                for j in k..n {
+                    lu[i * n + j] -= factor * lu[k * n + j];
+                }
+                for j in 0..m {
+                    x[i * m + j] -= factor * x[k * m + j];
+                }
+            }
+        }
+
+        for i in (0..n).rev() {
+            for j in 0..m {
+                let sum: f64 = (i + 1..n).map(|p| lu[i * n + p] * x[p * m + j]).sum();
+                x[i * m + j] = (x[i * m + j] - sum) / lu[i * n + i];
+            }
+        }
+
+        Matrix::with_data(n, m, x.into_iter().map(|v| v as f32).collect())
+    }
+
+    fn inverse(&self, a: &Matrix) -> Result<Matrix> {
+        self.solve(a, &Matrix::identity(a.rows))
+    }
+}
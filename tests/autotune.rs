@@ -0,0 +1,214 @@
+use metal_matrix::autotune::{
+    candidates, default_threadgroup_size, shape_class, ThreadgroupSize, TuningProfile,
+    MAX_SHAPE_DIMENSION,
+};
+
+fn size(width: u64, height: u64) -> ThreadgroupSize {
+    ThreadgroupSize { width, height }
+}
+
+#[test]
+fn classifies_shapes_by_power_of_two() {
+    assert_eq!(shape_class(100, 1), "128x1");
+    assert_eq!(shape_class(256, 256), "256x256");
+    assert_eq!(shape_class(257, 3), "512x4");
+    assert_eq!(shape_class(0, 0), "1x1");
+    assert_eq!(
+        shape_class(100_000, 5000),
+        format!("{0}x{0}", MAX_SHAPE_DIMENSION)
+    );
+}
+
+#[test]
+fn default_sizes_match_the_fixed_heuristic() {
+    assert_eq!(default_threadgroup_size(10_000, 1, 1024), size(256, 1));
+    assert_eq!(default_threadgroup_size(10_000, 1, 64), size(64, 1));
+    assert_eq!(default_threadgroup_size(1000, 1000, 1024), size(16, 64));
+    assert_eq!(default_threadgroup_size(4, 1000, 1024), size(4, 256));
+    assert_eq!(default_threadgroup_size(1000, 3, 1024), size(16, 3));
+    assert_eq!(default_threadgroup_size(0, 5, 1024), size(1, 5));
+}
+
+#[test]
+fn candidates_respect_the_thread_limit_and_grid() {
+    let sizes = candidates(1024, 1024, 1024);
+    assert!(sizes.contains(&size(32, 32)));
+    assert!(sizes.contains(&size(1024, 1)));
+    assert!(sizes.contains(&size(1, 1024)));
+    assert!(sizes
+        .iter()
+        .all(|s| s.threads() >= 32 && s.threads() <= 1024));
+    assert!(sizes
+        .iter()
+        .all(|s| s.width.is_power_of_two() && s.height.is_power_of_two()));
+
+    let mut sorted = sizes.clone();
+    sorted.sort();
+    assert_eq!(sizes, sorted);
+
+    // A one-dimensional grid only gets rows
+    let linear = candidates(100_000, 1, 512);
+    assert_eq!(
+        linear,
+        vec![
+            size(32, 1),
+            size(64, 1),
+            size(128, 1),
+            size(256, 1),
+            size(512, 1)
+        ]
+    );
+
+    // A narrow grid does not get threadgroups much wider than itself
+    assert!(candidates(3, 1000, 1024).iter().all(|s| s.width <= 4));
+}
+
+#[test]
+fn tiny_grids_fall_back_to_small_candidates() {
+    assert_eq!(
+        candidates(2, 2, 1024),
+        vec![default_threadgroup_size(2, 2, 1024)]
+    );
+    assert!(candidates(4, 4, 16).iter().all(|s| s.threads() <= 16));
+}
+
+#[test]
+fn profile_lookup_uses_kernel_device_and_shape_class() {
+    let mut profile = TuningProfile::new();
+    profile.insert("matrix_multiply", "Apple M2", 200, 200, size(32, 8));
+
+    // Same shape class
+    assert_eq!(
+        profile.get("matrix_multiply", "Apple M2", 256, 129),
+        Some(size(32, 8))
+    );
+    assert_eq!(profile.get("matrix_multiply", "Apple M2", 512, 256), None);
+    assert_eq!(profile.get("matrix_multiply", "Apple M1", 256, 256), None);
+    assert_eq!(profile.get("matrix_transpose", "Apple M2", 256, 256), None);
+}
+
+#[test]
+fn profile_falls_back_to_defaults() {
+    let mut profile = TuningProfile::new();
+    profile.insert("matrix_add", "Apple M2", 4096, 1, size(1024, 1));
+    profile.insert("matrix_add", "Apple M2", 64, 1, size(0, 1));
+
+    assert_eq!(
+        profile.threadgroup_size("matrix_add", "Apple M2", 4096, 1, 1024),
+        size(1024, 1)
+    );
+    // Too many threads for this pipeline
+    assert_eq!(
+        profile.threadgroup_size("matrix_add", "Apple M2", 4096, 1, 512),
+        size(256, 1)
+    );
+    // Invalid entry
+    assert_eq!(
+        profile.threadgroup_size("matrix_add", "Apple M2", 64, 1, 1024),
+        size(256, 1)
+    );
+    // Untuned shape
+    assert_eq!(
+        profile.threadgroup_size("matrix_add", "Apple M2", 100, 100, 1024),
+        size(16, 64)
+    );
+}
+
+#[test]
+fn simd_kernels_use_whole_simd_groups() {
+    let mut profile = TuningProfile::new();
+    profile.insert("spmm_vector", "Apple M2", 3200, 50, size(64, 4));
+    profile.insert("spmm_vector", "Apple M2", 320, 50, size(16, 16));
+
+    assert_eq!(
+        profile.simd_threadgroup_size("spmm_vector", "Apple M2", 3200, 50, 1024, 32),
+        size(64, 4)
+    );
+    // A tuned width that splits SIMD groups, and the 2-D default, are replaced by a
+    // row of whole groups
+    assert_eq!(
+        profile.simd_threadgroup_size("spmm_vector", "Apple M2", 320, 50, 1024, 32),
+        size(256, 1)
+    );
+    assert_eq!(
+        profile.simd_threadgroup_size("spmv_vector", "Apple M2", 3200, 50, 1024, 32),
+        size(256, 1)
+    );
+    assert_eq!(
+        profile.simd_threadgroup_size("spmv_vector", "Apple M2", 3200, 1, 96, 32),
+        size(96, 1)
+    );
+    assert_eq!(
+        profile.simd_threadgroup_size("spmv_vector", "Apple M2", 3200, 1, 80, 32),
+        size(64, 1)
+    );
+}
+
+#[test]
+fn entries_can_be_removed() {
+    let mut profile = TuningProfile::new();
+    profile.insert("matrix_add", "Apple M2", 4096, 1, size(1024, 1));
+    profile.remove("matrix_add", "Apple M2", 3000, 1);
+    assert!(profile.entries.is_empty());
+    profile.remove("matrix_add", "Apple M2", 3000, 1);
+}
+
+#[test]
+fn round_trips_through_json() {
+    let mut profile = TuningProfile::new();
+    profile.insert("matrix_multiply", "Apple M2", 1024, 1024, size(32, 8));
+    profile.insert("matrix_add", "Apple M2", 1 << 20, 1, size(512, 1));
+    profile.insert("matrix_add", "AMD Radeon Pro W6800X", 300, 1, size(64, 1));
+
+    let json = profile.to_json();
+    assert!(json.contains("\"version\": 1"));
+    assert_eq!(TuningProfile::from_json(&json).unwrap(), profile);
+
+    let empty = TuningProfile::new();
+    assert_eq!(TuningProfile::from_json(&empty.to_json()).unwrap(), empty);
+}
+
+#[test]
+fn round_trips_through_a_file() {
+    let path = std::env::temp_dir().join(format!("tuning-{}.json", std::process::id()));
+    let mut profile = TuningProfile::new();
+    profile.insert("matrix_transpose", "Apple M2", 64, 64, size(8, 8));
+
+    profile.save(&path).unwrap();
+    let loaded = TuningProfile::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(loaded, profile);
+}
+
+#[test]
+fn rejects_invalid_profiles() {
+    let error = |text: &str| TuningProfile::from_json(text).unwrap_err().to_string();
+
+    assert_eq!(error("not json"), "Invalid tuning profile");
+    assert_eq!(
+        error(r#"{"version": 2, "entries": []}"#),
+        "Invalid tuning profile: unsupported version 2"
+    );
+    assert_eq!(
+        error(r#"{"entries": []}"#),
+        "Invalid tuning profile: unsupported version null"
+    );
+    assert_eq!(
+        error(r#"{"version": 1}"#),
+        "Invalid tuning profile: missing 'entries' array"
+    );
+    assert_eq!(
+        error(
+            r#"{"version": 1, "entries": [
+                {"kernel": "matrix_add", "device": "Apple M2", "shape": "64x1", "width": 64, "height": 1},
+                {"kernel": "matrix_add", "device": "Apple M2", "shape": "128x1", "width": "64", "height": 1}
+            ]}"#
+        ),
+        "Invalid tuning profile entry 1: missing integer 'width'"
+    );
+    assert_eq!(
+        error(r#"{"version": 1, "entries": [{"kernel": "matrix_add"}]}"#),
+        "Invalid tuning profile entry 0: missing string 'device'"
+    );
+}
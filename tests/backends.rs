@@ -0,0 +1,177 @@
+mod common;
+
+use common::{assert_close, backends, dominant_matrix, test_matrix};
+use metal_matrix::{cpu, expm, matrix_power, Backend, Matrix, SelectedBackend};
+
+#[test]
+fn backends_agree_on_basic_operations() {
+    let a = test_matrix(37, 19, 0);
+    let b = test_matrix(19, 23, 1);
+    let c = test_matrix(37, 19, 2);
+
+    for backend in backends() {
+        let name = backend.name();
+        // Quarter-integer data keeps these results exact
+        assert_eq!(
+            backend.matrix_multiply(&a, &b).unwrap().data,
+            cpu::matrix_multiply_naive(&a, &b).unwrap().data,
+            "{}",
+            name
+        );
+        assert_eq!(
+            backend.matrix_add(&a, &c).unwrap().data,
+            cpu::matrix_add(&a, &c).unwrap().data,
+            "{}",
+            name
+        );
+        assert_eq!(
+            backend.matrix_subtract(&a, &c).unwrap().data,
+            cpu::matrix_subtract(&a, &c).unwrap().data,
+            "{}",
+            name
+        );
+        assert_eq!(
+            backend.matrix_transpose(&a).unwrap().data,
+            cpu::matrix_transpose(&a).unwrap().data,
+            "{}",
+            name
+        );
+        assert_eq!(
+            backend.matrix_scalar_multiply(-0.5, &a).unwrap().data,
+            cpu::matrix_scalar_multiply(-0.5, &a).unwrap().data,
+            "{}",
+            name
+        );
+    }
+}
+
+#[test]
+fn backends_propagate_nan_and_infinity() {
+    let a = Matrix::with_data(2, 2, vec![f32::NAN, f32::INFINITY, -f32::INFINITY, 1.0]).unwrap();
+
+    for backend in backends() {
+        let name = backend.name();
+        for scalar in [0.0, -0.0, 2.0] {
+            let expected = cpu::matrix_scalar_multiply(scalar, &a).unwrap();
+            let actual = backend.matrix_scalar_multiply(scalar, &a).unwrap();
+            for (x, y) in actual.data.iter().zip(&expected.data) {
+                assert!(
+                    x == y || (x.is_nan() && y.is_nan()),
+                    "{}: {} * {:?}: {} != {}",
+                    name,
+                    scalar,
+                    a.data,
+                    x,
+                    y
+                );
+            }
+        }
+
+        // Inf - Inf is NaN, and NaN propagates through sums
+        let sum = backend.matrix_add(&a, &a).unwrap();
+        assert!(sum.data[0].is_nan(), "{}", name);
+        assert_eq!(sum.data[1], f32::INFINITY, "{}", name);
+        let difference = backend.matrix_subtract(&a, &a).unwrap();
+        assert!(difference.data[..3].iter().all(|x| x.is_nan()), "{}", name);
+        assert_eq!(difference.data[3], 0.0, "{}", name);
+    }
+}
+
+#[test]
+fn backends_agree_on_solves() {
+    let a = dominant_matrix(12);
+    let b = test_matrix(12, 3, 4);
+
+    for backend in backends() {
+        let x = backend.solve(&a, &b).unwrap();
+        assert_close(
+            &cpu::matrix_multiply(&a, &x).unwrap(),
+            &b,
+            1e-5,
+            backend.name(),
+        );
+
+        let inverse = backend.inverse(&a).unwrap();
+        assert_close(
+            &cpu::matrix_multiply(&a, &inverse).unwrap(),
+            &Matrix::identity(12),
+            1e-5,
+            backend.name(),
+        );
+    }
+}
+
+#[test]
+fn backends_agree_on_matrix_functions() {
+    let a = cpu::matrix_scalar_multiply(0.05, &test_matrix(6, 6, 5)).unwrap();
+    let expected = expm(&cpu_backend(), &a).unwrap();
+
+    for backend in backends() {
+        assert_close(
+            &expm(&backend, &a).unwrap(),
+            &expected,
+            1e-5,
+            backend.name(),
+        );
+        assert_close(
+            &matrix_power(&backend, &dominant_matrix(5), -2).unwrap(),
+            &matrix_power(&cpu_backend(), &dominant_matrix(5), -2).unwrap(),
+            1e-5,
+            backend.name(),
+        );
+    }
+}
+
+#[test]
+fn backends_report_the_same_errors() {
+    let a = test_matrix(2, 3, 0);
+    let singular = Matrix::with_data(2, 2, vec![1.0, 2.0, 2.0, 4.0]).unwrap();
+
+    for backend in backends() {
+        let error = |result: anyhow::Result<Matrix>| result.unwrap_err().to_string();
+        assert_eq!(
+            error(backend.matrix_multiply(&a, &a)),
+            "Matrix dimensions incompatible for multiplication"
+        );
+        assert_eq!(
+            error(backend.matrix_add(&a, &Matrix::new(3, 2))),
+            "Matrix dimensions must match for addition"
+        );
+        assert_eq!(
+            error(backend.solve(&a, &a)),
+            "Coefficient matrix must be square"
+        );
+        assert_eq!(
+            error(backend.solve(&singular, &Matrix::identity(2))),
+            "Matrix is singular"
+        );
+    }
+}
+
+#[test]
+fn selects_backends_by_name() {
+    assert_eq!(
+        SelectedBackend::from_name("CPU", None).unwrap().name(),
+        "cpu"
+    );
+    assert_eq!(
+        SelectedBackend::from_name("metal", None)
+            .err()
+            .unwrap()
+            .to_string(),
+        "The metal backend needs a MetalContext"
+    );
+    let unknown = SelectedBackend::from_name("cuda", None)
+        .err()
+        .unwrap()
+        .to_string();
+    assert!(unknown.starts_with("Unknown backend 'cuda' (expected one of: metal, cpu"));
+    assert_eq!(
+        SelectedBackend::from_name("blas", None).is_ok(),
+        cfg!(feature = "blas")
+    );
+}
+
+fn cpu_backend() -> SelectedBackend<'static> {
+    SelectedBackend::from_name("cpu", None).unwrap()
+}
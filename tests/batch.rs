@@ -0,0 +1,208 @@
+mod common;
+
+use common::metal_context;
+use metal_matrix::batch::{Argument, CommandRecorder, EncoderEvent, Grid, RecordingEncoder};
+use metal_matrix::Matrix;
+
+fn recorded(recorder: &CommandRecorder) -> Vec<EncoderEvent> {
+    let mut encoder = RecordingEncoder::default();
+    recorder.encode(&mut encoder);
+    encoder.events
+}
+
+fn kernels_and_barriers(events: &[EncoderEvent]) -> Vec<String> {
+    events
+        .iter()
+        .filter_map(|event| match event {
+            EncoderEvent::SetKernel(name) => Some(name.to_string()),
+            EncoderEvent::Barrier(ids) => Some(format!("barrier {:?}", ids)),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn encodes_arguments_in_kernel_order() {
+    let mut recorder = CommandRecorder::new();
+    let a = recorder.upload(&Matrix::new(2, 3));
+    let b = recorder.upload(&Matrix::new(3, 4));
+    let c = recorder.matrix_multiply(&a, &b).unwrap();
+
+    assert_eq!((c.rows, c.cols), (2, 4));
+    assert_eq!(
+        recorded(&recorder),
+        vec![
+            EncoderEvent::SetKernel("matrix_multiply"),
+            EncoderEvent::SetArgument(0, Argument::Buffer(a.id())),
+            EncoderEvent::SetArgument(1, Argument::Buffer(b.id())),
+            EncoderEvent::SetArgument(2, Argument::Buffer(c.id())),
+            EncoderEvent::SetArgument(3, Argument::U32(2)),
+            EncoderEvent::SetArgument(4, Argument::U32(4)),
+            EncoderEvent::SetArgument(5, Argument::U32(3)),
+            EncoderEvent::Dispatch(Grid::Planar {
+                width: 4,
+                height: 2
+            }),
+        ]
+    );
+}
+
+#[test]
+fn scalar_multiply_passes_scalar_between_buffers() {
+    let mut recorder = CommandRecorder::new();
+    let a = recorder.upload(&Matrix::new(2, 2));
+    let b = recorder.matrix_scalar_multiply(1.5, &a).unwrap();
+
+    assert_eq!(
+        recorded(&recorder)[1..4],
+        [
+            EncoderEvent::SetArgument(0, Argument::Buffer(a.id())),
+            EncoderEvent::SetArgument(1, Argument::F32(1.5)),
+            EncoderEvent::SetArgument(2, Argument::Buffer(b.id())),
+        ]
+    );
+}
+
+#[test]
+fn independent_operations_need_no_barriers() {
+    let mut recorder = CommandRecorder::new();
+    let a = recorder.upload(&Matrix::identity(3));
+    let b = recorder.upload(&Matrix::identity(3));
+    recorder.matrix_add(&a, &b).unwrap();
+    recorder.matrix_subtract(&a, &b).unwrap();
+    recorder.matrix_transpose(&a).unwrap();
+
+    assert_eq!(
+        kernels_and_barriers(&recorded(&recorder)),
+        vec!["matrix_add", "matrix_subtract", "matrix_transpose"]
+    );
+}
+
+#[test]
+fn barriers_precede_reads_of_earlier_results() {
+    let mut recorder = CommandRecorder::new();
+    let a = recorder.upload(&Matrix::identity(3));
+    let b = recorder.upload(&Matrix::identity(3));
+    let ab = recorder.matrix_multiply(&a, &b).unwrap();
+    let ba = recorder.matrix_multiply(&b, &a).unwrap();
+    let difference = recorder.matrix_subtract(&ab, &ba).unwrap();
+    recorder.matrix_transpose(&difference).unwrap();
+
+    assert_eq!(
+        kernels_and_barriers(&recorded(&recorder)),
+        vec![
+            "matrix_multiply".to_string(),
+            "matrix_multiply".to_string(),
+            format!("barrier {:?}", [ab.id(), ba.id()]),
+            "matrix_subtract".to_string(),
+            format!("barrier {:?}", [difference.id()]),
+            "matrix_transpose".to_string(),
+        ]
+    );
+}
+
+#[test]
+fn fenced_results_are_not_fenced_again() {
+    let mut recorder = CommandRecorder::new();
+    let a = recorder.upload(&Matrix::identity(2));
+    let doubled = recorder.matrix_scalar_multiply(2.0, &a).unwrap();
+    let sum = recorder.matrix_add(&doubled, &a).unwrap();
+    // Reads `doubled` again, but its write was already fenced before the addition,
+    // while `sum` is still pending
+    recorder.matrix_subtract(&doubled, &sum).unwrap();
+
+    assert_eq!(
+        kernels_and_barriers(&recorded(&recorder)),
+        vec![
+            "matrix_scalar_multiply".to_string(),
+            format!("barrier {:?}", [doubled.id()]),
+            "matrix_add".to_string(),
+            format!("barrier {:?}", [sum.id()]),
+            "matrix_subtract".to_string(),
+        ]
+    );
+}
+
+#[test]
+fn squaring_a_result_fences_it_once() {
+    let mut recorder = CommandRecorder::new();
+    let a = recorder.upload(&Matrix::identity(2));
+    let b = recorder.matrix_scalar_multiply(3.0, &a).unwrap();
+    recorder.matrix_multiply(&b, &b).unwrap();
+
+    let events = recorded(&recorder);
+    assert!(events.contains(&EncoderEvent::Barrier(vec![b.id()])));
+}
+
+#[test]
+fn rejects_mismatched_shapes_while_recording() {
+    let mut recorder = CommandRecorder::new();
+    let a = recorder.upload(&Matrix::new(2, 3));
+    let b = recorder.upload(&Matrix::new(2, 2));
+
+    assert_eq!(
+        recorder.matrix_multiply(&a, &b).unwrap_err().to_string(),
+        "Matrix dimensions incompatible for multiplication"
+    );
+    assert_eq!(
+        recorder.matrix_add(&a, &b).unwrap_err().to_string(),
+        "Matrix dimensions must match for addition"
+    );
+    assert_eq!(
+        recorder.matrix_subtract(&a, &b).unwrap_err().to_string(),
+        "Matrix dimensions must match for subtraction"
+    );
+    assert!(recorder.is_empty());
+}
+
+#[test]
+fn rejects_handles_from_another_recorder_while_recording() {
+    let mut other = CommandRecorder::new();
+    let foreign = other.upload(&Matrix::identity(2));
+    let foreign_result = other.matrix_scalar_multiply(2.0, &foreign).unwrap();
+
+    let mut recorder = CommandRecorder::new();
+    let a = recorder.upload(&Matrix::identity(2));
+    let message = "Matrix was not recorded in this batch";
+    let error = |result: anyhow::Result<_>| result.unwrap_err().to_string();
+
+    // One handle has an id this recorder also uses, the other an id it has not reached
+    for handle in [foreign, foreign_result] {
+        assert_eq!(error(recorder.matrix_multiply(&a, &handle)), message);
+        assert_eq!(error(recorder.matrix_multiply(&handle, &a)), message);
+        assert_eq!(error(recorder.matrix_add(&handle, &a)), message);
+        assert_eq!(error(recorder.matrix_subtract(&a, &handle)), message);
+        assert_eq!(error(recorder.matrix_transpose(&handle)), message);
+        assert_eq!(
+            error(recorder.matrix_scalar_multiply(2.0, &handle)),
+            message
+        );
+    }
+    assert!(recorder.is_empty());
+
+    let mut encoder = RecordingEncoder::default();
+    recorder.encode(&mut encoder);
+    assert!(encoder.events.is_empty());
+}
+
+#[test]
+fn rejects_handles_from_another_batch() {
+    let Some(context) = metal_context() else {
+        return;
+    };
+    let matrix = Matrix::with_data(2, 2, vec![1.0, 2.0, 3.0, 4.0]).unwrap();
+    let first = context
+        .batch(|recorder| Ok(recorder.upload(&matrix)))
+        .unwrap();
+    let second = context
+        .batch(|recorder| Ok(recorder.upload(&Matrix::identity(2))))
+        .unwrap();
+
+    // Same buffer index and shape, but recorded on a different recorder
+    assert_eq!(first.output.id(), second.output.id());
+    assert_eq!(
+        second.read(&first.output).unwrap_err().to_string(),
+        "Matrix was not recorded in this batch"
+    );
+    assert_eq!(first.read(&first.output).unwrap().data, matrix.data);
+}
@@ -0,0 +1,132 @@
+use metal_matrix::buffer_pool::{BufferPool, PoolBuffer, PoolStats, MIN_BUCKET_BYTES};
+
+#[derive(Debug, PartialEq)]
+struct FakeBuffer {
+    id: usize,
+    size: u64,
+}
+
+impl PoolBuffer for FakeBuffer {
+    fn size(&self) -> u64 {
+        self.size
+    }
+}
+
+/// Allocates fake buffers with increasing ids, counting allocations.
+#[derive(Default)]
+struct FakeAllocator {
+    allocated: Vec<u64>,
+}
+
+impl FakeAllocator {
+    fn acquire(&mut self, pool: &mut BufferPool<FakeBuffer>, bytes: u64) -> FakeBuffer {
+        pool.acquire(bytes, |size| {
+            self.allocated.push(size);
+            FakeBuffer {
+                id: self.allocated.len() - 1,
+                size,
+            }
+        })
+    }
+}
+
+#[test]
+fn rounds_requests_up_to_power_of_two_buckets() {
+    assert_eq!(BufferPool::<FakeBuffer>::bucket_size(0), MIN_BUCKET_BYTES);
+    assert_eq!(BufferPool::<FakeBuffer>::bucket_size(1), MIN_BUCKET_BYTES);
+    assert_eq!(BufferPool::<FakeBuffer>::bucket_size(256), 256);
+    assert_eq!(BufferPool::<FakeBuffer>::bucket_size(257), 512);
+    assert_eq!(BufferPool::<FakeBuffer>::bucket_size(4 << 20), 4 << 20);
+}
+
+#[test]
+fn reuses_released_buffers_of_the_same_bucket() {
+    let mut pool = BufferPool::new(1 << 20);
+    let mut allocator = FakeAllocator::default();
+
+    let first = allocator.acquire(&mut pool, 3000);
+    pool.release(first);
+
+    let second = allocator.acquire(&mut pool, 2100);
+    assert_eq!(second, FakeBuffer { id: 0, size: 4096 });
+
+    // A different bucket misses even though an idle buffer exists
+    pool.release(second);
+    let third = allocator.acquire(&mut pool, 100);
+    assert_eq!(third.id, 1);
+
+    assert_eq!(allocator.allocated, vec![4096, 256]);
+    assert_eq!(
+        pool.stats(),
+        PoolStats {
+            hits: 1,
+            misses: 2,
+            bytes_resident: 4096,
+            buffers_resident: 1,
+        }
+    );
+}
+
+#[test]
+fn frees_released_buffers_beyond_the_cap() {
+    let mut pool = BufferPool::new(1024);
+    let mut allocator = FakeAllocator::default();
+
+    let a = allocator.acquire(&mut pool, 512);
+    let b = allocator.acquire(&mut pool, 512);
+    let c = allocator.acquire(&mut pool, 512);
+    pool.release(a);
+    pool.release(b);
+    pool.release(c);
+
+    assert_eq!(pool.stats().bytes_resident, 1024);
+    assert_eq!(pool.stats().buffers_resident, 2);
+}
+
+#[test]
+fn does_not_keep_buffers_it_did_not_size() {
+    let mut pool = BufferPool::new(1 << 20);
+    pool.release(FakeBuffer { id: 0, size: 1000 });
+
+    assert_eq!(pool.stats().buffers_resident, 0);
+}
+
+#[test]
+fn trim_frees_idle_buffers_but_keeps_counters() {
+    let mut pool = BufferPool::new(1 << 20);
+    let mut allocator = FakeAllocator::default();
+
+    let a = allocator.acquire(&mut pool, 1000);
+    pool.release(a);
+    pool.trim();
+
+    let stats = pool.stats();
+    assert_eq!((stats.bytes_resident, stats.buffers_resident), (0, 0));
+    assert_eq!((stats.hits, stats.misses), (0, 1));
+
+    allocator.acquire(&mut pool, 1000);
+    assert_eq!(allocator.allocated.len(), 2);
+}
+
+#[test]
+fn lowering_the_cap_evicts_largest_buffers_first() {
+    let mut pool = BufferPool::new(1 << 20);
+    let mut allocator = FakeAllocator::default();
+
+    let buffers: Vec<FakeBuffer> = [256, 1024, 4096, 256]
+        .iter()
+        .map(|&bytes| allocator.acquire(&mut pool, bytes))
+        .collect();
+    for buffer in buffers {
+        pool.release(buffer);
+    }
+    assert_eq!(pool.stats().bytes_resident, 5632);
+
+    pool.set_max_bytes(1600);
+    assert_eq!(pool.max_bytes(), 1600);
+    assert_eq!(pool.stats().bytes_resident, 1536);
+
+    pool.set_max_bytes(300);
+    assert_eq!(pool.stats().bytes_resident, 256);
+    assert_eq!(pool.stats().buffers_resident, 1);
+}
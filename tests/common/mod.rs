@@ -0,0 +1,63 @@
+//! Fixtures shared by the integration tests.
+
+#![allow(dead_code)]
+
+use metal_matrix::{Matrix, MetalContext, SelectedBackend};
+use std::sync::OnceLock;
+
+/// A deterministic matrix of quarter-integer entries in [-2.5, 3].
+///
+/// Products and partial sums of these entries stay exact in f32 for the sizes used in
+/// the tests, so results that only differ in summation order agree bit for bit.
+pub fn test_matrix(rows: usize, cols: usize, seed: usize) -> Matrix {
+    let data = (0..rows * cols)
+        .map(|i| (((i + seed) * 7919) % 23) as f32 / 4.0 - 2.5)
+        .collect();
+    Matrix::with_data(rows, cols, data).unwrap()
+}
+
+/// A well-conditioned matrix: diagonally dominant.
+pub fn dominant_matrix(n: usize) -> Matrix {
+    let mut a = test_matrix(n, n, 3);
+    for i in 0..n {
+        a.set(i, i, a.get(i, i) + 4.0 * n as f32);
+    }
+    a
+}
+
+/// A Metal context shared by the tests, or `None` when there is no Metal device.
+pub fn metal_context() -> Option<&'static MetalContext> {
+    static CONTEXT: OnceLock<Option<MetalContext>> = OnceLock::new();
+    CONTEXT.get_or_init(|| MetalContext::new().ok()).as_ref()
+}
+
+/// Every backend available in this build, including Metal when there is a device.
+pub fn backends() -> Vec<SelectedBackend<'static>> {
+    let context = metal_context();
+    SelectedBackend::NAMES
+        .iter()
+        .filter(|&&name| name != "metal" || context.is_some())
+        .map(|name| SelectedBackend::from_name(name, context).unwrap())
+        .collect()
+}
+
+/// Asserts that two matrices have the same shape and that every element is within
+/// `tolerance * (1 + |expected|)`.
+pub fn assert_close(actual: &Matrix, expected: &Matrix, tolerance: f32, what: &str) {
+    assert_eq!(
+        (actual.rows, actual.cols),
+        (expected.rows, expected.cols),
+        "{}",
+        what
+    );
+    for (i, (x, y)) in actual.data.iter().zip(&expected.data).enumerate() {
+        assert!(
+            (x - y).abs() <= tolerance * (1.0 + y.abs()),
+            "{}: element {}: {} != {}",
+            what,
+            i,
+            x,
+            y
+        );
+    }
+}
@@ -0,0 +1,72 @@
+mod common;
+
+use common::test_matrix;
+use metal_matrix::{cpu, Matrix};
+
+#[test]
+fn blocked_product_matches_naive_product() {
+    // Shapes around the micro-kernel tile, the row block, the panel depth and width, and
+    // one with a single row block but several column tiles run in parallel
+    for &(m, k, n) in &[
+        (1, 1, 1),
+        (7, 9, 13),
+        (8, 8, 8),
+        (129, 300, 17),
+        (300, 513, 70),
+        (3, 5, 4100),
+        (64, 300, 1000),
+    ] {
+        let a = test_matrix(m, k, 0);
+        let b = test_matrix(k, n, 1);
+
+        let blocked = cpu::matrix_multiply(&a, &b).unwrap();
+        let naive = cpu::matrix_multiply_naive(&a, &b).unwrap();
+        assert_eq!((blocked.rows, blocked.cols), (m, n));
+        assert_eq!(blocked.data, naive.data, "{}x{} * {}x{}", m, k, k, n);
+    }
+}
+
+#[test]
+fn rows_do_not_depend_on_the_rest_of_the_matrix() {
+    let data = (0..400 * 300).map(|i| (i as f32 * 0.37).sin()).collect();
+    let a = Matrix::with_data(400, 300, data).unwrap();
+    let b = Matrix::with_data(300, 50, a.data[..300 * 50].to_vec()).unwrap();
+    let full = cpu::matrix_multiply(&a, &b).unwrap();
+
+    let rows = 137..211;
+    let block = Matrix::with_data(
+        rows.len(),
+        300,
+        a.data[rows.start * 300..rows.end * 300].to_vec(),
+    )
+    .unwrap();
+    let partial = cpu::matrix_multiply(&block, &b).unwrap();
+
+    assert_eq!(partial.data, full.data[rows.start * 50..rows.end * 50]);
+}
+
+#[test]
+fn empty_inner_dimension_gives_zeros() {
+    let a = Matrix::new(3, 0);
+    let b = Matrix::new(0, 4);
+
+    let result = cpu::matrix_multiply(&a, &b).unwrap();
+    assert_eq!((result.rows, result.cols), (3, 4));
+    assert!(result.data.iter().all(|&x| x == 0.0));
+}
+
+#[test]
+fn rejects_incompatible_dimensions() {
+    let a = test_matrix(2, 3, 0);
+    let b = test_matrix(2, 3, 1);
+
+    for result in [
+        cpu::matrix_multiply(&a, &b),
+        cpu::matrix_multiply_naive(&a, &b),
+    ] {
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Matrix dimensions incompatible for multiplication"
+        );
+    }
+}
@@ -0,0 +1,142 @@
+use metal_matrix::io::csv::{CsvOptions, MissingValues};
+use metal_matrix::Matrix;
+
+fn read(text: &str, options: &CsvOptions) -> anyhow::Result<Matrix> {
+    Matrix::from_csv(text.as_bytes(), options)
+}
+
+#[test]
+fn reads_spreadsheet_export() {
+    let text = "\"a\",\"b\",\"c\"\r\n\"1\", 2.5 ,-3e2\r\n\r\n4,5,6\r\n";
+    let options = CsvOptions {
+        has_header: true,
+        ..CsvOptions::default()
+    };
+
+    let matrix = read(text, &options).unwrap();
+    assert_eq!((matrix.rows, matrix.cols), (2, 3));
+    assert_eq!(matrix.data, vec![1.0, 2.5, -300.0, 4.0, 5.0, 6.0]);
+}
+
+#[test]
+fn reads_tab_separated_text() {
+    let options = CsvOptions {
+        delimiter: '\t',
+        ..CsvOptions::default()
+    };
+
+    let matrix = read("1\t2\n3\t4\n", &options).unwrap();
+    assert_eq!(matrix.data, vec![1.0, 2.0, 3.0, 4.0]);
+}
+
+#[test]
+fn missing_value_policies() {
+    let text = "1,,NaN\nNA,2,3\n";
+
+    let error = read(text, &CsvOptions::default()).unwrap_err();
+    assert_eq!(error.to_string(), "CSV line 1, column 2: empty cell");
+
+    let nan = CsvOptions {
+        missing: MissingValues::Nan,
+        ..CsvOptions::default()
+    };
+    let matrix = read(text, &nan).unwrap();
+    assert_eq!(matrix.data.iter().filter(|v| v.is_nan()).count(), 3);
+
+    let fill = CsvOptions {
+        missing: MissingValues::Fill(-1.0),
+        ..CsvOptions::default()
+    };
+    let matrix = read(text, &fill).unwrap();
+    assert_eq!(matrix.data, vec![1.0, -1.0, -1.0, -1.0, 2.0, 3.0]);
+}
+
+#[test]
+fn errors_report_line_and_column() {
+    let error = read("1,2,3\n4,five,6\n", &CsvOptions::default()).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "CSV line 2, column 2: invalid number 'five'"
+    );
+
+    let error = read("1,2,3\n\n4,5\n", &CsvOptions::default()).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "CSV line 3, column 3: expected 3 columns, found 2"
+    );
+}
+
+#[test]
+fn values_round_trip_exactly() {
+    let matrix = Matrix::with_data(2, 2, vec![0.1, -1.0e-30, f32::MAX, 1.0 / 3.0]).unwrap();
+    let options = CsvOptions {
+        delimiter: ';',
+        has_header: true,
+        ..CsvOptions::default()
+    };
+
+    let mut text = Vec::new();
+    matrix.to_csv(&mut text, &options).unwrap();
+    let copy = Matrix::from_csv(text.as_slice(), &options).unwrap();
+
+    assert_eq!((copy.rows, copy.cols), (2, 2));
+    assert_eq!(copy.data, matrix.data);
+}
+
+#[test]
+fn empty_input_reads_as_empty_matrix() {
+    let matrix = read("", &CsvOptions::default()).unwrap();
+    assert_eq!((matrix.rows, matrix.cols), (0, 0));
+}
+
+#[test]
+fn quoted_cells_may_contain_the_delimiter() {
+    let text = "\"1,234.5\",\"-2,000\",3\n\"4\",\"5.5\",\"1,000,000\"\n";
+
+    let matrix = read(text, &CsvOptions::default()).unwrap();
+    assert_eq!((matrix.rows, matrix.cols), (2, 3));
+    assert_eq!(matrix.data, vec![1234.5, -2000.0, 3.0, 4.0, 5.5, 1.0e6]);
+
+    // A quoted comma that is not digit grouping is still an invalid number
+    let error = read("1,\"2,5\"\n", &CsvOptions::default()).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "CSV line 1, column 2: invalid number '2,5'"
+    );
+}
+
+#[test]
+fn skips_a_byte_order_mark() {
+    let matrix = read("\u{feff}1,2\n3,4\n", &CsvOptions::default()).unwrap();
+    assert_eq!(matrix.data, vec![1.0, 2.0, 3.0, 4.0]);
+
+    let options = CsvOptions {
+        has_header: true,
+        ..CsvOptions::default()
+    };
+    let matrix = read("\u{feff}\"a\",\"b\"\n1,2\n", &options).unwrap();
+    assert_eq!(matrix.data, vec![1.0, 2.0]);
+}
+
+#[test]
+fn nan_reads_back_as_a_missing_value() {
+    let matrix = Matrix::with_data(1, 3, vec![f32::NAN, f32::INFINITY, f32::NEG_INFINITY]).unwrap();
+    let mut text = Vec::new();
+    matrix.to_csv(&mut text, &CsvOptions::default()).unwrap();
+    assert_eq!(String::from_utf8(text.clone()).unwrap(), "NaN,inf,-inf\n");
+
+    // The default options reject NaN as missing; infinities are values
+    let error = Matrix::from_csv(text.as_slice(), &CsvOptions::default()).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "CSV line 1, column 1: missing value 'NaN'"
+    );
+
+    let options = CsvOptions {
+        missing: MissingValues::Nan,
+        ..CsvOptions::default()
+    };
+    let copy = Matrix::from_csv(text.as_slice(), &options).unwrap();
+    assert!(copy.data[0].is_nan());
+    assert_eq!(copy.data[1..], [f32::INFINITY, f32::NEG_INFINITY]);
+}
@@ -0,0 +1,85 @@
+use metal_matrix::device::{DeviceInfo, DeviceSelector};
+
+fn device(name: &str, registry_id: u64, low_power: bool, memory_gb: u64) -> DeviceInfo {
+    DeviceInfo {
+        name: name.to_string(),
+        registry_id,
+        low_power,
+        headless: false,
+        removable: false,
+        recommended_working_set_size: memory_gb << 30,
+        unified_memory: false,
+    }
+}
+
+/// A Mac Pro with an integrated GPU, two discrete GPUs and an eGPU.
+fn mac_pro() -> Vec<DeviceInfo> {
+    vec![
+        device("Intel UHD Graphics 630", 0x1000, true, 1),
+        device("AMD Radeon Pro W6800X", 0x2000, false, 32),
+        device("AMD Radeon Pro W6800X Duo", 0x3000, false, 32),
+        DeviceInfo {
+            removable: true,
+            headless: true,
+            ..device("AMD Radeon RX 6900 XT", 0x4000, false, 16)
+        },
+    ]
+}
+
+#[test]
+fn selects_by_exact_name_before_substring() {
+    let devices = mac_pro();
+
+    let exact = DeviceSelector::Name("amd radeon pro w6800x duo".to_string());
+    assert_eq!(exact.select(&devices), Some(2));
+
+    // "W6800X" is a substring of both; the exact match wins
+    let ambiguous = DeviceSelector::Name("AMD Radeon Pro W6800X".to_string());
+    assert_eq!(ambiguous.select(&devices), Some(1));
+
+    let partial = DeviceSelector::Name("6900".to_string());
+    assert_eq!(partial.select(&devices), Some(3));
+
+    let missing = DeviceSelector::Name("Apple M2".to_string());
+    assert_eq!(missing.select(&devices), None);
+}
+
+#[test]
+fn selects_by_registry_id() {
+    let devices = mac_pro();
+
+    assert_eq!(DeviceSelector::RegistryId(0x4000).select(&devices), Some(3));
+    assert_eq!(DeviceSelector::RegistryId(0x5000).select(&devices), None);
+}
+
+#[test]
+fn highest_memory_prefers_the_earlier_device_on_ties() {
+    assert_eq!(DeviceSelector::HighestMemory.select(&mac_pro()), Some(1));
+}
+
+#[test]
+fn power_preferences_fall_back_to_the_first_device() {
+    let devices = mac_pro();
+    assert_eq!(DeviceSelector::LowPower.select(&devices), Some(0));
+    assert_eq!(DeviceSelector::HighPerformance.select(&devices), Some(1));
+
+    let discrete_only = &devices[1..];
+    assert_eq!(DeviceSelector::LowPower.select(discrete_only), Some(0));
+
+    let integrated_only = &devices[..1];
+    assert_eq!(
+        DeviceSelector::HighPerformance.select(integrated_only),
+        Some(0)
+    );
+}
+
+#[test]
+fn nothing_is_selected_without_devices() {
+    for selector in [
+        DeviceSelector::HighestMemory,
+        DeviceSelector::LowPower,
+        DeviceSelector::HighPerformance,
+    ] {
+        assert_eq!(selector.select(&[]), None);
+    }
+}
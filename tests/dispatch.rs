@@ -0,0 +1,237 @@
+use metal_matrix::dispatch::{CostModel, ExecutionPolicy, OperationCost, Target};
+use serde_json::{json, Map, Value};
+
+fn model() -> CostModel {
+    CostModel {
+        gpu_overhead_us: 100.0,
+        gpu_flops_per_us: 10_000.0,
+        cpu_flops_per_us: 1_000.0,
+        transfer_bytes_per_us: 1_000.0,
+        min_gpu_elements: 1024,
+    }
+}
+
+#[test]
+fn counts_matrix_multiply_work() {
+    let cost = OperationCost::matrix_multiply(2, 3, 4);
+
+    assert_eq!(cost.elements, 6);
+    assert_eq!(cost.flops, 48);
+    assert_eq!(cost.transfer_bytes, 4 * (8 + 12 + 6));
+}
+
+#[test]
+fn counts_elementwise_work() {
+    let cost = OperationCost::elementwise(100, 2);
+
+    assert_eq!(cost.elements, 100);
+    assert_eq!(cost.flops, 100);
+    assert_eq!(cost.transfer_bytes, 1200);
+}
+
+#[test]
+fn estimates_times_from_the_model() {
+    let cost = OperationCost {
+        elements: 0,
+        flops: 20_000,
+        transfer_bytes: 5_000,
+    };
+
+    assert_eq!(model().cpu_time_us(&cost), 20.0);
+    assert_eq!(model().gpu_time_us(&cost), 100.0 + 5.0 + 2.0);
+}
+
+#[test]
+fn large_products_go_to_the_gpu_and_small_ones_stay_on_the_cpu() {
+    let model = model();
+
+    assert_eq!(
+        model.route(&OperationCost::matrix_multiply(8, 8, 8)),
+        Target::Cpu
+    );
+    assert_eq!(
+        model.route(&OperationCost::matrix_multiply(256, 256, 256)),
+        Target::Gpu
+    );
+}
+
+#[test]
+fn memory_bound_operations_stay_on_the_cpu_when_transfers_dominate() {
+    // 1 flop per element cannot pay for 12 bytes of transfer per element
+    let cost = OperationCost::elementwise(1 << 20, 2);
+    assert_eq!(model().route(&cost), Target::Cpu);
+
+    // ...unless transfers are free, as with a faster copy rate
+    let fast_copies = CostModel {
+        transfer_bytes_per_us: f64::INFINITY,
+        cpu_flops_per_us: 10.0,
+        ..model()
+    };
+    assert_eq!(fast_copies.route(&cost), Target::Gpu);
+}
+
+#[test]
+fn small_results_never_use_the_gpu() {
+    // Enough flops to favour the GPU, but too few output elements
+    let cost = OperationCost::matrix_multiply(16, 16, 100_000);
+    let model = model();
+    assert!(model.gpu_time_us(&cost) < model.cpu_time_us(&cost));
+    assert_eq!(model.route(&cost), Target::Cpu);
+
+    let no_threshold = CostModel {
+        min_gpu_elements: 0,
+        ..model
+    };
+    assert_eq!(no_threshold.route(&cost), Target::Gpu);
+}
+
+#[test]
+fn ties_go_to_the_cpu() {
+    let model = CostModel {
+        gpu_overhead_us: 0.0,
+        gpu_flops_per_us: 1_000.0,
+        transfer_bytes_per_us: f64::INFINITY,
+        min_gpu_elements: 0,
+        ..model()
+    };
+
+    assert_eq!(
+        model.route(&OperationCost::elementwise(4096, 1)),
+        Target::Cpu
+    );
+}
+
+#[test]
+fn fixed_policies_ignore_the_cost() {
+    let tiny = OperationCost::elementwise(1, 1);
+    let huge = OperationCost::matrix_multiply(4096, 4096, 4096);
+
+    for cost in [tiny, huge] {
+        assert_eq!(ExecutionPolicy::Cpu.route(&cost), Target::Cpu);
+        assert_eq!(ExecutionPolicy::Gpu.route(&cost), Target::Gpu);
+    }
+    assert_eq!(ExecutionPolicy::default().route(&tiny), Target::Cpu);
+    assert_eq!(ExecutionPolicy::default().route(&huge), Target::Gpu);
+}
+
+#[test]
+fn round_trips_through_a_file() {
+    let path = std::env::temp_dir().join(format!("cost-model-{}.json", std::process::id()));
+    let model = CostModel {
+        gpu_overhead_us: 123.25,
+        ..model()
+    };
+
+    model.save(&path).unwrap();
+    let loaded = CostModel::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(loaded, model);
+}
+
+#[test]
+fn parses_fields_in_any_order() {
+    let text = r#"{
+        "min_gpu_elements": 10,
+        "cpu_flops_per_us": 2,
+        "gpu_flops_per_us": 3.0,
+        "version": 1,
+        "transfer_bytes_per_us": 4,
+        "gpu_overhead_us": 5.5
+    }"#;
+
+    assert_eq!(
+        CostModel::from_json(text).unwrap(),
+        CostModel {
+            gpu_overhead_us: 5.5,
+            gpu_flops_per_us: 3.0,
+            cpu_flops_per_us: 2.0,
+            transfer_bytes_per_us: 4.0,
+            min_gpu_elements: 10,
+        }
+    );
+}
+
+#[test]
+fn rejects_invalid_files() {
+    let valid: Value = serde_json::from_str(&model().to_json()).unwrap();
+    let error = |edit: &dyn Fn(&mut Map<String, Value>)| {
+        let mut value = valid.clone();
+        edit(value.as_object_mut().unwrap());
+        CostModel::from_json(&value.to_string())
+            .unwrap_err()
+            .to_string()
+    };
+
+    assert_eq!(
+        error(&|fields| {
+            fields.remove("min_gpu_elements");
+        }),
+        "Invalid cost model: missing 'min_gpu_elements'"
+    );
+    assert_eq!(
+        error(&|fields| {
+            fields.insert("speed".into(), json!(1));
+        }),
+        "Invalid cost model: unknown key 'speed'"
+    );
+    assert_eq!(
+        error(&|fields| {
+            fields.insert("version".into(), json!(2));
+        }),
+        "Invalid cost model: unsupported version 2"
+    );
+    assert_eq!(
+        error(&|fields| {
+            fields.insert("gpu_overhead_us".into(), json!("fast"));
+        }),
+        "Invalid cost model: 'gpu_overhead_us' must be a finite non-negative number"
+    );
+    assert_eq!(
+        error(&|fields| {
+            fields.insert("gpu_overhead_us".into(), json!(-1));
+        }),
+        "Invalid cost model: 'gpu_overhead_us' must be a finite non-negative number"
+    );
+
+    // A zero throughput would make every estimate infinite; a zero overhead is fine
+    for rate in [
+        "gpu_flops_per_us",
+        "cpu_flops_per_us",
+        "transfer_bytes_per_us",
+    ] {
+        assert_eq!(
+            error(&|fields| {
+                fields.insert(rate.into(), json!(0.0));
+            }),
+            format!(
+                "Invalid cost model: '{}' must be a finite positive number",
+                rate
+            )
+        );
+    }
+    let mut zero_overhead = valid.clone();
+    zero_overhead["gpu_overhead_us"] = json!(0.0);
+    assert_eq!(
+        CostModel::from_json(&zero_overhead.to_string())
+            .unwrap()
+            .gpu_overhead_us,
+        0.0
+    );
+
+    // A fractional threshold is rejected rather than truncated
+    for threshold in [json!(1024.5), json!(-1), json!("1024")] {
+        assert_eq!(
+            error(&|fields| {
+                fields.insert("min_gpu_elements".into(), threshold.clone());
+            }),
+            "Invalid cost model: 'min_gpu_elements' must be a non-negative integer"
+        );
+    }
+
+    assert!(CostModel::from_json("gpu_overhead_us = 100").is_err());
+    assert_eq!(
+        CostModel::from_json("[]").unwrap_err().to_string(),
+        "Invalid cost model: expected a JSON object"
+    );
+}
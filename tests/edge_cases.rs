@@ -0,0 +1,240 @@
+//! Empty and degenerate shapes on the CPU path.
+//!
+//! A 0×N or N×0 matrix has no elements, and a product with an inner dimension of
+//! zero is an empty sum, so its (possibly non-empty) result is all zeros.
+
+mod common;
+
+use common::backends;
+use metal_matrix::triangular::{Diag, Side, Transpose, Uplo};
+use metal_matrix::verify::{self, F64Backend, Operation};
+use metal_matrix::{cpu, expm, logm, matrix_power, sqrtm, Backend, CpuBackend, CsrMatrix, Matrix};
+
+fn ones(rows: usize, cols: usize) -> Matrix {
+    Matrix::with_data(rows, cols, vec![1.0; rows * cols]).unwrap()
+}
+
+fn assert_zeros(m: &Matrix, rows: usize, cols: usize, what: &str) {
+    assert_eq!((m.rows, m.cols), (rows, cols), "{}", what);
+    assert_eq!(m.data, vec![0.0; rows * cols], "{}", what);
+}
+
+#[test]
+fn empty_matrices_have_no_elements() {
+    for (rows, cols) in [(0, 0), (0, 3), (3, 0)] {
+        let m = Matrix::new(rows, cols);
+        assert!(m.data.is_empty());
+        assert!(Matrix::with_data(rows, cols, vec![]).is_ok());
+        assert!(Matrix::with_data(rows, cols, vec![1.0]).is_err());
+    }
+
+    let identity = Matrix::identity(0);
+    assert_eq!((identity.rows, identity.cols), (0, 0));
+
+    let v = Matrix::vector(vec![]);
+    assert_eq!((v.rows, v.cols), (0, 1));
+    assert_eq!(v.vector_size(), 0);
+    assert_eq!(
+        v.vector_get(0).unwrap_err().to_string(),
+        "Vector index 0 out of bounds for length 0"
+    );
+
+    // Rows of an N×0 matrix and columns of a 0×N matrix are themselves empty
+    let row = Matrix::new(3, 0).row(2);
+    assert_eq!((row.rows, row.cols), (1, 0));
+    let column = Matrix::new(0, 3).column(2);
+    assert_eq!((column.rows, column.cols), (0, 1));
+}
+
+#[test]
+#[should_panic(expected = "Row index 3 out of bounds")]
+fn rows_of_an_empty_matrix_are_bounds_checked() {
+    Matrix::new(3, 0).row(3);
+}
+
+#[test]
+fn products_with_empty_operands() {
+    for backend in backends() {
+        let name = backend.name();
+
+        // 0×K * K×N and M×K * K×0 have no elements
+        let product = backend
+            .matrix_multiply(&Matrix::new(0, 4), &ones(4, 3))
+            .unwrap();
+        assert_zeros(&product, 0, 3, name);
+        let product = backend
+            .matrix_multiply(&ones(2, 4), &Matrix::new(4, 0))
+            .unwrap();
+        assert_zeros(&product, 2, 0, name);
+
+        // M×0 * 0×N is an M×N matrix of empty sums
+        let product = backend
+            .matrix_multiply(&Matrix::new(5, 0), &Matrix::new(0, 3))
+            .unwrap();
+        assert_zeros(&product, 5, 3, name);
+
+        let product = backend
+            .matrix_multiply(&Matrix::new(0, 0), &Matrix::new(0, 0))
+            .unwrap();
+        assert_zeros(&product, 0, 0, name);
+
+        // Dimensions are still checked
+        assert!(backend
+            .matrix_multiply(&Matrix::new(0, 2), &Matrix::new(3, 0))
+            .is_err());
+    }
+
+    let product = cpu::matrix_multiply_naive(&Matrix::new(5, 0), &Matrix::new(0, 3)).unwrap();
+    assert_zeros(&product, 5, 3, "naive");
+}
+
+#[test]
+fn elementwise_operations_on_empty_matrices() {
+    for backend in backends() {
+        let name = backend.name();
+        for (rows, cols) in [(0, 0), (0, 3), (3, 0)] {
+            let a = Matrix::new(rows, cols);
+
+            assert_zeros(&backend.matrix_add(&a, &a).unwrap(), rows, cols, name);
+            assert_zeros(&backend.matrix_subtract(&a, &a).unwrap(), rows, cols, name);
+            assert_zeros(
+                &backend.matrix_scalar_multiply(2.0, &a).unwrap(),
+                rows,
+                cols,
+                name,
+            );
+            assert_zeros(&backend.matrix_transpose(&a).unwrap(), cols, rows, name);
+        }
+
+        // 0×3 and 3×0 are different shapes
+        assert!(backend
+            .matrix_add(&Matrix::new(0, 3), &Matrix::new(3, 0))
+            .is_err());
+    }
+}
+
+#[test]
+fn single_element_and_single_line_shapes() {
+    for backend in backends() {
+        let name = backend.name();
+        let x = Matrix::with_data(1, 1, vec![3.0]).unwrap();
+        assert_eq!(
+            backend.matrix_multiply(&x, &x).unwrap().data,
+            vec![9.0],
+            "{}",
+            name
+        );
+
+        // Outer and inner products of a row and a column
+        let row = Matrix::with_data(1, 3, vec![1.0, 2.0, 3.0]).unwrap();
+        let column = Matrix::vector(vec![4.0, 5.0, 6.0]);
+        let inner = backend.matrix_multiply(&row, &column).unwrap();
+        assert_eq!(
+            (inner.rows, inner.cols, inner.data[0]),
+            (1, 1, 32.0),
+            "{}",
+            name
+        );
+        let outer = backend.matrix_multiply(&column, &row).unwrap();
+        assert_eq!((outer.rows, outer.cols), (3, 3), "{}", name);
+        assert_eq!(outer.get(2, 1), 12.0, "{}", name);
+
+        let transposed = backend.matrix_transpose(&row).unwrap();
+        assert_eq!((transposed.rows, transposed.cols), (3, 1), "{}", name);
+        assert_eq!(transposed.data, row.data, "{}", name);
+    }
+}
+
+#[test]
+fn solves_with_empty_systems() {
+    for backend in backends() {
+        let name = backend.name();
+
+        let x = backend
+            .solve(&Matrix::new(0, 0), &Matrix::new(0, 2))
+            .unwrap();
+        assert_zeros(&x, 0, 2, name);
+        let inverse = backend.inverse(&Matrix::new(0, 0)).unwrap();
+        assert_zeros(&inverse, 0, 0, name);
+
+        // No right-hand sides
+        let x = backend
+            .solve(&Matrix::identity(3), &Matrix::new(3, 0))
+            .unwrap();
+        assert_zeros(&x, 3, 0, name);
+    }
+
+    for side in [Side::Left, Side::Right] {
+        let b = match side {
+            Side::Left => Matrix::new(0, 2),
+            Side::Right => Matrix::new(2, 0),
+        };
+        let t = Matrix::new(0, 0);
+        let x = cpu::triangular_solve(&t, &b, Uplo::Lower, Transpose::NoTrans, Diag::NonUnit, side)
+            .unwrap();
+        assert_eq!((x.rows, x.cols), (b.rows, b.cols));
+        let y = cpu::triangular_multiply(&t, &b, Uplo::Upper, Transpose::Trans, Diag::Unit, side)
+            .unwrap();
+        assert_eq!((y.rows, y.cols), (b.rows, b.cols));
+    }
+}
+
+#[test]
+fn matrix_functions_of_an_empty_matrix() {
+    let empty = Matrix::new(0, 0);
+
+    assert_zeros(&expm(&CpuBackend, &empty).unwrap(), 0, 0, "expm");
+    assert_zeros(&logm(&CpuBackend, &empty).unwrap(), 0, 0, "logm");
+    assert_zeros(&sqrtm(&CpuBackend, &empty).unwrap(), 0, 0, "sqrtm");
+    for p in [-2, 0, 3] {
+        assert_zeros(
+            &matrix_power(&CpuBackend, &empty, p).unwrap(),
+            0,
+            0,
+            "matrix_power",
+        );
+    }
+}
+
+#[test]
+fn sparse_products_with_empty_shapes() {
+    let a = CsrMatrix::zeros(0, 4);
+    assert_zeros(&cpu::spmm(&a, &ones(4, 3)).unwrap(), 0, 3, "spmm");
+    assert_zeros(
+        &cpu::spmv(&a, &Matrix::vector(vec![1.0; 4])).unwrap(),
+        0,
+        1,
+        "spmv",
+    );
+
+    let a = CsrMatrix::from_dense(&ones(3, 0)).unwrap();
+    assert_eq!(a.nnz(), 0);
+    assert_zeros(&cpu::spmm(&a, &Matrix::new(0, 2)).unwrap(), 3, 2, "spmm");
+}
+
+#[test]
+fn lazy_expressions_with_empty_shapes() {
+    let a = Matrix::new(4, 0).lazy();
+    let b = Matrix::new(0, 2).lazy();
+
+    let product = a.matmul(&b);
+    assert_eq!(product.shape().unwrap(), (4, 2));
+    assert_zeros(&product.eval_cpu().unwrap(), 4, 2, "matmul");
+
+    let sum = b.add(&b).relu();
+    assert_zeros(&sum.eval_cpu().unwrap(), 0, 2, "add");
+}
+
+#[test]
+fn verifies_empty_results() {
+    for operation in [Operation::MatrixMultiply, Operation::MatrixAdd] {
+        let a = Matrix::new(3, 0);
+        let b = match operation {
+            Operation::MatrixMultiply => Matrix::new(0, 2),
+            _ => Matrix::new(3, 0),
+        };
+        let report = verify::verify(operation, &CpuBackend, &F64Backend, &a, &b).unwrap();
+        assert!(report.passed(), "{}", report);
+        assert_eq!(report.failures, 0);
+    }
+}
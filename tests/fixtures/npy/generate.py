@@ -0,0 +1,80 @@
+#!/usr/bin/env python3
+"""Generates the .npy/.npz fixtures used by tests/npy.rs.
+
+The files reproduce the byte layout of numpy.save / numpy.savez (format version
+1.0, header padded to 64 bytes) using only the standard library, so they can be
+regenerated without NumPy installed. The NumPy call each file corresponds to is
+noted next to it.
+
+Run from this directory: python3 generate.py
+"""
+
+import struct
+import zipfile
+
+MATRIX = [[1.5, -2.0, 3.25], [4.0, 0.125, -6.5]]
+
+
+def npy(descr, shape, values, fortran_order=False, version=(1, 0)):
+    shape_text = "(" + "".join(f"{n}, " for n in shape).rstrip(" ")
+    if len(shape) != 1:
+        shape_text = shape_text.rstrip(",")
+    shape_text += ")"
+    header = (
+        f"{{'descr': '{descr}', 'fortran_order': {fortran_order}, "
+        f"'shape': {shape_text}, }}"
+    )
+    length_size = 2 if version[0] == 1 else 4
+    preamble = 6 + 2 + length_size
+    header += " " * ((64 - (preamble + len(header) + 1) % 64) % 64) + "\n"
+
+    endian = descr[0]
+    code = {"f4": "f", "f8": "d", "i4": "i"}[descr[1:]]
+    data = struct.pack(f"{endian}{len(values)}{code}", *values)
+
+    length = struct.pack("<H" if length_size == 2 else "<I", len(header))
+    return b"\x93NUMPY" + bytes(version) + length + header.encode("latin1") + data
+
+
+def c_order(rows):
+    return [value for row in rows for value in row]
+
+
+def f_order(rows):
+    return [row[col] for col in range(len(rows[0])) for row in rows]
+
+
+def write(name, contents):
+    with open(name, "wb") as file:
+        file.write(contents)
+
+
+# np.save("f32_c.npy", np.array(MATRIX, dtype="<f4"))
+write("f32_c.npy", npy("<f4", (2, 3), c_order(MATRIX)))
+# np.save("f64_big_endian.npy", np.array(MATRIX, dtype=">f8"))
+write("f64_big_endian.npy", npy(">f8", (2, 3), c_order(MATRIX)))
+# np.save("f32_fortran.npy", np.asfortranarray(np.array(MATRIX, dtype="<f4")))
+write("f32_fortran.npy", npy("<f4", (2, 3), f_order(MATRIX), fortran_order=True))
+# np.save("i32_big_endian_fortran.npy",
+#         np.asfortranarray(np.array([[1, -2, 3], [-4, 5, 6]], dtype=">i4")))
+write(
+    "i32_big_endian_fortran.npy",
+    npy(">i4", (2, 3), f_order([[1, -2, 3], [-4, 5, 6]]), fortran_order=True),
+)
+# np.save("vector_f64.npy", np.array([0.1, 0.2, 0.3, 0.4]))
+write("vector_f64.npy", npy("<f8", (4,), [0.1, 0.2, 0.3, 0.4]))
+# np.save("f32_version2.npy", np.array(MATRIX, dtype="<f4")), forcing format 2.0
+write("f32_version2.npy", npy("<f4", (2, 3), c_order(MATRIX), version=(2, 0)))
+
+arrays = {
+    "weights.npy": npy("<f4", (2, 3), c_order(MATRIX)),
+    "bias.npy": npy("<f8", (3,), [0.5, -0.25, 1.0]),
+}
+# np.savez("arrays.npz", weights=..., bias=...)
+with zipfile.ZipFile("arrays.npz", "w", zipfile.ZIP_STORED) as archive:
+    for name, contents in arrays.items():
+        archive.writestr(name, contents)
+# np.savez_compressed("arrays_compressed.npz", weights=..., bias=...)
+with zipfile.ZipFile("arrays_compressed.npz", "w", zipfile.ZIP_DEFLATED) as archive:
+    for name, contents in arrays.items():
+        archive.writestr(name, contents)
@@ -0,0 +1,47 @@
+#!/usr/bin/env python3
+"""Generates the .safetensors fixture used by tests/safetensors.rs.
+
+The file follows the safetensors layout (little-endian u64 header length, JSON
+header padded with spaces to 8 bytes, then the tensor data) and is written with
+the standard library only, so it can be regenerated without torch or the
+safetensors package installed.
+
+Run from this directory: python3 generate.py
+"""
+
+import json
+import struct
+
+MATRIX = [1.5, -2.0, 3.25, 4.0, 0.125, -6.5]
+
+
+def bf16(values):
+    # bfloat16 is the upper half of the float32 bit pattern; these values are exact.
+    words = struct.unpack(f"<{len(values)}I", struct.pack(f"<{len(values)}f", *values))
+    return struct.pack(f"<{len(values)}H", *(w >> 16 for w in words))
+
+
+tensors = [
+    ("f32", "F32", [2, 3], struct.pack("<6f", *MATRIX)),
+    ("f16", "F16", [2, 3], struct.pack("<6e", *MATRIX)),
+    ("bf16", "BF16", [3, 2], bf16(MATRIX)),
+    ("bias", "F64", [3], struct.pack("<3d", 0.5, -0.25, 1.0)),
+    ("conv", "F32", [1, 1, 2], struct.pack("<2f", 1.0, 2.0)),
+    ("step", "I64", [1], struct.pack("<q", 1000)),
+]
+
+header = {"__metadata__": {"format": "pt"}}
+data = b""
+for name, dtype, shape, contents in tensors:
+    header[name] = {
+        "dtype": dtype,
+        "shape": shape,
+        "data_offsets": [len(data), len(data) + len(contents)],
+    }
+    data += contents
+
+header = json.dumps(header, separators=(",", ":")).encode()
+header += b" " * (-len(header) % 8)
+
+with open("weights.safetensors", "wb") as file:
+    file.write(struct.pack("<Q", len(header)) + header + data)
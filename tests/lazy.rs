@@ -0,0 +1,245 @@
+mod common;
+
+use common::{assert_close, test_matrix};
+use metal_matrix::{cpu, Expr, KernelKind, Matrix};
+
+fn kinds(expr: &Expr) -> Vec<KernelKind> {
+    let plan = expr.compile().unwrap();
+    plan.kernels().iter().map(|k| k.kind()).collect()
+}
+
+#[test]
+fn infers_shapes() {
+    let a = test_matrix(4, 3, 0);
+    let b = test_matrix(3, 5, 1);
+
+    assert_eq!(a.lazy().shape().unwrap(), (4, 3));
+    assert_eq!(a.lazy().matmul(&b).shape().unwrap(), (4, 5));
+    assert_eq!(
+        a.lazy().matmul(&b).relu().scale(2.0).shape().unwrap(),
+        (4, 5)
+    );
+}
+
+#[test]
+fn reports_shape_errors_from_anywhere_in_the_graph() {
+    let a = test_matrix(4, 3, 0);
+    let b = test_matrix(3, 5, 1);
+
+    let error = a.lazy().matmul(&a).shape().unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Matrix dimensions incompatible for multiplication"
+    );
+
+    // The error propagates through later operations and into evaluation
+    let expr = a.lazy().add(&b).relu().matmul(&b);
+    assert_eq!(
+        expr.shape().unwrap_err().to_string(),
+        "Matrix dimensions must match for addition"
+    );
+    assert!(expr.compile().is_err());
+    assert!(expr.eval_cpu().is_err());
+}
+
+#[test]
+fn fuses_elementwise_chains_into_one_kernel() {
+    let a = test_matrix(3, 3, 0);
+    let b = test_matrix(3, 3, 1);
+    let c = test_matrix(3, 3, 2);
+
+    let expr = a.lazy().add(&b).mul(&c).scale(0.5).tanh();
+    assert_eq!(kinds(&expr), vec![KernelKind::Elementwise]);
+
+    let expected: Vec<f32> = (0..9)
+        .map(|i| ((a.data[i] + b.data[i]) * c.data[i] * 0.5).tanh())
+        .collect();
+    assert_eq!(expr.eval_cpu().unwrap().data, expected);
+}
+
+#[test]
+fn folds_epilogue_into_matmul() {
+    let a = test_matrix(4, 3, 0);
+    let b = test_matrix(3, 5, 1);
+    let c = test_matrix(4, 5, 2);
+
+    let expr = a.lazy().matmul(&b).add(&c).relu();
+    assert_eq!(kinds(&expr), vec![KernelKind::MatMul]);
+
+    let product = cpu::matrix_multiply(&a, &b).unwrap();
+    let sum = cpu::matrix_add(&product, &c).unwrap();
+    let expected = Matrix::with_data(4, 5, sum.data.iter().map(|x| x.max(0.0)).collect()).unwrap();
+    assert_close(&expr.eval_cpu().unwrap(), &expected, 1e-5, "eval_cpu");
+}
+
+#[test]
+fn materializes_matmul_operands_and_shared_results() {
+    let a = test_matrix(3, 3, 0);
+    let b = test_matrix(3, 3, 1);
+
+    // The operand of the matmul is computed by its own element-wise kernel
+    let expr = a.lazy().relu().matmul(&b);
+    assert_eq!(
+        kinds(&expr),
+        vec![KernelKind::Elementwise, KernelKind::MatMul]
+    );
+
+    // A product used twice is written once and read by the element-wise kernel
+    let product = a.lazy().matmul(&b);
+    let expr = product.mul(&product).sub(&product);
+    assert_eq!(
+        kinds(&expr),
+        vec![KernelKind::MatMul, KernelKind::Elementwise]
+    );
+
+    let p = cpu::matrix_multiply(&a, &b).unwrap();
+    let expected = Matrix::with_data(3, 3, p.data.iter().map(|x| x * x - x).collect()).unwrap();
+    assert_close(&expr.eval_cpu().unwrap(), &expected, 1e-5, "eval_cpu");
+}
+
+#[test]
+fn chained_matmuls_each_fold_their_epilogue() {
+    let a = test_matrix(2, 3, 0);
+    let b = test_matrix(3, 4, 1);
+    let c = test_matrix(4, 2, 2);
+
+    let expr = a.lazy().matmul(&b).sigmoid().matmul(&c).exp();
+    assert_eq!(kinds(&expr), vec![KernelKind::MatMul, KernelKind::MatMul]);
+
+    let ab = cpu::matrix_multiply(&a, &b).unwrap();
+    let s = Matrix::with_data(
+        2,
+        4,
+        ab.data.iter().map(|x| 1.0 / (1.0 + (-x).exp())).collect(),
+    )
+    .unwrap();
+    let sc = cpu::matrix_multiply(&s, &c).unwrap();
+    let expected = Matrix::with_data(2, 2, sc.data.iter().map(|x| x.exp()).collect()).unwrap();
+    assert_close(&expr.eval_cpu().unwrap(), &expected, 1e-5, "eval_cpu");
+}
+
+#[test]
+fn generates_elementwise_source() {
+    let a = test_matrix(2, 2, 0);
+    let b = test_matrix(2, 2, 1);
+
+    let plan = a.lazy().add(&b).neg().scale(2.5).compile().unwrap();
+    let kernel = &plan.kernels()[0];
+
+    assert_eq!(kernel.name(), "fused_0");
+    assert_eq!(
+        kernel.source(),
+        "#include <metal_stdlib>
+using namespace metal;
+
+kernel void fused_0(device const float* in0,
+                    device const float* in1,
+                    constant float* scalars,
+                    device float* out,
+                    constant uint& count,
+                    uint id [[thread_position_in_grid]])
+{
+    if (id >= count) {
+        return;
+    }
+
+    float v0 = in0[id];
+    float v1 = in1[id];
+    float v2 = v0 + v1;
+    float v3 = -v2;
+    float v4 = v3 * scalars[0];
+    out[id] = v4;
+}
+"
+    );
+}
+
+#[test]
+fn generates_matmul_epilogue_source() {
+    let a = test_matrix(2, 3, 0);
+    let b = test_matrix(3, 2, 1);
+    let bias = test_matrix(2, 2, 2);
+
+    let plan = a.lazy().matmul(&b).add(&bias).relu().compile().unwrap();
+    let kernel = &plan.kernels()[0];
+
+    assert_eq!(
+        kernel.source(),
+        "#include <metal_stdlib>
+using namespace metal;
+
+kernel void fused_0(device const float* A,
+                    device const float* B,
+                    device const float* in0,
+                    device float* out,
+                    constant uint& M,
+                    constant uint& N,
+                    constant uint& K,
+                    uint2 position [[thread_position_in_grid]])
+{
+    uint row = position.y;
+    uint col = position.x;
+    if (row >= M || col >= N) {
+        return;
+    }
+
+    float acc = 0.0f;
+    for (uint i = 0; i < K; i++) {
+        acc += A[row * K + i] * B[i * N + col];
+    }
+
+    uint id = row * N + col;
+    float v0 = acc;
+    float v1 = in0[id];
+    float v2 = v0 + v1;
+    float v3 = max(v2, 0.0f);
+    out[id] = v3;
+}
+"
+    );
+}
+
+#[test]
+fn repeated_operands_are_loaded_once() {
+    let a = test_matrix(2, 2, 0);
+    let x = a.lazy();
+
+    let plan = x.mul(&x).add(&x).compile().unwrap();
+    let source = plan.kernels()[0].source();
+
+    assert!(source.contains("device const float* in0,"));
+    assert!(!source.contains("in1"));
+}
+
+#[test]
+fn inputs_evaluate_without_kernels() {
+    let a = test_matrix(2, 3, 0);
+    let plan = a.lazy().compile().unwrap();
+
+    assert!(plan.kernels().is_empty());
+    assert_eq!(plan.eval_cpu().unwrap().data, a.data);
+}
+
+#[test]
+fn scalars_are_not_part_of_the_source() {
+    let a = test_matrix(1, 2, 0);
+    let sources: Vec<String> = [2.0, -0.5, f32::INFINITY]
+        .iter()
+        .map(|&scalar| {
+            let plan = a.lazy().scale(scalar).relu().compile().unwrap();
+            plan.kernels()[0].source().to_string()
+        })
+        .collect();
+    assert!(sources.iter().all(|source| *source == sources[0]));
+
+    // Each scale reads its own scalar
+    let plan = a.lazy().scale(2.0).scale(f32::INFINITY).compile().unwrap();
+    assert!(plan.kernels()[0].source().contains("v1 * scalars[1]"));
+    assert_eq!(
+        plan.eval_cpu().unwrap().data,
+        vec![
+            a.data[0] * 2.0 * f32::INFINITY,
+            a.data[1] * 2.0 * f32::INFINITY
+        ]
+    );
+}
@@ -0,0 +1,140 @@
+use metal_matrix::limits::{check_32bit, DeviceLimits, IndexWidth, LimitError, MAX_32BIT_INDEX};
+use metal_matrix::SparseKernel;
+
+const MAX: usize = MAX_32BIT_INDEX as usize;
+
+/// A device allowing buffers of up to 64 GiB.
+fn limits() -> DeviceLimits {
+    DeviceLimits {
+        max_buffer_length: 64 << 30,
+    }
+}
+
+#[test]
+fn checks_32bit_dimensions_and_element_counts() {
+    assert_eq!(check_32bit(MAX, 1), Ok(()));
+    assert_eq!(
+        check_32bit(MAX + 1, 1),
+        Err(LimitError::DimensionTooLarge { dimension: MAX + 1 })
+    );
+    assert_eq!(
+        check_32bit(1, MAX + 1),
+        Err(LimitError::DimensionTooLarge { dimension: MAX + 1 })
+    );
+
+    // 65536 x 65536 is one element past the limit; 65535 x 65537 is within it
+    assert_eq!(
+        check_32bit(1 << 16, 1 << 16),
+        Err(LimitError::IndexOverflow { elements: 1 << 32 })
+    );
+    assert_eq!(check_32bit(65535, 65537), Ok(()));
+}
+
+#[test]
+fn checks_dimensions_of_empty_matrices() {
+    // A dimension that does not fit in a uint is rejected even with no elements,
+    // since kernels receive it as an argument
+    assert_eq!(
+        check_32bit(0, MAX + 1),
+        Err(LimitError::DimensionTooLarge { dimension: MAX + 1 })
+    );
+}
+
+#[test]
+fn chooses_the_index_width() {
+    assert_eq!(IndexWidth::for_elements(0), IndexWidth::U32);
+    assert_eq!(IndexWidth::for_elements(MAX), IndexWidth::U32);
+    assert_eq!(IndexWidth::for_elements(MAX + 1), IndexWidth::U64);
+
+    assert_eq!(
+        limits().check_elementwise(1 << 16, 1 << 16),
+        Ok(IndexWidth::U64)
+    );
+    assert_eq!(limits().check_elementwise(MAX, 1), Ok(IndexWidth::U32));
+}
+
+#[test]
+fn checks_buffer_lengths() {
+    let limits = DeviceLimits {
+        max_buffer_length: 1024,
+    };
+    assert_eq!(limits.check_buffer(256), Ok(()));
+    assert_eq!(
+        limits.check_buffer(257),
+        Err(LimitError::BufferTooLarge {
+            bytes: 1028,
+            max: 1024
+        })
+    );
+
+    // The byte count saturates instead of wrapping
+    assert_eq!(
+        limits.check_buffer(usize::MAX),
+        Err(LimitError::BufferTooLarge {
+            bytes: u64::MAX,
+            max: 1024
+        })
+    );
+    assert!(limits.check_elementwise(usize::MAX, 2).is_err());
+}
+
+#[test]
+fn checks_products_and_transposes() {
+    let limits = limits();
+    assert_eq!(limits.check_matrix_multiply(4096, 4096, 4096), Ok(()));
+
+    // Each operand and the result is checked
+    let error = Err(LimitError::IndexOverflow { elements: 1 << 32 });
+    assert_eq!(limits.check_matrix_multiply(1 << 16, 1 << 16, 1), error);
+    assert_eq!(limits.check_matrix_multiply(1, 1 << 16, 1 << 16), error);
+    assert_eq!(limits.check_matrix_multiply(1 << 16, 1, 1 << 16), error);
+
+    assert_eq!(limits.check_transpose(65535, 65537), Ok(()));
+    assert_eq!(limits.check_transpose(1 << 16, 1 << 16), error);
+
+    // Within the index range but not the device's buffers
+    let small = DeviceLimits {
+        max_buffer_length: 1 << 20,
+    };
+    assert_eq!(
+        small.check_matrix_multiply(1024, 512, 1),
+        Err(LimitError::BufferTooLarge {
+            bytes: 2 << 20,
+            max: 1 << 20
+        })
+    );
+}
+
+#[test]
+fn limit_errors_are_typed_through_anyhow() {
+    let error: anyhow::Error = LimitError::DimensionTooLarge { dimension: MAX + 1 }.into();
+    assert_eq!(
+        error.downcast_ref::<LimitError>(),
+        Some(&LimitError::DimensionTooLarge { dimension: MAX + 1 })
+    );
+    assert_eq!(
+        error.to_string(),
+        "Matrix dimension 4294967296 exceeds the kernel limit of 4294967295"
+    );
+
+    let error = limits().check_buffer(1 << 36).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Buffer of 274877906944 bytes exceeds the device's maximum buffer length of 68719476736 bytes"
+    );
+}
+
+#[test]
+fn wide_sparse_products_fall_back_to_the_scalar_kernel() {
+    let widest = MAX / 32;
+    assert_eq!(
+        SparseKernel::Vector.within_32bit(widest),
+        SparseKernel::Vector
+    );
+    assert_eq!(
+        SparseKernel::Vector.within_32bit(widest + 1),
+        SparseKernel::Scalar
+    );
+    assert_eq!(SparseKernel::Scalar.within_32bit(MAX), SparseKernel::Scalar);
+    assert_eq!(SparseKernel::Vector.within_32bit(0), SparseKernel::Vector);
+}
@@ -0,0 +1,105 @@
+mod common;
+
+use common::test_matrix;
+use metal_matrix::{cpu, matrix_multiply_mapped, CpuBackend, MappedMatrix, MappedMatrixMut};
+use std::path::PathBuf;
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("metal_matrix_{}_{}", std::process::id(), name))
+}
+
+#[test]
+fn written_matrices_map_back() {
+    let path = temp_path("round_trip.bin");
+    let matrix = test_matrix(5, 3, 0);
+
+    MappedMatrix::write(&path, &matrix).unwrap();
+    let mapped = MappedMatrix::open(&path).unwrap();
+
+    assert_eq!((mapped.rows, mapped.cols), (5, 3));
+    assert_eq!(mapped.data(), matrix.data.as_slice());
+    assert_eq!(mapped.row(4), &matrix.data[12..15]);
+    assert_eq!(mapped.block(1, 2).data, matrix.data[3..9]);
+    assert_eq!(mapped.to_matrix().data, matrix.data);
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn files_have_a_64_byte_header() {
+    let path = temp_path("layout.bin");
+    MappedMatrix::write(&path, &test_matrix(2, 3, 0)).unwrap();
+
+    let bytes = std::fs::read(&path).unwrap();
+    assert_eq!(bytes.len(), 64 + 6 * 4);
+    assert_eq!(&bytes[..8], b"METALMAT");
+    assert_eq!(bytes[8], 2);
+    assert_eq!(bytes[16], 3);
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn out_of_core_multiply_matches_in_memory_product() {
+    let path_a = temp_path("ooc_a.bin");
+    let path_c = temp_path("ooc_c.bin");
+    let a = test_matrix(37, 11, 1);
+    let b = test_matrix(11, 5, 2);
+    let expected = cpu::matrix_multiply(&a, &b).unwrap();
+
+    MappedMatrix::write(&path_a, &a).unwrap();
+    let mapped_a = MappedMatrix::open(&path_a).unwrap();
+
+    // Block sizes that divide the rows, leave a remainder, and exceed the rows
+    for block_rows in [1, 8, 37, 100] {
+        let mut c = MappedMatrixMut::create(&path_c, 37, 5).unwrap();
+        matrix_multiply_mapped(&CpuBackend, &mapped_a, &b, block_rows, &mut c).unwrap();
+
+        let c = c.into_read_only().unwrap();
+        assert_eq!(
+            c.data(),
+            expected.data.as_slice(),
+            "block_rows={}",
+            block_rows
+        );
+    }
+
+    std::fs::remove_file(path_a).unwrap();
+    std::fs::remove_file(path_c).unwrap();
+}
+
+#[test]
+fn out_of_core_multiply_validates_dimensions() {
+    let path_a = temp_path("invalid_a.bin");
+    let path_c = temp_path("invalid_c.bin");
+    MappedMatrix::write(&path_a, &test_matrix(4, 3, 0)).unwrap();
+    let a = MappedMatrix::open(&path_a).unwrap();
+    let mut c = MappedMatrixMut::create(&path_c, 4, 2).unwrap();
+
+    assert!(matrix_multiply_mapped(&CpuBackend, &a, &test_matrix(2, 2, 0), 2, &mut c).is_err());
+    assert!(matrix_multiply_mapped(&CpuBackend, &a, &test_matrix(3, 3, 0), 2, &mut c).is_err());
+    assert!(matrix_multiply_mapped(&CpuBackend, &a, &test_matrix(3, 2, 0), 0, &mut c).is_err());
+
+    std::fs::remove_file(path_a).unwrap();
+    std::fs::remove_file(path_c).unwrap();
+}
+
+#[test]
+fn rejects_invalid_files() {
+    let path = temp_path("invalid.bin");
+
+    std::fs::write(&path, b"not a matrix").unwrap();
+    assert!(MappedMatrix::open(&path).is_err());
+
+    MappedMatrix::write(&path, &test_matrix(2, 2, 0)).unwrap();
+    let mut bytes = std::fs::read(&path).unwrap();
+    bytes.truncate(bytes.len() - 4);
+    std::fs::write(&path, &bytes).unwrap();
+    let error = MappedMatrix::open(&path).err().unwrap();
+    assert_eq!(
+        error.to_string(),
+        "Invalid matrix file: size does not match a 2x2 matrix"
+    );
+
+    std::fs::remove_file(path).unwrap();
+}
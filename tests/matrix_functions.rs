@@ -0,0 +1,151 @@
+//! Matrix functions against closed forms and identities.
+
+mod common;
+
+use common::{assert_close, dominant_matrix, test_matrix};
+use metal_matrix::{cpu, expm, logm, matrix_power, sqrtm, CpuBackend, Matrix};
+
+fn matrix(rows: usize, cols: usize, data: &[f32]) -> Matrix {
+    Matrix::with_data(rows, cols, data.to_vec()).unwrap()
+}
+
+fn rotation(theta: f32) -> Matrix {
+    let (sin, cos) = theta.sin_cos();
+    matrix(2, 2, &[cos, -sin, sin, cos])
+}
+
+fn multiply(a: &Matrix, b: &Matrix) -> Matrix {
+    cpu::matrix_multiply(a, b).unwrap()
+}
+
+#[test]
+fn exponential_of_a_nilpotent_matrix_is_a_finite_series() {
+    // N^3 = 0, so exp(N) = I + N + N^2 / 2 exactly
+    let n = matrix(3, 3, &[0.0, 1.5, -2.0, 0.0, 0.0, 3.0, 0.0, 0.0, 0.0]);
+    let expected = matrix(3, 3, &[1.0, 1.5, 0.25, 0.0, 1.0, 3.0, 0.0, 0.0, 1.0]);
+
+    assert_close(
+        &expm(&CpuBackend, &n).unwrap(),
+        &expected,
+        1e-6,
+        "nilpotent",
+    );
+}
+
+#[test]
+fn exponential_of_a_skew_matrix_is_a_rotation() {
+    // Angles that need no scaling, some, and many squarings
+    for (theta, tolerance) in [(0.3, 1e-6), (2.0, 1e-5), (10.0, 1e-4)] {
+        let generator = matrix(2, 2, &[0.0, -theta, theta, 0.0]);
+        assert_close(
+            &expm(&CpuBackend, &generator).unwrap(),
+            &rotation(theta),
+            tolerance,
+            &format!("theta = {}", theta),
+        );
+    }
+}
+
+#[test]
+fn exponential_of_a_diagonal_matrix_is_elementwise() {
+    let a = matrix(3, 3, &[-3.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 2.0]);
+    let expected = matrix(
+        3,
+        3,
+        &[(-3f32).exp(), 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 2f32.exp()],
+    );
+    assert_close(&expm(&CpuBackend, &a).unwrap(), &expected, 1e-6, "diagonal");
+
+    // exp(A) * exp(-A) = I for a general A
+    let a = cpu::matrix_scalar_multiply(0.2, &test_matrix(6, 6, 1)).unwrap();
+    let minus_a = cpu::matrix_scalar_multiply(-1.0, &a).unwrap();
+    let product = multiply(
+        &expm(&CpuBackend, &a).unwrap(),
+        &expm(&CpuBackend, &minus_a).unwrap(),
+    );
+    assert_close(&product, &Matrix::identity(6), 1e-5, "exp(A) exp(-A)");
+}
+
+#[test]
+fn logarithm_inverts_the_exponential() {
+    // Small enough that every eigenvalue has an imaginary part within (-pi, pi)
+    let a = cpu::matrix_scalar_multiply(0.1, &test_matrix(5, 5, 2)).unwrap();
+    let log = logm(&CpuBackend, &expm(&CpuBackend, &a).unwrap()).unwrap();
+    assert_close(&log, &a, 1e-4, "log(exp(A))");
+
+    // The principal logarithm of a rotation by theta is the skew generator
+    let log = logm(&CpuBackend, &rotation(1.0)).unwrap();
+    assert_close(
+        &log,
+        &matrix(2, 2, &[0.0, -1.0, 1.0, 0.0]),
+        1e-5,
+        "rotation",
+    );
+
+    let log = logm(&CpuBackend, &Matrix::identity(4)).unwrap();
+    assert_close(&log, &Matrix::new(4, 4), 1e-6, "identity");
+}
+
+#[test]
+fn square_root_squares_back() {
+    // Triangular closed form: sqrt([[4, 1], [0, 9]]) = [[2, 0.2], [0, 3]]
+    let a = matrix(2, 2, &[4.0, 1.0, 0.0, 9.0]);
+    let root = sqrtm(&CpuBackend, &a).unwrap();
+    assert_close(
+        &root,
+        &matrix(2, 2, &[2.0, 0.2, 0.0, 3.0]),
+        1e-5,
+        "triangular",
+    );
+
+    for n in [3, 8, 20] {
+        let what = format!("n = {}", n);
+        let a = dominant_matrix(n);
+        let root = sqrtm(&CpuBackend, &a).unwrap();
+        assert_close(&multiply(&root, &root), &a, 1e-4, &what);
+    }
+}
+
+#[test]
+fn negative_powers_invert() {
+    let diagonal = matrix(2, 2, &[2.0, 0.0, 0.0, 4.0]);
+    assert_eq!(
+        matrix_power(&CpuBackend, &diagonal, -2).unwrap().data,
+        vec![0.25, 0.0, 0.0, 0.0625]
+    );
+
+    // Powers of a Jordan block: [[1, 1], [0, 1]]^p = [[1, p], [0, 1]]
+    let jordan = matrix(2, 2, &[1.0, 1.0, 0.0, 1.0]);
+    for p in [-7, -5, -1, 0, 1, 6] {
+        assert_close(
+            &matrix_power(&CpuBackend, &jordan, p).unwrap(),
+            &matrix(2, 2, &[1.0, p as f32, 0.0, 1.0]),
+            1e-6,
+            &format!("p = {}", p),
+        );
+    }
+
+    let a = dominant_matrix(6);
+    let product = multiply(
+        &matrix_power(&CpuBackend, &a, 3).unwrap(),
+        &matrix_power(&CpuBackend, &a, -3).unwrap(),
+    );
+    assert_close(&product, &Matrix::identity(6), 1e-4, "A^3 A^-3");
+}
+
+#[test]
+fn rejects_matrices_without_a_result() {
+    let negative = matrix(2, 2, &[-1.0, 0.0, 0.0, 1.0]);
+    assert!(sqrtm(&CpuBackend, &negative).is_err());
+    assert!(logm(&CpuBackend, &negative).is_err());
+
+    let singular = matrix(2, 2, &[1.0, 2.0, 2.0, 4.0]);
+    assert!(matrix_power(&CpuBackend, &singular, -1).is_err());
+    assert!(matrix_power(&CpuBackend, &singular, 2).is_ok());
+
+    let rectangular = Matrix::new(2, 3);
+    assert!(expm(&CpuBackend, &rectangular).is_err());
+    assert!(logm(&CpuBackend, &rectangular).is_err());
+    assert!(sqrtm(&CpuBackend, &rectangular).is_err());
+    assert!(matrix_power(&CpuBackend, &rectangular, 2).is_err());
+}
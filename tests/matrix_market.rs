@@ -0,0 +1,201 @@
+use metal_matrix::io::matrix_market;
+use metal_matrix::Matrix;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::PathBuf;
+
+fn fixture(name: &str) -> BufReader<File> {
+    let path: PathBuf = [
+        env!("CARGO_MANIFEST_DIR"),
+        "tests",
+        "fixtures",
+        "matrix_market",
+        name,
+    ]
+    .iter()
+    .collect();
+    BufReader::new(File::open(path).unwrap())
+}
+
+fn round_trip_dense(matrix: &Matrix) -> Matrix {
+    let mut file = Vec::new();
+    matrix_market::write_matrix(&mut file, matrix).unwrap();
+    matrix_market::read_matrix(file.as_slice()).unwrap()
+}
+
+#[test]
+fn reads_coordinate_real_general() {
+    let matrix = matrix_market::read_matrix(fixture("coordinate_real_general.mtx")).unwrap();
+
+    assert_eq!((matrix.rows, matrix.cols), (3, 4));
+    #[rustfmt::skip]
+    assert_eq!(matrix.data, vec![
+        1.5, 0.0, 0.0, -2.0,
+        0.0, 3.25, 0.0, 0.0,
+        0.4, 0.0, -7.5, 0.0,
+    ]);
+}
+
+#[test]
+fn reads_coordinate_real_symmetric() {
+    let matrix = matrix_market::read_matrix(fixture("coordinate_real_symmetric.mtx")).unwrap();
+
+    #[rustfmt::skip]
+    assert_eq!(matrix.data, vec![
+        2.0, -1.0, 0.0,
+        -1.0, 2.0, -1.0,
+        0.0, -1.0, 0.0,
+    ]);
+}
+
+#[test]
+fn reads_coordinate_integer_skew_symmetric() {
+    let matrix = matrix_market::read_matrix(fixture("coordinate_integer_skew.mtx")).unwrap();
+
+    #[rustfmt::skip]
+    assert_eq!(matrix.data, vec![
+        0.0, -3.0, 4.0,
+        3.0, 0.0, 0.0,
+        -4.0, 0.0, 0.0,
+    ]);
+}
+
+#[test]
+fn reads_coordinate_pattern_as_triplets() {
+    let coo = matrix_market::read_coo(fixture("coordinate_pattern_general.mtx")).unwrap();
+
+    assert_eq!((coo.rows, coo.cols), (4, 4));
+    assert_eq!(coo.row_indices, vec![0, 1, 2, 3]);
+    assert_eq!(coo.col_indices, vec![1, 2, 3, 0]);
+    assert_eq!(coo.values, vec![1.0; 4]);
+}
+
+#[test]
+fn reads_array_real_general_column_major() {
+    let matrix = matrix_market::read_matrix(fixture("array_real_general.mtx")).unwrap();
+
+    assert_eq!((matrix.rows, matrix.cols), (2, 3));
+    assert_eq!(matrix.data, vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+}
+
+#[test]
+fn reads_array_real_symmetric() {
+    let matrix = matrix_market::read_matrix(fixture("array_real_symmetric.mtx")).unwrap();
+
+    #[rustfmt::skip]
+    assert_eq!(matrix.data, vec![
+        1.0, 2.0, 3.0,
+        2.0, 4.0, 5.0,
+        3.0, 5.0, 6.0,
+    ]);
+}
+
+#[test]
+fn symmetric_triplets_are_expanded() {
+    let coo = matrix_market::read_coo(fixture("coordinate_real_symmetric.mtx")).unwrap();
+    let csr = coo.to_csr().unwrap();
+
+    assert_eq!(csr.nnz(), 6);
+    assert_eq!(csr.get(0, 1), -1.0);
+    assert_eq!(csr.get(1, 0), -1.0);
+}
+
+#[test]
+fn every_fixture_round_trips_through_the_dense_writer() {
+    for name in [
+        "coordinate_real_general.mtx",
+        "coordinate_real_symmetric.mtx",
+        "coordinate_integer_skew.mtx",
+        "coordinate_pattern_general.mtx",
+        "array_real_general.mtx",
+        "array_real_symmetric.mtx",
+    ] {
+        let matrix = matrix_market::read_matrix(fixture(name)).unwrap();
+        let copy = round_trip_dense(&matrix);
+
+        assert_eq!(
+            (copy.rows, copy.cols),
+            (matrix.rows, matrix.cols),
+            "{}",
+            name
+        );
+        assert_eq!(copy.data, matrix.data, "{}", name);
+    }
+}
+
+#[test]
+fn triplets_round_trip_through_the_coordinate_writer() {
+    let coo = matrix_market::read_coo(fixture("coordinate_real_general.mtx")).unwrap();
+
+    let mut file = Vec::new();
+    matrix_market::write_coo(&mut file, &coo).unwrap();
+    let copy = matrix_market::read_coo(file.as_slice()).unwrap();
+
+    assert_eq!(copy.row_indices, coo.row_indices);
+    assert_eq!(copy.col_indices, coo.col_indices);
+    assert_eq!(copy.values, coo.values);
+}
+
+#[test]
+fn values_round_trip_exactly() {
+    let matrix = Matrix::with_data(1, 4, vec![0.1, -1.0e-30, 3.4028235e38, 1.0 / 3.0]).unwrap();
+
+    assert_eq!(round_trip_dense(&matrix).data, matrix.data);
+}
+
+#[test]
+fn errors_report_the_line_number() {
+    let file = "%%MatrixMarket matrix coordinate real general\n\
+                % comment\n\
+                2 2 2\n\
+                1 1 1.0\n\
+                3 1 2.0\n";
+
+    let error = matrix_market::read_matrix(file.as_bytes()).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Matrix Market line 5: index out of bounds"
+    );
+}
+
+#[test]
+fn rejects_missing_entries_and_unsupported_fields() {
+    let truncated = "%%MatrixMarket matrix coordinate real general\n2 2 2\n1 1 1.0\n";
+    assert!(matrix_market::read_matrix(truncated.as_bytes()).is_err());
+
+    let complex = "%%MatrixMarket matrix coordinate complex general\n1 1 1\n1 1 1.0 0.0\n";
+    assert!(matrix_market::read_matrix(complex.as_bytes()).is_err());
+}
+
+#[test]
+fn declared_sizes_are_not_trusted() {
+    let overflow = "%%MatrixMarket matrix coordinate real general\n\
+                    4294967296 4294967297 0\n";
+    assert_eq!(
+        matrix_market::read_coo(overflow.as_bytes())
+            .unwrap_err()
+            .to_string(),
+        "Matrix Market line 2: matrix dimensions overflow"
+    );
+
+    // A huge matrix whose entries are missing fails on the entries, before allocating
+    let truncated = "%%MatrixMarket matrix array real general\n\
+                     2147483648 2147483648\n\
+                     1.0\n";
+    assert_eq!(
+        matrix_market::read_matrix(truncated.as_bytes())
+            .unwrap_err()
+            .to_string(),
+        "Matrix Market file ended after 1 of 4611686018427387904 entries"
+    );
+
+    let empty = "%%MatrixMarket matrix coordinate real general\n\
+                 2147483648 2147483648 0\n";
+    assert_eq!(
+        matrix_market::read_matrix(empty.as_bytes())
+            .unwrap_err()
+            .to_string(),
+        "Matrix Market matrix of 2147483648x2147483648 is too large to allocate"
+    );
+    assert_eq!(matrix_market::read_coo(empty.as_bytes()).unwrap().nnz(), 0);
+}
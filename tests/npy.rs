@@ -0,0 +1,171 @@
+use metal_matrix::io::npy;
+use metal_matrix::Matrix;
+use std::fs::File;
+use std::io::{BufReader, Cursor, Read};
+use std::path::PathBuf;
+
+#[rustfmt::skip]
+const MATRIX: [f32; 6] = [
+    1.5, -2.0, 3.25,
+    4.0, 0.125, -6.5,
+];
+
+fn fixture(name: &str) -> BufReader<File> {
+    let path: PathBuf = [env!("CARGO_MANIFEST_DIR"), "tests", "fixtures", "npy", name]
+        .iter()
+        .collect();
+    BufReader::new(File::open(path).unwrap())
+}
+
+fn round_trip(matrix: &Matrix) -> Matrix {
+    let mut file = Vec::new();
+    matrix.write_npy(&mut file).unwrap();
+    Matrix::read_npy(file.as_slice()).unwrap()
+}
+
+#[test]
+fn reads_little_endian_f32_in_c_order() {
+    let matrix = Matrix::read_npy(fixture("f32_c.npy")).unwrap();
+
+    assert_eq!((matrix.rows, matrix.cols), (2, 3));
+    assert_eq!(matrix.data, MATRIX);
+}
+
+#[test]
+fn reads_big_endian_f64() {
+    let matrix = Matrix::read_npy(fixture("f64_big_endian.npy")).unwrap();
+
+    assert_eq!((matrix.rows, matrix.cols), (2, 3));
+    assert_eq!(matrix.data, MATRIX);
+}
+
+#[test]
+fn transposes_fortran_order_into_row_major() {
+    let matrix = Matrix::read_npy(fixture("f32_fortran.npy")).unwrap();
+
+    assert_eq!((matrix.rows, matrix.cols), (2, 3));
+    assert_eq!(matrix.data, MATRIX);
+}
+
+#[test]
+fn reads_big_endian_i32_in_fortran_order() {
+    let matrix = Matrix::read_npy(fixture("i32_big_endian_fortran.npy")).unwrap();
+
+    assert_eq!((matrix.rows, matrix.cols), (2, 3));
+    assert_eq!(matrix.data, vec![1.0, -2.0, 3.0, -4.0, 5.0, 6.0]);
+}
+
+#[test]
+fn reads_one_dimensional_arrays_as_vectors() {
+    let matrix = Matrix::read_npy(fixture("vector_f64.npy")).unwrap();
+
+    assert!(matrix.is_vector());
+    assert_eq!((matrix.rows, matrix.cols), (4, 1));
+    assert_eq!(matrix.data, vec![0.1, 0.2, 0.3, 0.4]);
+}
+
+#[test]
+fn reads_format_version_2() {
+    let matrix = Matrix::read_npy(fixture("f32_version2.npy")).unwrap();
+
+    assert_eq!(matrix.data, MATRIX);
+}
+
+#[test]
+fn reads_stored_and_compressed_archives() {
+    for name in ["arrays.npz", "arrays_compressed.npz"] {
+        let arrays = npy::read_npz(fixture(name)).unwrap();
+
+        assert_eq!(
+            arrays.keys().collect::<Vec<_>>(),
+            vec!["bias", "weights"],
+            "{}",
+            name
+        );
+        assert_eq!(arrays["weights"].data, MATRIX, "{}", name);
+        assert_eq!(arrays["bias"].data, vec![0.5, -0.25, 1.0], "{}", name);
+        assert_eq!(arrays["bias"].cols, 1, "{}", name);
+    }
+}
+
+#[test]
+fn written_files_match_numpy_layout() {
+    let matrix = Matrix::with_data(2, 3, MATRIX.to_vec()).unwrap();
+
+    let mut file = Vec::new();
+    matrix.write_npy(&mut file).unwrap();
+
+    let mut expected = Vec::new();
+    fixture("f32_c.npy").read_to_end(&mut expected).unwrap();
+    assert_eq!(file, expected);
+}
+
+#[test]
+fn values_round_trip_exactly() {
+    let matrix = Matrix::with_data(1, 4, vec![0.1, -1.0e-30, f32::MAX, 1.0 / 3.0]).unwrap();
+
+    assert_eq!(round_trip(&matrix).data, matrix.data);
+}
+
+#[test]
+fn archives_round_trip() {
+    let a = Matrix::with_data(2, 3, MATRIX.to_vec()).unwrap();
+    let b = Matrix::identity(4);
+
+    let mut archive = Cursor::new(Vec::new());
+    npy::write_npz(&mut archive, [("a", &a), ("b", &b)]).unwrap();
+    archive.set_position(0);
+    let arrays = npy::read_npz(archive).unwrap();
+
+    assert_eq!(arrays.len(), 2);
+    assert_eq!((arrays["a"].rows, arrays["a"].cols), (2, 3));
+    assert_eq!(arrays["a"].data, a.data);
+    assert_eq!(arrays["b"].data, b.data);
+}
+
+#[test]
+fn rejects_malformed_files() {
+    assert!(Matrix::read_npy(&b"not a numpy file"[..]).is_err());
+
+    let mut truncated = Vec::new();
+    Matrix::identity(3).write_npy(&mut truncated).unwrap();
+    truncated.truncate(truncated.len() - 4);
+    let error = Matrix::read_npy(truncated.as_slice()).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Invalid .npy file: array data is truncated"
+    );
+
+    // Declared sizes far beyond the file are reported as truncation, not allocated
+    let huge = b"\x93NUMPY\x01\x00\x46\x00{'descr': '<f4', 'fortran_order': False, 'shape': (1099511627776,), }\n\0\0\0\0";
+    let error = Matrix::read_npy(&huge[..]).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Invalid .npy file: array data is truncated"
+    );
+    let error = Matrix::read_npy(&b"\x93NUMPY\x02\x00\xff\xff\xff\xff{'descr'"[..]).unwrap_err();
+    assert_eq!(error.to_string(), "Invalid .npy file: header is truncated");
+
+    let overflow = b"\x93NUMPY\x01\x00\x4e\x00{'descr': '<f8', 'fortran_order': False, 'shape': (2147483648, 2147483648), }\n";
+    let error = Matrix::read_npy(&overflow[..]).unwrap_err();
+    assert_eq!(error.to_string(), "Invalid .npy file: array is too large");
+
+    let complex =
+        b"\x93NUMPY\x01\x00\x3a\x00{'descr': '<c8', 'fortran_order': False, 'shape': (1,), }\n";
+    let error = Matrix::read_npy(&complex[..]).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Invalid .npy file: unsupported dtype '<c8'"
+    );
+
+    // A dtype starting with a multi-byte character is rejected, not split inside it
+    let dictionary = "{'descr': '\u{e9}4', 'fortran_order': False, 'shape': (1,), }\n";
+    let mut non_ascii = b"\x93NUMPY\x01\x00".to_vec();
+    non_ascii.extend_from_slice(&(dictionary.len() as u16).to_le_bytes());
+    non_ascii.extend_from_slice(dictionary.as_bytes());
+    let error = Matrix::read_npy(non_ascii.as_slice()).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Invalid .npy file: unsupported dtype '\u{e9}4'"
+    );
+}
@@ -0,0 +1,219 @@
+use half::{bf16, f16};
+use metal_matrix::precision::{
+    self, bf16_to_f32, f16_to_f32, f32_to_bf16, f32_to_f16, HalfFormat, HalfMatrix, MixedMatrix,
+    Precision,
+};
+use metal_matrix::verify::sample_matrix;
+use metal_matrix::{cpu, Matrix};
+
+/// f32 bit patterns covering every exponent, with varied mantissas and both signs.
+fn f32_samples() -> impl Iterator<Item = u32> {
+    (0..=u32::MAX).step_by(4093).chain([
+        0x0000_0000,
+        0x8000_0000,
+        0x7f80_0000,
+        0xff80_0000,
+        0x7f7f_ffff,
+        // Halfway cases of f16: exactly at, and just past, the largest finite value
+        // plus half an ulp, and the smallest subnormal's midpoint
+        0x477f_f000,
+        0x477f_efff,
+        0x3300_0000,
+        0x3300_0001,
+        // bf16 halfway cases with even and odd kept bits
+        0x3f80_8000,
+        0x3f81_8000,
+        // A signalling NaN
+        0x7f80_0001,
+    ])
+}
+
+#[test]
+fn half_to_f32_matches_the_half_crate_exhaustively() {
+    for bits in 0..=u16::MAX {
+        let expected = f16::from_bits(bits).to_f32();
+        let actual = f16_to_f32(bits);
+        if expected.is_nan() {
+            assert!(actual.is_nan(), "f16 {:#06x}", bits);
+        } else {
+            assert_eq!(actual.to_bits(), expected.to_bits(), "f16 {:#06x}", bits);
+        }
+
+        // bf16 NaNs keep their payload bits exactly rather than being quieted
+        let expected = bf16::from_bits(bits).to_f32();
+        let actual = bf16_to_f32(bits);
+        if expected.is_nan() {
+            assert_eq!(actual.to_bits(), (bits as u32) << 16, "bf16 {:#06x}", bits);
+        } else {
+            assert_eq!(actual.to_bits(), expected.to_bits(), "bf16 {:#06x}", bits);
+        }
+    }
+}
+
+#[test]
+fn f32_to_half_rounds_like_the_half_crate() {
+    for bits in f32_samples() {
+        let x = f32::from_bits(bits);
+        if x.is_nan() {
+            assert_eq!(f32_to_f16(x) & 0x7e00, 0x7e00, "{:#010x}", bits);
+            assert_eq!(f32_to_bf16(x) & 0x7fc0, 0x7fc0, "{:#010x}", bits);
+            continue;
+        }
+        assert_eq!(
+            f32_to_f16(x),
+            f16::from_f32(x).to_bits(),
+            "f16 of {:#010x}",
+            bits
+        );
+        assert_eq!(
+            f32_to_bf16(x),
+            bf16::from_f32(x).to_bits(),
+            "bf16 of {:#010x}",
+            bits
+        );
+    }
+}
+
+#[test]
+fn conversions_round_trip_representable_values() {
+    for format in [HalfFormat::F16, HalfFormat::Bf16] {
+        for bits in 0..=u16::MAX {
+            let x = format.to_f32(bits);
+            if !x.is_nan() {
+                assert_eq!(format.from_f32(x), bits, "{} {:#06x}", format.name(), bits);
+            }
+        }
+    }
+}
+
+#[test]
+fn half_matrices_convert_on_the_cpu() {
+    let a = Matrix::with_data(2, 2, vec![1.0, -0.5, 2.0e-8, 1.0e10]).unwrap();
+
+    let half = HalfMatrix::from_matrix(&a, HalfFormat::F16);
+    assert_eq!((half.rows, half.cols), (2, 2));
+    // 2e-8 is below half the smallest f16 subnormal, 1e10 beyond the largest value
+    assert_eq!(half.to_matrix().data, vec![1.0, -0.5, 0.0, f32::INFINITY]);
+
+    let half = HalfMatrix::from_matrix(&a, HalfFormat::Bf16);
+    assert_eq!(half.to_matrix().data[..2], [1.0, -0.5]);
+    assert!(half.to_matrix().data[3] > 9.9e9);
+
+    assert!(HalfMatrix::with_data(2, 2, HalfFormat::F16, vec![0; 3]).is_err());
+}
+
+#[test]
+fn products_of_small_integers_are_exact() {
+    let a = Matrix::with_data(2, 3, vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]).unwrap();
+    let b = Matrix::with_data(3, 2, vec![7.0, 8.0, 9.0, 10.0, 11.0, 12.0]).unwrap();
+
+    for format in [HalfFormat::F16, HalfFormat::Bf16] {
+        let a = HalfMatrix::from_matrix(&a, format);
+        let b = HalfMatrix::from_matrix(&b, format);
+
+        for output in [
+            Precision::F32,
+            Precision::Half(HalfFormat::F16),
+            Precision::Half(HalfFormat::Bf16),
+        ] {
+            let c = cpu::mixed_matrix_multiply(&a, &b, output).unwrap();
+            assert_eq!(c.precision(), output);
+            assert_eq!(c.to_matrix().data, vec![58.0, 64.0, 139.0, 154.0]);
+        }
+    }
+}
+
+#[test]
+fn output_is_rounded_once_from_the_f32_sum() {
+    // 1 + 2^-11 + 2^-11 is exact in f32 and rounds to 1 + 2^-10 in f16; rounding
+    // after each addition would give 1 (ties to even) instead
+    let a = Matrix::with_data(1, 3, vec![1.0, 2f32.powi(-11), 2f32.powi(-11)]).unwrap();
+    let b = Matrix::vector(vec![1.0; 3]);
+    let a = HalfMatrix::from_matrix(&a, HalfFormat::F16);
+    let b = HalfMatrix::from_matrix(&b, HalfFormat::F16);
+
+    let c = cpu::mixed_matrix_multiply(&a, &b, Precision::Half(HalfFormat::F16)).unwrap();
+    match c {
+        MixedMatrix::Half(c) => assert_eq!(c.data, vec![f32_to_f16(1.0 + 2f32.powi(-10))]),
+        MixedMatrix::F32(_) => panic!("expected a half-precision result"),
+    }
+}
+
+#[test]
+fn mixed_products_are_within_tolerance_of_f32() {
+    let a = sample_matrix(37, 64, 1);
+    let b = sample_matrix(64, 29, 2);
+    let reference = cpu::matrix_multiply(&a, &b).unwrap();
+
+    for format in [HalfFormat::F16, HalfFormat::Bf16] {
+        let half_a = HalfMatrix::from_matrix(&a, format);
+        let half_b = HalfMatrix::from_matrix(&b, format);
+
+        for output in [Precision::F32, Precision::Half(format)] {
+            let c = cpu::mixed_matrix_multiply(&half_a, &half_b, output).unwrap();
+            let report = precision::accuracy_report(&c, &reference, &a, &b, format).unwrap();
+            assert!(report.passed(), "{}", report);
+            assert_eq!(report.shape, (37, 29));
+        }
+    }
+
+    // bf16 keeps fewer mantissa bits, so its tolerance is looser
+    let f16 = precision::tolerance(&a, &b, HalfFormat::F16, Precision::F32);
+    let bf16 = precision::tolerance(&a, &b, HalfFormat::Bf16, Precision::F32);
+    assert!(bf16.rel > f16.rel && bf16.abs > f16.abs);
+}
+
+#[test]
+fn mixed_tolerance_grows_linearly_with_k() {
+    // Alternating signs cancel to an exact zero, so only the absolute bound applies
+    let k = 4096;
+    let a = Matrix::with_data(1, k, vec![1.0; k]).unwrap();
+    let b = Matrix::vector(
+        (0..k)
+            .map(|i| if i % 2 == 0 { 1.0 } else { -1.0 })
+            .collect(),
+    );
+    let reference = cpu::matrix_multiply(&a, &b).unwrap();
+    assert_eq!(reference.data, vec![0.0]);
+
+    let format = HalfFormat::F16;
+    let tolerance = precision::tolerance(&a, &b, format, Precision::F32);
+    // One f16 input rounding, one f32 accumulation rounding and half an f32 output ulp
+    // per term, with max|a| = max|b| = 1
+    let linear = (format.epsilon() as f64 + 1.5 * f32::EPSILON as f64) * k as f64;
+    assert!((tolerance.abs - linear).abs() <= 1e-12 * linear);
+
+    let report_for = |error: f64| {
+        let c = MixedMatrix::F32(Matrix::with_data(1, 1, vec![error as f32]).unwrap());
+        precision::accuracy_report(&c, &reference, &a, &b, format).unwrap()
+    };
+    assert!(report_for(linear * 0.99).passed());
+    let report = report_for(linear * 1.01);
+    assert!(!report.passed(), "{}", report);
+}
+
+#[test]
+fn mixed_products_check_their_operands() {
+    let a = HalfMatrix::from_matrix(&Matrix::new(2, 3), HalfFormat::F16);
+    let b = HalfMatrix::from_matrix(&Matrix::new(2, 3), HalfFormat::F16);
+    assert_eq!(
+        cpu::mixed_matrix_multiply(&a, &b, Precision::F32)
+            .unwrap_err()
+            .to_string(),
+        "Matrix dimensions incompatible for multiplication"
+    );
+
+    let b = HalfMatrix::from_matrix(&Matrix::new(3, 2), HalfFormat::Bf16);
+    assert_eq!(
+        cpu::mixed_matrix_multiply(&a, &b, Precision::F32)
+            .unwrap_err()
+            .to_string(),
+        "Operands must have the same format, not f16 and bf16"
+    );
+
+    // An empty inner dimension gives zeros
+    let a = HalfMatrix::from_matrix(&Matrix::new(2, 0), HalfFormat::Bf16);
+    let b = HalfMatrix::from_matrix(&Matrix::new(0, 3), HalfFormat::Bf16);
+    let c = cpu::mixed_matrix_multiply(&a, &b, Precision::Half(HalfFormat::Bf16)).unwrap();
+    assert_eq!(c.to_matrix().data, vec![0.0; 6]);
+}
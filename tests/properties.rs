@@ -0,0 +1,170 @@
+//! Algebraic laws of `Matrix` and the CPU operations, checked on random shapes and
+//! values with proptest.
+//!
+//! Shapes include empty (0-sized) and single-row/column matrices as well as column
+//! vectors built with `Matrix::vector`. When a property fails, proptest shrinks the
+//! input to a minimal case and records its seed as a `cc <hex>` line in
+//! `tests/properties.proptest-regressions`; those seeds are replayed before any new
+//! cases on every later run, so commit the file to keep the failure reproducible.
+//! Set `PROPTEST_CASES` to run more cases.
+
+use metal_matrix::{cpu, Matrix};
+use proptest::collection::vec;
+use proptest::prelude::*;
+
+/// Dimensions, biased towards the 0 and 1 edge cases.
+fn dim() -> impl Strategy<Value = usize> {
+    prop_oneof![Just(0), Just(1), 2..=20usize]
+}
+
+fn value() -> impl Strategy<Value = f32> {
+    -1.0e3f32..1.0e3
+}
+
+fn matrix(rows: usize, cols: usize) -> impl Strategy<Value = Matrix> {
+    vec(value(), rows * cols).prop_map(move |data| Matrix::with_data(rows, cols, data).unwrap())
+}
+
+/// Any matrix, including column vectors built with `Matrix::vector`.
+fn any_matrix() -> impl Strategy<Value = Matrix> {
+    prop_oneof![
+        4 => (dim(), dim()).prop_flat_map(|(rows, cols)| matrix(rows, cols)),
+        1 => vec(value(), 0..20).prop_map(Matrix::vector),
+    ]
+}
+
+/// Two matrices of the same shape.
+fn same_shape_pair() -> impl Strategy<Value = (Matrix, Matrix)> {
+    any_matrix().prop_flat_map(|a| {
+        let (rows, cols) = (a.rows, a.cols);
+        (Just(a), matrix(rows, cols))
+    })
+}
+
+/// An M x K and a K x N matrix.
+fn product_pair() -> impl Strategy<Value = (Matrix, Matrix)> {
+    (dim(), dim(), dim()).prop_flat_map(|(m, k, n)| (matrix(m, k), matrix(k, n)))
+}
+
+/// Asserts `|actual - expected| <= bound` element-wise for a per-element `bound`.
+fn assert_within(
+    actual: &Matrix,
+    expected: &Matrix,
+    bound: impl Fn(usize) -> f32,
+) -> Result<(), TestCaseError> {
+    prop_assert_eq!((actual.rows, actual.cols), (expected.rows, expected.cols));
+    for (i, (x, y)) in actual.data.iter().zip(&expected.data).enumerate() {
+        prop_assert!(
+            (x - y).abs() <= bound(i),
+            "element {}: {} != {} (bound {})",
+            i,
+            x,
+            y,
+            bound(i)
+        );
+    }
+    Ok(())
+}
+
+proptest! {
+    #[test]
+    fn adding_then_subtracting_is_identity((a, b) in same_shape_pair()) {
+        let result = cpu::matrix_subtract(&cpu::matrix_add(&a, &b).unwrap(), &b).unwrap();
+
+        // Two roundings, each at most half an ulp of an intermediate of size |a| + |b|
+        assert_within(&result, &a, |i| {
+            f32::EPSILON * (a.data[i].abs() + b.data[i].abs())
+        })?;
+    }
+
+    #[test]
+    fn transposing_twice_is_identity(a in any_matrix()) {
+        let result = cpu::matrix_transpose(&cpu::matrix_transpose(&a).unwrap()).unwrap();
+
+        prop_assert_eq!((result.rows, result.cols), (a.rows, a.cols));
+        prop_assert_eq!(result.data, a.data);
+    }
+
+    #[test]
+    fn transpose_of_product_is_reversed_product_of_transposes((a, b) in product_pair()) {
+        let left = cpu::matrix_transpose(&cpu::matrix_multiply(&a, &b).unwrap()).unwrap();
+        let right = cpu::matrix_multiply(&cpu::matrix_transpose(&b).unwrap(), &cpu::matrix_transpose(&a).unwrap()).unwrap();
+
+        // Both sum the same products; allow for a different accumulation order
+        let magnitudes = cpu::matrix_transpose(&cpu::matrix_multiply(&abs(&a), &abs(&b)).unwrap()).unwrap();
+        let k = a.cols as f32;
+        assert_within(&left, &right, |i| k * f32::EPSILON * magnitudes.data[i])?;
+    }
+
+    #[test]
+    fn identity_is_neutral_for_multiplication(a in any_matrix()) {
+        let left = cpu::matrix_multiply(&Matrix::identity(a.rows), &a).unwrap();
+        let right = cpu::matrix_multiply(&a, &Matrix::identity(a.cols)).unwrap();
+
+        prop_assert_eq!((left.rows, left.cols), (a.rows, a.cols));
+        prop_assert_eq!(&left.data, &a.data);
+        prop_assert_eq!((right.rows, right.cols), (a.rows, a.cols));
+        prop_assert_eq!(&right.data, &a.data);
+    }
+
+    #[test]
+    fn scalar_multiplication_distributes_over_addition(
+        (a, b) in same_shape_pair(),
+        s in -10.0f32..10.0,
+        t in -10.0f32..10.0,
+    ) {
+        // s * (A + B) = s * A + s * B
+        let left = cpu::matrix_scalar_multiply(s, &cpu::matrix_add(&a, &b).unwrap()).unwrap();
+        let right = cpu::matrix_add(
+            &cpu::matrix_scalar_multiply(s, &a).unwrap(),
+            &cpu::matrix_scalar_multiply(s, &b).unwrap(),
+        ).unwrap();
+        assert_within(&left, &right, |i| {
+            2.0 * f32::EPSILON * s.abs() * (a.data[i].abs() + b.data[i].abs())
+        })?;
+
+        // (s + t) * A = s * A + t * A
+        let left = cpu::matrix_scalar_multiply(s + t, &a).unwrap();
+        let right = cpu::matrix_add(
+            &cpu::matrix_scalar_multiply(s, &a).unwrap(),
+            &cpu::matrix_scalar_multiply(t, &a).unwrap(),
+        ).unwrap();
+        assert_within(&left, &right, |i| {
+            2.0 * f32::EPSILON * (s.abs() + t.abs()) * a.data[i].abs()
+        })?;
+    }
+
+    #[test]
+    fn rows_and_columns_agree_with_get(a in any_matrix()) {
+        for r in 0..a.rows {
+            let row = a.row(r);
+            prop_assert_eq!((row.rows, row.cols), (1, a.cols));
+            for c in 0..a.cols {
+                prop_assert_eq!(row.get(0, c), a.get(r, c));
+            }
+        }
+
+        for c in 0..a.cols {
+            let column = a.column(c);
+            prop_assert_eq!((column.rows, column.cols), (a.rows, 1));
+            for r in 0..a.rows {
+                prop_assert_eq!(column.get(r, 0), a.get(r, c));
+            }
+        }
+    }
+
+    #[test]
+    fn vectors_are_columns(data in vec(value(), 0..20)) {
+        let v = Matrix::vector(data.clone());
+
+        prop_assert_eq!((v.rows, v.cols), (data.len(), 1));
+        for (i, &x) in data.iter().enumerate() {
+            prop_assert_eq!(v.vector_get(i).unwrap(), x);
+            prop_assert_eq!(v.get(i, 0), x);
+        }
+    }
+}
+
+fn abs(m: &Matrix) -> Matrix {
+    Matrix::with_data(m.rows, m.cols, m.data.iter().map(|x| x.abs()).collect()).unwrap()
+}
@@ -0,0 +1,246 @@
+use metal_matrix::quantization::{dequantize, quantize, QuantizedMatrix, Scheme};
+use metal_matrix::verify::sample_matrix;
+use metal_matrix::{cpu, Matrix};
+
+const SCHEMES: [Scheme; 4] = [
+    Scheme::Int8PerRow,
+    Scheme::Int4Group(1),
+    Scheme::Int4Group(4),
+    Scheme::Int4Group(64),
+];
+
+/// Decodes a code from the packed data independently of `QuantizedMatrix::code`.
+fn unpack(q: &QuantizedMatrix, row: usize, col: usize) -> u8 {
+    match q.scheme {
+        Scheme::Int8PerRow => q.data[row * q.cols + col],
+        Scheme::Int4Group(_) => {
+            let byte = q.data[row * q.cols.div_ceil(2) + col / 2];
+            if col.is_multiple_of(2) {
+                byte & 0xf
+            } else {
+                byte >> 4
+            }
+        }
+    }
+}
+
+#[test]
+fn codes_span_the_range_of_each_row() {
+    let m = Matrix::with_data(3, 2, vec![0.0, 255.0, -128.0, 127.0, 0.0, 0.0]).unwrap();
+    let q = quantize(&m, Scheme::Int8PerRow).unwrap();
+
+    assert_eq!(q.scales, vec![1.0, 1.0, 1.0]);
+    assert_eq!(q.zero_points, vec![0, 128, 0]);
+    assert_eq!(q.data, vec![0, 255, 0, 255, 0, 0]);
+    assert_eq!(dequantize(&q).data, m.data);
+
+    // A positive row still includes zero in its range
+    let m = Matrix::with_data(1, 2, vec![15.0, 30.0]).unwrap();
+    let q = quantize(&m, Scheme::Int4Group(2)).unwrap();
+    assert_eq!((q.scales[0], q.zero_points[0]), (2.0, 0));
+    assert_eq!(q.data, vec![0xf0 | 0x8]);
+}
+
+#[test]
+fn groups_cover_partial_tails_of_rows() {
+    let m = sample_matrix(3, 10, 1);
+    let q = quantize(&m, Scheme::Int4Group(4)).unwrap();
+
+    // Groups of 4, 4 and 2 columns; rows of 10 codes in 5 bytes
+    assert_eq!(q.scales.len(), 9);
+    assert_eq!(q.zero_points.len(), 9);
+    assert_eq!(q.data.len(), 15);
+    assert_eq!(q.group(1, 9), 5);
+    assert_eq!(q.group(2, 0), 6);
+
+    // An odd number of columns leaves the last high nibble of each row unused
+    let q = quantize(&sample_matrix(2, 3, 2), Scheme::Int4Group(2)).unwrap();
+    assert_eq!(q.data.len(), 4);
+    assert_eq!(q.data[1] >> 4, 0);
+    assert_eq!(q.data[3] >> 4, 0);
+}
+
+#[test]
+fn dequantization_is_within_half_a_step() {
+    let m = sample_matrix(17, 130, 3);
+
+    for scheme in SCHEMES {
+        let q = quantize(&m, scheme).unwrap();
+        let restored = dequantize(&q);
+
+        for row in 0..m.rows {
+            for col in 0..m.cols {
+                let scale = q.scales[q.group(row, col)];
+                let error = (restored.get(row, col) - m.get(row, col)).abs();
+                assert!(
+                    error <= scale * 0.5 * (1.0 + 1e-5),
+                    "{:?} ({}, {}): error {} with scale {}",
+                    scheme,
+                    row,
+                    col,
+                    error,
+                    scale
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn dequantization_uses_integer_offsets_from_the_zero_point() {
+    let m = sample_matrix(9, 33, 4);
+
+    for scheme in SCHEMES {
+        let q = quantize(&m, scheme).unwrap();
+        let restored = dequantize(&q);
+
+        for row in 0..m.rows {
+            for col in 0..m.cols {
+                let code = unpack(&q, row, col);
+                assert_eq!(q.code(row, col), code);
+                assert!(code <= scheme.max_code());
+
+                let group = q.group(row, col);
+                let offset = code as i32 - q.zero_points[group] as i32;
+                assert_eq!(
+                    restored.get(row, col).to_bits(),
+                    (offset as f32 * q.scales[group]).to_bits()
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn zero_is_exactly_representable() {
+    let mut m = sample_matrix(4, 16, 5);
+    for row in 0..4 {
+        m.set(row, row * 3, 0.0);
+    }
+    // A row of zeros gets scale 1
+    for col in 0..16 {
+        m.set(3, col, 0.0);
+    }
+
+    for scheme in SCHEMES {
+        let q = quantize(&m, scheme).unwrap();
+        let restored = dequantize(&q);
+        for row in 0..4 {
+            assert_eq!(restored.get(row, row * 3), 0.0, "{:?}", scheme);
+        }
+        assert_eq!(q.scales[q.group(3, 0)], 1.0);
+        assert_eq!(restored.row(3).data, vec![0.0; 16]);
+    }
+}
+
+#[test]
+fn quantized_products_match_the_dequantized_weights_exactly() {
+    let x = sample_matrix(5, 70, 6);
+    let w = sample_matrix(12, 70, 7);
+
+    for scheme in SCHEMES {
+        let q = quantize(&w, scheme).unwrap();
+        let y = cpu::quantized_matrix_multiply(&x, &q).unwrap();
+        assert_eq!((y.rows, y.cols), (5, 12));
+
+        let weights = cpu::matrix_transpose(&dequantize(&q)).unwrap();
+        let expected = cpu::matrix_multiply_naive(&x, &weights).unwrap();
+        assert_eq!(y.data, expected.data, "{:?}", scheme);
+    }
+}
+
+#[test]
+fn int4_groups_use_less_memory() {
+    let m = sample_matrix(64, 256, 8);
+
+    let q = quantize(&m, Scheme::Int8PerRow).unwrap();
+    assert_eq!(q.size_in_bytes(), 64 * 256 + 64 * 5);
+
+    let q = quantize(&m, Scheme::Int4Group(64)).unwrap();
+    assert_eq!(q.size_in_bytes(), 64 * 128 + 64 * 4 * 5);
+    assert!(q.size_in_bytes() * 6 < m.data.len() * 4);
+}
+
+#[test]
+fn quantization_rejects_invalid_input() {
+    let m = Matrix::with_data(1, 2, vec![1.0, f32::NAN]).unwrap();
+    assert_eq!(
+        quantize(&m, Scheme::Int8PerRow).unwrap_err().to_string(),
+        "Cannot quantize a matrix with NaN or infinite values"
+    );
+    let m = Matrix::with_data(1, 2, vec![1.0, f32::INFINITY]).unwrap();
+    assert!(quantize(&m, Scheme::Int4Group(2)).is_err());
+
+    assert_eq!(
+        quantize(&Matrix::new(2, 2), Scheme::Int4Group(0))
+            .unwrap_err()
+            .to_string(),
+        "Quantization group size must be positive"
+    );
+
+    let q = quantize(&Matrix::new(4, 3), Scheme::Int8PerRow).unwrap();
+    assert_eq!(
+        cpu::quantized_matrix_multiply(&Matrix::new(2, 4), &q)
+            .unwrap_err()
+            .to_string(),
+        "Matrix dimensions incompatible for multiplication"
+    );
+}
+
+#[test]
+fn products_reject_inconsistent_quantized_matrices() {
+    let x = sample_matrix(2, 10, 9);
+    let valid = quantize(&sample_matrix(3, 10, 10), Scheme::Int4Group(4)).unwrap();
+    let error = |edit: &dyn Fn(&mut QuantizedMatrix)| {
+        let mut w = valid.clone();
+        edit(&mut w);
+        cpu::quantized_matrix_multiply(&x, &w)
+            .unwrap_err()
+            .to_string()
+    };
+
+    assert_eq!(
+        error(&|w| w.scheme = Scheme::Int4Group(0)),
+        "Quantization group size must be positive"
+    );
+    assert_eq!(
+        error(&|w| w.data.truncate(14)),
+        "Quantized matrix has 14 data bytes, expected 15 for a 3x10 Int4Group(4) matrix"
+    );
+    // Int8 codes take twice the bytes of int4 codes
+    assert_eq!(
+        error(&|w| w.scheme = Scheme::Int8PerRow),
+        "Quantized matrix has 15 data bytes, expected 30 for a 3x10 Int8PerRow matrix"
+    );
+    assert_eq!(
+        error(&|w| w.scales.push(1.0)),
+        "Quantized matrix has 10 scales, expected 9 for a 3x10 Int4Group(4) matrix"
+    );
+    assert_eq!(
+        error(&|w| {
+            w.zero_points.pop();
+        }),
+        "Quantized matrix has 8 zero points, expected 9 for a 3x10 Int4Group(4) matrix"
+    );
+    assert_eq!(
+        error(&|w| w.rows = 4),
+        "Quantized matrix has 15 data bytes, expected 20 for a 4x10 Int4Group(4) matrix"
+    );
+}
+
+#[test]
+fn quantizes_empty_matrices() {
+    for scheme in SCHEMES {
+        for (rows, cols) in [(0, 0), (0, 5), (3, 0)] {
+            let q = quantize(&Matrix::new(rows, cols), scheme).unwrap();
+            assert!(q.data.is_empty() && q.scales.is_empty());
+            let restored = dequantize(&q);
+            assert_eq!((restored.rows, restored.cols), (rows, cols));
+        }
+
+        // An empty inner dimension gives zeros
+        let q = quantize(&Matrix::new(3, 0), scheme).unwrap();
+        let y = cpu::quantized_matrix_multiply(&Matrix::new(2, 0), &q).unwrap();
+        assert_eq!((y.rows, y.cols, y.data), (2, 3, vec![0.0; 6]));
+    }
+}
@@ -0,0 +1,124 @@
+use metal_matrix::io::safetensors::{self, SafetensorsFile};
+use metal_matrix::Matrix;
+use std::path::PathBuf;
+
+#[rustfmt::skip]
+const MATRIX: [f32; 6] = [
+    1.5, -2.0, 3.25,
+    4.0, 0.125, -6.5,
+];
+
+fn fixture() -> SafetensorsFile {
+    let path: PathBuf = [
+        env!("CARGO_MANIFEST_DIR"),
+        "tests",
+        "fixtures",
+        "safetensors",
+        "weights.safetensors",
+    ]
+    .iter()
+    .collect();
+    SafetensorsFile::open(path).unwrap()
+}
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("metal_matrix_{}_{}", std::process::id(), name))
+}
+
+#[test]
+fn lists_tensors_in_file_order() {
+    assert_eq!(
+        fixture().names(),
+        vec!["f32", "f16", "bf16", "bias", "conv", "step"]
+    );
+}
+
+#[test]
+fn loads_f32_tensor() {
+    let matrix = fixture().matrix("f32").unwrap();
+
+    assert_eq!((matrix.rows, matrix.cols), (2, 3));
+    assert_eq!(matrix.data, MATRIX);
+}
+
+#[test]
+fn converts_half_precision_tensors() {
+    let file = fixture();
+
+    let f16 = file.matrix("f16").unwrap();
+    assert_eq!((f16.rows, f16.cols), (2, 3));
+    assert_eq!(f16.data, MATRIX);
+
+    let bf16 = file.matrix("bf16").unwrap();
+    assert_eq!((bf16.rows, bf16.cols), (3, 2));
+    assert_eq!(bf16.data, MATRIX);
+}
+
+#[test]
+fn loads_one_dimensional_tensors_as_vectors() {
+    let file = fixture();
+
+    assert_eq!(file.shape("bias").unwrap(), (3, 1));
+    let bias = file.matrix("bias").unwrap();
+    assert!(bias.is_vector());
+    assert_eq!(bias.data, vec![0.5, -0.25, 1.0]);
+}
+
+#[test]
+fn rejects_unsupported_tensors() {
+    let file = fixture();
+
+    let error = file.matrix("conv").unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Tensor 'conv' has shape [1, 1, 2], expected a 1-D or 2-D tensor"
+    );
+
+    let error = file.matrix("step").unwrap_err();
+    assert_eq!(error.to_string(), "Tensor 'step' has unsupported dtype I64");
+
+    let error = file.matrix("missing").unwrap_err();
+    assert_eq!(error.to_string(), "No tensor named 'missing'");
+}
+
+#[test]
+fn written_files_round_trip() {
+    let path = temp_path("round_trip.safetensors");
+    let weights = Matrix::with_data(2, 3, MATRIX.to_vec()).unwrap();
+    let bias = Matrix::vector(vec![0.1, 1.0 / 3.0]);
+
+    safetensors::write_file(&path, [("layer.weight", &weights), ("layer.bias", &bias)]).unwrap();
+    let file = SafetensorsFile::open(&path).unwrap();
+
+    let mut names = file.names();
+    names.sort();
+    assert_eq!(names, vec!["layer.bias", "layer.weight"]);
+    assert_eq!(file.shape("layer.weight").unwrap(), (2, 3));
+    assert_eq!(file.matrix("layer.weight").unwrap().data, weights.data);
+    assert_eq!(file.shape("layer.bias").unwrap(), (2, 1));
+    assert_eq!(file.matrix("layer.bias").unwrap().data, bias.data);
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn writer_output_matches_file_output() {
+    let path = temp_path("writer.safetensors");
+    let matrix = Matrix::identity(3);
+
+    let mut bytes = Vec::new();
+    safetensors::write(&mut bytes, [("identity", &matrix)]).unwrap();
+    safetensors::write_file(&path, [("identity", &matrix)]).unwrap();
+
+    assert_eq!(std::fs::read(&path).unwrap(), bytes);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn rejects_invalid_files() {
+    let path = temp_path("invalid.safetensors");
+    std::fs::write(&path, b"\x10\x00\x00\x00\x00\x00\x00\x00not json").unwrap();
+
+    assert!(SafetensorsFile::open(&path).is_err());
+    std::fs::remove_file(path).unwrap();
+}
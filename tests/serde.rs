@@ -0,0 +1,79 @@
+#![cfg(feature = "serde")]
+
+use metal_matrix::Matrix;
+
+#[test]
+fn json_uses_nested_rows() {
+    let matrix = Matrix::with_data(2, 3, vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]).unwrap();
+
+    let json = serde_json::to_string(&matrix).unwrap();
+    assert_eq!(
+        json,
+        r#"{"rows":2,"cols":3,"data":[[1.0,2.0,3.0],[4.0,5.0,6.0]]}"#
+    );
+
+    let copy: Matrix = serde_json::from_str(&json).unwrap();
+    assert_eq!((copy.rows, copy.cols), (2, 3));
+    assert_eq!(copy.data, matrix.data);
+}
+
+#[test]
+fn json_accepts_flat_data() {
+    let matrix: Matrix = serde_json::from_str(r#"{"rows":2,"cols":2,"data":[1,2,3,4]}"#).unwrap();
+
+    assert_eq!((matrix.rows, matrix.cols), (2, 2));
+    assert_eq!(matrix.data, vec![1.0, 2.0, 3.0, 4.0]);
+}
+
+#[test]
+fn json_round_trips_empty_matrices() {
+    for (rows, cols) in [(0, 0), (0, 3), (3, 0)] {
+        let matrix = Matrix::new(rows, cols);
+
+        let json = serde_json::to_string(&matrix).unwrap();
+        let copy: Matrix = serde_json::from_str(&json).unwrap();
+
+        assert_eq!((copy.rows, copy.cols), (rows, cols), "{}", json);
+    }
+}
+
+#[test]
+fn json_rejects_mismatched_dimensions() {
+    let error =
+        serde_json::from_str::<Matrix>(r#"{"rows":2,"cols":2,"data":[1,2,3]}"#).unwrap_err();
+    assert!(error
+        .to_string()
+        .starts_with("Data length does not match matrix dimensions"));
+
+    let error =
+        serde_json::from_str::<Matrix>(r#"{"rows":2,"cols":2,"data":[[1,2],[3]]}"#).unwrap_err();
+    assert!(error
+        .to_string()
+        .starts_with("expected 2 columns in row 1, found 1"));
+
+    let error =
+        serde_json::from_str::<Matrix>(r#"{"rows":3,"cols":1,"data":[[1],[2]]}"#).unwrap_err();
+    assert!(error.to_string().starts_with("expected 3 rows, found 2"));
+}
+
+#[test]
+fn binary_encoding_is_flat() {
+    let matrix = Matrix::with_data(2, 2, vec![1.0, 2.0, 3.0, 4.0]).unwrap();
+
+    let bytes = postcard::to_allocvec(&matrix).unwrap();
+    // Two varint dimensions, the length prefix and four 4-byte floats
+    assert_eq!(bytes.len(), 3 + 4 * 4);
+
+    let copy: Matrix = postcard::from_bytes(&bytes).unwrap();
+    assert_eq!((copy.rows, copy.cols), (2, 2));
+    assert_eq!(copy.data, matrix.data);
+}
+
+#[test]
+fn binary_rejects_mismatched_dimensions() {
+    let matrix = Matrix::with_data(1, 3, vec![1.0, 2.0, 3.0]).unwrap();
+    let mut bytes = postcard::to_allocvec(&matrix).unwrap();
+    bytes[0] = 2;
+
+    assert!(postcard::from_bytes::<Matrix>(&bytes).is_err());
+}
@@ -0,0 +1,321 @@
+mod common;
+
+use common::{dominant_matrix, test_matrix};
+use metal_matrix::{
+    bicgstab, conjugate_gradient, cpu, gmres, preconditioned_conjugate_gradient, CpuBackend,
+    FnOperator, IncompleteCholesky, Jacobi, Matrix, Preconditioner, SolverOptions, SolverResult,
+    SolverStatus,
+};
+
+fn options(tolerance: f32, max_iterations: usize) -> SolverOptions {
+    SolverOptions {
+        tolerance,
+        max_iterations,
+    }
+}
+
+/// A symmetric positive definite matrix: Bᵀ * B + n * I.
+fn spd_matrix(n: usize) -> Matrix {
+    let b = test_matrix(n, n, 7);
+    let mut a = cpu::matrix_multiply(&cpu::matrix_transpose(&b).unwrap(), &b).unwrap();
+    for i in 0..n {
+        a.set(i, i, a.get(i, i) + n as f32);
+    }
+    a
+}
+
+/// The tridiagonal matrix with `diagonal` on the diagonal and -1 beside it.
+fn tridiagonal(n: usize, diagonal: f32) -> Matrix {
+    let mut a = Matrix::new(n, n);
+    for i in 0..n {
+        a.set(i, i, diagonal);
+        if i > 0 {
+            a.set(i, i - 1, -1.0);
+            a.set(i - 1, i, -1.0);
+        }
+    }
+    a
+}
+
+fn rhs(n: usize) -> Matrix {
+    Matrix::vector(test_matrix(n, 1, 2).data)
+}
+
+/// ||b - A * x|| / ||b||, computed independently of the solver.
+fn relative_residual(a: &Matrix, x: &Matrix, b: &Matrix) -> f32 {
+    let r = cpu::matrix_subtract(b, &cpu::matrix_multiply(a, x).unwrap()).unwrap();
+    let norm = |v: &Matrix| v.data.iter().map(|x| x * x).sum::<f32>().sqrt();
+    norm(&r) / norm(b)
+}
+
+fn assert_solved(result: &SolverResult, a: &Matrix, b: &Matrix, tolerance: f32, what: &str) {
+    assert!(result.converged(), "{}: {:?}", what, result.status);
+    assert_eq!(
+        result.residual_history.len(),
+        result.iterations + 1,
+        "{}",
+        what
+    );
+    assert_eq!(result.residual_history[0], 1.0, "{}", what);
+    assert!(
+        *result.residual_history.last().unwrap() <= tolerance,
+        "{}",
+        what
+    );
+    let residual = relative_residual(a, &result.solution, b);
+    assert!(residual <= 10.0 * tolerance, "{}: {}", what, residual);
+}
+
+#[test]
+fn krylov_solvers_converge_on_suitable_systems() {
+    let n = 40;
+    let b = rhs(n);
+    let opts = options(1e-5, 500);
+
+    let spd = spd_matrix(n);
+    let result = conjugate_gradient(&CpuBackend, &spd, &b, &opts).unwrap();
+    assert_solved(&result, &spd, &b, 1e-5, "cg");
+
+    let general = dominant_matrix(n);
+    let result = bicgstab(&CpuBackend, &general, &b, &opts).unwrap();
+    assert_solved(&result, &general, &b, 1e-5, "bicgstab");
+
+    let result = gmres(&CpuBackend, &general, &b, n, &opts).unwrap();
+    assert_solved(&result, &general, &b, 1e-5, "gmres");
+}
+
+#[test]
+fn gmres_restarts_until_converged() {
+    let n = 60;
+    let a = dominant_matrix(n);
+    let b = rhs(n);
+    let opts = options(1e-6, 500);
+
+    let full = gmres(&CpuBackend, &a, &b, n, &opts).unwrap();
+    assert_solved(&full, &a, &b, 1e-6, "gmres(n)");
+
+    for restart in [1, 2, 5] {
+        let what = format!("gmres({})", restart);
+        let result = gmres(&CpuBackend, &a, &b, restart, &opts).unwrap();
+        assert_solved(&result, &a, &b, 1e-6, &what);
+
+        // Several cycles were needed, and restarting never helps
+        assert!(result.iterations > restart, "{}", what);
+        assert!(result.iterations >= full.iterations, "{}", what);
+        for (x, y) in result.solution.data.iter().zip(&full.solution.data) {
+            assert!((x - y).abs() <= 1e-4 * (1.0 + y.abs()), "{}", what);
+        }
+    }
+}
+
+#[test]
+fn preconditioners_reduce_iterations() {
+    // A badly scaled SPD matrix D * T * D, which Jacobi scaling undoes
+    let n = 50;
+    let t = tridiagonal(n, 4.0);
+    let mut a = t.clone();
+    for i in 0..n {
+        for j in 0..n {
+            let scale = |k: usize| 10f32.powi((k % 4) as i32);
+            a.set(i, j, t.get(i, j) * scale(i) * scale(j));
+        }
+    }
+    let b = rhs(n);
+    let opts = options(1e-5, 1000);
+
+    let plain = conjugate_gradient(&CpuBackend, &a, &b, &opts).unwrap();
+    let jacobi = Jacobi::new(&a).unwrap();
+    let preconditioned =
+        preconditioned_conjugate_gradient(&CpuBackend, &a, &b, &jacobi, &opts).unwrap();
+    assert!(preconditioned.converged());
+    assert!(
+        preconditioned.iterations < plain.iterations,
+        "{} >= {}",
+        preconditioned.iterations,
+        plain.iterations
+    );
+
+    // The incomplete factor of a tridiagonal matrix is its exact Cholesky factor
+    let ic = IncompleteCholesky::new(&t).unwrap();
+    let lower = ic.factor();
+    let product = cpu::matrix_multiply(lower, &cpu::matrix_transpose(lower).unwrap()).unwrap();
+    for (x, y) in product.data.iter().zip(&t.data) {
+        assert!((x - y).abs() <= 1e-5);
+    }
+    let result = preconditioned_conjugate_gradient(&CpuBackend, &t, &b, &ic, &opts).unwrap();
+    assert_solved(&result, &t, &b, 1e-5, "ic");
+    assert!(result.iterations <= 2);
+}
+
+#[test]
+fn closures_act_as_operators() {
+    let n = 50;
+    let dense = tridiagonal(n, 3.0);
+    let operator = FnOperator::new(n, |x: &Matrix| {
+        let data = (0..n)
+            .map(|i| {
+                let left = if i > 0 { x.data[i - 1] } else { 0.0 };
+                let right = if i + 1 < n { x.data[i + 1] } else { 0.0 };
+                3.0 * x.data[i] - left - right
+            })
+            .collect();
+        Ok(Matrix::vector(data))
+    });
+    let b = rhs(n);
+    let opts = options(1e-6, 200);
+
+    // The same system through the dense matrix
+    let expected = conjugate_gradient(&CpuBackend, &dense, &b, &opts)
+        .unwrap()
+        .solution;
+    for result in [
+        conjugate_gradient(&CpuBackend, &operator, &b, &opts).unwrap(),
+        bicgstab(&CpuBackend, &operator, &b, &opts).unwrap(),
+        gmres(&CpuBackend, &operator, &b, 10, &opts).unwrap(),
+    ] {
+        assert_solved(&result, &dense, &b, 1e-6, "closure");
+        for (x, y) in result.solution.data.iter().zip(&expected.data) {
+            assert!((x - y).abs() <= 1e-4 * (1.0 + y.abs()));
+        }
+    }
+
+    // An operator returning the wrong size is an error, not a wrong answer
+    let wrong = FnOperator::new(n, |_: &Matrix| Ok(Matrix::vector(vec![0.0; 3])));
+    assert!(conjugate_gradient(&CpuBackend, &wrong, &b, &opts).is_err());
+}
+
+#[test]
+fn reports_hitting_the_iteration_limit() {
+    let n = 50;
+    let a = tridiagonal(n, 2.0);
+    let b = rhs(n);
+    let opts = options(1e-6, 3);
+
+    for (what, result) in [
+        (
+            "cg",
+            conjugate_gradient(&CpuBackend, &a, &b, &opts).unwrap(),
+        ),
+        ("bicgstab", bicgstab(&CpuBackend, &a, &b, &opts).unwrap()),
+        ("gmres", gmres(&CpuBackend, &a, &b, 2, &opts).unwrap()),
+    ] {
+        assert_eq!(result.status, SolverStatus::MaxIterations, "{}", what);
+        assert!(!result.converged(), "{}", what);
+        assert!(*result.residual_history.last().unwrap() > 1e-6, "{}", what);
+        assert!(
+            result.solution.data.iter().all(|x| x.is_finite()),
+            "{}",
+            what
+        );
+    }
+}
+
+#[test]
+fn reports_breakdown() {
+    // GMRES: A * b = 0, so A is singular on the Krylov subspace of b
+    let singular = Matrix::with_data(2, 2, vec![1.0, 0.0, 0.0, 0.0]).unwrap();
+    let b = Matrix::vector(vec![0.0, 1.0]);
+    let result = gmres(&CpuBackend, &singular, &b, 2, &SolverOptions::default()).unwrap();
+    assert_eq!(result.status, SolverStatus::Breakdown);
+    assert!(result.solution.data.iter().all(|x| x.is_finite()));
+    assert!(result.residual_history.iter().all(|x| x.is_finite()));
+
+    // A nilpotent operator breaks down on the second step instead
+    let nilpotent = Matrix::with_data(2, 2, vec![0.0, 1.0, 0.0, 0.0]).unwrap();
+    let result = gmres(&CpuBackend, &nilpotent, &b, 2, &SolverOptions::default()).unwrap();
+    assert_eq!(result.status, SolverStatus::Breakdown);
+    assert!(result.solution.data.iter().all(|x| x.is_finite()));
+
+    // CG: pᵀ * A * p = 0 for an indefinite matrix
+    let indefinite = Matrix::with_data(2, 2, vec![1.0, 0.0, 0.0, -1.0]).unwrap();
+    let b = Matrix::vector(vec![1.0, 1.0]);
+    let result =
+        conjugate_gradient(&CpuBackend, &indefinite, &b, &SolverOptions::default()).unwrap();
+    assert_eq!(result.status, SolverStatus::Breakdown);
+}
+
+/// A diagonal preconditioner applying the given factors, which need not be positive.
+struct Diagonal(Vec<f32>);
+
+impl Preconditioner for Diagonal {
+    fn apply(&self, r: &Matrix) -> anyhow::Result<Matrix> {
+        Ok(Matrix::vector(
+            r.data.iter().zip(&self.0).map(|(x, d)| x * d).collect(),
+        ))
+    }
+}
+
+#[test]
+fn reports_breakdown_of_the_preconditioner() {
+    let a = Matrix::identity(2);
+    let b = Matrix::vector(vec![1.0, 1.0]);
+
+    // rᵀ * z = 0 for an indefinite preconditioner, a non-finite one gives NaN, and a
+    // singular one gives zero once the residual lies in its null space
+    let cases = [
+        (vec![1.0, -1.0], &a),
+        (vec![1.0, f32::NAN], &a),
+        (
+            vec![1.0, 0.0],
+            &Matrix::with_data(2, 2, vec![1.0, 0.0, 0.0, 2.0]).unwrap(),
+        ),
+    ];
+    for (factors, a) in cases {
+        let preconditioner = Diagonal(factors.clone());
+        let result = preconditioned_conjugate_gradient(
+            &CpuBackend,
+            a,
+            &b,
+            &preconditioner,
+            &SolverOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(result.status, SolverStatus::Breakdown, "{:?}", factors);
+        assert!(
+            result.solution.data.iter().all(|x| x.is_finite()),
+            "{:?}",
+            factors
+        );
+    }
+}
+
+#[test]
+fn zero_right_hand_side_converges_immediately() {
+    let a = spd_matrix(5);
+    let b = Matrix::vector(vec![0.0; 5]);
+    let result = gmres(&CpuBackend, &a, &b, 3, &SolverOptions::default()).unwrap();
+    assert!(result.converged());
+    assert_eq!(result.iterations, 0);
+    assert_eq!(result.solution.data, vec![0.0; 5]);
+}
+
+#[test]
+fn rejects_invalid_input() {
+    let a = spd_matrix(4);
+    let opts = SolverOptions::default();
+
+    let error = conjugate_gradient(&CpuBackend, &a, &Matrix::vector(vec![1.0; 3]), &opts);
+    assert_eq!(
+        error.unwrap_err().to_string(),
+        "Expected a 4x1 vector, got a 3x1 matrix"
+    );
+    assert_eq!(
+        gmres(&CpuBackend, &a, &rhs(4), 0, &opts)
+            .unwrap_err()
+            .to_string(),
+        "GMRES restart length must be at least 1"
+    );
+
+    let mut zero_diagonal = a.clone();
+    zero_diagonal.set(2, 2, 0.0);
+    assert!(Jacobi::new(&zero_diagonal).is_err());
+    assert!(Jacobi::new(&Matrix::new(2, 3)).is_err());
+
+    let indefinite = Matrix::with_data(2, 2, vec![1.0, 2.0, 2.0, 1.0]).unwrap();
+    assert_eq!(
+        IncompleteCholesky::new(&indefinite)
+            .unwrap_err()
+            .to_string(),
+        "Incomplete Cholesky breakdown: matrix is not positive definite"
+    );
+}
@@ -0,0 +1,197 @@
+mod common;
+
+use common::{assert_close, test_matrix};
+use metal_matrix::{cpu, sparse_add, CooMatrix, CsrMatrix, Matrix};
+
+/// A matrix with roughly a third of its elements stored, including empty rows and
+/// columns (row 2 and column 1 are always zero).
+fn sparse_dense(rows: usize, cols: usize, seed: usize) -> Matrix {
+    let mut m = test_matrix(rows, cols, seed);
+    for row in 0..rows {
+        for col in 0..cols {
+            if row == 2 || col == 1 || !(row * 7 + col * 3 + seed).is_multiple_of(3) {
+                m.set(row, col, 0.0);
+            }
+        }
+    }
+    m
+}
+
+/// Checks the CSR invariants that `CsrMatrix::new` validates.
+fn assert_valid(csr: &CsrMatrix) {
+    let checked = CsrMatrix::new(
+        csr.rows,
+        csr.cols,
+        csr.row_offsets.clone(),
+        csr.col_indices.clone(),
+        csr.values.clone(),
+    );
+    assert!(checked.is_ok(), "{:?}", checked.unwrap_err());
+}
+
+#[test]
+fn formats_convert_without_loss() {
+    for (rows, cols) in [(6, 9), (9, 6), (1, 1), (0, 4), (4, 0)] {
+        let dense = sparse_dense(rows, cols, rows + cols);
+        let csr = CsrMatrix::from_dense(&dense).unwrap();
+        assert_valid(&csr);
+        assert_eq!(csr.nnz(), dense.data.iter().filter(|&&x| x != 0.0).count());
+        assert_eq!(csr.to_dense().data, dense.data);
+
+        let coo = csr.to_coo();
+        assert_eq!(coo.nnz(), csr.nnz());
+        assert_eq!(coo.to_csr().unwrap().to_dense().data, dense.data);
+
+        let csc = csr.to_csc();
+        assert_eq!((csc.rows, csc.cols, csc.nnz()), (rows, cols, csr.nnz()));
+        assert_eq!(csc.col_offsets.len(), cols + 1);
+        for col in 0..cols {
+            let range = csc.col_offsets[col] as usize..csc.col_offsets[col + 1] as usize;
+            for i in range {
+                let row = csc.row_indices[i] as usize;
+                assert_eq!(csc.values[i], dense.get(row, col));
+            }
+        }
+
+        let back = csc.to_csr();
+        assert_eq!(back.row_offsets, csr.row_offsets);
+        assert_eq!(back.col_indices, csr.col_indices);
+        assert_eq!(back.values, csr.values);
+        assert_eq!(coo.to_csc().unwrap().to_csr().values, csr.values);
+    }
+}
+
+#[test]
+fn coo_sums_duplicates_in_any_order() {
+    let mut coo = CooMatrix::new(4, 3);
+    for (row, col, value) in [
+        (3, 2, 1.0),
+        (0, 1, 2.0),
+        (3, 2, 0.5),
+        (0, 0, -1.0),
+        (0, 1, 4.0),
+        (3, 0, 7.0),
+        (3, 2, 0.25),
+    ] {
+        coo.push(row, col, value).unwrap();
+    }
+    assert_eq!(coo.nnz(), 7);
+
+    let csr = coo.to_csr().unwrap();
+    assert_valid(&csr);
+    // Rows 1 and 2 have no entries
+    assert_eq!(csr.row_offsets, vec![0, 2, 2, 2, 4]);
+    assert_eq!(csr.col_indices, vec![0, 1, 0, 2]);
+    assert_eq!(csr.values, vec![-1.0, 6.0, 7.0, 1.75]);
+    assert_eq!(csr.get(1, 1), 0.0);
+    assert_eq!(csr.get(3, 1), 0.0);
+
+    assert!(coo.push(4, 0, 1.0).is_err());
+    assert!(coo.push(0, 3, 1.0).is_err());
+    assert_eq!(coo.nnz(), 7);
+}
+
+#[test]
+fn transpose_matches_dense_transpose() {
+    for (rows, cols) in [(7, 5), (5, 7), (3, 0)] {
+        let dense = sparse_dense(rows, cols, 4);
+        let csr = CsrMatrix::from_dense(&dense).unwrap();
+        let transpose = csr.transpose();
+        assert_valid(&transpose);
+        assert_eq!((transpose.rows, transpose.cols), (cols, rows));
+        assert_eq!(
+            transpose.to_dense().data,
+            cpu::matrix_transpose(&dense).unwrap().data
+        );
+        assert_eq!(transpose.transpose().col_indices, csr.col_indices);
+    }
+}
+
+#[test]
+fn addition_merges_rows() {
+    let a = sparse_dense(8, 6, 1);
+    let b = sparse_dense(8, 6, 2);
+    let sum = sparse_add(
+        &CsrMatrix::from_dense(&a).unwrap(),
+        &CsrMatrix::from_dense(&b).unwrap(),
+    )
+    .unwrap();
+    assert_valid(&sum);
+    assert_eq!(sum.to_dense().data, cpu::matrix_add(&a, &b).unwrap().data);
+
+    // Cancelling entries are dropped and an empty operand is the identity
+    let csr = CsrMatrix::from_dense(&a).unwrap();
+    let negated = CsrMatrix::from_dense(&cpu::matrix_scalar_multiply(-1.0, &a).unwrap()).unwrap();
+    let zero = sparse_add(&csr, &negated).unwrap();
+    assert_eq!(zero.nnz(), 0);
+    assert_eq!(zero.row_offsets, vec![0; 9]);
+    assert_eq!(
+        sparse_add(&csr, &CsrMatrix::zeros(8, 6)).unwrap().values,
+        csr.values
+    );
+
+    assert!(sparse_add(&csr, &CsrMatrix::zeros(6, 8)).is_err());
+}
+
+#[test]
+fn cpu_products_match_dense_products() {
+    let dense = sparse_dense(9, 13, 3);
+    let a = CsrMatrix::from_dense(&dense).unwrap();
+
+    let x = test_matrix(13, 1, 5);
+    let expected = cpu::matrix_multiply(&dense, &x).unwrap();
+    let y = cpu::spmv(&a, &x).unwrap();
+    assert_close(&y, &expected, 1e-6, "spmv");
+    // Row 2 has no entries
+    assert_eq!(y.get(2, 0), 0.0);
+
+    for cols in [1, 4, 17] {
+        let b = test_matrix(13, cols, 6);
+        let expected = cpu::matrix_multiply(&dense, &b).unwrap();
+        assert_close(&cpu::spmm(&a, &b).unwrap(), &expected, 1e-6, "spmm");
+    }
+
+    let empty = cpu::spmm(&CsrMatrix::zeros(3, 13), &test_matrix(13, 2, 1)).unwrap();
+    assert_eq!(empty.data, vec![0.0; 6]);
+
+    assert!(cpu::spmv(&a, &test_matrix(13, 2, 1)).is_err());
+    assert!(cpu::spmv(&a, &test_matrix(12, 1, 1)).is_err());
+    assert!(cpu::spmm(&a, &test_matrix(9, 2, 1)).is_err());
+}
+
+#[test]
+fn new_rejects_inconsistent_arrays() {
+    let error = |offsets: Vec<u32>, cols: Vec<u32>, values: Vec<f32>| {
+        CsrMatrix::new(2, 3, offsets, cols, values)
+            .unwrap_err()
+            .to_string()
+    };
+
+    assert!(CsrMatrix::new(2, 3, vec![0, 1, 2], vec![2, 0], vec![1.0, 2.0]).is_ok());
+
+    // An offset beyond the non-zeros, followed by a decrease to the total
+    assert_eq!(
+        error(vec![0, 5, 2], vec![0, 1], vec![1.0, 2.0]),
+        "Row offsets must be non-decreasing"
+    );
+    assert_eq!(
+        error(vec![0, 2, 1], vec![0, 1], vec![1.0]),
+        "Row offsets do not match the number of non-zeros"
+    );
+    assert_eq!(
+        error(vec![1, 1, 2], vec![0, 1], vec![1.0, 2.0]),
+        "Row offsets must have rows + 1 entries starting at 0"
+    );
+    assert_eq!(
+        error(vec![0, 2], vec![0, 1], vec![1.0, 2.0]),
+        "Row offsets must have rows + 1 entries starting at 0"
+    );
+    assert_eq!(
+        error(vec![0, 2, 2], vec![1, 1], vec![1.0, 2.0]),
+        "Column indices must be strictly increasing within row 0"
+    );
+    assert_eq!(
+        error(vec![0, 0, 1], vec![3], vec![1.0]),
+        "Column index out of bounds in row 1"
+    );
+}
@@ -0,0 +1,191 @@
+//! Triangular solves and products against dense references, for every combination of
+//! `Side`, `Transpose`, `Uplo` and `Diag`, on sizes below and above the block size of
+//! the blocked solve.
+
+mod common;
+
+use common::{assert_close, test_matrix};
+use metal_matrix::{
+    cpu, triangular_multiply, triangular_solve, CpuBackend, Diag, Matrix, Side, Transpose, Uplo,
+};
+
+/// Sizes within one block, exactly two blocks, and two full blocks plus a partial one.
+const SIZES: [usize; 3] = [5, 128, 150];
+
+fn combinations() -> Vec<(Side, Transpose, Uplo, Diag)> {
+    let mut combinations = Vec::new();
+    for side in [Side::Left, Side::Right] {
+        for trans in [Transpose::NoTrans, Transpose::Trans] {
+            for uplo in [Uplo::Lower, Uplo::Upper] {
+                for diag in [Diag::NonUnit, Diag::Unit] {
+                    combinations.push((side, trans, uplo, diag));
+                }
+            }
+        }
+    }
+    combinations
+}
+
+/// A well-conditioned n × n matrix: small off-diagonal entries and a diagonal of at
+/// least 2 in magnitude. Both triangles are filled, so a solve that reads the wrong
+/// one gives a wrong answer.
+fn triangular_matrix(n: usize) -> Matrix {
+    let mut t = cpu::matrix_scalar_multiply(1.0 / (2 * n) as f32, &test_matrix(n, n, 5)).unwrap();
+    for i in 0..n {
+        t.set(i, i, 2.0 + (i % 3) as f32);
+    }
+    t
+}
+
+/// The dense op(T): the referenced triangle of `t`, with a unit diagonal for
+/// `Diag::Unit`, transposed for `Transpose::Trans`.
+fn dense_op(t: &Matrix, uplo: Uplo, trans: Transpose, diag: Diag) -> Matrix {
+    let mut triangle = match uplo {
+        Uplo::Lower => t.lower_triangle(0),
+        Uplo::Upper => t.upper_triangle(0),
+    };
+    if diag == Diag::Unit {
+        for i in 0..t.rows {
+            triangle.set(i, i, 1.0);
+        }
+    }
+    match trans {
+        Transpose::NoTrans => triangle,
+        Transpose::Trans => cpu::matrix_transpose(&triangle).unwrap(),
+    }
+}
+
+fn rhs(n: usize, side: Side) -> Matrix {
+    match side {
+        Side::Left => test_matrix(n, 7, 1),
+        Side::Right => test_matrix(7, n, 1),
+    }
+}
+
+/// op(T) * x or x * op(T) with the dense op(T).
+fn apply(op: &Matrix, x: &Matrix, side: Side) -> Matrix {
+    match side {
+        Side::Left => cpu::matrix_multiply(op, x).unwrap(),
+        Side::Right => cpu::matrix_multiply(x, op).unwrap(),
+    }
+}
+
+#[test]
+fn multiplies_by_every_kind_of_triangle() {
+    for n in SIZES {
+        let t = triangular_matrix(n);
+        for (side, trans, uplo, diag) in combinations() {
+            let what = format!("n = {}, {:?} {:?} {:?} {:?}", n, side, trans, uplo, diag);
+            let b = rhs(n, side);
+            let expected = apply(&dense_op(&t, uplo, trans, diag), &b, side);
+
+            let product =
+                triangular_multiply(&CpuBackend, &t, &b, uplo, trans, diag, side).unwrap();
+            assert_close(&product, &expected, 1e-5, &what);
+
+            let reference = cpu::triangular_multiply(&t, &b, uplo, trans, diag, side).unwrap();
+            assert_close(&reference, &expected, 1e-5, &what);
+        }
+    }
+}
+
+#[test]
+fn solves_every_kind_of_triangular_system() {
+    for n in SIZES {
+        let t = triangular_matrix(n);
+        for (side, trans, uplo, diag) in combinations() {
+            let what = format!("n = {}, {:?} {:?} {:?} {:?}", n, side, trans, uplo, diag);
+            let b = rhs(n, side);
+            let op = dense_op(&t, uplo, trans, diag);
+
+            let x = triangular_solve(&CpuBackend, &t, &b, uplo, trans, diag, side).unwrap();
+            assert_eq!((x.rows, x.cols), (b.rows, b.cols), "{}", what);
+            assert_close(&apply(&op, &x, side), &b, 1e-4, &what);
+
+            // The blocked solve agrees with substitution
+            let reference = cpu::triangular_solve(&t, &b, uplo, trans, diag, side).unwrap();
+            assert_close(&x, &reference, 1e-4, &what);
+        }
+    }
+}
+
+#[test]
+fn solve_inverts_multiply() {
+    let t = triangular_matrix(150);
+    for (side, trans, uplo, diag) in combinations() {
+        let what = format!("{:?} {:?} {:?} {:?}", side, trans, uplo, diag);
+        let x = rhs(150, side);
+
+        let b = triangular_multiply(&CpuBackend, &t, &x, uplo, trans, diag, side).unwrap();
+        let solved = triangular_solve(&CpuBackend, &t, &b, uplo, trans, diag, side).unwrap();
+        assert_close(&solved, &x, 1e-4, &what);
+    }
+}
+
+#[test]
+fn unit_diagonal_is_not_read() {
+    let mut t = triangular_matrix(70);
+    t.set(3, 3, 0.0);
+    t.set(66, 66, 0.0);
+    let b = rhs(70, Side::Left);
+
+    let error = triangular_solve(
+        &CpuBackend,
+        &t,
+        &b,
+        Uplo::Lower,
+        Transpose::NoTrans,
+        Diag::NonUnit,
+        Side::Left,
+    );
+    assert!(error.is_err());
+
+    let x = triangular_solve(
+        &CpuBackend,
+        &t,
+        &b,
+        Uplo::Lower,
+        Transpose::NoTrans,
+        Diag::Unit,
+        Side::Left,
+    )
+    .unwrap();
+    let op = dense_op(&t, Uplo::Lower, Transpose::NoTrans, Diag::Unit);
+    assert_close(&apply(&op, &x, Side::Left), &b, 1e-4, "unit diagonal");
+}
+
+#[test]
+fn rejects_incompatible_shapes() {
+    let t = triangular_matrix(4);
+    for side in [Side::Left, Side::Right] {
+        let b = match side {
+            Side::Left => Matrix::new(3, 2),
+            Side::Right => Matrix::new(2, 3),
+        };
+        assert!(triangular_solve(
+            &CpuBackend,
+            &t,
+            &b,
+            Uplo::Upper,
+            Transpose::NoTrans,
+            Diag::NonUnit,
+            side
+        )
+        .is_err());
+        assert!(
+            cpu::triangular_multiply(&t, &b, Uplo::Upper, Transpose::Trans, Diag::Unit, side)
+                .is_err()
+        );
+    }
+
+    let rectangular = Matrix::new(4, 3);
+    assert!(cpu::triangular_solve(
+        &rectangular,
+        &Matrix::new(4, 1),
+        Uplo::Lower,
+        Transpose::NoTrans,
+        Diag::NonUnit,
+        Side::Left
+    )
+    .is_err());
+}
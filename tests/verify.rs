@@ -0,0 +1,205 @@
+use metal_matrix::verify::{
+    self, compare_elements, ulp_distance, F64Backend, Operation, Report, Tolerance,
+};
+use metal_matrix::{cpu, Backend, CpuBackend, Matrix};
+
+/// The CPU backend with one element of every result replaced.
+struct FaultyBackend {
+    index: usize,
+    value: f32,
+}
+
+impl FaultyBackend {
+    fn corrupt(&self, result: anyhow::Result<Matrix>) -> anyhow::Result<Matrix> {
+        let mut result = result?;
+        result.data[self.index] = self.value;
+        Ok(result)
+    }
+}
+
+impl Backend for FaultyBackend {
+    fn matrix_multiply(&self, a: &Matrix, b: &Matrix) -> anyhow::Result<Matrix> {
+        self.corrupt(CpuBackend.matrix_multiply(a, b))
+    }
+
+    fn matrix_add(&self, a: &Matrix, b: &Matrix) -> anyhow::Result<Matrix> {
+        self.corrupt(CpuBackend.matrix_add(a, b))
+    }
+
+    fn matrix_subtract(&self, a: &Matrix, b: &Matrix) -> anyhow::Result<Matrix> {
+        self.corrupt(CpuBackend.matrix_subtract(a, b))
+    }
+
+    fn matrix_transpose(&self, a: &Matrix) -> anyhow::Result<Matrix> {
+        self.corrupt(CpuBackend.matrix_transpose(a))
+    }
+
+    fn matrix_scalar_multiply(&self, scalar: f32, a: &Matrix) -> anyhow::Result<Matrix> {
+        self.corrupt(CpuBackend.matrix_scalar_multiply(scalar, a))
+    }
+}
+
+fn operations() -> Vec<Operation> {
+    Operation::NAMES
+        .iter()
+        .map(|name| Operation::from_name(name).unwrap())
+        .collect()
+}
+
+#[test]
+fn measures_ulp_distance() {
+    let next = |x: f32| f32::from_bits(x.to_bits() + 1);
+
+    assert_eq!(ulp_distance(1.0, 1.0), 0);
+    assert_eq!(ulp_distance(1.0, next(next(1.0))), 2);
+    assert_eq!(ulp_distance(next(next(1.0)), 1.0), 2);
+    assert_eq!(ulp_distance(-1.0, -next(1.0)), 1);
+    assert_eq!(ulp_distance(0.0, -0.0), 0);
+    assert_eq!(ulp_distance(f32::MAX, f32::INFINITY), 1);
+    assert_eq!(ulp_distance(f32::NAN, f32::NAN), 0);
+    assert_eq!(ulp_distance(f32::NAN, 1.0), u64::MAX);
+
+    // From the smallest negative to the smallest positive subnormal: -d, 0, +d
+    let tiny = f32::from_bits(1);
+    assert_eq!(ulp_distance(-tiny, tiny), 2);
+}
+
+#[test]
+fn compares_elements() {
+    let actual = Matrix::with_data(2, 2, vec![1.0, 2.5, 0.0, -4.0]).unwrap();
+    let expected = Matrix::with_data(2, 2, vec![1.0, 2.0, 1e-3, -4.0]).unwrap();
+
+    let errors = compare_elements(&actual, &expected).unwrap();
+    assert_eq!(errors.len(), 4);
+    assert_eq!((errors[1].row, errors[1].col), (0, 1));
+    assert_eq!(errors[1].abs_error, 0.5);
+    assert_eq!(errors[1].rel_error, 0.25);
+    assert_eq!((errors[2].row, errors[2].col), (1, 0));
+    assert!((errors[2].rel_error - 1.0).abs() < 1e-12);
+    assert_eq!(errors[3].ulps, 0);
+
+    let error = compare_elements(&actual, &Matrix::new(4, 1)).unwrap_err();
+    assert_eq!(error.to_string(), "Result is 2x2 but the reference is 4x1");
+}
+
+#[test]
+fn flags_nonfinite_mismatches() {
+    let inf = f32::INFINITY;
+    let actual = Matrix::with_data(1, 6, vec![f32::NAN, inf, 1.0, inf, f32::NAN, 2.0]).unwrap();
+    let expected = Matrix::with_data(1, 6, vec![f32::NAN, inf, f32::NAN, -inf, inf, 2.0]).unwrap();
+
+    let errors = compare_elements(&actual, &expected).unwrap();
+    let mismatches: Vec<bool> = errors.iter().map(|e| e.nonfinite_mismatch).collect();
+    assert_eq!(mismatches, vec![false, false, true, true, true, false]);
+
+    let report = Report::new("test", (1, 6), &errors, Tolerance::exact());
+    assert_eq!(report.nonfinite_mismatches, 3);
+    assert_eq!(report.failures, 3);
+    assert_eq!(report.max_abs_error, 0.0);
+    assert!(!report.passed());
+}
+
+#[test]
+fn tolerances_depend_on_the_operation() {
+    let a = Matrix::with_data(1, 100, vec![2.0; 100]).unwrap();
+    let b = Matrix::with_data(100, 1, vec![-3.0; 100]).unwrap();
+    let epsilon = f32::EPSILON as f64;
+
+    assert_eq!(
+        Operation::MatrixTranspose.tolerance(&a, &b),
+        Tolerance::exact()
+    );
+    assert_eq!(Operation::MatrixAdd.tolerance(&a, &a).rel, epsilon);
+
+    let product = Operation::MatrixMultiply.tolerance(&a, &b);
+    assert_eq!(product.rel, 100.0 * epsilon);
+    assert_eq!(product.abs, 100.0 * epsilon * 6.0);
+}
+
+#[test]
+fn cpu_agrees_with_the_f64_reference() {
+    let a = verify::sample_matrix(70, 300, 1);
+    let b = verify::sample_matrix(300, 45, 2);
+    let c = verify::sample_matrix(70, 300, 3);
+
+    for operation in operations() {
+        let second = match operation {
+            Operation::MatrixMultiply => &b,
+            _ => &c,
+        };
+        let report = verify::verify(operation, &CpuBackend, &F64Backend, &a, second).unwrap();
+        assert!(report.passed(), "{}", report);
+        assert_eq!(report.nonfinite_mismatches, 0);
+    }
+
+    // Transposes and element-wise operations round exactly as the reference does
+    let report = verify::verify(Operation::MatrixAdd, &CpuBackend, &F64Backend, &a, &c).unwrap();
+    assert_eq!(report.max_ulps, 0);
+}
+
+#[test]
+fn detects_a_wrong_element() {
+    let a = verify::sample_matrix(8, 8, 4);
+    let b = verify::sample_matrix(8, 8, 5);
+
+    for operation in operations() {
+        let faulty = FaultyBackend {
+            index: 19,
+            value: 100.0,
+        };
+        let report = verify::verify(operation, &faulty, &CpuBackend, &a, &b).unwrap();
+
+        assert!(!report.passed(), "{}", operation.name());
+        assert_eq!(report.failures, 1);
+        let worst = report.worst.unwrap();
+        assert_eq!((worst.row, worst.col), (2, 3));
+        assert_eq!(worst.actual, 100.0);
+        assert!(report.max_ulps > 1 << 20);
+    }
+
+    let nan = FaultyBackend {
+        index: 0,
+        value: f32::NAN,
+    };
+    let report = verify::verify(Operation::MatrixAdd, &nan, &CpuBackend, &a, &b).unwrap();
+    assert_eq!(report.nonfinite_mismatches, 1);
+    assert!(report.to_string().contains("NaN/Inf mismatches: 1"));
+    assert!(report.to_string().contains("FAILED"));
+}
+
+#[test]
+fn f64_reference_solves_accurately() {
+    let mut a = verify::sample_matrix(10, 10, 6);
+    for i in 0..10 {
+        a.set(i, i, a.get(i, i) + 10.0);
+    }
+    let b = verify::sample_matrix(10, 2, 7);
+
+    let x = F64Backend.solve(&a, &b).unwrap();
+    let residual = cpu::matrix_subtract(&F64Backend.matrix_multiply(&a, &x).unwrap(), &b).unwrap();
+    assert!(residual.data.iter().all(|r| r.abs() < 1e-6));
+
+    let singular = Matrix::with_data(2, 2, vec![1.0, 2.0, 2.0, 4.0]).unwrap();
+    assert_eq!(
+        F64Backend.inverse(&singular).unwrap_err().to_string(),
+        "Matrix is singular"
+    );
+}
+
+#[test]
+fn parses_operation_names() {
+    assert_eq!(
+        Operation::from_name("multiply").unwrap(),
+        Operation::MatrixMultiply
+    );
+    assert_eq!(
+        Operation::from_name("matrix_scalar_multiply")
+            .unwrap()
+            .name(),
+        "matrix_scalar_multiply"
+    );
+    assert!(Operation::from_name("matrix_divide")
+        .unwrap_err()
+        .to_string()
+        .starts_with("Unknown operation 'matrix_divide'"));
+}